@@ -1,5 +1,7 @@
 use std::sync::Arc;
 
+use chrono::Utc;
+
 use poise::serenity_prelude as serenity;
 
 use ::serenity::all::{ReactionCollector, ReactionType};
@@ -8,11 +10,14 @@ use serenity::all::UserId;
 use serenity::prelude::*;
 
 use crate::components::logger::StdoutLogger;
+use crate::model::drop::{DropMatch, DroppedCard};
+use crate::model::history::ChangeSource;
+use crate::model::wishlist::{CardPriority, CardRef, SeriesName, WishlistEntry};
 use crate::commands::*;
 use crate::integrations::*;
 use crate::traits::wishlist_db::WishlistDB;
 use crate::util::either::Either;
-use crate::util::parse_util::{is_series_analysis, parse_series_card_from_analysis, parse_series_from_analysis};
+use crate::util::parse_util::{is_series_analysis, parse_drop_from_analysis, parse_series_from_analysis};
 use crate::components::logger::Logger;
 
 pub const _SOFI_USER_ID: UserId = UserId::new(853629533855809596);
@@ -30,7 +35,7 @@ pub type Context<'a> = poise::Context<'a, Data, Error>;
 
 pub async fn start_bot (
     token:impl AsRef<str>, 
-    wishlist_db: impl WishlistDB + 'static, 
+    wishlist_db: Box<dyn WishlistDB>, 
     logger: Arc<StdoutLogger>
 ) -> serenity::Client
 {
@@ -47,6 +52,18 @@ pub async fn start_bot (
                 command_wa(), 
                 command_wr(), 
                 wl(),
+                command_wcompare(),
+                command_wnote(),
+                command_wfilter(),
+                command_wundo(),
+                command_whistory(),
+                command_wscope(),
+                command_wfuzzy(),
+                command_walias(),
+                command_wcatalog(),
+                command_wexport(),
+                command_wprivacy(),
+                command_wimport(),
                 // Integration
                 integration_ssl(),
                 integration_sg(),
@@ -77,7 +94,7 @@ pub async fn start_bot (
             Box::pin(async move {
                 poise::builtins::register_globally(ctx, &framework.options().commands).await?;
                 Ok(Data {
-                    wishlist_db: Box::new(wishlist_db),
+                    wishlist_db,
                     logger
                 })
             })
@@ -90,7 +107,7 @@ pub async fn start_bot (
                         .framework(framework).await
                         .expect("Err creating client");
 
-    return client;
+    client
 }
 
 async fn stripped_dynamic_prefix<'a>(
@@ -154,15 +171,12 @@ async fn handle(
     data: &Data,
     msg:&Message 
 ) -> Result<(), Error> {
-    match msg.author.id {
-        _NORI_USER_ID => {
-            if is_series_analysis(&msg.content) {
-                wishlist_check_series(ctx, msg, data).await?;
-            } else {
-                wishlist_check_cards(ctx, msg, data).await?;
-            }
+    if msg.author.id == _NORI_USER_ID {
+        if is_series_analysis(&msg.content) {
+            wishlist_check_series(ctx, msg, data).await?;
+        } else {
+            wishlist_check_cards(ctx, msg, data).await?;
         }
-        _ => ()
     }
 
     Ok(())
@@ -191,22 +205,43 @@ async fn wishlist_check_series(
     data: &Data
 ) -> Result<(), Error> 
 {
-    let targets = msg.content.lines()
-        .map(|line| parse_series_from_analysis(line).unwrap_or(""))
+    let targets: Vec<SeriesName> = msg.content.lines()
+        .filter_map(parse_series_from_analysis)
+        .map(SeriesName::new)
+        .collect();
+
+    let res = ping_series(ctx, msg, data, &targets).await;
+
+    // every analyzed series goes into the catalog, wishlisted or not, once the pings are sent
+    let entries: Vec<WishlistEntry> = targets.into_iter()
+        .map(|series| WishlistEntry { series, cards: vec![] })
         .collect();
+    add_to_catalog(data, "wishlist_check_series", &entries).await;
+
+    res
+}
+
+async fn ping_series(
+    ctx: &serenity::Context, 
+    msg: &Message, 
+    data: &Data,
+    targets: &[SeriesName]
+) -> Result<(), Error> 
+{
+    let scope = data.wishlist_db.resolve_scope(msg.guild_id).await?;
 
     let wishlisted_res = 
-        data.wishlist_db.get_users_with_series(&targets).await;
+        data.wishlist_db.get_users_with_series(scope, targets).await;
 
 
     if let Err(why) = wishlisted_res {
         data.logger.log_error(format!("wishlist_check_series: Error retrieving wishlisted users for '{:?}' : {why:?}", targets));
-        return Err(why);
+        return Err(why.into());
     }
 
     let wishlist_pings = wishlisted_res.unwrap();
 
-    if wishlist_pings.len() > 0 
+    if !wishlist_pings.is_empty() 
     {
         let mut message = MessageBuilder::new();
         message.push("A series from your wishlist is up for grabs!\n");
@@ -214,11 +249,10 @@ async fn wishlist_check_series(
         for (series, users) in wishlist_pings {
             message.push(format!("{series}: \n"));
     
-            for (user, amount) in users {
-                let user_id = user.parse::<u64>().unwrap();
+            for (user_id, amount) in users {
                 message
                     .push("\t")
-                    .mention(&UserId::new(user_id).mention())
+                    .mention(&user_id.mention())
                     .push(format!("({amount})\n"));
                 
                 data.logger.log_info(format!("wishlist_check_series: Pinging user `{user_id}` for series `{series}`"));
@@ -242,16 +276,45 @@ async fn wishlist_check_cards (
     msg: &Message, 
     data: &Data
 ) -> Result<(), Error> {
-    let targets = msg.content.lines()
-        .map(|line| parse_series_card_from_analysis(line).unwrap_or(("", "")))
+    let targets: Vec<DroppedCard> = msg.content.lines()
+        .filter_map(parse_drop_from_analysis)
         .collect();
 
+    let res = ping_cards(ctx, msg, data, &targets).await;
+
+    // every dropped card goes into the catalog, wishlisted or not, once the pings are sent
+    let entries: Vec<WishlistEntry> = targets.into_iter()
+        .map(|dropped| WishlistEntry::from(dropped.card))
+        .collect();
+    add_to_catalog(data, "wishlist_check_cards", &entries).await;
+
+    res
+}
+
+async fn add_to_catalog(data: &Data, function: &str, entries: &[WishlistEntry]) {
+    if entries.is_empty() {
+        return;
+    }
+
+    if let Err(why) = data.wishlist_db.add_to_catalog(entries, Utc::now()).await {
+        data.logger.log_error(format!("{function}: Error adding to the catalog: {why:?}"));
+    }
+}
+
+async fn ping_cards(
+    ctx: &serenity::Context, 
+    msg: &Message, 
+    data: &Data,
+    targets: &[DroppedCard]
+) -> Result<(), Error> {
+    let scope = data.wishlist_db.resolve_scope(msg.guild_id).await?;
+
     let wishlist_pings_res = 
-        data.wishlist_db.get_users_with_series_card(targets).await;
+        data.wishlist_db.get_users_with_series_card(scope, targets).await;
 
     if let Err(why) = wishlist_pings_res {
         data.logger.log_error(format!("wishlist_check_cards: Error retrieving wishlisted users: {why:?}"));
-        return Err(why);
+        return Err(why.into());
     }
 
     let mut wishlist_pings = wishlist_pings_res.unwrap();
@@ -264,14 +327,26 @@ async fn wishlist_check_cards (
     let mut message = MessageBuilder::new();
     message.push("A card from your wishlist is dropping!\n");
 
-    for ((_, card), users) in wishlist_pings.iter()
+    for (CardRef { card, .. }, users) in wishlist_pings.iter_mut()
     {
-        message.push(format!("{card}: "));
+        // high priority matches come first and stand out, exact matches before possible ones
+        users.sort_by_key(|drop_match| (drop_match.priority, drop_match.possible_match.is_some()));
+        if users.iter().any(|drop_match| drop_match.priority == CardPriority::High) {
+            message.push_bold(format!("{card}: "));
+        } else {
+            message.push(format!("{card}: "));
+        }
+
+        for DropMatch { user_id, priority, possible_match } in users.iter() {
+            if *priority == CardPriority::High {
+                message.push("⭐");
+            }
+            message.mention(&user_id.mention());
 
-        for user in users {
-            let user_id = user.parse::<u64>().unwrap();
-            message.mention(&UserId::new(user_id).mention());
-            data.logger.log_info(format!("wishlist_check_cards: Pinging user `{user_id}` for card `{card}`"));
+            if let Some(wishlisted) = possible_match {
+                message.push(" ").push_italic(format!("(possible match: {})", wishlisted.card)).push(" ");
+            }
+            data.logger.log_info(format!("wishlist_check_cards: Pinging user `{user_id}` for card `{card}` ({})", priority.as_str()));
         }
 
         message.push("\n");
@@ -284,7 +359,7 @@ async fn wishlist_check_cards (
     match msg.channel_id.send_message(ctx, builder).await {
         Err(why) => {
             data.logger.log_error(format!("Error sending message: {why:?}"));
-            return Err(Box::new(why));
+            Err(Box::new(why))
         }
         Ok(reply_msg) => {
 
@@ -292,7 +367,7 @@ async fn wishlist_check_cards (
             let reaction_two  : ReactionType = ReactionType::Unicode("2️⃣".to_string());
             let reaction_three: ReactionType = ReactionType::Unicode("3️⃣".to_string());
 
-            if wishlist_pings.len() > 0 { reply_msg.react(ctx, reaction_one.clone()).await?; }
+            if !wishlist_pings.is_empty() { reply_msg.react(ctx, reaction_one.clone()).await?; }
             if wishlist_pings.len() > 1 { reply_msg.react(ctx, reaction_two.clone()).await?; }
             if wishlist_pings.len() > 2 { reply_msg.react(ctx, reaction_three.clone()).await?; }
 
@@ -319,24 +394,28 @@ async fn wishlist_check_cards (
                     _ => None
                 }.map(|index| wishlist_pings.get_mut(index).unwrap());
 
-                match opt_ping {
-                    Some(ping) => {
-                        if ping.1.contains(&reaction_user_id.to_string()) {
-                            // remove user that reacted from wishlist_pings internal list if the user is in there
-                            ping.1.retain(|user| *user != reaction_user_id.to_string());
-        
-                            // activate wr for the user
-                            wr_cards( ctx, 
-                                Either::Right(reaction.channel_id), 
-                                &data, 
-                                reaction_user_id, 
-                                ping.0.0, 
-                                vec![ping.0.1],
-                                None
-                            ).await.unwrap();
+                if let Some(ping) = opt_ping {
+                    if let Some(index) = ping.1.iter().position(|drop_match| drop_match.user_id == reaction_user_id) {
+                        // remove user that reacted from wishlist_pings internal list if the user is in there
+                        let drop_match = ping.1.remove(index);
+
+                        // a possible match removes the card the user wishlisted, not the dropped one
+                        let card = drop_match.possible_match.unwrap_or_else(|| ping.0.clone());
+
+                        // activate wr for the user
+                        let res = wr_cards( ctx, 
+                            Either::Right(reaction.channel_id), 
+                            data, 
+                            scope,
+                            reaction_user_id, 
+                            &WishlistEntry::from(card),
+                            ChangeSource::DropReaction,
+                            None
+                        ).await;
+                        if let Err(why) = res {
+                            data.logger.log_error(format!("Error removing a dropped card from the wishlist: {why:?}"));
                         }
                     }
-                    None => {}
                 }
             }
 
@@ -344,7 +423,7 @@ async fn wishlist_check_cards (
             reply_msg.delete_reaction_emoji(ctx, reaction_two).await?;
             reply_msg.delete_reaction_emoji(ctx, reaction_three).await?;
 
-            return Ok(());
+            Ok(())
         }
     }
 }
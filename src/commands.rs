@@ -1,16 +1,26 @@
 use std::cmp::min;
+use std::future::Future;
 
+use chrono::Utc;
 use poise::serenity_prelude as serenity;
 use poise::samples::HelpConfiguration;
 use poise::CreateReply;
 use rand::Rng;
-use ::serenity::all::{ChannelId, ComponentInteractionCollector, CreateEmbedFooter, CreateMessage, EditMessage, UserId};
+use ::serenity::all::{ChannelId, ComponentInteractionCollector, CreateAttachment, CreateEmbedFooter, CreateMessage, EditMessage, UserId};
 use serenity::all::MessageBuilder;
 use serenity::all::{Message, User};
 
 use crate::components::logger::Logger;
+use crate::model::catalog::{CatalogSeries, CatalogStats};
+use crate::model::compare::WishlistComparison;
+use crate::model::history::{ChangeSource, HistoryAction, HistoryRecord, WishlistChange};
+use crate::model::export::WishlistExport;
+use crate::model::import::{read_csv, read_json, ImportMode, ImportPlan};
+use crate::model::wishlist::{AddedCards, CardName, CardPriority, CardRef, OverviewQuery, RemovedCards, ScopeMode, SeriesName, SeriesOrder, WishlistEntry, WishlistPrivacy, WishlistScope, WishlistedCard};
+use crate::traits::wishlist_db::{WishlistDbError, WishlistDbResult};
 use crate::util::either::Either;
-use crate::util::parse_util::parse_series_cards;
+use crate::util::fuzzy::closest_match;
+use crate::util::parse_util::{escape_card_name, parse_card_names, parse_command_lines, parse_constraints, parse_priority_option, parse_series_alias, parse_series_card_value, parse_series_cards, parse_series_name, parse_sort_option, split_last_card, ArgumentError, ArgumentErrorKind};
 use crate::bot::{Context, Data, Error};
 
 // ##############################
@@ -18,261 +28,2055 @@ use crate::bot::{Context, Data, Error};
 // ##############################
 
 /// Adds all selected cards from a series to your wishlist.
+///
+/// `.wa (priority:<high|normal|low>)? <series> || <card name> (, <card name>)*`
 /// Will not add duplicates.
-#[poise::command(prefix_command, rename = "wa")]
+/// Use `.wa!` or start with `priority:<high|normal|low>` to set the cards' priority,
+/// which also applies to the ones already in your wishlist.
+/// Several series can be added at once, one per line (or in a code block).
+/// The card `*` stands for every card of the series, removing a card with `.wr` leaves it out.
+#[poise::command(prefix_command, slash_command, rename = "wa", aliases("wa!"))]
 pub async fn command_wa (
     ctx: Context<'_>,
-    #[description = "<series> || <card name> (, <card name>)*"]
+    #[description = "Series of the cards"]
+    #[autocomplete = "autocomplete_catalog_series"]
+    series: CommandText,
+    #[description = "Card names separated by commas, * for every card of the series"]
+    #[autocomplete = "autocomplete_catalog_cards"]
+    cards: Option<String>,
+    #[description = "Priority of the cards, also set on the ones already in your wishlist"]
+    priority: Option<PriorityChoice>,
+) -> Result<(), Error> 
+{
+    ctx.defer().await?;
+
+    let Some(scope) = command_scope(ctx).await?
+    else {
+        return Ok(());
+    };
+
+    if let Some(cards) = cards {
+        return match parse_card_names(&cards) {
+            Err(err) => {
+                ctx.reply(argument_error_reply(&err, &cards, "wa")).await?;
+                Ok(())
+            },
+            Ok(card_names) => wa_entry(ctx, scope, &WishlistEntry::new(series.0, card_names), priority.map(CardPriority::from)).await,
+        };
+    }
+
+    // without the cards option, the series one holds the lines of `.wa`
+    let Some((line_priority, command)) = parse_priority_option(&series.0)
+    else {
+        ctx.reply("Incorrect priority, use `high`, `normal` or `low`. Check `.help wa`").await?;
+        return Ok(());
+    };
+
+    // `.wa!` is a shorthand for `priority:high`
+    let priority = priority.map(CardPriority::from)
+        .or(line_priority)
+        .or((ctx.invoked_command_name() == "wa!").then_some(CardPriority::High));
+
+    let lines = parse_command_lines(command);
+    if lines.len() > 1 {
+        return wa_lines(ctx, scope, &lines, priority).await;
+    }
+
+    let line = lines.first().map_or("", |(_, line)| *line);
+    match parse_series_cards(line) {
+        Err(err) => { 
+            ctx.reply(argument_error_reply(&err, line, "wa")).await?;
+            Ok(())
+        },
+        Ok((series, card_names)) => wa_entry(ctx, scope, &WishlistEntry::new(series, card_names), priority).await,
+    }
+}
+
+// `.wa` and `/wa` with a single series
+async fn wa_entry(ctx: Context<'_>, scope: WishlistScope, entry: &WishlistEntry, priority: Option<CardPriority>) -> Result<(), Error> {
+    let reply = match add_entry(ctx.data(), scope, ctx.author().id, entry, priority, ChangeSource::Command).await {
+        Ok((res, updated_cards_count)) => {
+            let reply = added_reply(&entry.series, &res, updated_cards_count, priority, None);
+            match catalog_warning(ctx.data(), entry).await {
+                Some(warning) => format!("{reply}\n{warning}"),
+                None => reply
+            }
+        },
+        Err(err) => {
+            ctx.data().logger.log_error(format!(".wa | {err}"));
+            db_error_reply(&err, "adding cards to your wishlist")
+        }
+    };
+
+    ctx.reply(reply).await?;
+
+    Ok(())
+}
+
+#[allow(clippy::too_many_arguments)]
+pub async fn wa (
+    ctx: &serenity::Context, 
+    msg: &Message, 
+    data: &Data, 
+    scope: WishlistScope,
+    user_id: UserId, 
+    entry: &WishlistEntry,
+    priority: Option<CardPriority>,
+    source: ChangeSource,
+    prev_response: Option<(Message, i32)>
+) -> Result<(Message, i32), Error> 
+{
+    match add_entry(data, scope, user_id, entry, priority, source).await {
+        Ok((res, updated_cards_count)) => {
+            let added_cards_count = res.added.len() as i32;
+
+            match prev_response {
+                Some((mut prev_msg, prev_added_count)) => {
+                    let total = added_cards_count + prev_added_count;
+                    let message = added_reply(&entry.series, &res, updated_cards_count, priority, Some(total));
+                    prev_msg.edit(ctx, EditMessage::new().content(message)).await?;
+                    Ok((prev_msg, total))
+                },
+                None => {
+                    let message = added_reply(&entry.series, &res, updated_cards_count, priority, None);
+                    let response_msg = msg.reply_ping(ctx, message).await?;
+                    Ok((response_msg, added_cards_count))
+                }
+            }
+        },
+        Err(err) =>  {
+            data.logger.log_error(format!(".wa | {err}"));
+            msg.reply_ping(ctx, db_error_reply(&err, "adding cards to your wishlist")).await?;
+            Err(err.into())
+        }
+    }
+}
+
+// What the user is told after adding cards, `total` counting the ones added
+// by the previous reactions to the same message
+fn added_reply(series: &SeriesName, res: &AddedCards, updated_cards_count: i32, priority: Option<CardPriority>, total: Option<i32>) -> String {
+    let mut message = MessageBuilder::new();
+
+    match priority.filter(|_| updated_cards_count > 0) {
+        Some(priority) => {
+            message.push(format!("Set {updated_cards_count} wishlisted card(s) to {} priority. ", priority.as_str()));
+        },
+        None if total.is_none() && !res.already_wishlisted.is_empty() => {
+            message.push(format!("{} already on your wishlist. ", cards_were(&res.already_wishlisted)));
+        },
+        None => ()
+    }
+
+    let added_cards_count = total.unwrap_or(res.added.len() as i32);
+    message.push(format!("Added {added_cards_count} card(s) from `{series}` to your wishlist!"));
+
+    message.build()
+}
+
+// Adds the cards and records the change. Returns how many of the already wishlisted cards
+// were set to the priority, as an explicit priority also applies to them.
+pub(crate) async fn add_entry(
+    data: &Data, 
+    scope: WishlistScope,
+    user_id: UserId, 
+    entry: &WishlistEntry,
+    priority: Option<CardPriority>,
+    source: ChangeSource
+) -> WishlistDbResult<(AddedCards, i32)> 
+{
+    let res = data.wishlist_db.add_all_to_wishlist(scope, user_id, entry, priority.unwrap_or_default(), priority.is_some()).await?;
+
+    let added = WishlistEntry { series: entry.series.clone(), cards: res.added.clone() };
+    record_change(data, scope, user_id, WishlistChange::new(HistoryAction::Added, added, source).with_wildcard(res.included.clone())).await;
+
+    let updated_cards_count = match priority {
+        Some(_) => res.already_wishlisted.len() as i32,
+        None => 0
+    };
+
+    Ok((res, updated_cards_count))
+}
+
+// Warns about the cards never seen from a series whose cards the catalog knows, as they are likely
+// misspelled. Series the catalog knows no cards of are not checked.
+async fn catalog_warning(data: &Data, entry: &WishlistEntry) -> Option<String> {
+    let catalog_series = data.wishlist_db.get_catalog_series(&entry.series).await.ok()??;
+    if catalog_series.cards.is_empty() {
+        return None;
+    }
+
+    let warnings: Vec<String> = entry.cards.iter()
+        .filter(|card| !card.is_wildcard() && !catalog_series.has_card(card))
+        .map(|card| match catalog_series.closest_card(card) {
+            Some(known) => format!("⚠️ `{card}` was never seen in `{}`, did you mean `{known}`?", catalog_series.series),
+            None => format!("⚠️ `{card}` was never seen in `{}`.", catalog_series.series)
+        })
+        .collect();
+
+    (!warnings.is_empty()).then(|| warnings.join("\n"))
+}
+
+// `.wa` with one series per line, answered by a single summary
+async fn wa_lines(
+    ctx: Context<'_>, 
+    scope: WishlistScope, 
+    lines: &[(usize, &str)], 
+    priority: Option<CardPriority>
+) -> Result<(), Error> 
+{
+    let data = ctx.data();
+    let mut results = vec![];
+    let mut invalid_lines = vec![];
+
+    for (line_number, line) in lines {
+        let (series, card_names) = match parse_series_cards(line) {
+            Ok(parsed) => parsed,
+            Err(err) => {
+                invalid_lines.push(format!("Line {line_number}: {err}"));
+                continue;
+            }
+        };
+
+        let entry = WishlistEntry::new(series, card_names);
+        let result = match add_entry(data, scope, ctx.author().id, &entry, priority, ChangeSource::Command).await {
+            Ok((res, updated_cards_count)) => {
+                let mut result = format!("`{}`: added {} card(s)", entry.series, res.added.len());
+                match priority.filter(|_| updated_cards_count > 0) {
+                    Some(priority) => result.push_str(&format!(", set {updated_cards_count} to {} priority", priority.as_str())),
+                    None if !res.already_wishlisted.is_empty() => result.push_str(&format!(", {} already on your wishlist", cards_were(&res.already_wishlisted))),
+                    None => ()
+                }
+                if let Some(warning) = catalog_warning(data, &entry).await {
+                    result.push_str(&format!("\n{warning}"));
+                }
+                result
+            },
+            Err(err) => {
+                data.logger.log_error(format!(".wa | {err}"));
+                format!("`{}`: {}", entry.series, db_error_reply(&err, "adding cards to your wishlist"))
+            }
+        };
+        results.push(result);
+    }
+
+    ctx.send(CreateReply::default().embed(lines_summary("Wishlist add", "wa", &results, &invalid_lines))).await?;
+
+    Ok(())
+}
+
+// Summary of a command run on several lines, with the results in the description
+// and the lines that could not be parsed in a field, both cut to fit Discord's limits
+fn lines_summary(title: &str, command: &str, results: &[String], invalid_lines: &[String]) -> serenity::CreateEmbed {
+    let mut embed = serenity::CreateEmbed::new().title(title);
+
+    if !results.is_empty() {
+        embed = embed.description(join_lines(results, 4096));
+    }
+    if !invalid_lines.is_empty() {
+        embed = embed.field(format!("Incorrect format, check `.help {command}`"), join_lines(invalid_lines, 1024), false);
+    }
+
+    embed
+}
+
+// Joins lines up to `max_len` characters, with a note on how many were left out
+fn join_lines(lines: &[String], max_len: usize) -> String {
+    // room for the note on the lines left out
+    let max_len = max_len - 32;
+    let mut joined = String::new();
+
+    for (index, line) in lines.iter().enumerate() {
+        if joined.chars().count() + line.chars().count() + 1 > max_len {
+            joined.push_str(&format!("… and {} more", lines.len() - index));
+            break;
+        }
+        joined.push_str(line);
+        joined.push('\n');
+    }
+
+    joined
+}
+
+// ##############################
+// ##############################  WISHLIST REMOVE
+// ##############################
+
+/// Removes cards from a series, or the entire series if no cards are given, from your wishlist.
+///
+/// `.wr <series> ( || <card name> (, <card name>)* )?`
+/// Will only remove cards already in your wishlist.
+/// Several series can be removed at once, one per line (or in a code block).
+/// When nothing is removed, suggests the closest wishlisted series or cards.
+#[poise::command(prefix_command, slash_command, rename = "wr")]
+pub async fn command_wr(
+    ctx: Context<'_>,
+    #[description = "Series to remove, or to remove the cards from"]
+    #[autocomplete = "autocomplete_wishlisted_series"]
+    series: CommandText,
+    #[description = "Card names separated by commas, the entire series if left out"]
+    #[autocomplete = "autocomplete_wishlisted_cards"]
+    cards: Option<String>,
+) -> Result<(), Error> 
+{
+    ctx.defer().await?;
+
+    let Some(scope) = command_scope(ctx).await?
+    else {
+        return Ok(());
+    };
+
+    if let Some(cards) = cards {
+        return match parse_card_names(&cards) {
+            Err(err) => {
+                ctx.reply(argument_error_reply(&err, &cards, "wr")).await?;
+                Ok(())
+            },
+            Ok(card_names) => wr_entry(ctx, scope, Either::Right(WishlistEntry::new(series.0, card_names))).await,
+        };
+    }
+
+    // without the cards option, the series one holds the lines of `.wr`
+    let lines = parse_command_lines(&series.0);
+    if lines.len() > 1 {
+        return wr_lines(ctx, scope, &lines).await;
+    }
+
+    let line = lines.first().map_or("", |(_, line)| *line);
+    match parse_wr_line(line) {
+        Err(err) => {
+            ctx.reply(argument_error_reply(&err, line, "wr")).await?;
+            Ok(())
+        },
+        Ok(parsed) => wr_entry(ctx, scope, parsed).await,
+    }
+}
+
+// `.wr` and `/wr` with a single series, removed whole or only the selected cards.
+// When nothing was removed, offers to remove the closest wishlisted series or cards instead.
+async fn wr_entry(ctx: Context<'_>, scope: WishlistScope, parsed: Either<SeriesName, WishlistEntry>) -> Result<(), Error> {
+    let data = ctx.data();
+    let user_id = ctx.author().id;
+
+    let (reply, removed_nothing) = remove_parsed(data, scope, user_id, &parsed).await;
+
+    let suggestion = match removed_nothing {
+        true => removal_suggestion(data, scope, user_id, &parsed).await.unwrap_or_else(|err| {
+            data.logger.log_error(format!(".wr | {err}"));
+            None
+        }),
+        false => None
+    };
+    let Some(suggestion) = suggestion
+    else {
+        ctx.reply(reply).await?;
+        return Ok(());
+    };
+
+    let ctx_id = ctx.id();
+    let remove_button_id = format!("{ctx_id}remove");
+
+    let reply = CreateReply::default()
+        .content(format!("{reply}\n{}", suggestion_question(&suggestion)))
+        .components(vec![serenity::CreateActionRow::Buttons(vec![
+            serenity::CreateButton::new(&remove_button_id).label("Remove instead").style(serenity::ButtonStyle::Danger),
+        ])]);
+    let handle = ctx.send(reply).await?;
+
+    let press = ComponentInteractionCollector::new(ctx)
+        .filter(move |press| press.data.custom_id == remove_button_id)
+        .author_id(user_id)
+        .timeout(std::time::Duration::from_secs(60))
+        .await;
+
+    match press {
+        Some(press) => {
+            let (reply, _) = remove_parsed(data, scope, user_id, &suggestion).await;
+            update_pressed_message(ctx, &press, reply).await?;
+        },
+        None => {
+            handle.edit(ctx, CreateReply::default().components(vec![])).await?;
+        }
+    }
+
+    Ok(())
+}
+
+// Removes a whole series or cards from it, returning the reply and whether nothing was removed
+async fn remove_parsed(
+    data: &Data, 
+    scope: WishlistScope,
+    user_id: UserId, 
+    parsed: &Either<SeriesName, WishlistEntry>
+) -> (String, bool) 
+{
+    match parsed {
+        Either::Left(series) => match remove_series(data, scope, user_id, series, ChangeSource::Command).await {
+            Ok(amount) => (format!("Removed series `{series}` with {amount} card(s) from your wishlist!"), false),
+            Err(WishlistDbError::NotFound(_)) => (format!("Series `{series}` is not in your wishlist."), true),
+            Err(err) => {
+                data.logger.log_error(format!(".wr | {err}"));
+                (db_error_reply(&err, "removing a series from your wishlist"), false)
+            }
+        },
+        Either::Right(entry) => match remove_entry(data, scope, user_id, entry, ChangeSource::Command).await {
+            Ok(res) => (removed_reply(&res, None), res.removed.is_empty()),
+            Err(err) => {
+                data.logger.log_error(format!(".wr | {err}"));
+                (db_error_reply(&err, "removing cards from your wishlist"), false)
+            }
+        },
+    }
+}
+
+// What a removal that found nothing most likely meant: the wishlisted series closest to an unknown one,
+// along with the wishlisted card of that series closest to each of the cards
+async fn removal_suggestion(
+    data: &Data, 
+    scope: WishlistScope,
+    user_id: UserId, 
+    parsed: &Either<SeriesName, WishlistEntry>
+) -> WishlistDbResult<Option<Either<SeriesName, WishlistEntry>>> 
+{
+    let entry = match parsed {
+        Either::Left(series) => return Ok(closest_series(data, scope, user_id, series).await?.map(Either::Left)),
+        Either::Right(entry) => entry
+    };
+
+    let mut series = entry.series.clone();
+    let mut wishlisted = data.wishlist_db.get_user_wishlisted_cards(scope, user_id, &series).await?;
+    if wishlisted.is_empty() {
+        let Some(closest) = closest_series(data, scope, user_id, &series).await?
+        else {
+            return Ok(None);
+        };
+        wishlisted = data.wishlist_db.get_user_wishlisted_cards(scope, user_id, &closest).await?;
+        series = closest;
+    }
+
+    let mut cards: Vec<CardName> = vec![];
+    for card in entry.cards.iter().filter(|card| !card.is_wildcard()) {
+        let candidates = wishlisted.iter()
+            .filter(|wishlisted| !wishlisted.name.is_wildcard())
+            .map(|wishlisted| (wishlisted.name.search_term(), &wishlisted.name));
+
+        if let Some(closest) = closest_match(&card.search_term(), candidates) {
+            if !cards.contains(closest) {
+                cards.push(closest.clone());
+            }
+        }
+    }
+
+    Ok((!cards.is_empty()).then_some(Either::Right(WishlistEntry { series, cards })))
+}
+
+async fn closest_series(
+    data: &Data, 
+    scope: WishlistScope,
+    user_id: UserId, 
+    series: &SeriesName
+) -> WishlistDbResult<Option<SeriesName>> 
+{
+    let overview = data.wishlist_db.get_wishlist_overview(scope, user_id, &OverviewQuery::default()).await?;
+
+    Ok(closest_match(&series.search_term(), overview.series.into_iter().map(|series| (series.series.search_term(), series.series))))
+}
+
+// Did you mean `Nami`, `Nico Robin` from `One Piece`?
+fn suggestion_question(suggestion: &Either<SeriesName, WishlistEntry>) -> String {
+    match suggestion {
+        Either::Left(series) => format!("Did you mean `{series}`?"),
+        Either::Right(entry) => format!("Did you mean {} from `{}`?", cards_list(&entry.cards), entry.series)
+    }
+}
+
+// `.wr` takes a whole series, or cards from it
+fn parse_wr_line(line: &str) -> Result<Either<SeriesName, WishlistEntry>, ArgumentError> {
+    match parse_series_cards(line) {
+        Ok((series, card_names)) => Ok(Either::Right(WishlistEntry::new(series, card_names))),
+        Err(err) if err.kind == ArgumentErrorKind::MissingSeparator => parse_series_name(line).map(|series| Either::Left(SeriesName::new(series))),
+        Err(err) => Err(err)
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub async fn wr_cards (
+    ctx: &serenity::Context, 
+    user_msg: Either<&Message, ChannelId>, 
+    data: &Data, 
+    scope: WishlistScope,
+    user_id: UserId, 
+    entry: &WishlistEntry,
+    source: ChangeSource,
+    prev_response: Option<(Message, i32)>
+) -> Result<(Message, i32), Error> 
+{
+    let mut message = MessageBuilder::new();
+
+    match remove_entry(data, scope, user_id, entry, source).await {
+        Ok(res) => {
+            let amount_removed = res.removed.len() as i32;
+
+            match prev_response {
+                Some((mut prev_msg, prev_removed_count)) => {
+                    let total = prev_removed_count + amount_removed;
+
+                    prev_msg.edit(ctx, EditMessage::new().content(removed_reply(&res, Some(total)))).await?;
+
+                    Ok((prev_msg, total))
+                },
+                None => {
+                    message.push(removed_reply(&res, None));
+
+                    let response = match user_msg {
+                        Either::Left(msg) => msg.reply_ping(ctx, message.build()).await?,
+                        Either::Right(channel_id) => {
+                            message.user(user_id);
+                            let builder = CreateMessage::new().content(message.build());
+                            ctx.http.send_message(channel_id, vec![], &builder).await?
+                        }
+                    };
+
+                    Ok((response, amount_removed))
+                }
+            }
+        },
+        Err(err) => {
+            data.logger.log_error(err.to_string());
+            message.push(db_error_reply(&err, "removing cards from your wishlist"));
+            match user_msg {
+                Either::Left(msg) => msg.reply_ping(ctx, message.build()).await?,
+                Either::Right(channel_id) => {
+                    message.push(" ");
+                    message.user(user_id);
+                    let builder = CreateMessage::new().content(message.build());
+                    ctx.http.send_message(channel_id, vec![], &builder).await?
+                }
+            };
+
+            Err(err.into())
+        }
+    }
+}
+
+// What the user is told after removing cards, `total` counting the ones removed
+// by the previous reactions to the same message
+fn removed_reply(res: &RemovedCards, total: Option<i32>) -> String {
+    let amount_removed = res.removed.len() as i32;
+
+    match total {
+        Some(total) => format!("Removed {total} card(s) from your wishlist!"),
+        None => {
+            let mut message = MessageBuilder::new();
+            if !res.not_found.is_empty() {
+                message.push(format!("{} not on your wishlist. ", cards_were(&res.not_found)));
+            }
+
+            if res.left > 0 {
+                message.push(format!("Removed {amount_removed} card(s) from your wishlist! ({} card(s) left)", res.left));
+            } else {
+                message.push(format!("Removed {amount_removed} card(s) from your wishlist! No more cards left!"));
+            }
+
+            message.build()
+        }
+    }
+}
+
+pub(crate) async fn remove_entry(
+    data: &Data, 
+    scope: WishlistScope,
+    user_id: UserId, 
+    entry: &WishlistEntry,
+    source: ChangeSource
+) -> WishlistDbResult<RemovedCards> 
+{
+    let res = data.wishlist_db.remove_all_from_wishlist(scope, user_id, entry, true).await?;
+
+    // undoing the removal restores how the cards were wishlisted
+    let removed = WishlistEntry { series: entry.series.clone(), cards: res.removed.clone() };
+    let change = WishlistChange::new(HistoryAction::Removed, removed, source)
+        .with_details(res.details.clone())
+        .with_wildcard(res.excluded.clone());
+    record_change(data, scope, user_id, change).await;
+
+    Ok(res)
+}
+
+// Returns how many cards the series had
+async fn remove_series(
+    data: &Data, 
+    scope: WishlistScope,
+    user_id: UserId, 
+    series: &SeriesName,
+    source: ChangeSource
+) -> WishlistDbResult<i32> 
+{
+    let cards = data.wishlist_db.get_user_wishlisted_cards(scope, user_id, series).await?;
+    let amount = data.wishlist_db.remove_series_from_wishlist(scope, user_id, series).await?;
+
+    let removed = WishlistEntry { series: series.clone(), cards: cards.iter().map(|card| card.name.clone()).collect() };
+    record_change(data, scope, user_id, WishlistChange::new(HistoryAction::Removed, removed, source).with_details(cards)).await;
+
+    Ok(amount)
+}
+
+// `.wr` with one series per line, answered by a single summary
+async fn wr_lines(ctx: Context<'_>, scope: WishlistScope, lines: &[(usize, &str)]) -> Result<(), Error> {
+    let data = ctx.data();
+    let user_id = ctx.author().id;
+    let mut results = vec![];
+    let mut invalid_lines = vec![];
+
+    for (line_number, line) in lines {
+        let parsed = match parse_wr_line(line) {
+            Ok(parsed) => parsed,
+            Err(err) => {
+                invalid_lines.push(format!("Line {line_number}: {err}"));
+                continue;
+            }
+        };
+
+        let result = match parsed {
+            Either::Left(series) => match remove_series(data, scope, user_id, &series, ChangeSource::Command).await {
+                Ok(amount) => format!("`{series}`: removed the series with {amount} card(s)"),
+                Err(WishlistDbError::NotFound(_)) => format!("`{series}`: not in your wishlist"),
+                Err(err) => {
+                    data.logger.log_error(format!(".wr | {err}"));
+                    format!("`{series}`: {}", db_error_reply(&err, "removing a series from your wishlist"))
+                }
+            },
+            Either::Right(entry) => match remove_entry(data, scope, user_id, &entry, ChangeSource::Command).await {
+                Ok(res) => {
+                    let mut result = format!("`{}`: removed {} card(s)", entry.series, res.removed.len());
+                    if !res.not_found.is_empty() {
+                        result.push_str(&format!(", {} not on your wishlist", cards_were(&res.not_found)));
+                    }
+                    result
+                },
+                Err(err) => {
+                    data.logger.log_error(format!(".wr | {err}"));
+                    format!("`{}`: {}", entry.series, db_error_reply(&err, "removing cards from your wishlist"))
+                }
+            },
+        };
+        results.push(result);
+    }
+
+    ctx.send(CreateReply::default().embed(lines_summary("Wishlist remove", "wr", &results, &invalid_lines))).await?;
+
+    Ok(())
+}
+
+// ##############################
+// ##############################  WISHLIST LIST
+// ##############################
+
+/// List all series, or cards from a series in your wishlist.
+///
+/// `.wl @user? (sort:<added|name|cards>)? <series>?`
+/// `sort:<added|name|cards>` orders the series.
+#[poise::command(prefix_command, slash_command, ephemeral)]
+pub async fn wl(
+    ctx: Context<'_>,
+    #[description = "Whose wishlist to list, yours by default"]
+    user: Option<User>,
+    #[description = "Series to list the cards of, every series by default"]
+    #[autocomplete = "autocomplete_wishlisted_series"]
+    series: Option<CommandText>,
+    #[description = "Order of the series"]
+    sort: Option<SortChoice>,
+) -> Result<(), Error> 
+{
+    ctx.defer_ephemeral().await?;
+
+    let Some(scope) = command_scope(ctx).await?
+    else {
+        return Ok(());
+    };
+
+    let Some((line_order, series)) = parse_sort_option(series.as_ref().map_or("", |series| series.0.as_str()))
+    else {
+        ctx.reply("Unknown sort order, use `added`, `name` or `cards`.").await?;
+        return Ok(());
+    };
+
+    let user = user.as_ref().unwrap_or(ctx.author());
+    match can_read_wishlist(ctx.data(), ctx.author().id, user.id).await {
+        Ok(true) => (),
+        Ok(false) => {
+            ctx.reply(format!("{}'s wishlist is private.", user.name)).await?;
+            return Ok(());
+        },
+        Err(err) => {
+            ctx.data().logger.log_error(format!(".wl | {err}"));
+            ctx.reply(db_error_reply(&err, "listing the wishlist")).await?;
+            return Ok(());
+        }
+    }
+
+    let order = sort.map(SeriesOrder::from).or(line_order).unwrap_or_default();
+
+    wl_list(ctx, scope, user.id, order, series).await
+}
+
+// `.wl` and `/wl`, paginating the series of the wishlist or the cards of `series`
+async fn wl_list(ctx: Context<'_>, scope: WishlistScope, user_id: UserId, order: SeriesOrder, series: &str) -> Result<(), Error> {
+    let data = ctx.data();
+
+    let series = series.trim();
+    if !series.is_empty() {
+        match wl_cards_pages(data, scope, user_id, &SeriesName::new(series)).await {
+            Ok((pages, total_count)) => paginate(ctx, pages, total_count).await?,
+            Err(err) => {
+                data.logger.log_error(format!(".wl | {err}"));
+                ctx.reply(db_error_reply(&err, "listing the wishlist")).await?;
+            }
+        }
+
+        return Ok(());
+    }
+
+    let (first_page, total_series) = match wl_series_page(data, scope, user_id, order, 0).await {
+        Ok(page) => page,
+        Err(err) => {
+            data.logger.log_error(format!(".wl | {err}"));
+            ctx.reply(db_error_reply(&err, "listing the wishlist")).await?;
+            return Ok(());
+        }
+    };
+
+    // the other series pages are only read when the user browses to them
+    let first_page = &first_page;
+    paginate_with(ctx, total_series.div_ceil(WL_PAGE_SIZE), total_series, |page| async move {
+        if page == 0 {
+            return first_page.clone();
+        }
+
+        wl_series_page(data, scope, user_id, order, page).await
+            .map(|(page, _)| page)
+            .unwrap_or_else(|err| {
+                data.logger.log_error(format!(".wl | {err}"));
+                db_error_reply(&err, "listing the wishlist")
+            })
+    }).await?;
+
+    Ok(())
+}
+
+const WL_PAGE_SIZE: usize = 10;
+
+// A page of the wishlist's series and how many series it has
+async fn wl_series_page(
+    data: &Data, 
+    scope: WishlistScope, 
+    user_id: UserId, 
+    order: SeriesOrder, 
+    page: usize
+) -> WishlistDbResult<(String, usize)> 
+{
+    let query = OverviewQuery { order, skip: page * WL_PAGE_SIZE, limit: Some(WL_PAGE_SIZE), with_cards: false };
+    let overview = data.wishlist_db.get_wishlist_overview(scope, user_id, &query).await?;
+
+    let lines: Vec<String> = overview.series.iter()
+        .map(|series| match series.whole {
+            true => format!("{} (all cards)", series.series),
+            false => format!("{} ({})", series.series, series.cards_count)
+        })
+        .collect();
+
+    Ok((lines.join("\n"), overview.total_series))
+}
+
+async fn wl_cards_pages(data: &Data, scope: WishlistScope, user_id: UserId, series: &SeriesName) -> WishlistDbResult<(Vec<String>, usize)> {
+    let mut wishlisted_cards = data.wishlist_db.get_user_wishlisted_cards(scope, user_id, series).await?;
+    let total_size = wishlisted_cards.len();
+
+    // high priority cards first, keeping the wishlist order within a tier
+    wishlisted_cards.sort_by_key(|card| card.priority);
+
+    let pages = wishlisted_cards.chunks(10)
+        .map(|chunk| {
+            chunk.iter()
+                .map(|card| {
+                    let name = match card.name.is_wildcard() {
+                        true if card.excluded.is_empty() => "*All cards*".to_string(),
+                        true => format!("*All cards but* {}", cards_list(&card.excluded)),
+                        false => card.name.as_str().chars().take(32).collect::<String>()
+                    };
+                    let mut line = match card.priority {
+                        CardPriority::High => format!("⭐ {name}"),
+                        CardPriority::Normal => name,
+                        CardPriority::Low => format!("{name} *(low)*")
+                    };
+
+                    if !card.constraints.is_empty() {
+                        let constraints: Vec<String> = card.constraints.iter().map(ToString::to_string).collect();
+                        line.push_str(&format!(" `{}`", constraints.join(", ")));
+                    }
+                    if let Some(note) = &card.note {
+                        line.push_str(&format!(" - {note}"));
+                    }
+
+                    line
+                })
+                .collect::<Vec<String>>()
+                .join("\n")
+        })
+        .collect();
+
+    Ok((pages, total_size))
+}
+
+// ##############################
+// ##############################  WISHLIST COMPARE
+// ##############################
+
+/// Compares your wishlist with another user's, for trading.
+///
+/// `.wcompare @user`
+/// Shows the series and cards you both wishlist, and the cards one of you owns that the other wants.
+/// Owned cards are known from each user's SOFI `ssl` lookups.
+#[poise::command(prefix_command, rename = "wcompare")]
+pub async fn command_wcompare(
+    ctx: Context<'_>,
+    #[description = "User to compare with"]
+    user: User,
+) -> Result<(), Error> 
+{
+    let Some(scope) = command_scope(ctx).await?
+    else {
+        return Ok(());
+    };
+
+    let author = ctx.author();
+    if user.id == author.id {
+        ctx.reply("Mention another user to compare your wishlist with.").await?;
+        return Ok(());
+    }
+
+    let data = ctx.data();
+    match data.wishlist_db.get_wishlist_privacy(user.id).await {
+        Ok(WishlistPrivacy::Public) => (),
+        Ok(WishlistPrivacy::Private) => {
+            ctx.reply(format!("{}'s wishlist is private.", user.name)).await?;
+            return Ok(());
+        },
+        Err(err) => {
+            data.logger.log_error(format!(".wcompare | {err}"));
+            ctx.reply(db_error_reply(&err, "comparing the wishlists")).await?;
+            return Ok(());
+        }
+    }
+
+    let comparison = match compare_wishlists(data, scope, author.id, user.id).await {
+        Ok(comparison) => comparison,
+        Err(err) => {
+            data.logger.log_error(format!(".wcompare | {err}"));
+            ctx.reply(db_error_reply(&err, "comparing the wishlists")).await?;
+            return Ok(());
+        }
+    };
+
+    if comparison.is_empty() {
+        ctx.reply(format!("Your wishlist has nothing in common with {}'s.", user.name)).await?;
+        return Ok(());
+    }
+
+    let you_own = format!("You own, {} wants", user.name);
+    let they_own = format!("{} owns, you want", user.name);
+    let sections = [
+        ("Both wishlisted", &comparison.shared),
+        (you_own.as_str(), &comparison.first_owns),
+        (they_own.as_str(), &comparison.second_owns)
+    ];
+
+    let mut lines = vec![];
+    for (title, entries) in sections {
+        if entries.is_empty() {
+            continue;
+        }
+
+        lines.push(format!("**{title}**"));
+        lines.extend(entries.iter().map(|entry| match entry.cards.is_empty() {
+            true => format!("`{}` (no cards in common)", entry.series),
+            false => format!("`{}`: {}", entry.series, compared_cards_list(&entry.cards))
+        }));
+    }
+
+    let total = comparison.shared.len() + comparison.first_owns.len() + comparison.second_owns.len();
+    let pages = lines.chunks(10).map(|chunk| chunk.join("\n")).collect();
+
+    paginate(ctx, pages, total).await?;
+
+    Ok(())
+}
+
+async fn compare_wishlists(data: &Data, scope: WishlistScope, first: UserId, second: UserId) -> WishlistDbResult<WishlistComparison> {
+    let query = OverviewQuery { with_cards: true, ..Default::default() };
+
+    let first_overview = data.wishlist_db.get_wishlist_overview(scope, first, &query).await?;
+    let second_overview = data.wishlist_db.get_wishlist_overview(scope, second, &query).await?;
+    let first_owned = data.wishlist_db.get_owned_cards(first).await?;
+    let second_owned = data.wishlist_db.get_owned_cards(second).await?;
+
+    Ok(WishlistComparison::new(&first_overview, &second_overview, &first_owned, &second_owned))
+}
+
+// `Nami`, `Zoro`, *all other cards*
+fn compared_cards_list(cards: &[CardName]) -> String {
+    cards.iter()
+        .map(|card| match card.is_wildcard() {
+            true => "*all other cards*".to_string(),
+            false => format!("`{card}`")
+        })
+        .collect::<Vec<String>>()
+        .join(", ")
+}
+
+// ##############################
+// ##############################  WISHLIST CARD DETAILS
+// ##############################
+
+const NOTE_MAX_LENGTH: usize = 100;
+
+/// Sets a note on a wishlisted card, shown in `.wl`. Leave the note out to clear it.
+#[poise::command(prefix_command, rename = "wnote")]
+pub async fn command_wnote(
+    ctx: Context<'_>,
+    #[description = "<series> || <card name> (|| <note>)?"]
+    #[rest] command: String,
+) -> Result<(), Error> 
+{
+    let Some(scope) = command_scope(ctx).await?
+    else {
+        return Ok(());
+    };
+
+    let Some((series, card, note)) = parse_series_card_value(&command)
+    else {
+        ctx.reply("Incorrect argument format. Check `.help wnote`").await?;
+        return Ok(());
+    };
+
+    if note.is_some_and(|note| note.chars().count() > NOTE_MAX_LENGTH) {
+        ctx.reply(format!("Notes can be up to {NOTE_MAX_LENGTH} characters long.")).await?;
+        return Ok(());
+    }
+
+    let card = CardRef::new(series, card);
+    let reply = match ctx.data().wishlist_db.set_card_note(scope, ctx.author().id, &card, note).await {
+        Ok(()) if note.is_some() => format!("Saved the note of `{}`.", card.card),
+        Ok(()) => format!("Cleared the note of `{}`.", card.card),
+        Err(err) => {
+            ctx.data().logger.log_error(format!(".wnote | {err}"));
+            db_error_reply(&err, "saving the note")
+        }
+    };
+
+    ctx.reply(reply).await?;
+
+    Ok(())
+}
+
+/// Only pings for a wishlisted card when its drops meet all the constraints, like `gen<=100, edition=1`.
+/// Constraints can use `gen` (or `print`) and `edition`. Leave them out to clear them.
+#[poise::command(prefix_command, rename = "wfilter")]
+pub async fn command_wfilter(
+    ctx: Context<'_>,
+    #[description = "<series> || <card name> (|| <gen|edition><op><number> (, <gen|edition><op><number>)*)?"]
     #[rest] command: String,
 ) -> Result<(), Error> 
 {
-    match parse_series_cards(&command) {
-        None => { 
-            ctx.reply("Incorrect argument format. Check `.help wa`").await.unwrap();
-            Ok(())
-        },
-        Some((series, card_names)) => {
-           wa(ctx.serenity_context()
-             , &ctx.http().get_message(ctx.channel_id(), ctx.id().into()).await.unwrap()
-             , ctx.data()
-             , ctx.author().id
-             , series
-             , card_names
-             , None
-            ).await.map(|_| ())
+    let Some(scope) = command_scope(ctx).await?
+    else {
+        return Ok(());
+    };
+
+    let Some((series, card, constraints)) = parse_series_card_value(&command)
+    else {
+        ctx.reply("Incorrect argument format. Check `.help wfilter`").await?;
+        return Ok(());
+    };
+
+    let Some(constraints) = constraints.map_or(Some(vec![]), parse_constraints)
+    else {
+        ctx.reply("Incorrect constraint, use something like `gen<=100` or `edition=1`. Check `.help wfilter`").await?;
+        return Ok(());
+    };
+
+    let card = CardRef::new(series, card);
+    let reply = match ctx.data().wishlist_db.set_card_constraints(scope, ctx.author().id, &card, &constraints).await {
+        Ok(()) if constraints.is_empty() => format!("Drops of `{}` will always ping you.", card.card),
+        Ok(()) => {
+            let constraints: Vec<String> = constraints.iter().map(ToString::to_string).collect();
+            format!("Drops of `{}` will only ping you for `{}`.", card.card, constraints.join(", "))
+        },
+        Err(err) => {
+            ctx.data().logger.log_error(format!(".wfilter | {err}"));
+            db_error_reply(&err, "saving the constraints")
+        }
+    };
+
+    ctx.reply(reply).await?;
+
+    Ok(())
+}
+
+// ##############################
+// ##############################  WISHLIST HISTORY
+// ##############################
+
+const HISTORY_LIMIT: usize = 50;
+
+/// Reverts your last wishlist change.
+#[poise::command(prefix_command, rename = "wundo")]
+pub async fn command_wundo(
+    ctx: Context<'_>,
+) -> Result<(), Error> 
+{
+    let Some(scope) = command_scope(ctx).await?
+    else {
+        return Ok(());
+    };
+
+    let data = ctx.data();
+
+    let reply = match undo_last_change(data, scope, ctx.author().id).await {
+        Ok(None) => "Nothing to undo.".to_string(),
+        Ok(Some((undo, Either::Left(added)))) => {
+            let mut reply = format!("Undone! Added {} card(s) from `{}` back to your wishlist.", undo.entry.cards.len(), undo.entry.series);
+            if !added.already_wishlisted.is_empty() {
+                reply.push_str(&format!(" {} already on your wishlist.", cards_were(&added.already_wishlisted)));
+            }
+            reply
+        },
+        Ok(Some((undo, Either::Right(removed)))) => {
+            let mut reply = format!("Undone! Removed {} card(s) from `{}` from your wishlist.", undo.entry.cards.len(), undo.entry.series);
+            if !removed.not_found.is_empty() {
+                reply.push_str(&format!(" {} no longer on your wishlist.", cards_were(&removed.not_found)));
+            }
+            reply
+        },
+        Err(err) => {
+            data.logger.log_error(format!(".wundo | {err}"));
+            db_error_reply(&err, "undoing your last change")
+        }
+    };
+
+    ctx.reply(reply).await?;
+
+    Ok(())
+}
+
+// Reverts the user's last undoable change, returning the undo as recorded with what it did
+pub(crate) async fn undo_last_change(
+    data: &Data, 
+    scope: WishlistScope,
+    user_id: UserId
+) -> WishlistDbResult<Option<(WishlistChange, Either<AddedCards, RemovedCards>)>> 
+{
+    let Some(record) = data.wishlist_db.get_last_undoable_change(scope, user_id).await?
+    else {
+        return Ok(None);
+    };
+
+    let mut undo = WishlistChange::undo(&record);
+    let outcome = match undo.action {
+        HistoryAction::Added => Either::Left(restore_cards(data, scope, user_id, &undo.entry, &undo.details, &undo.wildcard).await?),
+        HistoryAction::Removed => Either::Right(unlist_cards(data, scope, user_id, &undo.entry, &undo.wildcard).await?)
+    };
+
+    // unlike other changes, a lost undo would let the same change be reverted twice,
+    // so it is recorded even when no card changed
+    (undo.entry.cards, undo.wildcard) = match &outcome {
+        Either::Left(added) => {
+            let included_only = added.included.iter()
+                .filter(|card| !added.added.iter().any(|added| added.search_term() == card.search_term()));
+            (added.added.iter().chain(included_only).cloned().collect(), added.included.clone())
+        },
+        Either::Right(removed) => (removed.removed.clone(), removed.excluded.clone())
+    };
+    undo.details = vec![];
+    data.wishlist_db.record_change(scope, user_id, &undo).await?;
+
+    Ok(Some((undo, outcome)))
+}
+
+/// Lists your recent wishlist changes.
+#[poise::command(prefix_command, rename = "whistory")]
+pub async fn command_whistory(
+    ctx: Context<'_>,
+) -> Result<(), Error> 
+{
+    let Some(scope) = command_scope(ctx).await?
+    else {
+        return Ok(());
+    };
+
+    let history = match ctx.data().wishlist_db.get_user_history(scope, ctx.author().id, HISTORY_LIMIT).await {
+        Ok(history) => history,
+        Err(err) => {
+            ctx.data().logger.log_error(format!(".whistory | {err}"));
+            ctx.reply(db_error_reply(&err, "listing your history")).await?;
+            return Ok(());
+        }
+    };
+
+    let pages = history.chunks(10)
+        .map(|chunk| chunk.iter().map(history_line).collect::<Vec<String>>().join("\n"))
+        .collect();
+
+    paginate(ctx, pages, history.len()).await?;
+
+    Ok(())
+}
+
+fn history_line(record: &HistoryRecord) -> String {
+    let change = &record.change;
+    let sign = match change.action {
+        HistoryAction::Added => "+",
+        HistoryAction::Removed => "-"
+    };
+    let cards: Vec<&str> = change.entry.cards.iter()
+        .map(CardName::as_str)
+        .collect();
+
+    format!("<t:{}:R> {sign} `{}`: {} ({})", record.timestamp.timestamp(), change.entry.series, cards.join(", "), change.source.as_str())
+}
+
+// `Zoro` was, or `Zoro`, `Nami` were
+fn cards_were(cards: &[CardName]) -> String {
+    let verb = if cards.len() == 1 { "was" } else { "were" };
+
+    format!("{} {verb}", cards_list(cards))
+}
+
+// `Zoro`, `Nami`
+fn cards_list(cards: &[CardName]) -> String {
+    cards.iter()
+        .map(|card| format!("`{card}`"))
+        .collect::<Vec<String>>()
+        .join(", ")
+}
+
+// Adds the cards of an undone removal back with the priority, note, constraints and exclusions
+// they had, cards wishlisted again since then keep their current ones. Cards that were only
+// left out of the wildcard card go back into it instead.
+async fn restore_cards(
+    data: &Data, 
+    scope: WishlistScope,
+    user_id: UserId, 
+    entry: &WishlistEntry,
+    details: &[WishlistedCard],
+    wildcard: &[CardName]
+) -> WishlistDbResult<AddedCards> 
+{
+    let (included, listed): (Vec<CardName>, Vec<CardName>) = entry.cards.iter().cloned()
+        .partition(|card| {
+            let card_search = card.search_term();
+            wildcard.iter().any(|wildcard| wildcard.search_term() == card_search)
+                && !details.iter().any(|details| details.name.search_term() == card_search)
+        });
+
+    let mut res = match listed.is_empty() {
+        true => AddedCards::default(),
+        false => {
+            let listed = WishlistEntry { series: entry.series.clone(), cards: listed };
+            data.wishlist_db.add_all_to_wishlist(scope, user_id, &listed, CardPriority::default(), false).await?
+        }
+    };
+
+    for card in &res.added {
+        let card_search = card.search_term();
+        let Some(details) = details.iter().find(|details| details.name.search_term() == card_search)
+        else {
+            continue;
+        };
+
+        let card_ref = CardRef { series: entry.series.clone(), card: card.clone() };
+        if details.priority != CardPriority::default() {
+            let card_entry = WishlistEntry { series: entry.series.clone(), cards: vec![card.clone()] };
+            data.wishlist_db.set_cards_priority(scope, user_id, &card_entry, details.priority).await?;
+        }
+        if details.note.is_some() {
+            data.wishlist_db.set_card_note(scope, user_id, &card_ref, details.note.as_deref()).await?;
+        }
+        if !details.constraints.is_empty() {
+            data.wishlist_db.set_card_constraints(scope, user_id, &card_ref, &details.constraints).await?;
+        }
+        if !details.excluded.is_empty() {
+            data.wishlist_db.set_wildcard_exclusions(scope, user_id, &entry.series, &details.excluded).await?;
+        }
+    }
+
+    if !included.is_empty() {
+        let included = WishlistEntry { series: entry.series.clone(), cards: included };
+        res.included.extend(data.wishlist_db.include_in_wildcard(scope, user_id, &included).await?);
+    }
+
+    Ok(res)
+}
+
+// Removes the cards of an undone addition, the ones it took back into the wildcard card are left out of it again
+async fn unlist_cards(
+    data: &Data, 
+    scope: WishlistScope,
+    user_id: UserId, 
+    entry: &WishlistEntry,
+    wildcard: &[CardName]
+) -> WishlistDbResult<RemovedCards> 
+{
+    let (excluded, listed): (Vec<CardName>, Vec<CardName>) = entry.cards.iter().cloned()
+        .partition(|card| wildcard.iter().any(|wildcard| wildcard.search_term() == card.search_term()));
+
+    let mut res = RemovedCards::default();
+    for (cards, exclude) in [(listed, false), (excluded, true)] {
+        if cards.is_empty() {
+            continue;
+        }
+
+        let removed = data.wishlist_db.remove_all_from_wishlist(scope, user_id, &WishlistEntry { series: entry.series.clone(), cards }, exclude).await?;
+        res.removed.extend(removed.removed);
+        res.not_found.extend(removed.not_found);
+        res.excluded.extend(removed.excluded);
+        res.details.extend(removed.details);
+        res.left = removed.left;
+    }
+
+    Ok(res)
+}
+
+// The change already went through, so a failure to record it is only logged (by the database)
+async fn record_change(data: &Data, scope: WishlistScope, user_id: UserId, change: WishlistChange) {
+    if change.entry.cards.is_empty() {
+        return;
+    }
+
+    let _ = data.wishlist_db.record_change(scope, user_id, &change).await;
+}
+
+// ##############################
+// ##############################  FUZZY MATCHING
+// ##############################
+
+const DEFAULT_FUZZY_THRESHOLD: u8 = 85;
+const MIN_FUZZY_THRESHOLD: u8 = 50;
+
+/// Shows or changes whether drops of cards resembling your wishlisted ones ping you as possible matches.
+/// The optional threshold is how similar, in percent, the names must be (default 85).
+#[poise::command(prefix_command, rename = "wfuzzy")]
+pub async fn command_wfuzzy(
+    ctx: Context<'_>,
+    #[description = "on (<threshold>)? | off"]
+    mode: Option<String>,
+    #[description = "50 - 100"]
+    threshold: Option<u8>,
+) -> Result<(), Error> 
+{
+    let user_id = ctx.author().id;
+
+    let threshold = match (mode.as_deref().map(str::to_lowercase).as_deref(), threshold) {
+        (None, _) => {
+            let reply = match ctx.data().wishlist_db.get_fuzzy_threshold(user_id).await {
+                Ok(Some(threshold)) => format!("Fuzzy matching is on, with a {threshold}% threshold."),
+                Ok(None) => "Fuzzy matching is off.".to_string(),
+                Err(err) => {
+                    ctx.data().logger.log_error(format!(".wfuzzy | {err}"));
+                    db_error_reply(&err, "reading your settings")
+                }
+            };
+
+            ctx.reply(reply).await?;
+            return Ok(());
+        },
+        (Some("on"), threshold) => Some(threshold.unwrap_or(DEFAULT_FUZZY_THRESHOLD)),
+        (Some("off"), None) => None,
+        _ => {
+            ctx.reply("Incorrect argument format. Check `.help wfuzzy`").await?;
+            return Ok(());
+        }
+    };
+
+    if threshold.is_some_and(|threshold| !(MIN_FUZZY_THRESHOLD..=100).contains(&threshold)) {
+        ctx.reply(format!("The threshold must be between {MIN_FUZZY_THRESHOLD} and 100.")).await?;
+        return Ok(());
+    }
+
+    let reply = match ctx.data().wishlist_db.set_fuzzy_threshold(user_id, threshold).await {
+        Ok(()) => match threshold {
+            Some(threshold) => format!("Fuzzy matching is now on, with a {threshold}% threshold."),
+            None => "Fuzzy matching is now off.".to_string()
+        },
+        Err(err) => {
+            ctx.data().logger.log_error(format!(".wfuzzy | {err}"));
+            db_error_reply(&err, "changing your settings")
+        }
+    };
+
+    ctx.reply(reply).await?;
+
+    Ok(())
+}
+
+// ##############################
+// ##############################  SERIES ALIASES
+// ##############################
+
+/// Lists, adds or removes series aliases, which every wishlist and drop treats as the series they stand for.
+/// Adding an alias also merges the wishlisted series named after it into the canonical one.
+#[poise::command(prefix_command, rename = "walias", owners_only)]
+pub async fn command_walias(
+    ctx: Context<'_>,
+    #[description = "add <alias> || <series> | remove <alias>"]
+    action: Option<String>,
+    #[rest] command: Option<String>,
+) -> Result<(), Error> 
+{
+    let reply = match (action.as_deref().map(str::to_lowercase).as_deref(), command.as_deref()) {
+        (None, _) => {
+            let aliases = match ctx.data().wishlist_db.get_series_aliases().await {
+                Ok(aliases) => aliases,
+                Err(err) => {
+                    ctx.data().logger.log_error(format!(".walias | {err}"));
+                    ctx.reply(db_error_reply(&err, "listing the aliases")).await?;
+                    return Ok(());
+                }
+            };
+
+            let pages = aliases.chunks(10)
+                .map(|chunk| chunk.iter()
+                    .map(|(alias, canonical)| format!("`{alias}` → {canonical}"))
+                    .collect::<Vec<String>>()
+                    .join("\n")
+                )
+                .collect();
+
+            paginate(ctx, pages, aliases.len()).await?;
+            return Ok(());
+        },
+        (Some("add"), Some(command)) => match parse_series_alias(command) {
+            Some((alias, canonical)) => {
+                let (alias, canonical) = (SeriesName::new(alias), SeriesName::new(canonical));
+                match ctx.data().wishlist_db.add_series_alias(&alias, &canonical).await {
+                    Ok(()) => format!("`{alias}` is now an alias of `{canonical}`."),
+                    Err(err) => {
+                        ctx.data().logger.log_error(format!(".walias | {err}"));
+                        db_error_reply(&err, "adding the alias")
+                    }
+                }
+            },
+            None => "Incorrect argument format. Check `.help walias`".to_string()
+        },
+        (Some("remove"), Some(alias)) => {
+            let alias = SeriesName::new(alias.trim());
+            match ctx.data().wishlist_db.remove_series_alias(&alias).await {
+                Ok(()) => format!("`{alias}` is no longer an alias."),
+                Err(WishlistDbError::NotFound(_)) => format!("`{alias}` is not an alias."),
+                Err(err) => {
+                    ctx.data().logger.log_error(format!(".walias | {err}"));
+                    db_error_reply(&err, "removing the alias")
+                }
+            }
+        },
+        _ => "Incorrect argument format. Check `.help walias`".to_string()
+    };
+
+    ctx.reply(reply).await?;
+
+    Ok(())
+}
+
+// ##############################
+// ##############################  CARD CATALOG
+// ##############################
+
+/// Shows the series and cards seen in drops and SOFI lookups, and how much of a series you wishlisted.
+///
+/// `.wcatalog <series>?`
+/// Without a series, shows the size of the catalog.
+/// Looking a series up with SOFI's `ssl` teaches the bot all of its cards.
+#[poise::command(prefix_command, rename = "wcatalog")]
+pub async fn command_wcatalog(
+    ctx: Context<'_>,
+    #[description = "Series to show the known cards of"]
+    #[rest] series: Option<String>,
+) -> Result<(), Error> 
+{
+    let series = series.as_deref().map(str::trim).unwrap_or_default();
+    if series.is_empty() {
+        let reply = match ctx.data().wishlist_db.get_catalog_stats().await {
+            Ok(stats) => catalog_stats_reply(&stats),
+            Err(err) => {
+                ctx.data().logger.log_error(format!(".wcatalog | {err}"));
+                db_error_reply(&err, "reading the catalog")
+            }
+        };
+        ctx.reply(reply).await?;
+        return Ok(());
+    }
+
+    let Some(scope) = command_scope(ctx).await?
+    else {
+        return Ok(());
+    };
+
+    let series = SeriesName::new(series);
+    let res = match ctx.data().wishlist_db.get_catalog_series(&series).await {
+        Ok(Some(catalog_series)) => ctx.data().wishlist_db
+            .get_user_wishlisted_cards(scope, ctx.author().id, &catalog_series.series).await
+            .map(|wishlisted| Some((catalog_series, wishlisted))),
+        Ok(None) => Ok(None),
+        Err(err) => Err(err)
+    };
+
+    let (catalog_series, wishlisted) = match res {
+        Ok(Some(found)) => found,
+        Ok(None) => {
+            ctx.reply(format!("`{series}` was never seen yet.")).await?;
+            return Ok(());
+        },
+        Err(err) => {
+            ctx.data().logger.log_error(format!(".wcatalog | {err}"));
+            ctx.reply(db_error_reply(&err, "reading the catalog")).await?;
+            return Ok(());
+        }
+    };
+
+    let header = catalog_series_header(&catalog_series, &wishlisted);
+    let pages = match catalog_series.cards.is_empty() {
+        true => vec![header],
+        false => catalog_series.cards.chunks(10)
+            .map(|chunk| {
+                let cards: Vec<String> = chunk.iter()
+                    .map(|card| match wishlisted.iter().any(|wishlisted| wishlisted.covers(&card.name)) {
+                        true => format!("✅ {}", card.name),
+                        false => format!("▫️ {}", card.name)
+                    })
+                    .collect();
+                format!("{header}\n\n{}", cards.join("\n"))
+            })
+            .collect()
+    };
+
+    paginate(ctx, pages, catalog_series.cards.len()).await?;
+
+    Ok(())
+}
+
+fn catalog_stats_reply(stats: &CatalogStats) -> String {
+    match stats.first_seen {
+        Some(first_seen) => format!(
+            "The catalog knows {} series and {} card(s), seen since <t:{}:D>.", 
+            stats.series_count, stats.cards_count, first_seen.timestamp()
+        ),
+        None => "The catalog is empty, it fills up with the drops and SOFI lookups the bot sees.".to_string()
+    }
+}
+
+// When the series was seen and how much of it is wishlisted, 2/3 cards (66%)
+fn catalog_series_header(catalog_series: &CatalogSeries, wishlisted: &[WishlistedCard]) -> String {
+    let seen = format!(
+        "`{}` first seen <t:{}:D>, last seen <t:{}:R>.", 
+        catalog_series.series, catalog_series.first_seen.timestamp(), catalog_series.last_seen.timestamp()
+    );
+
+    let known_count = catalog_series.cards.len();
+    if known_count == 0 {
+        return format!("{seen}\nNo cards of this series were seen yet.");
+    }
+
+    let wishlisted_count = catalog_series.wishlisted_count(wishlisted);
+    format!(
+        "{seen}\nYou wishlisted {wishlisted_count}/{known_count} known card(s) ({}%).", 
+        wishlisted_count * 100 / known_count
+    )
+}
+
+// ##############################
+// ##############################  WISHLIST EXPORT
+// ##############################
+
+/// Sends your whole wishlist as a JSON (default) or CSV file.
+/// Other users' wishlists can be exported unless they made them private with `.wprivacy`.
+#[poise::command(prefix_command, rename = "wexport")]
+pub async fn command_wexport(
+    ctx: Context<'_>,
+    #[description = "Target user"]
+    user: Option<User>,
+    #[description = "json | csv"]
+    format: Option<String>,
+) -> Result<(), Error> 
+{
+    let Some(scope) = command_scope(ctx).await?
+    else {
+        return Ok(());
+    };
+
+    let format = format.as_deref().map(str::to_lowercase);
+    let extension = match format.as_deref() {
+        None | Some("json") => "json",
+        Some("csv") => "csv",
+        Some(_) => {
+            ctx.reply("Incorrect argument format. Check `.help wexport`").await?;
+            return Ok(());
+        }
+    };
+
+    let user = user.as_ref().unwrap_or(ctx.author());
+    let db = &ctx.data().wishlist_db;
+
+    match can_read_wishlist(ctx.data(), ctx.author().id, user.id).await {
+        Ok(true) => (),
+        Ok(false) => {
+            ctx.reply(format!("{}'s wishlist is private.", user.name)).await?;
+            return Ok(());
         },
+        Err(err) => {
+            ctx.data().logger.log_error(format!(".wexport | {err}"));
+            ctx.reply(db_error_reply(&err, "exporting the wishlist")).await?;
+            return Ok(());
+        }
+    }
+
+    let query = OverviewQuery { with_cards: true, ..Default::default() };
+    let res = db.get_wishlist_overview(scope, user.id, &query).await
+        .map(|overview| WishlistExport::new(user.id, scope, Utc::now(), &overview));
+
+    let export = match res {
+        Ok(export) => export,
+        Err(err) => {
+            ctx.data().logger.log_error(format!(".wexport | {err}"));
+            ctx.reply(db_error_reply(&err, "exporting the wishlist")).await?;
+            return Ok(());
+        }
+    };
+
+    if export.series.is_empty() {
+        ctx.reply("Nothing to export, the wishlist is empty.").await?;
+        return Ok(());
     }
+
+    let content = match extension {
+        "csv" => export.to_csv().map_err(Error::from),
+        _ => export.to_json().map_err(Error::from)
+    };
+    let content = match content {
+        Ok(content) => content,
+        Err(err) => {
+            ctx.data().logger.log_error(format!(".wexport | {err}"));
+            ctx.reply("Something went wrong while exporting the wishlist.").await?;
+            return Ok(());
+        }
+    };
+
+    let reply = CreateReply::default()
+        .content(format!("{} series, {} cards.", export.series.len(), export.cards_count()))
+        .attachment(CreateAttachment::bytes(content.into_bytes(), format!("wishlist_{}.{extension}", user.id)));
+    ctx.send(reply).await?;
+
+    Ok(())
 }
 
-pub async fn wa (
-    ctx: &serenity::Context, 
-    msg: &Message, 
-    data: &Data, 
-    user_id: UserId, 
-    series: &str, 
-    card_names: Vec<&str>,
-    prev_response: Option<(Message, i32)>
-) -> Result<(Message, i32), Error> 
+/// Shows or changes whether other users can list, export or compare your wishlists.
+#[poise::command(prefix_command, rename = "wprivacy")]
+pub async fn command_wprivacy(
+    ctx: Context<'_>,
+    #[description = "public | private"]
+    privacy: Option<String>,
+) -> Result<(), Error> 
 {
-    let mut message = MessageBuilder::new();
+    let user_id = ctx.author().id;
+
+    let Some(privacy) = privacy
+    else {
+        let reply = match ctx.data().wishlist_db.get_wishlist_privacy(user_id).await {
+            Ok(privacy) => format!("Your wishlists are {}.", privacy.as_str()),
+            Err(err) => {
+                ctx.data().logger.log_error(format!(".wprivacy | {err}"));
+                db_error_reply(&err, "reading your settings")
+            }
+        };
 
-    let res = 
-        data.wishlist_db.add_all_to_wishlist(&user_id.to_string(), series, card_names).await;
+        ctx.reply(reply).await?;
+        return Ok(());
+    };
 
-    match res {
-        Ok(added_cards_count) => {
-            match prev_response {
-                Some((mut prev_msg, prev_added_count)) => {
-                    let total = added_cards_count + prev_added_count;
-                    message.push(format!("Added {total} card(s) from `{series}` to your wishlist!"));
-                    prev_msg.edit(ctx, EditMessage::new().content(message.build())).await.unwrap();
-                    return Ok((prev_msg, total));
-                },
-                None => {
-                    message.push(format!("Added {added_cards_count} card(s) from `{series}` to your wishlist!"));
-                    let response_msg = msg.reply_ping(ctx, message.build()).await.unwrap();
-                    return Ok((response_msg, added_cards_count));
-                }
-            }
-        },
-        Err(err) =>  {
-            data.logger.log_error(format!(".wa | {}", err.to_string()));
-            message.push(format!("Something went wrong adding cards to your wishlist."));
-            return Err(err);
+    let Some(privacy) = WishlistPrivacy::parse(&privacy)
+    else {
+        ctx.reply("Incorrect argument format. Check `.help wprivacy`").await?;
+        return Ok(());
+    };
+
+    let reply = match ctx.data().wishlist_db.set_wishlist_privacy(user_id, privacy).await {
+        Ok(()) => format!("Your wishlists are now {}.", privacy.as_str()),
+        Err(err) => {
+            ctx.data().logger.log_error(format!(".wprivacy | {err}"));
+            db_error_reply(&err, "changing your settings")
         }
     };
+
+    ctx.reply(reply).await?;
+
+    Ok(())
 }
 
 // ##############################
-// ##############################  WISHLIST REMOVE
+// ##############################  WISHLIST IMPORT
 // ##############################
 
-/// Removes all selected cards from a series OR an entire series (if no cards are specified) from your wishlist.
-/// Will only remove cards already in your wishlist.
-#[poise::command(prefix_command, rename = "wr")]
-pub async fn command_wr(
+// Largest attachment read by an import, well above the size of an export of any real wishlist
+const IMPORT_MAX_FILE_SIZE: u32 = 1024 * 1024;
+const IMPORT_BATCH_SIZE: usize = 50;
+
+/// Imports a wishlist file from `.wexport`, attached to the command message.
+/// Shows a preview to confirm first. `merge` (default) only adds the missing cards,
+/// `replace` empties your wishlist first.
+#[poise::command(prefix_command, rename = "wimport")]
+pub async fn command_wimport(
     ctx: Context<'_>,
-    #[description = "<series> ( || <card name> (, <card name>)* )?"]
-    #[rest] command: String,
+    #[description = "merge | replace"]
+    mode: Option<String>,
 ) -> Result<(), Error> 
 {
-    if !command.contains("||") {
-        // Delete entire series
-        wr_series( ctx.serenity_context()
-                 , &ctx.http().get_message(ctx.channel_id(), ctx.id().into()).await.unwrap()
-                 , ctx.data()
-                 , ctx.author().id
-                 , command.as_str()
-                 ).await;       
-    } else {
-        // Delete selected cards from series
-        match parse_series_cards(&command) {
-            None => { ctx.reply("Incorrect argument format. Check `.help wr`").await.unwrap(); },
-            Some((series, card_names)) => {
-                wr_cards( ctx.serenity_context()
-                        , Either::Left(&ctx.http().get_message(ctx.channel_id(), ctx.id().into()).await.unwrap())
-                        , ctx.data()
-                        , ctx.author().id
-                        , series
-                        , card_names
-                        , None
-                        ).await.unwrap();
-            }
+    let Some(scope) = command_scope(ctx).await?
+    else {
+        return Ok(());
+    };
+
+    let mode = match mode.as_deref().map(ImportMode::parse) {
+        None => ImportMode::default(),
+        Some(Some(mode)) => mode,
+        Some(None) => {
+            ctx.reply("Incorrect argument format. Check `.help wimport`").await?;
+            return Ok(());
         }
+    };
+
+    let attachment = match ctx {
+        poise::Context::Prefix(prefix_ctx) => prefix_ctx.msg.attachments.first().cloned(),
+        poise::Context::Application(_) => None
+    };
+    let Some(attachment) = attachment
+    else {
+        ctx.reply("Attach the JSON or CSV file from `.wexport` to the command message.").await?;
+        return Ok(());
+    };
+
+    if attachment.size > IMPORT_MAX_FILE_SIZE {
+        ctx.reply(format!("The file is too large, imports are limited to {} KB.", IMPORT_MAX_FILE_SIZE / 1024)).await?;
+        return Ok(());
     }
 
-    Ok(())
-}
+    let content = match attachment.download().await.map(String::from_utf8) {
+        Ok(Ok(content)) => content,
+        Ok(Err(_)) => {
+            ctx.reply("The file is not a text file.").await?;
+            return Ok(());
+        },
+        Err(err) => {
+            ctx.data().logger.log_error(format!(".wimport | {err}"));
+            ctx.reply("Could not download the file, try again later.").await?;
+            return Ok(());
+        }
+    };
 
-pub async fn wr_cards (
-    ctx: &serenity::Context, 
-    user_msg: Either<&Message, ChannelId>, 
-    data: &Data, 
-    user_id: UserId, 
-    series: &str, 
-    card_names: Vec<&str>,
-    prev_response: Option<(Message, i32)>
-) -> Result<(Message, i32), Error> 
-{
-    let mut message = MessageBuilder::new();
+    let series = match attachment.filename.to_lowercase().ends_with(".csv") {
+        true => read_csv(&content),
+        false => read_json(&content)
+    };
+    let series = match series {
+        Ok(series) => series,
+        Err(err) => {
+            ctx.reply(format!("Could not import the file, {err}.")).await?;
+            return Ok(());
+        }
+    };
 
-    let res = 
-        data.wishlist_db.remove_all_from_wishlist(&user_id.to_string(), series, card_names).await; 
+    let user_id = ctx.author().id;
+    let data = ctx.data();
+    let query = OverviewQuery { with_cards: true, ..Default::default() };
+    let current = match data.wishlist_db.get_wishlist_overview(scope, user_id, &query).await {
+        Ok(current) => current,
+        Err(err) => {
+            data.logger.log_error(format!(".wimport | {err}"));
+            ctx.reply(db_error_reply(&err, "reading your wishlist")).await?;
+            return Ok(());
+        }
+    };
 
-    match res {
-        Ok((amount_removed, amount_left)) => {
-            match prev_response {
-                Some((mut prev_msg, prev_removed_count)) => {
-                    let total = prev_removed_count + amount_removed;
+    let plan = match ImportPlan::new(&series, &current, mode, IMPORT_BATCH_SIZE) {
+        Ok(plan) => plan,
+        Err(err) => {
+            ctx.reply(format!("Could not import the file, {err}.")).await?;
+            return Ok(());
+        }
+    };
 
-                    message.push(format!("Removed {total} card(s) from your wishlist!"));
-                    prev_msg.edit(ctx, EditMessage::new().content(message.build())).await.unwrap();
+    if plan.cards_to_add() == 0 && plan.replaced.is_empty() {
+        ctx.reply(format!("Nothing to import, all {} card(s) are already in your wishlist.", plan.duplicates)).await?;
+        return Ok(());
+    }
 
-                    return Ok((prev_msg, total));
-                },
-                None => {
-                    if amount_left > 0 {
-                        message.push(format!("Removed {amount_removed} card(s) from your wishlist! ({amount_left} card(s) left)"));
-                    } else {
-                        message.push(format!("Removed {amount_removed} card(s) from your wishlist! No more cards left!"));
-                    }
+    let ctx_id = ctx.id();
+    let confirm_button_id = format!("{ctx_id}confirm");
+    let cancel_button_id = format!("{ctx_id}cancel");
+
+    let mut preview = serenity::CreateEmbed::new()
+        .title(format!("Import ({})", mode.as_str()))
+        .field("Series", plan.series_count.to_string(), true)
+        .field("Cards to add", plan.cards_to_add().to_string(), true)
+        .field("Duplicates skipped", plan.duplicates.to_string(), true);
+    if mode == ImportMode::Replace {
+        preview = preview.description(format!(
+            "Your wishlist will be emptied first, removing {} series with {} card(s).",
+            plan.replaced.len(), plan.cards_to_remove()
+        ));
+    }
 
-                    let response = match user_msg {
-                        Either::Left(msg) => msg.reply_ping(ctx, message.build()).await.unwrap(),
-                        Either::Right(channel_id) => {
-                            message.user(user_id);
-                            let builder = CreateMessage::new().content(message.build());
-                            ctx.http.send_message(channel_id, vec![], &builder).await.unwrap()
-                        }
-                    };
+    let reply = CreateReply::default()
+        .embed(preview)
+        .components(vec![serenity::CreateActionRow::Buttons(vec![
+            serenity::CreateButton::new(&confirm_button_id).label("Confirm").style(serenity::ButtonStyle::Success),
+            serenity::CreateButton::new(&cancel_button_id).label("Cancel").style(serenity::ButtonStyle::Danger),
+        ])]);
+    let handle = ctx.send(reply).await?;
 
-                    return Ok((response, amount_removed));
-                }
-            }
+    let press = ComponentInteractionCollector::new(ctx)
+        .filter(move |press| press.data.custom_id.starts_with(&ctx_id.to_string()))
+        .author_id(user_id)
+        .timeout(std::time::Duration::from_secs(60))
+        .await;
+
+    let press = match press {
+        Some(press) if press.data.custom_id == confirm_button_id => press,
+        Some(press) => {
+            update_pressed_message(ctx, &press, "Import cancelled.".to_string()).await?;
+            return Ok(());
         },
-        Err(err) => {
-            data.logger.log_error(err.to_string());
-            message.push(format!("Something went wrong removing cards from your wishlist."));
-            match user_msg {
-                Either::Left(msg) => msg.reply_ping(ctx, message.build()).await.unwrap(),
-                Either::Right(channel_id) => {
-                    message.push(" ");
-                    message.user(user_id);
-                    let builder = CreateMessage::new().content(message.build());
-                    ctx.http.send_message(channel_id, vec![], &builder).await.unwrap()
-                }
-            };
+        None => {
+            handle.edit(ctx, CreateReply::default().content("Import cancelled.").components(vec![])).await?;
+            return Ok(());
+        }
+    };
 
-            return Err(err);
+    let summary = match apply_import(data, scope, user_id, &plan).await {
+        Ok((added, skipped)) => format!("Imported {added} card(s) into your wishlist, skipped {skipped}."),
+        Err((added, err)) => {
+            data.logger.log_error(format!(".wimport | {err}"));
+            format!("{} Imported {added} card(s) before stopping.", db_error_reply(&err, "importing the wishlist"))
         }
     };
+    update_pressed_message(ctx, &press, summary).await?;
+
+    Ok(())
 }
 
-pub async fn wr_series(
-    ctx: &serenity::Context, 
-    msg: &Message, 
+// Applies the plan and returns the added and skipped cards counts, or the cards added before an error
+async fn apply_import(
     data: &Data, 
+    scope: WishlistScope, 
     user_id: UserId, 
-    series: &str
-) {
-    let res = 
-    data.wishlist_db.remove_series_from_wishlist(&user_id.to_string(), series).await; 
-    
-    let mut message = MessageBuilder::new();
-    match res {
-        Ok(amount) => message.push(format!("Removed series `{series}` with {amount} card(s) from your wishlist!")),
-        Err(err) => {
-            data.logger.log_error(err.to_string());
-            message.push(format!("Something went wrong removing a series from your wishlist."))
+    plan: &ImportPlan
+) -> Result<(usize, usize), (usize, WishlistDbError)> 
+{
+    for entry in &plan.replaced {
+        remove_series(data, scope, user_id, &entry.series, ChangeSource::Import).await.map_err(|err| (0, err))?;
+    }
+
+    let mut added_count = 0;
+    let mut skipped_count = plan.duplicates;
+    for batch in &plan.batches {
+        let added = data.wishlist_db.add_all_to_wishlist(scope, user_id, &batch.entry, batch.priority, false).await.map_err(|err| (added_count, err))?;
+        added_count += added.added.len();
+        skipped_count += added.already_wishlisted.len();
+
+        let added = WishlistEntry { series: batch.entry.series.clone(), cards: added.added };
+        record_change(data, scope, user_id, WishlistChange::new(HistoryAction::Added, added, ChangeSource::Import)).await;
+    }
+
+    for details in &plan.details {
+        if details.note.is_some() {
+            data.wishlist_db.set_card_note(scope, user_id, &details.card, details.note.as_deref()).await.map_err(|err| (added_count, err))?;
         }
-    };
+        if !details.constraints.is_empty() {
+            data.wishlist_db.set_card_constraints(scope, user_id, &details.card, &details.constraints).await.map_err(|err| (added_count, err))?;
+        }
+    }
+
+    Ok((added_count, skipped_count))
+}
 
-    msg.reply_ping(ctx, message.build()).await.unwrap();
+async fn update_pressed_message(ctx: Context<'_>, press: &serenity::ComponentInteraction, content: String) -> Result<(), serenity::Error> {
+    press
+        .create_response(
+            ctx.serenity_context(),
+            serenity::CreateInteractionResponse::UpdateMessage(
+                serenity::CreateInteractionResponseMessage::new()
+                    .content(content)
+                    .embeds(vec![])
+                    .components(vec![])
+            )
+        )
+        .await
 }
 
 // ##############################
-// ##############################  WISHLIST LIST
+// ##############################  WISHLIST SCOPE
 // ##############################
 
-/// List all series, or cards from a series in your wishlist.
-#[poise::command(prefix_command)]
-pub async fn wl(
+/// Shows or changes whether this server uses the shared global wishlists or its own isolated ones.
+#[poise::command(prefix_command, rename = "wscope", guild_only, required_permissions = "MANAGE_GUILD")]
+pub async fn command_wscope(
     ctx: Context<'_>,
-    #[description = "Target user"]
-    user: Option<User>,
-    #[description = "Series name"]
-    #[rest] content: Option<String>,
+    #[description = "global | isolated"]
+    mode: Option<String>,
 ) -> Result<(), Error> 
 {
-    let user_id = user.map(|user| user.id).unwrap_or(ctx.author().id);
+    let guild_id = ctx.guild_id().unwrap();
+
+    let Some(mode) = mode
+    else {
+        let reply = match ctx.data().wishlist_db.get_guild_scope_mode(guild_id).await {
+            Ok(mode) => format!("This server uses {} wishlists.", mode.as_str()),
+            Err(err) => {
+                ctx.data().logger.log_error(format!(".wscope | {err}"));
+                db_error_reply(&err, "reading this server's settings")
+            }
+        };
 
-    let (pages, total_count) = match content {
-        None => {
-            let wishlisted_series = ctx.data().wishlist_db.get_user_wishlisted_series(&user_id.to_string()).await;
-            let total_size = wishlisted_series.len();
+        ctx.reply(reply).await?;
+        return Ok(());
+    };
+
+    let Some(mode) = ScopeMode::parse(&mode)
+    else {
+        ctx.reply("Incorrect argument format. Check `.help wscope`").await?;
+        return Ok(());
+    };
 
-            let wishlisted_series_chunks = wishlisted_series.chunks(10);
+    let reply = match ctx.data().wishlist_db.set_guild_scope_mode(guild_id, mode).await {
+        Ok(()) => format!("This server now uses {} wishlists.", mode.as_str()),
+        Err(err) => {
+            ctx.data().logger.log_error(format!(".wscope | {err}"));
+            db_error_reply(&err, "changing this server's settings")
+        }
+    };
 
-            let mut series_pages = Vec::with_capacity(wishlisted_series_chunks.len());
-            for series_chunk in wishlisted_series_chunks {
-                let mut series_page = Vec::with_capacity(series_chunk.len());
+    ctx.reply(reply).await?;
 
-                for series in series_chunk {
-                    let count = ctx.data().wishlist_db.get_user_wishlisted_cards_count(&user_id.to_string(), series).await;
-                    series_page.push(format!("{series} ({count})"));
-                }
+    Ok(())
+}
 
-                series_pages.push(series_page.join("\n"))
-            };
+// Scope of the wishlists used where the command was invoked, the user is told when it can't be read
+pub async fn command_scope(ctx: Context<'_>) -> Result<Option<WishlistScope>, Error> {
+    match ctx.data().wishlist_db.resolve_scope(ctx.guild_id()).await {
+        Ok(scope) => Ok(Some(scope)),
+        Err(err) => {
+            ctx.data().logger.log_error(format!("command_scope: {err}"));
+            ctx.reply(db_error_reply(&err, "reading this server's settings")).await?;
+            Ok(None)
+        }
+    }
+}
 
-            (series_pages, total_size)
-        },
-        Some(series) => {
-            let mut wishlisted_cards = ctx.data().wishlist_db.get_user_wishlisted_cards(&user_id.to_string(), &series).await;
-            let total_size = wishlisted_cards.len();
-
-            ( wishlisted_cards.chunks_mut(10)
-                .map(|chunk| {
-                    chunk.iter_mut().for_each(|s| s.truncate(32));
-                    chunk.join("\n")
-                })
-                .collect()
-            , total_size
-            )
+// What the user is told when a wishlist operation fails
+fn argument_error_reply(err: &ArgumentError, line: &str, command: &str) -> String {
+    format!("Incorrect argument format, {err}.\n{}Check `.help {command}`", err.pointer(line))
+}
+
+fn db_error_reply(err: &WishlistDbError, action: &str) -> String {
+    match err {
+        WishlistDbError::ConnectionLost(_) => "The wishlist database is unavailable right now, try again later.".to_string(),
+        WishlistDbError::InvalidInput(why) => format!("Could not finish {action}: {why}."),
+        WishlistDbError::NotFound(what) => format!("Could not find {what} in the wishlist."),
+        WishlistDbError::Backend(_) => format!("Something went wrong {action}."),
+    }
+}
+
+// Whether `reader` may read `owner`'s wishlists, private ones are only read by their owner
+pub(crate) async fn can_read_wishlist(data: &Data, reader: UserId, owner: UserId) -> WishlistDbResult<bool> {
+    if reader == owner {
+        return Ok(true);
+    }
+
+    Ok(data.wishlist_db.get_wishlist_privacy(owner).await? == WishlistPrivacy::Public)
+}
+
+// ##############################
+// ##############################  SLASH COMMANDS
+// ##############################
+
+/// The rest of a prefix command's message, which `.wa`/`.wr`/`.wl` parse line by line themselves,
+/// or a plain string option of their slash versions, followed by the typed options.
+pub struct CommandText(pub String);
+
+#[poise::async_trait]
+impl<'a> poise::PopArgument<'a> for CommandText {
+    async fn pop_from(
+        args: &'a str,
+        attachment_index: usize,
+        _: &serenity::Context,
+        _: &Message
+    ) -> Result<(&'a str, usize, Self), (Box<dyn std::error::Error + Send + Sync>, Option<String>)>
+    {
+        match args.trim_start() {
+            "" => Err((poise::TooFewArguments::default().into(), None)),
+            text => Ok(("", attachment_index, CommandText(text.to_string())))
+        }
+    }
+}
+
+#[poise::async_trait]
+impl poise::SlashArgument for CommandText {
+    async fn extract(
+        _: &serenity::Context,
+        _: &serenity::CommandInteraction,
+        value: &serenity::ResolvedValue<'_>
+    ) -> Result<Self, poise::SlashArgError>
+    {
+        match value {
+            serenity::ResolvedValue::String(text) => Ok(CommandText(text.to_string())),
+            _ => Err(poise::SlashArgError::new_command_structure_mismatch("expected string"))
         }
+    }
+
+    fn create(builder: serenity::CreateCommandOption) -> serenity::CreateCommandOption {
+        builder.kind(serenity::CommandOptionType::String)
+    }
+}
+
+// Discord shows at most 25 suggestions, each at most 100 characters long
+const AUTOCOMPLETE_LIMIT: usize = 25;
+const AUTOCOMPLETE_MAX_LEN: usize = 100;
+
+// Series from the wishlist being listed or changed, the `user` option's one if given
+async fn autocomplete_wishlisted_series(ctx: Context<'_>, partial: &str) -> Vec<String> {
+    let user_id = match autocomplete_option(ctx, "user") {
+        Some(serenity::ResolvedValue::User(user, _)) => user.id,
+        Some(serenity::ResolvedValue::Unresolved(serenity::Unresolved::User(user_id))) => *user_id,
+        _ => ctx.author().id
     };
 
-    paginate(ctx, pages, total_count).await?;
+    // private wishlists are not suggested to other users either
+    if !can_read_wishlist(ctx.data(), ctx.author().id, user_id).await.unwrap_or(false) {
+        return vec![];
+    }
 
-    Ok(())
+    let db = &ctx.data().wishlist_db;
+    let series = match db.resolve_scope(ctx.guild_id()).await {
+        Ok(scope) => db.search_user_series(scope, user_id, partial, AUTOCOMPLETE_LIMIT).await.unwrap_or_default(),
+        Err(_) => vec![]
+    };
+
+    series.iter()
+        .map(|series| series.as_str().to_string())
+        .filter(|series| series.chars().count() <= AUTOCOMPLETE_MAX_LEN)
+        .collect()
+}
+
+// Cards the user wishlisted from the series of the `series` option
+async fn autocomplete_wishlisted_cards(ctx: Context<'_>, partial: &str) -> Vec<String> {
+    let Some(serenity::ResolvedValue::String(series)) = autocomplete_option(ctx, "series")
+    else {
+        return vec![];
+    };
+    let (typed, last) = split_last_card(partial);
+
+    let db = &ctx.data().wishlist_db;
+    let cards = match db.resolve_scope(ctx.guild_id()).await {
+        Ok(scope) => db.search_user_cards(scope, ctx.author().id, &SeriesName::new(series), last, AUTOCOMPLETE_LIMIT).await.unwrap_or_default(),
+        Err(_) => vec![]
+    };
+
+    card_suggestions(typed, &cards)
+}
+
+// Series seen in drops and SOFI lookups
+async fn autocomplete_catalog_series(ctx: Context<'_>, partial: &str) -> Vec<String> {
+    let series = ctx.data().wishlist_db.search_catalog_series(partial, AUTOCOMPLETE_LIMIT).await.unwrap_or_default();
+
+    series.iter()
+        .map(|series| series.as_str().to_string())
+        .filter(|series| series.chars().count() <= AUTOCOMPLETE_MAX_LEN)
+        .collect()
+}
+
+// Cards seen in drops and SOFI lookups from the series of the `series` option
+async fn autocomplete_catalog_cards(ctx: Context<'_>, partial: &str) -> Vec<String> {
+    let Some(serenity::ResolvedValue::String(series)) = autocomplete_option(ctx, "series")
+    else {
+        return vec![];
+    };
+    let (typed, last) = split_last_card(partial);
+
+    let cards = ctx.data().wishlist_db.search_catalog_cards(&SeriesName::new(series), last, AUTOCOMPLETE_LIMIT).await.unwrap_or_default();
+
+    card_suggestions(typed, &cards)
+}
+
+// Value of another option of the slash command being autocompleted
+fn autocomplete_option<'a>(ctx: Context<'a>, name: &str) -> Option<&'a serenity::ResolvedValue<'a>> {
+    match ctx {
+        poise::Context::Application(ctx) => ctx.args.iter().find(|option| option.name == name).map(|option| &option.value),
+        poise::Context::Prefix(_) => None
+    }
+}
+
+// Completes the last of the cards being typed with each card, leaving out the ones already typed
+fn card_suggestions(typed: &str, cards: &[CardName]) -> Vec<String> {
+    let typed_search: Vec<String> = parse_card_names(typed.trim_end_matches(','))
+        .map(|names| names.iter().map(|name| CardName::new(name).search_term()).collect())
+        .unwrap_or_default();
+
+    cards.iter()
+        .filter(|card| !typed_search.contains(&card.search_term()))
+        .map(|card| match typed {
+            "" => escape_card_name(card.as_str()),
+            typed => format!("{typed} {}", escape_card_name(card.as_str()))
+        })
+        .filter(|suggestion| suggestion.chars().count() <= AUTOCOMPLETE_MAX_LEN)
+        .collect()
+}
+
+#[derive(poise::ChoiceParameter, Clone, Copy)]
+pub enum PriorityChoice {
+    High,
+    Normal,
+    Low,
+}
+
+impl From<PriorityChoice> for CardPriority {
+    fn from(choice: PriorityChoice) -> Self {
+        match choice {
+            PriorityChoice::High => CardPriority::High,
+            PriorityChoice::Normal => CardPriority::Normal,
+            PriorityChoice::Low => CardPriority::Low,
+        }
+    }
+}
+
+#[derive(poise::ChoiceParameter, Clone, Copy)]
+pub enum SortChoice {
+    Added,
+    Name,
+    Cards,
 }
 
+impl From<SortChoice> for SeriesOrder {
+    fn from(choice: SortChoice) -> Self {
+        match choice {
+            SortChoice::Added => SeriesOrder::Added,
+            SortChoice::Name => SeriesOrder::Name,
+            SortChoice::Cards => SeriesOrder::CardCount,
+        }
+    }
+}
 
 // ##############################
 // ##############################  PING
 // ##############################
 
-const PONG_GIF_LINKS: [&'static str; 6]  = [
+const PONG_GIF_LINKS: [&str; 6]  = [
     "https://tenor.com/view/pong-gif-26462133",
     "https://tenor.com/view/bombardierul-pazitor-pong-maca-pong-gif-25389982",
     "https://tenor.com/view/get-ponged-pong-lol-troll-gif-20311938",
@@ -357,14 +2161,30 @@ pub async fn paginate (
     pages: Vec<String>,
     total_size: usize,
 ) -> Result<(), serenity::Error> {
+    paginate_with(ctx, pages.len(), total_size, |page| std::future::ready(pages[page].clone())).await
+}
+
+/// Like `paginate`, but builds each page only when it is shown.
+pub async fn paginate_with<F, Fut> (
+    ctx: Context<'_>,
+    page_count: usize,
+    total_size: usize,
+    load_page: F,
+) -> Result<(), serenity::Error>
+    where F: Fn(usize) -> Fut, Fut: Future<Output = String>
+{
     // Define some unique identifiers for the navigation buttons
     let ctx_id = ctx.id();
     let prev_button_id = format!("{}prev", ctx_id);
     let next_button_id = format!("{}next", ctx_id);
 
     // Send the embed with the first page as content
+    let first_page = match page_count {
+        0 => "Nothing to show".to_string(),
+        _ => load_page(0).await
+    };
     let reply = {
-        let components = if pages.len() == 0 {
+        let components = if page_count == 0 {
             vec![]
         } else {
             vec![serenity::CreateActionRow::Buttons(vec![
@@ -376,15 +2196,15 @@ pub async fn paginate (
         CreateReply::default()
             .embed(
                 serenity::CreateEmbed::default()
-                    .description(pages.get(0).unwrap_or(&"Nothing to show".to_string()))
-                    .footer(CreateEmbedFooter::new(format!("Page {}/{} (Total {})", min(pages.len(), 1), pages.len(), total_size)))
+                    .description(first_page)
+                    .footer(CreateEmbedFooter::new(format!("Page {}/{} (Total {})", min(page_count, 1), page_count, total_size)))
                 )
             .components(components)
     };
 
     ctx.send(reply).await?;
 
-    if pages.len() == 0 {
+    if page_count == 0 {
         return Ok(());
     }
 
@@ -403,17 +2223,18 @@ pub async fn paginate (
         // Depending on which button was pressed, go to next or previous page
         if press.data.custom_id == next_button_id {
             current_page += 1;
-            if current_page >= pages.len() {
+            if current_page >= page_count {
                 current_page = 0;
             }
         } else if press.data.custom_id == prev_button_id {
-            current_page = current_page.checked_sub(1).unwrap_or(pages.len() - 1);
+            current_page = current_page.checked_sub(1).unwrap_or(page_count - 1);
         } else {
             // This is an unrelated button interaction
             continue;
         }
 
         // Update the message with the new page contents
+        let page = load_page(current_page).await;
         press
             .create_response(
                 ctx.serenity_context(),
@@ -421,8 +2242,8 @@ pub async fn paginate (
                     serenity::CreateInteractionResponseMessage::new()
                         .embed(
                             serenity::CreateEmbed::new()
-                                .description(pages.get(current_page).unwrap())
-                                .footer(CreateEmbedFooter::new(format!("Page {}/{} (Total {})", current_page + 1, pages.len(), total_size)))
+                                .description(page)
+                                .footer(CreateEmbedFooter::new(format!("Page {}/{} (Total {})", current_page + 1, page_count, total_size)))
                             ),
                 ),
             )
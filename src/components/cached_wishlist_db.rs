@@ -0,0 +1,683 @@
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, Utc};
+use serenity::all::{GuildId, UserId};
+use serenity::async_trait;
+
+use crate::components::logger::Logger;
+use crate::model::catalog::{CatalogSeries, CatalogStats};
+use crate::model::drop::{CardConstraint, DropMatch, DroppedCard, FuzzyWishlist};
+use crate::model::history::{HistoryRecord, WishlistChange};
+use crate::model::wishlist::{AddedCards, CardName, CardPriority, CardRef, OverviewQuery, RemovedCards, ScopeMode, SeriesName, WishlistEntry, WishlistOverview, WishlistPrivacy, WishlistScope, WishlistedCard};
+use crate::traits::wishlist_db::{WishlistDB, WishlistDbResult};
+
+pub const DEFAULT_CACHE_CAPACITY: usize = 10_000;
+pub const DEFAULT_CACHE_TTL: Duration = Duration::from_secs(5 * 60);
+
+// Hit/miss counts are logged once every this many lookups
+const STATS_LOG_INTERVAL: u64 = 1_000;
+
+/// Hits and misses of a `CachedWishlistDB` since it was created.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct CacheStats {
+    pub hits: u64,
+    pub misses: u64
+}
+
+/// `WishlistDB` decorator keeping per-user wishlists and drop lookups in memory.
+///
+/// Each cache holds at most `capacity` entries, each kept for at most `ttl`.
+/// Every mutating call invalidates the entries it could change before returning,
+/// so reads through the cache always see the writes made through it.
+/// Writes made to the wrapped database by anything else show up once the entries expire.
+pub struct CachedWishlistDB<D, T>
+    where D: WishlistDB, T: Logger
+{
+    inner: D,
+    logger: Arc<T>,
+    // Keyed by the search term of the series as it was asked for, aliases included
+    wishlists: Mutex<BoundedCache<(WishlistScope, UserId), UserWishlist>>,
+    card_drops: Mutex<BoundedCache<CardDropKey, Vec<DropMatch>>>,
+    series_drops: Mutex<BoundedCache<SeriesDropKey, Vec<(UserId, i32)>>>,
+    // Bumped by every invalidation, values read before one are not cached
+    generation: AtomicU64,
+    hits: AtomicU64,
+    misses: AtomicU64
+}
+
+// Dropped cards are looked up by search terms, so differently written names share their entry
+type CardDropKey = (WishlistScope, String, String, Option<u32>, Option<u32>);
+type SeriesDropKey = (WishlistScope, String);
+
+#[derive(Debug, Clone, Default)]
+struct UserWishlist {
+    overviews: HashMap<OverviewQuery, WishlistOverview>,
+    cards: HashMap<String, Vec<WishlistedCard>>
+}
+
+impl<D, T> CachedWishlistDB<D, T>
+    where D: WishlistDB, T: Logger
+{
+    pub fn new(inner: D, logger: Arc<T>, capacity: usize, ttl: Duration) -> Self {
+        Self {
+            inner,
+            logger,
+            wishlists: Mutex::new(BoundedCache::new(capacity, ttl)),
+            card_drops: Mutex::new(BoundedCache::new(capacity, ttl)),
+            series_drops: Mutex::new(BoundedCache::new(capacity, ttl)),
+            generation: AtomicU64::new(0),
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0)
+        }
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        CacheStats {
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed)
+        }
+    }
+
+    fn record_lookup(&self, hit: bool) {
+        let counter = if hit { &self.hits } else { &self.misses };
+        counter.fetch_add(1, Ordering::Relaxed);
+
+        let stats = self.stats();
+        if (stats.hits + stats.misses).is_multiple_of(STATS_LOG_INTERVAL) {
+            self.logger.log_info(format!("Wishlist cache: {} hits, {} misses", stats.hits, stats.misses));
+        }
+    }
+
+    // Reads a value from the user's cached wishlist, counting the lookup
+    fn cached_wishlist<V>(&self, scope: WishlistScope, user_id: UserId, read: impl FnOnce(&UserWishlist) -> Option<V>) -> Option<V> {
+        let value = self.wishlists.lock().unwrap()
+            .get(&(scope, user_id))
+            .and_then(read);
+
+        self.record_lookup(value.is_some());
+        value
+    }
+
+    fn generation(&self) -> u64 {
+        self.generation.load(Ordering::SeqCst)
+    }
+
+    // Caches a value unless an invalidation happened since it was read
+    fn insert_if_current<K, V>(&self, generation: u64, cache: &Mutex<BoundedCache<K, V>>, key: K, value: V)
+        where K: Eq + Hash + Clone
+    {
+        let mut cache = cache.lock().unwrap();
+        if generation == self.generation() {
+            cache.insert(key, value);
+        }
+    }
+
+    fn update_wishlist(&self, generation: u64, scope: WishlistScope, user_id: UserId, update: impl FnOnce(&mut UserWishlist)) {
+        let mut wishlists = self.wishlists.lock().unwrap();
+        if generation != self.generation() {
+            return;
+        }
+
+        let mut wishlist = wishlists.get(&(scope, user_id)).cloned().unwrap_or_default();
+        update(&mut wishlist);
+        wishlists.insert((scope, user_id), wishlist);
+    }
+
+    // A change to a wishlist changes the drop lookups of its whole scope
+    fn invalidate_user(&self, scope: WishlistScope, user_id: UserId) {
+        self.generation.fetch_add(1, Ordering::SeqCst);
+        self.wishlists.lock().unwrap().remove(&(scope, user_id));
+        self.card_drops.lock().unwrap().retain(|key| key.0 != scope);
+        self.series_drops.lock().unwrap().retain(|key| key.0 != scope);
+    }
+
+    fn invalidate_drops(&self) {
+        self.generation.fetch_add(1, Ordering::SeqCst);
+        self.card_drops.lock().unwrap().clear();
+        self.series_drops.lock().unwrap().clear();
+    }
+
+    fn invalidate_all(&self) {
+        self.wishlists.lock().unwrap().clear();
+        self.invalidate_drops();
+    }
+}
+
+fn card_drop_key(scope: WishlistScope, dropped: &DroppedCard) -> CardDropKey {
+    (scope, dropped.card.series.search_term(), dropped.card.card.search_term(), dropped.gen, dropped.edition)
+}
+
+#[async_trait]
+impl<D, T> WishlistDB for CachedWishlistDB<D, T>
+    where D: WishlistDB, T: Logger + Send + Sync
+{
+    async fn get_users_with_series_card (
+        &self,
+        scope: WishlistScope,
+        cards: &[DroppedCard]
+    ) -> WishlistDbResult<Vec<(CardRef, Vec<DropMatch>)>>
+    {
+        let cached: Vec<Option<Vec<DropMatch>>> = cards.iter()
+            .map(|dropped| {
+                let users = self.card_drops.lock().unwrap().get(&card_drop_key(scope, dropped)).cloned();
+                self.record_lookup(users.is_some());
+                users
+            })
+            .collect();
+
+        let missed: Vec<DroppedCard> = cards.iter().zip(cached.iter())
+            .filter(|(_, users)| users.is_none())
+            .map(|(dropped, _)| dropped.clone())
+            .collect();
+
+        let generation = self.generation();
+        let mut found = if missed.is_empty() {
+            vec![]
+        } else {
+            self.inner.get_users_with_series_card(scope, &missed).await?
+        }.into_iter().peekable();
+
+        // Found cards keep the order they were asked in, the ones nobody wishlisted are left out
+        let mut ret = Vec::with_capacity(cards.len());
+        for (dropped, users) in cards.iter().zip(cached) {
+            let users = match users {
+                Some(users) => users,
+                None => {
+                    let users = found.next_if(|(card, _)| *card == dropped.card)
+                        .map(|(_, users)| users)
+                        .unwrap_or_default();
+                    self.insert_if_current(generation, &self.card_drops, card_drop_key(scope, dropped), users.clone());
+                    users
+                }
+            };
+
+            if !users.is_empty() {
+                ret.push((dropped.card.clone(), users));
+            }
+        }
+
+        Ok(ret)
+    }
+
+    async fn get_users_with_series (
+        &self,
+        scope: WishlistScope,
+        series: &[SeriesName]
+    ) -> WishlistDbResult<Vec<(SeriesName, Vec<(UserId, i32)>)>>
+    {
+        let cached: Vec<Option<Vec<(UserId, i32)>>> = series.iter()
+            .map(|series_name| {
+                let users = self.series_drops.lock().unwrap().get(&(scope, series_name.search_term())).cloned();
+                self.record_lookup(users.is_some());
+                users
+            })
+            .collect();
+
+        let missed: Vec<SeriesName> = series.iter().zip(cached.iter())
+            .filter(|(_, users)| users.is_none())
+            .map(|(series_name, _)| series_name.clone())
+            .collect();
+
+        let generation = self.generation();
+        let mut found = if missed.is_empty() {
+            vec![]
+        } else {
+            self.inner.get_users_with_series(scope, &missed).await?
+        }.into_iter().peekable();
+
+        let mut ret = Vec::with_capacity(series.len());
+        for (series_name, users) in series.iter().zip(cached) {
+            let users = match users {
+                Some(users) => users,
+                None => {
+                    let users = found.next_if(|(found_series, _)| found_series == series_name)
+                        .map(|(_, users)| users)
+                        .unwrap_or_default();
+                    self.insert_if_current(generation, &self.series_drops, (scope, series_name.search_term()), users.clone());
+                    users
+                }
+            };
+
+            if !users.is_empty() {
+                ret.push((series_name.clone(), users));
+            }
+        }
+
+        Ok(ret)
+    }
+
+    async fn add_all_to_wishlist (
+        &self,
+        scope: WishlistScope,
+        user_id: UserId,
+        entry: &WishlistEntry,
+        priority: CardPriority,
+        reprioritize: bool
+    ) -> WishlistDbResult<AddedCards>
+    {
+        let res = self.inner.add_all_to_wishlist(scope, user_id, entry, priority, reprioritize).await;
+        self.invalidate_user(scope, user_id);
+        res
+    }
+
+    async fn set_cards_priority (
+        &self,
+        scope: WishlistScope,
+        user_id: UserId,
+        entry: &WishlistEntry,
+        priority: CardPriority
+    ) -> WishlistDbResult<i32>
+    {
+        let res = self.inner.set_cards_priority(scope, user_id, entry, priority).await;
+        self.invalidate_user(scope, user_id);
+        res
+    }
+
+    async fn set_card_note (
+        &self,
+        scope: WishlistScope,
+        user_id: UserId,
+        card: &CardRef,
+        note: Option<&str>
+    ) -> WishlistDbResult<()>
+    {
+        let res = self.inner.set_card_note(scope, user_id, card, note).await;
+        self.invalidate_user(scope, user_id);
+        res
+    }
+
+    async fn set_card_constraints (
+        &self,
+        scope: WishlistScope,
+        user_id: UserId,
+        card: &CardRef,
+        constraints: &[CardConstraint]
+    ) -> WishlistDbResult<()>
+    {
+        let res = self.inner.set_card_constraints(scope, user_id, card, constraints).await;
+        self.invalidate_user(scope, user_id);
+        res
+    }
+
+    async fn set_wildcard_exclusions (
+        &self,
+        scope: WishlistScope,
+        user_id: UserId,
+        series: &SeriesName,
+        excluded: &[CardName]
+    ) -> WishlistDbResult<()>
+    {
+        let res = self.inner.set_wildcard_exclusions(scope, user_id, series, excluded).await;
+        self.invalidate_user(scope, user_id);
+        res
+    }
+
+    async fn remove_all_from_wishlist (
+        &self,
+        scope: WishlistScope,
+        user_id: UserId,
+        entry: &WishlistEntry,
+        exclude: bool
+    ) -> WishlistDbResult<RemovedCards>
+    {
+        let res = self.inner.remove_all_from_wishlist(scope, user_id, entry, exclude).await;
+        self.invalidate_user(scope, user_id);
+        res
+    }
+
+    async fn include_in_wildcard (
+        &self,
+        scope: WishlistScope,
+        user_id: UserId,
+        entry: &WishlistEntry
+    ) -> WishlistDbResult<Vec<CardName>>
+    {
+        let res = self.inner.include_in_wildcard(scope, user_id, entry).await;
+        self.invalidate_user(scope, user_id);
+        res
+    }
+
+    async fn get_wishlist_overview (
+        &self,
+        scope: WishlistScope,
+        user_id: UserId,
+        query: &OverviewQuery
+    ) -> WishlistDbResult<WishlistOverview>
+    {
+        if let Some(overview) = self.cached_wishlist(scope, user_id, |wishlist| wishlist.overviews.get(query).cloned()) {
+            return Ok(overview);
+        }
+
+        let generation = self.generation();
+        let overview = self.inner.get_wishlist_overview(scope, user_id, query).await?;
+        self.update_wishlist(generation, scope, user_id, |wishlist| { wishlist.overviews.insert(*query, overview.clone()); });
+
+        Ok(overview)
+    }
+
+    async fn get_user_wishlisted_cards (
+        &self,
+        scope: WishlistScope,
+        user_id: UserId,
+        series: &SeriesName
+    ) -> WishlistDbResult<Vec<WishlistedCard>>
+    {
+        let series_search = series.search_term();
+        if let Some(cards) = self.cached_wishlist(scope, user_id, |wishlist| wishlist.cards.get(&series_search).cloned()) {
+            return Ok(cards);
+        }
+
+        let generation = self.generation();
+        let cards = self.inner.get_user_wishlisted_cards(scope, user_id, series).await?;
+        self.update_wishlist(generation, scope, user_id, |wishlist| { wishlist.cards.insert(series_search, cards.clone()); });
+
+        Ok(cards)
+    }
+
+    async fn user_has_card (
+        &self,
+        scope: WishlistScope,
+        user_id: UserId,
+        card: &CardRef
+    ) -> WishlistDbResult<bool>
+    {
+        let series_search = card.series.search_term();
+        let card_search = card.card.search_term();
+        let cached = self.cached_wishlist(scope, user_id, |wishlist| {
+            let cards = wishlist.cards.get(&series_search)?;
+            Some(cards.iter().any(|wishlisted| wishlisted.name.search_term() == card_search))
+        });
+        if let Some(has_card) = cached {
+            return Ok(has_card);
+        }
+
+        self.inner.user_has_card(scope, user_id, card).await
+    }
+
+    async fn remove_series_from_wishlist (
+        &self,
+        scope: WishlistScope,
+        user_id: UserId,
+        series: &SeriesName
+    ) -> WishlistDbResult<i32>
+    {
+        let res = self.inner.remove_series_from_wishlist(scope, user_id, series).await;
+        self.invalidate_user(scope, user_id);
+        res
+    }
+
+    // Searches are not cached, each keystroke of an autocompleted option asks for another prefix
+    async fn search_user_series (
+        &self,
+        scope: WishlistScope,
+        user_id: UserId,
+        prefix: &str,
+        limit: usize
+    ) -> WishlistDbResult<Vec<SeriesName>>
+    {
+        self.inner.search_user_series(scope, user_id, prefix, limit).await
+    }
+
+    async fn search_user_cards (
+        &self,
+        scope: WishlistScope,
+        user_id: UserId,
+        series: &SeriesName,
+        prefix: &str,
+        limit: usize
+    ) -> WishlistDbResult<Vec<CardName>>
+    {
+        self.inner.search_user_cards(scope, user_id, series, prefix, limit).await
+    }
+
+    // History is not cached, recording a change leaves the cached wishlists as they are
+    async fn record_change (
+        &self,
+        scope: WishlistScope,
+        user_id: UserId,
+        change: &WishlistChange
+    ) -> WishlistDbResult<()>
+    {
+        self.inner.record_change(scope, user_id, change).await
+    }
+
+    async fn get_user_history (
+        &self,
+        scope: WishlistScope,
+        user_id: UserId,
+        limit: usize
+    ) -> WishlistDbResult<Vec<HistoryRecord>>
+    {
+        self.inner.get_user_history(scope, user_id, limit).await
+    }
+
+    async fn get_last_undoable_change (
+        &self,
+        scope: WishlistScope,
+        user_id: UserId
+    ) -> WishlistDbResult<Option<HistoryRecord>>
+    {
+        self.inner.get_last_undoable_change(scope, user_id).await
+    }
+
+    async fn get_fuzzy_wishlists (
+        &self,
+        scope: WishlistScope
+    ) -> WishlistDbResult<Vec<FuzzyWishlist>>
+    {
+        self.inner.get_fuzzy_wishlists(scope).await
+    }
+
+    async fn get_fuzzy_threshold (
+        &self,
+        user_id: UserId
+    ) -> WishlistDbResult<Option<u8>>
+    {
+        self.inner.get_fuzzy_threshold(user_id).await
+    }
+
+    // The threshold applies to every scope, so does the invalidation
+    async fn set_fuzzy_threshold (
+        &self,
+        user_id: UserId,
+        threshold: Option<u8>
+    ) -> WishlistDbResult<()>
+    {
+        let res = self.inner.set_fuzzy_threshold(user_id, threshold).await;
+        self.invalidate_drops();
+        res
+    }
+
+    async fn get_wishlist_privacy (
+        &self,
+        user_id: UserId
+    ) -> WishlistDbResult<WishlistPrivacy>
+    {
+        self.inner.get_wishlist_privacy(user_id).await
+    }
+
+    async fn set_wishlist_privacy (
+        &self,
+        user_id: UserId,
+        privacy: WishlistPrivacy
+    ) -> WishlistDbResult<()>
+    {
+        self.inner.set_wishlist_privacy(user_id, privacy).await
+    }
+
+    async fn canonical_series (
+        &self,
+        series: &SeriesName
+    ) -> WishlistDbResult<SeriesName>
+    {
+        self.inner.canonical_series(series).await
+    }
+
+    async fn get_series_aliases(&self) -> WishlistDbResult<Vec<(SeriesName, SeriesName)>> {
+        self.inner.get_series_aliases().await
+    }
+
+    // Aliases are resolved by every lookup and merge wishlists, nothing cached survives them
+    async fn add_series_alias (
+        &self,
+        alias: &SeriesName,
+        canonical: &SeriesName
+    ) -> WishlistDbResult<()>
+    {
+        let res = self.inner.add_series_alias(alias, canonical).await;
+        self.invalidate_all();
+        res
+    }
+
+    async fn remove_series_alias (
+        &self,
+        alias: &SeriesName
+    ) -> WishlistDbResult<()>
+    {
+        let res = self.inner.remove_series_alias(alias).await;
+        self.invalidate_all();
+        res
+    }
+
+    // The catalog is not cached, nor part of any wishlist
+    async fn add_to_catalog (
+        &self,
+        entries: &[WishlistEntry],
+        seen_at: DateTime<Utc>
+    ) -> WishlistDbResult<()>
+    {
+        self.inner.add_to_catalog(entries, seen_at).await
+    }
+
+    async fn search_catalog_series (
+        &self,
+        prefix: &str,
+        limit: usize
+    ) -> WishlistDbResult<Vec<SeriesName>>
+    {
+        self.inner.search_catalog_series(prefix, limit).await
+    }
+
+    async fn search_catalog_cards (
+        &self,
+        series: &SeriesName,
+        prefix: &str,
+        limit: usize
+    ) -> WishlistDbResult<Vec<CardName>>
+    {
+        self.inner.search_catalog_cards(series, prefix, limit).await
+    }
+
+    async fn get_catalog_series (
+        &self,
+        series: &SeriesName
+    ) -> WishlistDbResult<Option<CatalogSeries>>
+    {
+        self.inner.get_catalog_series(series).await
+    }
+
+    async fn get_catalog_stats(&self) -> WishlistDbResult<CatalogStats> {
+        self.inner.get_catalog_stats().await
+    }
+
+    // Ownership is not cached, nor part of any wishlist
+    async fn set_cards_owned (
+        &self,
+        user_id: UserId,
+        entry: &WishlistEntry,
+        owned: bool
+    ) -> WishlistDbResult<()>
+    {
+        self.inner.set_cards_owned(user_id, entry, owned).await
+    }
+
+    async fn get_owned_cards (
+        &self,
+        user_id: UserId
+    ) -> WishlistDbResult<Vec<WishlistEntry>>
+    {
+        self.inner.get_owned_cards(user_id).await
+    }
+
+    async fn get_guild_scope_mode (
+        &self,
+        guild_id: GuildId
+    ) -> WishlistDbResult<ScopeMode>
+    {
+        self.inner.get_guild_scope_mode(guild_id).await
+    }
+
+    // Cached entries are keyed by scope, switching a guild to another scope leaves them valid
+    async fn set_guild_scope_mode (
+        &self,
+        guild_id: GuildId,
+        mode: ScopeMode
+    ) -> WishlistDbResult<()>
+    {
+        self.inner.set_guild_scope_mode(guild_id, mode).await
+    }
+}
+
+// Map holding at most `capacity` entries for at most `ttl` each.
+// When full, expired entries are dropped first, then the oldest one.
+struct BoundedCache<K, V> {
+    entries: HashMap<K, (Instant, V)>,
+    capacity: usize,
+    ttl: Duration
+}
+
+impl<K, V> BoundedCache<K, V>
+    where K: Eq + Hash + Clone
+{
+    fn new(capacity: usize, ttl: Duration) -> Self {
+        Self { entries: HashMap::new(), capacity, ttl }
+    }
+
+    fn get(&mut self, key: &K) -> Option<&V> {
+        let expired = self.entries.get(key)
+            .is_some_and(|(inserted, _)| inserted.elapsed() >= self.ttl);
+        if expired {
+            self.entries.remove(key);
+        }
+
+        self.entries.get(key).map(|(_, value)| value)
+    }
+
+    fn insert(&mut self, key: K, value: V) {
+        if self.capacity == 0 {
+            return;
+        }
+
+        if self.entries.len() >= self.capacity && !self.entries.contains_key(&key) {
+            let ttl = self.ttl;
+            self.entries.retain(|_, (inserted, _)| inserted.elapsed() < ttl);
+        }
+
+        if self.entries.len() >= self.capacity && !self.entries.contains_key(&key) {
+            let oldest = self.entries.iter()
+                .min_by_key(|(_, (inserted, _))| *inserted)
+                .map(|(key, _)| key.clone());
+            if let Some(oldest) = oldest {
+                self.entries.remove(&oldest);
+            }
+        }
+
+        self.entries.insert(key, (Instant::now(), value));
+    }
+
+    fn remove(&mut self, key: &K) {
+        self.entries.remove(key);
+    }
+
+    fn retain(&mut self, mut keep: impl FnMut(&K) -> bool) {
+        self.entries.retain(|key, _| keep(key));
+    }
+
+    fn clear(&mut self) {
+        self.entries.clear();
+    }
+}
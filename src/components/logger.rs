@@ -23,7 +23,7 @@ pub struct VoidLogger;
 unsafe impl Send for VoidLogger {}
 unsafe impl Sync for VoidLogger {}
 impl Logger for VoidLogger {
-    fn log<T: AsRef<str> + Display>(&self, _:Priority, _: T) { () }
+    fn log<T: AsRef<str> + Display>(&self, _:Priority, _: T) {}
 }
 
 pub struct StdoutLogger;
@@ -1,199 +1,819 @@
-use std::error::Error;
-use std::collections::{HashMap, HashSet};
-use std::fmt::Display;
+use std::cmp::Reverse;
+use std::collections::HashMap;
 use std::sync::RwLock;
+use chrono::{DateTime, Utc};
+use serenity::all::{GuildId, UserId};
 use serenity::async_trait;
 
-use crate::traits::wishlist_db::WishlistDB;
+use crate::model::catalog::{CatalogCard, CatalogSeries, CatalogStats};
+use crate::model::drop::{CardConstraint, DropMatch, DroppedCard, FuzzyWishlist};
+use crate::model::history::{ChangeSource, HistoryRecord, WishlistChange};
+use crate::model::wishlist::{AddedCards, CardName, CardPriority, CardRef, OverviewQuery, RemovedCards, ScopeMode, SeriesName, SeriesOrder, SeriesOverview, WishlistEntry, WishlistOverview, WishlistPrivacy, WishlistScope, WishlistedCard};
+use crate::traits::wishlist_db::{removed_cards, validate_alias, validate_entry, with_fuzzy_matches, WishlistDB, WishlistDbError, WishlistDbResult};
+use crate::util::normalize::search_key_range;
 
-#[derive(Debug, Clone)]
-struct MockWishlistDBError{
-    description: String
+struct MockSeries {
+    name: SeriesName,
+    search: String,
+    cards: Vec<(WishlistedCard, String)>
 }
 
-impl Display for MockWishlistDBError {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}", self.description)
+impl MockSeries {
+    fn has_card(&self, card_search: &str) -> bool {
+        self.find_card(card_search).is_some()
     }
+
+    fn find_card(&self, card_search: &str) -> Option<&WishlistedCard> {
+        self.cards.iter()
+            .find(|(_, search)| search == card_search)
+            .map(|(card, _)| card)
+    }
+}
+
+#[derive(Default)]
+pub struct MockWishlistDB {
+    wishlists: RwLock<HashMap<(WishlistScope, UserId), Vec<MockSeries>>>,
+    guild_scope_modes: RwLock<HashMap<GuildId, ScopeMode>>,
+    fuzzy_thresholds: RwLock<HashMap<UserId, u8>>,
+    privacy: RwLock<HashMap<UserId, WishlistPrivacy>>,
+    series_aliases: RwLock<Vec<(SeriesName, SeriesName)>>,
+    history: RwLock<Vec<(WishlistScope, UserId, HistoryRecord)>>,
+    catalog: RwLock<Vec<CatalogSeries>>,
+    owned: RwLock<HashMap<UserId, Vec<WishlistEntry>>>
 }
 
-impl Error for MockWishlistDBError {}
+impl MockWishlistDB {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn canonical(&self, series: &SeriesName) -> SeriesName {
+        let series_search = series.search_term();
+
+        self.series_aliases.read().unwrap().iter()
+            .find(|(alias, _)| alias.search_term() == series_search)
+            .map_or_else(|| series.clone(), |(_, canonical)| canonical.clone())
+    }
 
-struct MockWishlistDB {
-    wishlists: RwLock<HashMap<String, HashMap<String, HashSet<String>>>>
+    fn update_card(
+        &self,
+        scope: WishlistScope,
+        user_id: UserId,
+        card: &CardRef,
+        update: impl FnOnce(&mut WishlistedCard)
+    ) -> WishlistDbResult<()> {
+        let series_search = self.canonical(&card.series).search_term();
+        let card_search = card.card.search_term();
+
+        let mut wishlists = self.wishlists.write().unwrap();
+        let wishlisted_card = wishlists.get_mut(&(scope, user_id))
+            .and_then(|user_wishlist| user_wishlist.iter_mut().find(|s| s.search == series_search))
+            .and_then(|s| s.cards.iter_mut().find(|(_, search)| *search == card_search))
+            .map(|(wishlisted_card, _)| wishlisted_card)
+            .ok_or_else(|| WishlistDbError::NotFound(format!("card `{}` from `{}`", card.card, card.series)))?;
+
+        update(wishlisted_card);
+        Ok(())
+    }
+}
+
+// Names whose search term starts with the one of `prefix`, sorted by search term
+fn search_names<'a, N>(names: impl Iterator<Item = (&'a N, String)>, prefix: &str, limit: usize) -> Vec<N>
+    where N: Clone + 'a
+{
+    let (start, end) = search_key_range(prefix);
+
+    let mut found: Vec<(&N, String)> = names
+        .filter(|(_, search)| start <= *search && *search < end)
+        .collect();
+    found.sort_by(|(_, a), (_, b)| a.cmp(b));
+
+    found.into_iter().take(limit).map(|(name, _)| name.clone()).collect()
 }
 
 #[async_trait]
 impl WishlistDB for MockWishlistDB {
-    
-    async fn get_users_with_series_card<'b> (
-        &'b self, 
-        cards: Vec<(&'b str, &'b str)>
-    ) -> Result<Vec<((&str, &str), Vec<String>)>, Box<dyn Error + Send + Sync>> 
+
+    async fn get_users_with_series_card(
+        &self,
+        scope: WishlistScope,
+        cards: &[DroppedCard]
+    ) -> WishlistDbResult<Vec<(CardRef, Vec<DropMatch>)>>
     {
-        let res = cards.iter().map(|(series, card)| {
-            let users = self.wishlists.read().unwrap().iter()
-                .filter( |(_, v)| 
-                    v.get(&series.to_string()).is_some_and(|cards| cards.contains(&card.to_string()))
-                )
-                .map(|(k, _)| k.clone().to_string())
-                .collect();
+        let exact = {
+            let wishlists = self.wishlists.read().unwrap();
 
-            ((series.to_owned(), card.to_owned()), users)
-        }).collect::<Vec<((&str, &str), Vec<String>)>>();
+            cards.iter().map(|dropped| {
+                let series_search = self.canonical(&dropped.card.series).search_term();
+                let card_search = dropped.card.card.search_term();
 
-        Ok(res)
+                let users = wishlists.iter()
+                    .filter(|((wishlist_scope, _), _)| *wishlist_scope == scope)
+                    .filter_map(|((_, user_id), user_wishlist)|
+                        user_wishlist.iter()
+                            .filter(|s| s.search == series_search)
+                            .find_map(|s| s.find_card(&card_search).or_else(|| s.find_card(&CardName::wildcard().search_term())))
+                            .filter(|wishlisted_card| wishlisted_card.covers(&dropped.card.card))
+                            .filter(|wishlisted_card| dropped.satisfies(&wishlisted_card.constraints))
+                            .map(|wishlisted_card| DropMatch::exact(*user_id, wishlisted_card.priority))
+                    )
+                    .collect();
+
+                (dropped.card.clone(), users)
+            })
+            .filter(|(_, users): &(_, Vec<DropMatch>)| !users.is_empty())
+            .collect()
+        };
+
+        let fuzzy_wishlists = self.get_fuzzy_wishlists(scope).await?;
+
+        Ok(with_fuzzy_matches(cards, exact, &fuzzy_wishlists))
     }
 
-    async fn get_users_with_series<'b> (
-        &'b self, 
-        series:&Vec<&'b str>
-    ) -> Result<Vec<(&str, Vec<(String, i32)>)>, Box<dyn Error + Send + Sync>> {
+    async fn get_users_with_series(
+        &self,
+        scope: WishlistScope,
+        series: &[SeriesName]
+    ) -> WishlistDbResult<Vec<(SeriesName, Vec<(UserId, i32)>)>> {
+        let wishlists = self.wishlists.read().unwrap();
+
         let res = series.iter().map(|series_name| {
-            let users = self.wishlists.read().unwrap().iter()
-                .filter( |(_, v)| 
-                    v.contains_key(&series_name.to_string())
-                )
-                .map(|(k, v)| 
-                    (k.clone().to_string(), v.len().try_into().unwrap())
+            let series_search = self.canonical(series_name).search_term();
+
+            let users = wishlists.iter()
+                .filter(|((wishlist_scope, _), _)| *wishlist_scope == scope)
+                .filter_map(|((_, user_id), user_wishlist)|
+                    user_wishlist.iter()
+                        .find(|s| s.search == series_search)
+                        .map(|s| (*user_id, s.cards.len() as i32))
                 )
                 .collect();
 
-            (series_name.to_owned(), users)
-        }).collect::<Vec<(&str, Vec<(String, i32)>)>>();
+            (series_name.clone(), users)
+        })
+        .filter(|(_, users): &(_, Vec<(UserId, i32)>)| !users.is_empty())
+        .collect();
 
         Ok(res)
     }
-    
-    async fn add_all_to_wishlist<'b> (
-        &self, 
-        user_id: &'b str, 
-        series: &'b str, 
-        card_names: Vec<&'b str>
-    ) -> Result<i32, Box<dyn Error + Send + Sync>>
+
+    async fn add_all_to_wishlist(
+        &self,
+        scope: WishlistScope,
+        user_id: UserId,
+        entry: &WishlistEntry,
+        priority: CardPriority,
+        reprioritize: bool
+    ) -> WishlistDbResult<AddedCards>
     {
-        let prev_count = self.get_user_wishlisted_cards_count(user_id,series).await;
+        validate_entry(entry)?;
 
-        let series_s = series.to_string();
-        let user_id_s = user_id.to_string();
-        let card_names_s: Vec<String> = 
-            card_names.clone().iter().map(|s| s.to_string()).collect();
+        let series = self.canonical(&entry.series);
 
-        {
-            let mut wishlists = self.wishlists.write().unwrap();
-            match wishlists.get_mut(&user_id_s) {
-                Some(user_wishlist) => {
-                    match user_wishlist.get_mut(&series_s) {
-                        Some(wishlisted_cards) => {
-                            wishlisted_cards.extend(card_names_s);
-                        }
-                        None => {
-                            let cards_set = card_names_s.iter().cloned().collect();
-                            user_wishlist.insert(series_s, cards_set);
-                        }
+        let mut wishlists = self.wishlists.write().unwrap();
+        let user_wishlist = wishlists.entry((scope, user_id)).or_default();
+
+        let series_search = series.search_term();
+        let wishlisted_series = match user_wishlist.iter().position(|s| s.search == series_search) {
+            Some(index) => &mut user_wishlist[index],
+            None => {
+                user_wishlist.push(MockSeries { name: series, search: series_search, cards: vec![] });
+                user_wishlist.last_mut().unwrap()
+            }
+        };
+
+        let mut res = AddedCards::default();
+        let mut seen_cards_search = Vec::with_capacity(entry.cards.len());
+        for card in entry.cards.iter() {
+            let card_search = card.search_term();
+            if seen_cards_search.contains(&card_search) {
+                continue;
+            }
+            seen_cards_search.push(card_search.clone());
+
+            match wishlisted_series.cards.iter_mut().find(|(_, search)| *search == card_search) {
+                Some((wishlisted_card, _)) => {
+                    if reprioritize {
+                        wishlisted_card.priority = priority;
                     }
-                }
+                    res.already_wishlisted.push(wishlisted_card.name.clone());
+                },
                 None => {
-                    let cards_set = card_names_s.iter().cloned().collect();
-                    let mut series_wishlist = HashMap::new();
-                    series_wishlist.insert(series_s, cards_set);
-                    
-                    wishlists.insert(user_id_s, series_wishlist);
+                    wishlisted_series.cards.push((WishlistedCard::new(card.clone(), priority).with_added_at(Some(Utc::now())), card_search));
+                    res.added.push(card.clone());
                 }
             }
         }
 
-        let curr_count = self.get_user_wishlisted_cards_count(user_id,series).await;
-        Ok(curr_count - prev_count)
+        let wildcard_search = CardName::wildcard().search_term();
+        if let Some((wildcard, _)) = wishlisted_series.cards.iter_mut().find(|(_, search)| *search == wildcard_search) {
+            let (included, excluded) = wildcard.excluded.drain(..)
+                .partition(|excluded| seen_cards_search.contains(&excluded.search_term()));
+            wildcard.excluded = excluded;
+            res.included = included;
+        }
+
+        Ok(res)
     }
-    
+
+    async fn set_cards_priority(
+        &self,
+        scope: WishlistScope,
+        user_id: UserId,
+        entry: &WishlistEntry,
+        priority: CardPriority
+    ) -> WishlistDbResult<i32> {
+        let series_search = self.canonical(&entry.series).search_term();
+        let mut wishlists = self.wishlists.write().unwrap();
+
+        let Some(wishlisted_series) = wishlists.get_mut(&(scope, user_id))
+            .and_then(|user_wishlist| user_wishlist.iter_mut().find(|s| s.search == series_search))
+        else {
+            return Ok(0);
+        };
+
+        let cards_search: Vec<String> = entry.cards.iter()
+            .map(CardName::search_term)
+            .collect();
+
+        let mut matched_count = 0;
+        for (card, search) in wishlisted_series.cards.iter_mut() {
+            if cards_search.contains(search) {
+                card.priority = priority;
+                matched_count += 1;
+            }
+        }
+
+        Ok(matched_count)
+    }
+
+    async fn set_card_note(
+        &self,
+        scope: WishlistScope,
+        user_id: UserId,
+        card: &CardRef,
+        note: Option<&str>
+    ) -> WishlistDbResult<()> {
+        self.update_card(scope, user_id, card, |wishlisted_card| wishlisted_card.note = note.map(str::to_string))
+    }
+
+    async fn set_card_constraints(
+        &self,
+        scope: WishlistScope,
+        user_id: UserId,
+        card: &CardRef,
+        constraints: &[CardConstraint]
+    ) -> WishlistDbResult<()> {
+        self.update_card(scope, user_id, card, |wishlisted_card| wishlisted_card.constraints = constraints.to_vec())
+    }
+
+    async fn set_wildcard_exclusions(
+        &self,
+        scope: WishlistScope,
+        user_id: UserId,
+        series: &SeriesName,
+        excluded: &[CardName]
+    ) -> WishlistDbResult<()> {
+        let wildcard = CardRef { series: series.clone(), card: CardName::wildcard() };
+        self.update_card(scope, user_id, &wildcard, |wishlisted_card| wishlisted_card.excluded = excluded.to_vec())
+    }
+
     async fn remove_all_from_wishlist(
-        &self, 
-        user_id:&str, 
-        series:&str, 
-        card_names:Vec<&str>
-    ) -> Result<(i32, i32), Box<dyn Error + Send + Sync>> {
-        let res = self.wishlists.write().unwrap().get_mut(user_id)
-            .and_then( |user_wishlist|
-                user_wishlist.get_mut(series)
-            )
-            .map( |wishlisted_cards| {
-                let mut removed_count = 0;
-                for card in card_names {
-                    if wishlisted_cards.remove(card) {
-                        removed_count += 1;
-                    }
-                }
+        &self,
+        scope: WishlistScope,
+        user_id: UserId,
+        entry: &WishlistEntry,
+        exclude: bool
+    ) -> WishlistDbResult<RemovedCards> {
+        let series_search = self.canonical(&entry.series).search_term();
+
+        let cards_search: Vec<String> = entry.cards.iter()
+            .map(CardName::search_term)
+            .collect();
 
-                (removed_count, wishlisted_cards.len().try_into().unwrap())
-            }).unwrap_or((0, 0));
+        let mut wishlists = self.wishlists.write().unwrap();
+        let Some((index, user_wishlist)) = wishlists.get_mut(&(scope, user_id))
+            .and_then(|user_wishlist| Some((user_wishlist.iter().position(|s| s.search == series_search)?, user_wishlist)))
+        else {
+            return Ok(removed_cards(entry, vec![], None));
+        };
+
+        let wishlisted_cards = &mut user_wishlist[index].cards;
+        let (removed, kept): (Vec<_>, Vec<_>) = wishlisted_cards.drain(..)
+            .partition(|(_, search)| cards_search.contains(search));
+        *wishlisted_cards = kept;
+
+        let wildcard_search = CardName::wildcard().search_term();
+        let wildcard = wishlisted_cards.iter_mut()
+            .find(|(_, search)| *search == wildcard_search)
+            .map(|(wildcard, _)| wildcard)
+            .filter(|_| exclude);
+
+        let mut res = removed_cards(entry, removed.into_iter().map(|(card, _)| card).collect(), wildcard.as_deref());
+        if let Some(wildcard) = wildcard {
+            wildcard.excluded.extend(res.excluded.iter().cloned());
+        }
+
+        res.left = wishlisted_cards.len() as i32;
+        if res.left == 0 {
+            user_wishlist.remove(index);
+        }
 
         Ok(res)
     }
-    
-    async fn get_user_wishlisted_series(
-        &self, 
-        user_id: &str
-    ) -> Vec<String> {
-        self.wishlists.read().unwrap().get(user_id)
-            .map(|user_wishlist|
-                user_wishlist.keys().clone()
-                    .map(|x| x.to_string())
-                    .collect::<Vec<String>>()
-            )
-            .unwrap_or(vec![])
-    }
-    
-    async fn get_user_wishlisted_cards_count(
-        &self, 
-        user_id: &str, 
-        series: &str
-    ) -> i32 {
-        self.get_user_wishlisted_cards(user_id, series).await
-            .len().try_into().unwrap()
-    }
-    
+
+    async fn include_in_wildcard(
+        &self,
+        scope: WishlistScope,
+        user_id: UserId,
+        entry: &WishlistEntry
+    ) -> WishlistDbResult<Vec<CardName>> {
+        let series_search = self.canonical(&entry.series).search_term();
+        let cards_search: Vec<String> = entry.cards.iter()
+            .map(CardName::search_term)
+            .collect();
+
+        let mut wishlists = self.wishlists.write().unwrap();
+        let wildcard_search = CardName::wildcard().search_term();
+        let Some((wildcard, _)) = wishlists.get_mut(&(scope, user_id))
+            .and_then(|user_wishlist| user_wishlist.iter_mut().find(|s| s.search == series_search))
+            .and_then(|s| s.cards.iter_mut().find(|(_, search)| *search == wildcard_search))
+        else {
+            return Ok(vec![]);
+        };
+
+        let (included, excluded) = wildcard.excluded.drain(..)
+            .partition(|excluded| cards_search.contains(&excluded.search_term()));
+        wildcard.excluded = excluded;
+
+        Ok(included)
+    }
+
+    async fn get_wishlist_overview(
+        &self,
+        scope: WishlistScope,
+        user_id: UserId,
+        query: &OverviewQuery
+    ) -> WishlistDbResult<WishlistOverview> {
+        let wishlists = self.wishlists.read().unwrap();
+        let user_wishlist = wishlists.get(&(scope, user_id)).map(Vec::as_slice).unwrap_or_default();
+
+        let mut sorted: Vec<&MockSeries> = user_wishlist.iter().collect();
+        match query.order {
+            SeriesOrder::Added => (),
+            SeriesOrder::Name => sorted.sort_by(|a, b| a.search.cmp(&b.search)),
+            SeriesOrder::CardCount => sorted.sort_by_key(|s| Reverse(s.cards.len()))
+        }
+
+        let series = sorted.into_iter()
+            .skip(query.skip)
+            .take(query.limit.unwrap_or(usize::MAX))
+            .map(|s| SeriesOverview {
+                series: s.name.clone(),
+                cards_count: s.cards.len() as i32,
+                whole: s.cards.iter().any(|(card, _)| card.name.is_wildcard()),
+                cards: query.with_cards.then(|| s.cards.iter().map(|(card, _)| card.clone()).collect())
+            })
+            .collect();
+
+        Ok(WishlistOverview {
+            series,
+            total_series: user_wishlist.len(),
+            total_cards: user_wishlist.iter().map(|s| s.cards.len()).sum()
+        })
+    }
+
     async fn get_user_wishlisted_cards(
-        &self, 
-        user_id: &str, 
-        series: &str
-    ) -> Vec<String> {
-        self.wishlists.read().unwrap().get(user_id)
-            .and_then( |user_wishlist|
-                user_wishlist.get(series)
+        &self,
+        scope: WishlistScope,
+        user_id: UserId,
+        series: &SeriesName
+    ) -> WishlistDbResult<Vec<WishlistedCard>> {
+        let series_search = self.canonical(series).search_term();
+
+        let res = self.wishlists.read().unwrap().get(&(scope, user_id))
+            .and_then(|user_wishlist|
+                user_wishlist.iter().find(|s| s.search == series_search)
             )
-            .map( |wishlisted_cards|
-                wishlisted_cards.clone().iter()
-                    .map(|s| s.to_string())
-                    .collect::<Vec<String>>()
+            .map(|wishlisted_series|
+                wishlisted_series.cards.iter()
+                    .map(|(card, _)| card.clone())
+                    .collect()
             )
-            .unwrap_or(vec![])
+            .unwrap_or_default();
+
+        Ok(res)
     }
-    
+
     async fn user_has_card(
-        &self, 
-        user_id: &str, 
-        series: &str, 
-        card: &str
-    ) -> bool {
-        self.get_user_wishlisted_cards(user_id, series).await
-            .contains(&card.to_string())
-    }
-    
+        &self,
+        scope: WishlistScope,
+        user_id: UserId,
+        card: &CardRef
+    ) -> WishlistDbResult<bool> {
+        let series_search = self.canonical(&card.series).search_term();
+        let card_search = card.card.search_term();
+
+        let res = self.wishlists.read().unwrap().get(&(scope, user_id))
+            .is_some_and(|user_wishlist|
+                user_wishlist.iter().any(|s| s.search == series_search && s.has_card(&card_search))
+            );
+
+        Ok(res)
+    }
+
     async fn remove_series_from_wishlist (
         &self,
-        user_id:&str, 
-        series:&str
-    ) -> Result<i32, Box<dyn Error + Send + Sync>> {
-        let res = self.wishlists.write().unwrap().get_mut(user_id)
-            .and_then(|user_wishlist|
-                user_wishlist.remove(series)
+        scope: WishlistScope,
+        user_id: UserId,
+        series: &SeriesName
+    ) -> WishlistDbResult<i32> {
+        let series_search = self.canonical(series).search_term();
+
+        self.wishlists.write().unwrap().get_mut(&(scope, user_id))
+            .and_then(|user_wishlist| {
+                let index = user_wishlist.iter().position(|s| s.search == series_search)?;
+                Some(user_wishlist.remove(index).cards.len() as i32)
+            })
+            .ok_or_else(|| WishlistDbError::NotFound(format!("series `{series}`")))
+    }
+
+    async fn search_user_series(
+        &self,
+        scope: WishlistScope,
+        user_id: UserId,
+        prefix: &str,
+        limit: usize
+    ) -> WishlistDbResult<Vec<SeriesName>> {
+        let wishlists = self.wishlists.read().unwrap();
+        let user_wishlist = wishlists.get(&(scope, user_id)).map(Vec::as_slice).unwrap_or_default();
+
+        Ok(search_names(user_wishlist.iter().map(|s| (&s.name, s.search.clone())), prefix, limit))
+    }
+
+    async fn search_user_cards(
+        &self,
+        scope: WishlistScope,
+        user_id: UserId,
+        series: &SeriesName,
+        prefix: &str,
+        limit: usize
+    ) -> WishlistDbResult<Vec<CardName>> {
+        let series_search = self.canonical(series).search_term();
+
+        let wishlists = self.wishlists.read().unwrap();
+        let cards = wishlists.get(&(scope, user_id))
+            .and_then(|user_wishlist| user_wishlist.iter().find(|s| s.search == series_search))
+            .map(|s| s.cards.as_slice())
+            .unwrap_or_default();
+
+        Ok(search_names(cards.iter().map(|(card, search)| (&card.name, search.clone())), prefix, limit))
+    }
+
+    async fn record_change(
+        &self,
+        scope: WishlistScope,
+        user_id: UserId,
+        change: &WishlistChange
+    ) -> WishlistDbResult<()> {
+        let mut history = self.history.write().unwrap();
+
+        let record = HistoryRecord { id: history.len().to_string(), change: change.clone(), timestamp: Utc::now() };
+        history.push((scope, user_id, record));
+
+        Ok(())
+    }
+
+    async fn get_user_history(
+        &self,
+        scope: WishlistScope,
+        user_id: UserId,
+        limit: usize
+    ) -> WishlistDbResult<Vec<HistoryRecord>> {
+        let res = self.history.read().unwrap().iter()
+            .rev()
+            .filter(|(record_scope, record_user_id, _)| *record_scope == scope && *record_user_id == user_id)
+            .map(|(_, _, record)| record.clone())
+            .take(limit)
+            .collect();
+
+        Ok(res)
+    }
+
+    async fn get_last_undoable_change(
+        &self,
+        scope: WishlistScope,
+        user_id: UserId
+    ) -> WishlistDbResult<Option<HistoryRecord>> {
+        let history = self.history.read().unwrap();
+
+        let res = history.iter()
+            .rev()
+            .filter(|(record_scope, record_user_id, _)| *record_scope == scope && *record_user_id == user_id)
+            .map(|(_, _, record)| record)
+            .find(|record|
+                record.change.source != ChangeSource::Undo
+                && !history.iter().any(|(_, _, undo)| undo.change.reverts.as_ref() == Some(&record.id))
             )
-            .and_then(|wishlisted_cards|{
-                let count:i32 = wishlisted_cards.len().try_into().unwrap();
-                Some(count)
-            }).unwrap_or(0);
-        
+            .cloned();
+
         Ok(res)
     }
-}
\ No newline at end of file
+
+    async fn get_fuzzy_wishlists(
+        &self,
+        scope: WishlistScope
+    ) -> WishlistDbResult<Vec<FuzzyWishlist>> {
+        let fuzzy_thresholds = self.fuzzy_thresholds.read().unwrap();
+        let wishlists = self.wishlists.read().unwrap();
+
+        let res = wishlists.iter()
+            .filter(|((wishlist_scope, _), _)| *wishlist_scope == scope)
+            .filter_map(|((_, user_id), user_wishlist)| {
+                let threshold = *fuzzy_thresholds.get(user_id)?;
+                let cards = user_wishlist.iter()
+                    .flat_map(|s| s.cards.iter().map(|(card, _)| (s.name.clone(), card.clone())))
+                    .collect();
+
+                Some(FuzzyWishlist { user_id: *user_id, threshold, cards })
+            })
+            .collect();
+
+        Ok(res)
+    }
+
+    async fn get_fuzzy_threshold(
+        &self,
+        user_id: UserId
+    ) -> WishlistDbResult<Option<u8>> {
+        Ok(self.fuzzy_thresholds.read().unwrap().get(&user_id).copied())
+    }
+
+    async fn set_fuzzy_threshold(
+        &self,
+        user_id: UserId,
+        threshold: Option<u8>
+    ) -> WishlistDbResult<()> {
+        let mut fuzzy_thresholds = self.fuzzy_thresholds.write().unwrap();
+        match threshold {
+            Some(threshold) => fuzzy_thresholds.insert(user_id, threshold),
+            None => fuzzy_thresholds.remove(&user_id)
+        };
+
+        Ok(())
+    }
+
+    async fn get_wishlist_privacy(
+        &self,
+        user_id: UserId
+    ) -> WishlistDbResult<WishlistPrivacy> {
+        Ok(self.privacy.read().unwrap().get(&user_id).copied().unwrap_or_default())
+    }
+
+    async fn set_wishlist_privacy(
+        &self,
+        user_id: UserId,
+        privacy: WishlistPrivacy
+    ) -> WishlistDbResult<()> {
+        self.privacy.write().unwrap().insert(user_id, privacy);
+        Ok(())
+    }
+
+    async fn canonical_series(
+        &self,
+        series: &SeriesName
+    ) -> WishlistDbResult<SeriesName> {
+        Ok(self.canonical(series))
+    }
+
+    async fn get_series_aliases(&self) -> WishlistDbResult<Vec<(SeriesName, SeriesName)>> {
+        let mut aliases = self.series_aliases.read().unwrap().clone();
+        aliases.sort_by_key(|(alias, canonical)| (canonical.search_term(), alias.search_term()));
+
+        Ok(aliases)
+    }
+
+    async fn add_series_alias(
+        &self,
+        alias: &SeriesName,
+        canonical: &SeriesName
+    ) -> WishlistDbResult<()> {
+        validate_alias(alias, canonical)?;
+        let canonical = self.canonical(canonical);
+        validate_alias(alias, &canonical)?;
+
+        let alias_search = alias.search_term();
+        let canonical_search = canonical.search_term();
+
+        {
+            let mut aliases = self.series_aliases.write().unwrap();
+            aliases.retain(|(registered, _)| registered.search_term() != alias_search);
+            for (_, registered_canonical) in aliases.iter_mut().filter(|(_, c)| c.search_term() == alias_search) {
+                *registered_canonical = canonical.clone();
+            }
+            aliases.push((alias.clone(), canonical.clone()));
+        }
+
+        for user_wishlist in self.wishlists.write().unwrap().values_mut() {
+            let Some(index) = user_wishlist.iter().position(|s| s.search == alias_search)
+            else {
+                continue;
+            };
+
+            match user_wishlist.iter().position(|s| s.search == canonical_search) {
+                Some(canonical_index) => {
+                    let aliased = user_wishlist.remove(index);
+                    let canonical_index = if canonical_index > index { canonical_index - 1 } else { canonical_index };
+                    let merged = &mut user_wishlist[canonical_index];
+
+                    for (card, card_search) in aliased.cards {
+                        if !merged.has_card(&card_search) {
+                            merged.cards.push((card, card_search));
+                        }
+                    }
+                },
+                None => {
+                    user_wishlist[index].name = canonical.clone();
+                    user_wishlist[index].search = canonical_search.clone();
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn remove_series_alias(
+        &self,
+        alias: &SeriesName
+    ) -> WishlistDbResult<()> {
+        let alias_search = alias.search_term();
+
+        let mut aliases = self.series_aliases.write().unwrap();
+        let index = aliases.iter()
+            .position(|(registered, _)| registered.search_term() == alias_search)
+            .ok_or_else(|| WishlistDbError::NotFound(format!("alias `{alias}`")))?;
+
+        aliases.remove(index);
+        Ok(())
+    }
+
+    async fn add_to_catalog(
+        &self,
+        entries: &[WishlistEntry],
+        seen_at: DateTime<Utc>
+    ) -> WishlistDbResult<()> {
+        entries.iter().try_for_each(validate_entry)?;
+
+        let mut catalog = self.catalog.write().unwrap();
+        for entry in entries {
+            let series = self.canonical(&entry.series);
+            let series_search = series.search_term();
+
+            let catalog_series = match catalog.iter().position(|s| s.series.search_term() == series_search) {
+                Some(index) => &mut catalog[index],
+                None => {
+                    catalog.push(CatalogSeries { series, cards: vec![], first_seen: seen_at, last_seen: seen_at });
+                    catalog.last_mut().unwrap()
+                }
+            };
+            catalog_series.first_seen = catalog_series.first_seen.min(seen_at);
+            catalog_series.last_seen = catalog_series.last_seen.max(seen_at);
+
+            for card in entry.cards.iter() {
+                let card_search = card.search_term();
+                match catalog_series.cards.iter_mut().find(|c| c.name.search_term() == card_search) {
+                    Some(known) => {
+                        known.first_seen = known.first_seen.min(seen_at);
+                        known.last_seen = known.last_seen.max(seen_at);
+                    },
+                    None => catalog_series.cards.push(CatalogCard { name: card.clone(), first_seen: seen_at, last_seen: seen_at })
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn search_catalog_series(
+        &self,
+        prefix: &str,
+        limit: usize
+    ) -> WishlistDbResult<Vec<SeriesName>> {
+        let catalog = self.catalog.read().unwrap();
+
+        Ok(search_names(catalog.iter().map(|s| (&s.series, s.series.search_term())), prefix, limit))
+    }
+
+    async fn search_catalog_cards(
+        &self,
+        series: &SeriesName,
+        prefix: &str,
+        limit: usize
+    ) -> WishlistDbResult<Vec<CardName>> {
+        let series_search = self.canonical(series).search_term();
+
+        let catalog = self.catalog.read().unwrap();
+        let cards = catalog.iter()
+            .find(|s| s.series.search_term() == series_search)
+            .map(|s| s.cards.as_slice())
+            .unwrap_or_default();
+
+        Ok(search_names(cards.iter().map(|card| (&card.name, card.name.search_term())), prefix, limit))
+    }
+
+    async fn get_catalog_series(
+        &self,
+        series: &SeriesName
+    ) -> WishlistDbResult<Option<CatalogSeries>> {
+        let series_search = self.canonical(series).search_term();
+
+        let res = self.catalog.read().unwrap().iter()
+            .find(|s| s.series.search_term() == series_search)
+            .cloned()
+            .map(|mut catalog_series| {
+                catalog_series.cards.sort_by_key(|card| card.name.search_term());
+                catalog_series
+            });
+
+        Ok(res)
+    }
+
+    async fn get_catalog_stats(&self) -> WishlistDbResult<CatalogStats> {
+        let catalog = self.catalog.read().unwrap();
+
+        Ok(CatalogStats {
+            series_count: catalog.len(),
+            cards_count: catalog.iter().map(|s| s.cards.len()).sum(),
+            first_seen: catalog.iter().map(|s| s.first_seen).min()
+        })
+    }
+
+    async fn set_cards_owned(
+        &self,
+        user_id: UserId,
+        entry: &WishlistEntry,
+        owned: bool
+    ) -> WishlistDbResult<()> {
+        validate_entry(entry)?;
+
+        let series = self.canonical(&entry.series);
+        let series_search = series.search_term();
+
+        let mut owned_by_user = self.owned.write().unwrap();
+        let owned_entries = owned_by_user.entry(user_id).or_default();
+        let owned_entry = match owned_entries.iter().position(|owned| owned.series.search_term() == series_search) {
+            Some(index) => &mut owned_entries[index],
+            None => {
+                owned_entries.push(WishlistEntry { series: series.clone(), cards: vec![] });
+                owned_entries.last_mut().unwrap()
+            }
+        };
+        owned_entry.series = series;
+
+        for card in entry.cards.iter() {
+            let card_search = card.search_term();
+            owned_entry.cards.retain(|owned| owned.search_term() != card_search);
+            if owned {
+                owned_entry.cards.push(card.clone());
+            }
+        }
+
+        owned_entries.retain(|owned| !owned.cards.is_empty());
+
+        Ok(())
+    }
+
+    async fn get_owned_cards(
+        &self,
+        user_id: UserId
+    ) -> WishlistDbResult<Vec<WishlistEntry>> {
+        let mut entries = self.owned.read().unwrap().get(&user_id).cloned().unwrap_or_default();
+
+        entries.sort_by_key(|entry| entry.series.search_term());
+        for entry in entries.iter_mut() {
+            entry.cards.sort_by_key(CardName::search_term);
+        }
+
+        Ok(entries)
+    }
+
+    async fn get_guild_scope_mode(
+        &self,
+        guild_id: GuildId
+    ) -> WishlistDbResult<ScopeMode> {
+        let res = self.guild_scope_modes.read().unwrap().get(&guild_id)
+            .copied()
+            .unwrap_or_default();
+
+        Ok(res)
+    }
+
+    async fn set_guild_scope_mode(
+        &self,
+        guild_id: GuildId,
+        mode: ScopeMode
+    ) -> WishlistDbResult<()> {
+        self.guild_scope_modes.write().unwrap().insert(guild_id, mode);
+        Ok(())
+    }
+}
@@ -1,3 +1,6 @@
+pub mod cached_wishlist_db;
 pub mod logger;
 pub mod mongo_wishlist_db;
+pub mod sqlite_wishlist_db;
+#[cfg(test)]
 pub mod mocks;
\ No newline at end of file
@@ -1,373 +1,988 @@
-use std::{sync::Arc, vec};
+use std::{collections::{HashMap, HashSet}, sync::Arc, vec};
 
-use mongodb::{self, bson::{doc, Bson, Document}, error::Error, options::{ClientOptions, UpdateOptions}, Client};
-use serenity::{async_trait, futures::TryStreamExt};
+use chrono::{DateTime, Utc};
+use mongodb::{self, bson::{doc, oid::ObjectId, Bson, Document}, error::{Error, ErrorKind, WriteFailure}, options::{ClientOptions, FindOneAndUpdateOptions, FindOneOptions, FindOptions, IndexOptions, ReturnDocument, UpdateOptions}, Client, IndexModel};
+use serenity::all::{GuildId, UserId};
+use serenity::async_trait;
 
-use crate::{components::logger::Logger, traits::wishlist_db::WishlistDB};
+use crate::components::logger::Logger;
+use crate::model::catalog::{CatalogCard, CatalogSeries, CatalogStats};
+use crate::model::drop::{CardConstraint, DropMatch, DroppedCard, FuzzyWishlist};
+use crate::model::history::{ChangeSource, HistoryAction, HistoryRecord, WishlistChange};
+use crate::model::wishlist::{AddedCards, CardName, CardPriority, CardRef, OverviewQuery, RemovedCards, ScopeMode, SeriesName, SeriesOrder, SeriesOverview, WishlistEntry, WishlistOverview, WishlistPrivacy, WishlistScope, WishlistedCard, GLOBAL_SCOPE_KEY};
+use crate::traits::wishlist_db::{removed_cards, validate_alias, validate_entry, with_fuzzy_matches, WishlistDB, WishlistDbError, WishlistDbResult};
+use crate::util::normalize::search_key_range;
 
 pub struct MongoWishlistDB<T> 
     where T: Logger 
 {
     db_client: mongodb::Client,
+    database_name: String,
     logger: Arc<T>
 }
 
 pub async fn init_db<T>(logger: Arc<T>, uri: impl AsRef<str>) -> Result<MongoWishlistDB<T>, Error> 
     where T: Logger 
+{
+    init_db_with_database(logger, uri, WISHLIST_DATABASE_NAME).await
+}
+
+pub async fn init_db_with_database<T>(
+    logger: Arc<T>, 
+    uri: impl AsRef<str>, 
+    database_name: impl Into<String>
+) -> Result<MongoWishlistDB<T>, Error> 
+    where T: Logger 
 {
     // Create a new client and connect to the server
     let mut client_options = ClientOptions::parse_async(uri).await?;
     client_options.max_connecting = Some(3);
 
     // let client = mongodb::Client::with_uri_str(uri).await;
-    let client = Client::with_options(client_options);
+    let db_client = Client::with_options(client_options)?;
+    let database_name = database_name.into();
+
+    migrate_unscoped_wishlists(&db_client, &database_name, logger.as_ref()).await?;
+    migrate_search_terms(&db_client, &database_name, logger.as_ref()).await?;
+    migrate_catalog_seen_times(&db_client, &database_name, logger.as_ref()).await?;
+    migrate_added_times(&db_client, &database_name, logger.as_ref()).await?;
+    migrate_duplicate_wishlists(&db_client, &database_name, logger.as_ref()).await?;
+    create_indexes(&db_client, &database_name).await?;
+
+    let db = MongoWishlistDB{db_client, database_name, logger};
+
+    // scanning every wishlist is only worth it when the index was built by another version,
+    // or a failed write left it behind; this also builds it the first time
+    if db.card_index_needs_check().await? {
+        let report = db.check_card_index().await?;
+        if !report.is_consistent() {
+            db.logger.log_info(format!(
+                "Card index is out of sync ({} missing, {} stale entries), rebuilding it", report.missing, report.stale
+            ));
+            let entries = db.rebuild_card_index().await?;
+            db.logger.log_info(format!("Rebuilt the card index with {entries} entries"));
+        }
+        db.set_card_index_state(false).await?;
+    }
+
+    Ok(db)
+}
+
+// Indexes are only created when missing, so this is cheap on every startup
+async fn create_indexes(client: &Client, database_name: &str) -> Result<(), Error> {
+    // one wishlist per user and scope, concurrent first adds would otherwise both insert one
+    get_wishlist_collection(client, database_name)
+        .create_index(
+            IndexModel::builder()
+                .keys(doc! {"id": 1, "scope": 1})
+                .options(IndexOptions::builder().unique(true).build())
+                .build(),
+            None
+        )
+        .await?;
+
+    get_series_aliases_collection(client, database_name)
+        .create_index(IndexModel::builder().keys(doc! {"search": 1}).options(IndexOptions::builder().unique(true).build()).build(), None)
+        .await?;
+
+    create_card_index_indexes(&get_card_index_collection(client, database_name)).await?;
+
+    // only users opted in to fuzzy matching are indexed, so drops don't scan every user's settings
+    get_user_settings_collection(client, database_name)
+        .create_index(
+            IndexModel::builder()
+                .keys(doc! {"fuzzy_threshold": 1, "user_id": 1})
+                .options(IndexOptions::builder().partial_filter_expression(doc! {"fuzzy_threshold": {"$exists": true}}).build())
+                .build(),
+            None
+        )
+        .await?;
+
+    get_catalog_series_collection(client, database_name)
+        .create_index(IndexModel::builder().keys(doc! {"search": 1}).options(IndexOptions::builder().unique(true).build()).build(), None)
+        .await?;
+    get_catalog_cards_collection(client, database_name)
+        .create_index(
+            IndexModel::builder()
+                .keys(doc! {"series_search": 1, "search": 1})
+                .options(IndexOptions::builder().unique(true).build())
+                .build(),
+            None
+        )
+        .await?;
+    get_owned_cards_collection(client, database_name)
+        .create_index(
+            IndexModel::builder()
+                .keys(doc! {"user_id": 1, "series_search": 1, "search": 1})
+                .options(IndexOptions::builder().unique(true).build())
+                .build(),
+            None
+        )
+        .await?;
+
+    Ok(())
+}
+
+// Also used for the collection a rebuilt card index is written to, renaming it keeps them
+async fn create_card_index_indexes(card_index: &mongodb::Collection<Document>) -> Result<(), Error> {
+    card_index
+        .create_index(
+            IndexModel::builder()
+                .keys(doc! {"scope": 1, "series_search": 1, "card_search": 1, "user_id": 1})
+                .options(IndexOptions::builder().unique(true).build())
+                .build(),
+            None
+        )
+        .await?;
+    card_index
+        .create_index(IndexModel::builder().keys(doc! {"scope": 1, "user_id": 1}).build(), None)
+        .await?;
+
+    Ok(())
+}
+
+// Wishlists stored before scopes existed belong to the global scope
+async fn migrate_unscoped_wishlists<T>(client: &Client, database_name: &str, logger: &T) -> Result<(), Error> 
+    where T: Logger 
+{
+    let collection = get_wishlist_collection(client, database_name);
+
+    let res = collection.update_many(
+        doc! {"scope": {"$exists": false}},
+        doc! {"$set": {"scope": GLOBAL_SCOPE_KEY}},
+        None
+    ).await?;
+
+    if res.modified_count > 0 {
+        logger.log_info(format!("Moved {} wishlist(s) to the global scope", res.modified_count));
+    }
+
+    Ok(())
+}
+
+// Wishlists used to be created without a unique index, so concurrent first adds could create several
+// for the same user and scope. Merges them into the oldest one and drops the old index, once,
+// so `create_indexes` can make it unique.
+async fn migrate_duplicate_wishlists<T>(client: &Client, database_name: &str, logger: &T) -> Result<(), Error> 
+    where T: Logger 
+{
+    let migrations = get_migrations_collection(client, database_name);
+    if migrations.find_one(doc! {"name": UNIQUE_WISHLISTS_MIGRATION}, None).await?.is_some() {
+        return Ok(());
+    }
+
+    let collection = get_wishlist_collection(client, database_name);
+    let mut duplicates = collection.aggregate(
+        [
+            doc! { "$sort": { "_id": 1 }},
+            doc! { "$group": { "_id": { "id": "$id", "scope": "$scope" }, "ids": { "$push": "$_id" }, "count": { "$sum": 1 }}},
+            doc! { "$match": { "count": { "$gt": 1 }}}
+        ],
+        None
+    ).await?;
+
+    let mut merged_count = 0;
+    while duplicates.advance().await? {
+        let group: Document = duplicates.deserialize_current()?;
+        let ids: Vec<ObjectId> = group.get_array("ids").into_iter().flatten().filter_map(Bson::as_object_id).collect();
+        let Some((kept_id, other_ids)) = ids.split_first()
+        else {
+            continue;
+        };
+
+        let mut series: Vec<Document> = vec![];
+        let mut cursor = collection.find(doc! {"_id": {"$in": &ids}}, FindOptions::builder().sort(doc! {"_id": 1}).build()).await?;
+        while cursor.advance().await? {
+            let user_doc: Document = cursor.deserialize_current()?;
+            for series_doc in user_doc.get_array("series").into_iter().flatten().filter_map(Bson::as_document) {
+                merge_series_doc(&mut series, series_doc);
+            }
+        }
+
+        collection.update_one(doc! {"_id": kept_id}, doc! {"$set": {"series": series}}, None).await?;
+        collection.delete_many(doc! {"_id": {"$in": other_ids}}, None).await?;
+        merged_count += 1;
+    }
+
+    // the unique index can not replace the old one in place, it is created again by `create_indexes`
+    if let Err(err) = collection.drop_index("id_1_scope_1", None).await {
+        if !matches!(err.kind.as_ref(), ErrorKind::Command(command_err) if INDEX_NOT_FOUND_CODES.contains(&command_err.code)) {
+            return Err(err);
+        }
+    }
+
+    migrations.insert_one(doc! {"name": UNIQUE_WISHLISTS_MIGRATION, "applied_at": Utc::now().timestamp()}, None).await?;
+    if merged_count > 0 {
+        logger.log_info(format!("Merged the duplicate wishlists of {merged_count} user(s)"));
+    }
+
+    Ok(())
+}
+
+// Adds a series to the merged ones, or its cards not listed yet to the series of the same search term
+fn merge_series_doc(series: &mut Vec<Document>, series_doc: &Document) {
+    let search = series_doc.get_str("search").unwrap_or_default();
+    let Some(merged) = series.iter_mut().find(|merged| merged.get_str("search").unwrap_or_default() == search)
+    else {
+        series.push(series_doc.clone());
+        return;
+    };
+
+    let Ok(merged_cards) = merged.get_array_mut("cards")
+    else {
+        return;
+    };
+    for card_doc in series_doc.get_array("cards").into_iter().flatten() {
+        let card_search = card_doc.as_document().and_then(|card_doc| card_doc.get_str("search").ok());
+        let listed = merged_cards.iter()
+            .any(|merged_card| merged_card.as_document().and_then(|merged_card| merged_card.get_str("search").ok()) == card_search);
+        if !listed {
+            merged_cards.push(card_doc.clone());
+        }
+    }
+}
+
+// Catalog entries stored before seen times were tracked count as seen now
+async fn migrate_catalog_seen_times<T>(client: &Client, database_name: &str, logger: &T) -> Result<(), Error> 
+    where T: Logger 
+{
+    let now = Utc::now().timestamp();
+
+    let mut modified_count = 0;
+    for collection in [get_catalog_series_collection(client, database_name), get_catalog_cards_collection(client, database_name)] {
+        let res = collection.update_many(
+            doc! {"first_seen": {"$exists": false}},
+            doc! {"$set": {"first_seen": now, "last_seen": now}},
+            None
+        ).await?;
+        modified_count += res.modified_count;
+    }
+
+    if modified_count > 0 {
+        logger.log_info(format!("Set the seen times of {modified_count} catalog entries"));
+    }
+
+    Ok(())
+}
+
+// Search terms used to be lowercased and truncated, so series and cards whose names only differed
+// past the cut collided. Recomputes them from the stored names once, recorded in the migrations collection.
+async fn migrate_search_terms<T>(client: &Client, database_name: &str, logger: &T) -> Result<(), Error> 
+    where T: Logger 
+{
+    let migrations = get_migrations_collection(client, database_name);
+    if migrations.find_one(doc! {"name": SEARCH_TERMS_MIGRATION}, None).await?.is_some() {
+        return Ok(());
+    }
 
-    return client.map(|db_client| MongoWishlistDB{db_client, logger});
+    let collection = get_wishlist_collection(client, database_name);
+    let mut cursor = collection.find(doc! {}, None).await?;
+
+    let mut updated_count = 0;
+    while cursor.advance().await? {
+        let user_doc: Document = cursor.deserialize_current()?;
+        let (Ok(id), Ok(series)) = (user_doc.get_object_id("_id"), user_doc.get_array("series"))
+        else {
+            continue;
+        };
+
+        let normalized = normalized_series(series);
+        if normalized != *series {
+            collection.update_one(doc! {"_id": id}, doc! {"$set": {"series": normalized}}, None).await?;
+            updated_count += 1;
+        }
+    }
+
+    migrations.insert_one(doc! {"name": SEARCH_TERMS_MIGRATION, "applied_at": Utc::now().timestamp()}, None).await?;
+    logger.log_info(format!("Normalized the search terms of {updated_count} wishlist(s)"));
+
+    Ok(())
+}
+
+// Cards added before their time was stored get it from the history, replayed oldest change first.
+// Cards without any history keep an unknown time.
+async fn migrate_added_times<T>(client: &Client, database_name: &str, logger: &T) -> Result<(), Error> 
+    where T: Logger 
+{
+    let migrations = get_migrations_collection(client, database_name);
+    if migrations.find_one(doc! {"name": ADDED_TIMES_MIGRATION}, None).await?.is_some() {
+        return Ok(());
+    }
+
+    let mut added_at = HashMap::new();
+    let mut cursor = get_history_collection(client, database_name)
+        .find(doc! {}, FindOptions::builder().sort(doc! {"_id": 1}).build())
+        .await?;
+    while cursor.advance().await? {
+        let record_doc: Document = cursor.deserialize_current()?;
+        let (Ok(scope), Ok(user_id), Some(record)) = (record_doc.get_str("scope"), record_doc.get_str("user_id"), history_record(&record_doc))
+        else {
+            continue;
+        };
+
+        let series_search = record.change.entry.series.search_term();
+        for card in &record.change.entry.cards {
+            let key = (scope.to_string(), user_id.to_string(), series_search.clone(), card.search_term());
+            match record.change.action {
+                HistoryAction::Added => added_at.insert(key, record.timestamp.timestamp()),
+                HistoryAction::Removed => added_at.remove(&key)
+            };
+        }
+    }
+
+    let collection = get_wishlist_collection(client, database_name);
+    let mut cursor = collection.find(doc! {}, None).await?;
+
+    let mut updated_count = 0;
+    while cursor.advance().await? {
+        let user_doc: Document = cursor.deserialize_current()?;
+        let (Ok(id), Ok(user_id), Ok(scope), Ok(series)) = (
+            user_doc.get_object_id("_id"), user_doc.get_str("id"), user_doc.get_str("scope"), user_doc.get_array("series")
+        )
+        else {
+            continue;
+        };
+
+        let mut changed = false;
+        let mut series = series.clone();
+        for series_doc in series.iter_mut().filter_map(|series_doc| series_doc.as_document_mut()) {
+            let series_search = series_doc.get_str("search").unwrap_or_default().to_string();
+            let Ok(cards) = series_doc.get_array_mut("cards")
+            else {
+                continue;
+            };
+
+            for card_doc in cards.iter_mut().filter_map(|card_doc| card_doc.as_document_mut()) {
+                let key = (scope.to_string(), user_id.to_string(), series_search.clone(), card_doc.get_str("search").unwrap_or_default().to_string());
+                if let (false, Some(added_at)) = (card_doc.contains_key("added_at"), added_at.get(&key)) {
+                    card_doc.insert("added_at", *added_at);
+                    changed = true;
+                }
+            }
+        }
+
+        if changed {
+            collection.update_one(doc! {"_id": id}, doc! {"$set": {"series": series}}, None).await?;
+            updated_count += 1;
+        }
+    }
+
+    migrations.insert_one(doc! {"name": ADDED_TIMES_MIGRATION, "applied_at": Utc::now().timestamp()}, None).await?;
+    logger.log_info(format!("Filled the card added times of {updated_count} wishlist(s) from the history"));
+
+    Ok(())
+}
+
+// Recomputes the search terms of a wishlist's series, keeping the first of the series or cards
+// that now share a search term
+fn normalized_series(series: &[Bson]) -> Vec<Bson> {
+    let mut merged: Vec<Document> = Vec::with_capacity(series.len());
+
+    for series_doc in series.iter().filter_map(Bson::as_document) {
+        let Ok(name) = series_doc.get_str("name")
+        else {
+            continue;
+        };
+
+        let search = SeriesName::new(name).search_term();
+        let index = match merged.iter().position(|merged_doc| merged_doc.get_str("search") == Ok(search.as_str())) {
+            Some(index) => index,
+            None => {
+                let mut merged_doc = series_doc.clone();
+                merged_doc.insert("search", &search);
+                merged_doc.insert("cards", Vec::<Bson>::new());
+                merged.push(merged_doc);
+                merged.len() - 1
+            }
+        };
+
+        let Ok(merged_cards) = merged[index].get_array_mut("cards")
+        else {
+            continue;
+        };
+
+        let cards = series_doc.get_array("cards").into_iter().flatten().filter_map(Bson::as_document);
+        for card_doc in cards {
+            let Ok(card_name) = card_doc.get_str("name")
+            else {
+                continue;
+            };
+
+            let card_search = CardName::new(card_name).search_term();
+            let is_merged = merged_cards.iter()
+                .filter_map(Bson::as_document)
+                .any(|merged_card| merged_card.get_str("search") == Ok(card_search.as_str()));
+
+            if !is_merged {
+                let mut card_doc = card_doc.clone();
+                card_doc.insert("search", card_search);
+                merged_cards.push(Bson::Document(card_doc));
+            }
+        }
+    }
+
+    merged.into_iter().map(Bson::Document).collect()
+}
+
+// Moves a wishlist's series stored under an alias to its canonical series, merging the cards into
+// the canonical series when the wishlist already has it
+fn aliased_series_merged(series: &[Bson], alias_search: &str, canonical: &SeriesName) -> Vec<Bson> {
+    let mut series: Vec<Document> = series.iter().filter_map(Bson::as_document).cloned().collect();
+
+    let canonical_search = canonical.search_term();
+    let Some(alias_index) = series.iter().position(|series_doc| series_doc.get_str("search") == Ok(alias_search))
+    else {
+        return series.into_iter().map(Bson::Document).collect();
+    };
+
+    match series.iter().position(|series_doc| series_doc.get_str("search") == Ok(canonical_search.as_str())) {
+        Some(canonical_index) => {
+            let aliased = series.remove(alias_index);
+            let canonical_index = if canonical_index > alias_index { canonical_index - 1 } else { canonical_index };
+
+            if let Ok(merged_cards) = series[canonical_index].get_array_mut("cards") {
+                for card_bson in aliased.get_array("cards").into_iter().flatten() {
+                    let card_search = card_bson.as_document().and_then(|card_doc| card_doc.get_str("search").ok());
+                    let is_merged = merged_cards.iter()
+                        .filter_map(Bson::as_document)
+                        .any(|merged_card| merged_card.get_str("search").ok() == card_search);
+
+                    if !is_merged {
+                        merged_cards.push(card_bson.clone());
+                    }
+                }
+            }
+        },
+        None => {
+            series[alias_index].insert("name", canonical.as_str());
+            series[alias_index].insert("search", canonical_search);
+        }
+    }
+
+    series.into_iter().map(Bson::Document).collect()
 }
 
 #[async_trait]
 impl <T> WishlistDB for MongoWishlistDB<T> 
     where T: Logger + Send + Sync
 {
-    async fn get_users_with_series_card<'a> (
-        &'a self, 
-        cards: Vec<(&'a str, &'a str)>
-    ) -> Result<Vec<((&str, &str), Vec<String>)>, Box<dyn std::error::Error + Send + Sync>> 
-    {
-        let collection = get_wishlist_collection(&self.db_client);
-
-        let mut facet = doc! {};
-        let mut n = 0;
-        for (series, card_name) in cards.iter() {
-            let series_search = series_to_search_term(series);
-            let card_search = card_to_search_term(card_name);
-
-            facet.insert(format!("drop_{n}"), 
-                vec![
-                    doc!{ "$match": {"series": {"$elemMatch": {"search": series_search, "cards.search": card_search}}}},
-                    doc!{ "$project": { "id": 1}}
-                ]
-            );
-            n += 1;
+    async fn get_users_with_series_card (
+        &self, 
+        scope: WishlistScope,
+        cards: &[DroppedCard]
+    ) -> WishlistDbResult<Vec<(CardRef, Vec<DropMatch>)>> 
+    {
+        if cards.is_empty() {
+            return Ok(vec![]);
         }
 
-        let res =
-            collection.aggregate([doc! {"$facet": facet}], None).await;
+        let card_index = get_card_index_collection(&self.db_client, &self.database_name);
 
-        if let Err(err) = res {
-            self.logger.log_error(err.to_string());
-            return Err(Box::new(err));
+        // a drop usually repeats its series, so each distinct one is resolved once
+        let mut canonical: HashMap<String, String> = HashMap::new();
+        let mut keys = Vec::with_capacity(cards.len());
+        for dropped in cards {
+            let dropped_search = dropped.card.series.search_term();
+            let series_search = match canonical.get(&dropped_search) {
+                Some(series_search) => series_search.clone(),
+                None => {
+                    let series_search = self.canonical_series(&dropped.card.series).await?.search_term();
+                    canonical.insert(dropped_search, series_search.clone());
+                    series_search
+                }
+            };
+            keys.push((series_search, dropped.card.card.search_term()));
         }
 
-        let cursor = res.unwrap();
+        let wildcard_search = CardName::wildcard().search_term();
+        let mut branches: Vec<Document> = keys.iter()
+            .map(|(series_search, card_search)| doc!{ "series_search": series_search, "card_search": card_search })
+            .collect();
+        let series_searches: HashSet<&String> = keys.iter().map(|(series_search, _)| series_search).collect();
+        branches.extend(series_searches.into_iter().map(|series_search| doc!{ "series_search": series_search, "card_search": &wildcard_search }));
+
+        let mut cursor = card_index.find(
+            doc!{ "scope": scope.key(), "$or": branches },
+            FindOptions::builder().sort(doc!{ "user_id": 1 }).build()
+        ).await
+        .inspect_err(|err| self.logger.log_error(format!("get_users_with_series_card: {err}")))?;
+
+        let mut entries = Vec::new();
+        while cursor.advance().await? {
+            let entry_doc: Document = cursor.deserialize_current()?;
+            match card_index_entry(&entry_doc) {
+                Some(entry) => entries.push(entry),
+                None => self.logger.log_error("get_users_with_series_card: could not parse card index entry")
+            }
+        }
 
-        let ret: Vec<((&str, &str), Vec<String>)> = 
-            cursor.try_collect().await
-                  .unwrap_or_else(|_| vec![])
-                  .first()
-                  .map(move |x1| {
-                    x1.iter().map(|(drop_i, users_doc)|{
-                        let index: usize = drop_i[5..].parse().unwrap();
-                        let users: Vec<String> = 
-                            users_doc.as_array()
-                                     .unwrap()
-                                     .iter()
-                                     .map(|user_doc| user_doc.as_document().unwrap().get_str("id").unwrap().to_string())
-                                     .collect();
+        let mut ret = Vec::with_capacity(cards.len());
+        for (dropped, (series_search, card_search)) in cards.iter().zip(keys.iter()) {
+            let (exact, wildcards): (Vec<&CardIndexEntry>, Vec<&CardIndexEntry>) = entries.iter()
+                .filter(|entry| entry.series_search == *series_search)
+                .filter(|entry| entry.card_search == *card_search || entry.card_search == wildcard_search)
+                .partition(|entry| entry.card_search == *card_search);
+
+            // the wildcard card only stands in for users without the card itself
+            let wildcards = wildcards.into_iter()
+                .filter(|wildcard| !exact.iter().any(|entry| entry.user_id == wildcard.user_id))
+                .filter(|wildcard| !wildcard.excluded.contains(card_search));
+
+            let users: Vec<DropMatch> = exact.iter().copied()
+                .chain(wildcards)
+                .filter(|entry| dropped.satisfies(&entry.constraints))
+                .map(|entry| DropMatch::exact(entry.user_id, entry.priority))
+                .collect();
+
+            if !users.is_empty() {
+                ret.push((dropped.card.clone(), users));
+            }
+        }
 
-                        (cards.get(index).map(Clone::clone).unwrap(), users)
-                    })
-                    .filter(|(_, users)| !users.is_empty())
-                    .collect()
-                  }).unwrap();
+        let fuzzy_wishlists = self.get_fuzzy_wishlists(scope).await?;
 
-        return Ok(ret);
+        Ok(with_fuzzy_matches(cards, ret, &fuzzy_wishlists))
     }
 
-    async fn get_users_with_series<'a> (
-        &'a self, 
-        series:&Vec<&'a str>
-    ) -> Result<Vec<(&str, Vec<(String, i32)>)>, Box<dyn std::error::Error + Send + Sync>> 
+    async fn get_users_with_series (
+        &self, 
+        scope: WishlistScope,
+        series: &[SeriesName]
+    ) -> WishlistDbResult<Vec<(SeriesName, Vec<(UserId, i32)>)>> 
     {
-        let collection = get_wishlist_collection(&self.db_client);
+        if series.is_empty() {
+            return Ok(vec![]);
+        }
 
-        let mut facet = doc! {};
-        let mut n = 0;
-        for series in series.iter() {
-            let series_search = series_to_search_term(series);
+        let card_index = get_card_index_collection(&self.db_client, &self.database_name);
 
-            facet.insert(format!("drop_{n}"), 
-                vec![
-                    doc!{ "$match": { "series.search": &series_search }},
-                    doc!{ "$project": { 
-                        "id": 1, 
-                        "series":
-                            { "$filter": 
-                                { "input":"$series",
-                                "as": "serie",
-                                "cond": 
-                                    { "$eq": ["$$serie.search", series_search] }
-                                }
-                            }}},
-                    doc! { "$project": {
-                        "id": 1,
-                        "count": { "$size": { "$arrayElemAt": ["$series.cards", 0] } }
-                    }}
-                ]
-            );
-            n += 1;
-        }
-
-        let res =
-            collection.aggregate([doc! {"$facet": facet}], None).await;
-
-        if let Err(err) = res {
-            self.logger.log_error(err.to_string());
-            return Err(Box::new(err));
-        }
-
-        let cursor = res.unwrap();
-
-        let ret = 
-            cursor.try_collect().await
-                  .unwrap_or_else(|_| vec![])
-                  .first()
-                  .map(move |x1| {
-                    x1.iter().map(|(drop_i, users_count_doc)|{
-                        let index: usize = drop_i[5..].parse().unwrap();
-                        let users_count: Vec<(String, i32)> = users_count_doc
-                            .as_array()
-                            .unwrap()
-                            .iter()
-                            .map(|user_bson| {
-                                let user_doc = user_bson.as_document().unwrap();
-                                let user = user_doc.get_str("id").unwrap().to_string();
-                                let count = user_doc.get_i32("count").unwrap_or(0);
-                                (user, count)
-                            })
-                            .collect();
-
-                        (series.get(index).map(Clone::clone).unwrap(), users_count)
-                    })
-                    .filter(|(_, users)| !users.is_empty())
-                    .collect()
-                  }).unwrap();
+        let mut series_searches = Vec::with_capacity(series.len());
+        for series_name in series {
+            series_searches.push(self.canonical_series(series_name).await?.search_term());
+        }
+
+        let mut cursor = card_index.aggregate(
+            [
+                doc!{ "$match": { "scope": scope.key(), "series_search": { "$in": &series_searches }}},
+                doc!{ "$group": { "_id": { "series_search": "$series_search", "user_id": "$user_id" }, "count": { "$sum": 1 }}},
+                doc!{ "$sort": { "_id.user_id": 1 }}
+            ],
+            None
+        ).await
+        .inspect_err(|err| self.logger.log_error(format!("get_users_with_series: {err}")))?;
+
+        let mut counts: HashMap<String, Vec<(UserId, i32)>> = HashMap::new();
+        while cursor.advance().await? {
+            let count_doc: Document = cursor.deserialize_current()?;
+            let count = count_doc.get_document("_id").ok()
+                .and_then(|id| Some((id.get_str("series_search").ok()?, id.get_str("user_id").ok()?.parse().ok()?)))
+                .zip(count_doc.get_i32("count").ok());
+
+            match count {
+                Some(((series_search, user_id), count)) => counts.entry(series_search.to_string()).or_default().push((user_id, count)),
+                None => self.logger.log_error("get_users_with_series: could not parse card count")
+            }
+        }
+
+        let ret = series.iter().zip(series_searches.iter())
+            .filter_map(|(series_name, series_search)| Some((series_name.clone(), counts.get(series_search)?.clone())))
+            .collect();
 
-        return Ok(ret);
+        Ok(ret)
     }
 
-    async fn add_all_to_wishlist<'a> (
+    async fn add_all_to_wishlist (
         &self, 
-        user_id:&'a str, 
-        series:&'a str, 
-        mut card_names:Vec<&'a str>
-    ) -> Result<i32, Box<dyn std::error::Error + Send + Sync>> 
+        scope: WishlistScope,
+        user_id: UserId, 
+        entry: &WishlistEntry,
+        priority: CardPriority,
+        reprioritize: bool
+    ) -> WishlistDbResult<AddedCards> 
     {
-        let collection = get_wishlist_collection(&self.db_client);
-        
-        let series_search = series_to_search_term(series);
+        validate_entry(entry)?;
 
-        let initial_amount;
-        if !self.user_has_series(user_id, series).await {
-            let res = collection.update_one(
-                doc! {"id": user_id},
-                doc! {"$addToSet": { "series": { "name": series, "search": &series_search, "cards": [] }}},
-                UpdateOptions::builder().upsert(true).build()
-            ).await;
-            
-            if res.is_err() {
-                return Err(Box::new(res.unwrap_err()));
+        let series = self.canonical_series(&entry.series).await?;
+        let series_search = series.search_term();
+        let wildcard_search = CardName::wildcard().search_term();
+
+        // avoid processing duplicate cards
+        let mut seen_cards_search = HashSet::new();
+        let cards: Vec<(&CardName, String)> = entry.cards.iter()
+            .map(|card| (card, card.search_term()))
+            .filter(|(_, card_search)| seen_cards_search.insert(card_search.clone()))
+            .collect();
+
+        let added_at = Utc::now().timestamp();
+        let (res, series_doc) = self.change_series(scope, user_id, &series_search, |previous| {
+            let mut series_doc = previous.cloned()
+                .unwrap_or_else(|| doc!{"name": series.as_str(), "search": &series_search, "cards": []});
+            let mut card_docs = series_cards(&series_doc);
+
+            let mut res = AddedCards::default();
+            for (card, card_search) in cards.iter() {
+                match card_docs.iter_mut().find(|card_doc| card_doc.get_str("search").ok() == Some(card_search.as_str())) {
+                    Some(card_doc) => {
+                        res.already_wishlisted.push(CardName::new(card_doc.get_str("name").unwrap_or(card.as_str())));
+                        if reprioritize {
+                            card_doc.insert("priority", priority.as_str());
+                        }
+                    },
+                    None => {
+                        card_docs.push(doc!{"name": card.as_str(), "search": card_search, "priority": priority.as_str(), "added_at": added_at});
+                        res.added.push((*card).clone());
+                    }
+                }
             }
 
-            initial_amount = 0;
-        } else {
-            initial_amount = self.get_user_wishlisted_cards_count(user_id, series).await;
-        };
+            // adding a card takes it back into the series' wildcard
+            if let Some(wildcard_doc) = card_docs.iter_mut().find(|card_doc| card_doc.get_str("search").ok() == Some(wildcard_search.as_str())) {
+                let (included, excluded): (Vec<CardName>, Vec<CardName>) = wishlisted_card(wildcard_doc).map(|wildcard| wildcard.excluded).unwrap_or_default()
+                    .into_iter()
+                    .partition(|excluded| seen_cards_search.contains(&excluded.search_term()));
 
-        // avoid processing duplicate cards
-        card_names.sort();
-        card_names.dedup();
+                if !included.is_empty() {
+                    let excluded: Vec<&str> = excluded.iter().map(CardName::as_str).collect();
+                    wildcard_doc.insert("excluded", excluded);
+                    res.included = included;
+                }
+            }
+
+            series_doc.insert("cards", card_docs);
+            (Some(series_doc.clone()), (res, series_doc))
+        }).await
+        .inspect_err(|err| self.logger.log_error(format!("add_all_to_wishlist: {err}")))?;
+
+        let mut changed_search: Vec<String> = res.added.iter().map(CardName::search_term).collect();
+        if reprioritize {
+            changed_search.extend(res.already_wishlisted.iter().map(CardName::search_term));
+        }
+        if !res.included.is_empty() {
+            changed_search.push(wildcard_search);
+        }
 
-        // create card objects to insert
-        let cards : Vec<Document> = card_names.iter()
-            .map(|card| doc!{"name": card, "search": card_to_search_term(card)})
+        // the wishlist already changed, but a failed index write is still an error: drops would not
+        // ping for the cards until the startup check rebuilds the index
+        self.card_index_write("add_all_to_wishlist", self.reindex_cards(scope, user_id, &series_doc, &changed_search).await).await?;
+
+        Ok(res)
+    }
+
+    async fn set_cards_priority (
+        &self, 
+        scope: WishlistScope,
+        user_id: UserId, 
+        entry: &WishlistEntry,
+        priority: CardPriority
+    ) -> WishlistDbResult<i32> 
+    {
+        let collection = get_wishlist_collection(&self.db_client, &self.database_name);
+
+        let series = self.canonical_series(&entry.series).await?;
+        let series_search = series.search_term();
+        let cards_search : Vec<String> = entry.cards.iter()
+            .map(CardName::search_term)
             .collect();
 
-        // add all cards in one go
-        let res = collection.update_one( 
-            doc!{"id": user_id, "series.search": &series_search}, 
-            doc!{"$addToSet": { "series.$[elem].cards": doc!{"$each": cards} }}, 
-            UpdateOptions::builder()
-            .upsert(true)
-            .array_filters(vec![doc! {"elem.search": series_search }])
+        // the update only reports matched documents, the series as updated tells the matched cards
+        let updated_doc = collection.find_one_and_update( 
+            doc!{"id": user_id.to_string(), "scope": scope.key(), "series.search": &series_search}, 
+            doc!{"$set": { "series.$[elem].cards.$[card].priority": priority.as_str() }}, 
+            FindOneAndUpdateOptions::builder()
+            .array_filters(vec![doc! {"elem.search": &series_search }, doc! {"card.search": {"$in": &cards_search}}])
+            .return_document(ReturnDocument::After)
+            .projection(doc!{"series": {"$elemMatch": {"search": &series_search}}})
             .build()
+        ).await
+        .inspect_err(|err| self.logger.log_error(format!("set_cards_priority: {err}")))?;
+
+        let matched_count = updated_doc.as_ref().map(previous_series_cards).unwrap_or_default().iter()
+            .filter(|(_, search)| cards_search.contains(search))
+            .count();
+
+        let res = get_card_index_collection(&self.db_client, &self.database_name).update_many(
+            doc!{"scope": scope.key(), "user_id": user_id.to_string(), "series_search": &series_search, "card_search": {"$in": &cards_search}},
+            doc!{"$set": {"priority": priority.as_str()}},
+            None
         ).await;
+        self.card_index_write("set_cards_priority", res).await?;
 
-        match res {
-            Ok(_) => {
-                let curr_amount = self.get_user_wishlisted_cards_count(user_id, series).await;
-                Ok(curr_amount - initial_amount)
-            },
-            Err(err) => {
-                self.logger.log_error(err.to_string());
-                Err(Box::new(err))
-            }
-        }
+        Ok(matched_count as i32)
     }
 
-    async fn remove_all_from_wishlist (
+    async fn set_card_note (
+        &self, 
+        scope: WishlistScope,
+        user_id: UserId, 
+        card: &CardRef,
+        note: Option<&str>
+    ) -> WishlistDbResult<()> 
+    {
+        let update = match note {
+            Some(note) => doc!{"$set": { "series.$[elem].cards.$[card].note": note }},
+            None => doc!{"$unset": { "series.$[elem].cards.$[card].note": "" }}
+        };
+
+        self.update_card(scope, user_id, card, update).await
+            .inspect_err(|err| self.logger.log_error(format!("set_card_note: {err}")))
+    }
+
+    async fn set_card_constraints (
         &self, 
-        user_id:&str, 
-        series:&str, 
-        card_names:Vec<&str>
-    ) -> Result<(i32, i32), Box<dyn std::error::Error + Send + Sync>> 
-    {
-        let collection = get_wishlist_collection(&self.db_client);
-        
-        let initial_amount = self.get_user_wishlisted_cards_count(user_id, series).await;
-        let series_search = series_to_search_term(series);
-        let cards_search : Vec<String> = card_names.iter()
-            .map(|card| card_to_search_term(card))
+        scope: WishlistScope,
+        user_id: UserId, 
+        card: &CardRef,
+        constraints: &[CardConstraint]
+    ) -> WishlistDbResult<()> 
+    {
+        let constraints: Vec<String> = constraints.iter()
+            .map(CardConstraint::to_string)
             .collect();
 
-        let res = 
-            collection.update_one( 
-                doc!{"id": user_id, "series.search": &series_search}, 
-                doc!{"$pull": { "series.$[elem].cards": doc!{"search": {"$in": cards_search}} }}, 
-                UpdateOptions::builder()
-                .array_filters(vec![doc! {"elem.search": series_search }])
-                .build()
-            ).await;
+        self.update_card(scope, user_id, card, doc!{"$set": { "series.$[elem].cards.$[card].constraints": &constraints }}).await
+            .inspect_err(|err| self.logger.log_error(format!("set_card_constraints: {err}")))?;
+
+        let series_search = self.canonical_series(&card.series).await?.search_term();
+        let res = get_card_index_collection(&self.db_client, &self.database_name).update_one(
+            doc!{"scope": scope.key(), "user_id": user_id.to_string(), "series_search": series_search, "card_search": card.card.search_term()},
+            doc!{"$set": {"constraints": constraints}},
+            None
+        ).await;
+        self.card_index_write("set_card_constraints", res).await?;
 
-        match res {
-            Ok(_) => {
-                let curr_amount = self.get_user_wishlisted_cards_count(user_id, series).await;
+        Ok(())
+    }
 
-                if curr_amount == 0 {
-                    self.remove_series_from_wishlist(user_id, series).await?;
-                }
+    async fn set_wildcard_exclusions (
+        &self, 
+        scope: WishlistScope,
+        user_id: UserId, 
+        series: &SeriesName,
+        excluded: &[CardName]
+    ) -> WishlistDbResult<()> 
+    {
+        let wildcard = CardRef { series: series.clone(), card: CardName::wildcard() };
+        let excluded_names: Vec<&str> = excluded.iter().map(CardName::as_str).collect();
 
-                Ok((initial_amount - curr_amount, curr_amount))
-            },
-            Err(err) => Err(Box::new(err))
-        }
+        self.update_card(scope, user_id, &wildcard, doc!{"$set": { "series.$[elem].cards.$[card].excluded": excluded_names }}).await
+            .inspect_err(|err| self.logger.log_error(format!("set_wildcard_exclusions: {err}")))?;
+
+        let series_search = self.canonical_series(series).await?.search_term();
+        let excluded_search: Vec<String> = excluded.iter().map(CardName::search_term).collect();
+        let res = get_card_index_collection(&self.db_client, &self.database_name).update_one(
+            doc!{"scope": scope.key(), "user_id": user_id.to_string(), "series_search": series_search, "card_search": wildcard.card.search_term()},
+            doc!{"$set": {"excluded": excluded_search}},
+            None
+        ).await;
+        self.card_index_write("set_wildcard_exclusions", res).await?;
+
+        Ok(())
     }
 
-    async fn get_user_wishlisted_series (
+    async fn remove_all_from_wishlist (
         &self, 
-        user_id: &str
-    ) -> Vec<String> 
+        scope: WishlistScope,
+        user_id: UserId, 
+        entry: &WishlistEntry,
+        exclude: bool
+    ) -> WishlistDbResult<RemovedCards> 
     {
-        let collection = get_wishlist_collection(&self.db_client);
+        let series_search = self.canonical_series(&entry.series).await?.search_term();
+        let wildcard_search = CardName::wildcard().search_term();
+        let cards_search : Vec<String> = entry.cards.iter()
+            .map(CardName::search_term)
+            .collect();
 
-        let Ok(mut cursor) =
-            collection.aggregate(
-                [
-                    doc!{"$match": { "id": user_id }},
-                    doc!{"$project": { "series": "$series.name"}}
-                    // doc!{"$project": { "series": { "$slice": ["$series.name", start, end]}}}
-                ],
-                None
-            ).await 
-            else { todo!() };
-
-        match cursor.advance().await {
-            Ok(true) => (),
-            Ok(false) => return vec![], // TODO maybe log a warning?
-            Err(err) => {
-                self.logger.log_error(format!("get_user_wishlisted_series: {}", err.to_string()));
-                return vec![];
-            },
-        }
+        let (res, series_doc) = self.change_series(scope, user_id, &series_search, |previous| {
+            let Some(previous) = previous
+            else {
+                return (None, (removed_cards(entry, vec![], None), None));
+            };
+
+            let (removed, mut kept): (Vec<Document>, Vec<Document>) = series_cards(previous).into_iter()
+                .partition(|card_doc| card_doc.get_str("search").is_ok_and(|search| cards_search.iter().any(|card_search| card_search == search)));
+
+            // a card removed from a series wishlisted whole is left out of its wildcard,
+            // which counts as removing it even when it was never listed
+            let wildcard_doc = kept.iter_mut()
+                .find(|card_doc| card_doc.get_str("search").ok() == Some(wildcard_search.as_str()))
+                .filter(|_| exclude);
+            let wildcard = wildcard_doc.as_deref().and_then(wishlisted_card);
+
+            let mut res = removed_cards(entry, removed.iter().filter_map(wishlisted_card).collect(), wildcard.as_ref());
+            if let (Some(wildcard_doc), Some(wildcard)) = (wildcard_doc, wildcard) {
+                let excluded: Vec<&str> = wildcard.excluded.iter().chain(res.excluded.iter()).map(CardName::as_str).collect();
+                wildcard_doc.insert("excluded", excluded);
+            }
 
-        let Ok(x) = cursor.deserialize_current()
-        else {
-            // TODO log error
-            return vec![];
+            // an empty series is no longer part of the wishlist
+            res.left = kept.len() as i32;
+            if kept.is_empty() {
+                return (None, (res, None));
+            }
+
+            let mut series_doc = previous.clone();
+            series_doc.insert("cards", kept);
+            (Some(series_doc.clone()), (res, Some(series_doc)))
+        }).await
+        .inspect_err(|err| self.logger.log_error(format!("remove_all_from_wishlist: {err}")))?;
+
+        let card_index = async {
+            if !res.details.is_empty() {
+                let removed_search: Vec<String> = res.details.iter().map(|card| card.name.search_term()).collect();
+                get_card_index_collection(&self.db_client, &self.database_name).delete_many(
+                    doc!{"scope": scope.key(), "user_id": user_id.to_string(), "series_search": &series_search, "card_search": {"$in": removed_search}},
+                    None
+                ).await?;
+            }
+            match series_doc {
+                Some(series_doc) if !res.excluded.is_empty() => self.reindex_cards(scope, user_id, &series_doc, &[wildcard_search]).await,
+                _ => Ok(())
+            }
         };
+        self.card_index_write("remove_all_from_wishlist", card_index.await).await?;
 
-        let series_vec = x.get_array("series");
-        if let Err(_) = series_vec {
-            // TODO log error
-            return vec![];
-        }
+        Ok(res)
+    }
 
-        let mut ret = vec![];
-        for opt_series in series_vec.unwrap().iter().map(Bson::as_str) {
-            match opt_series {
-                Some(series) => ret.push(series.to_string()),
-                None => {
-                    self.logger.log_error("get_user_wishlisted_series: could not parse Bson as string")
-                }
-            }
+    async fn include_in_wildcard (
+        &self, 
+        scope: WishlistScope,
+        user_id: UserId, 
+        entry: &WishlistEntry
+    ) -> WishlistDbResult<Vec<CardName>> 
+    {
+        let series_search = self.canonical_series(&entry.series).await?.search_term();
+        let wildcard_search = CardName::wildcard().search_term();
+        let cards_search : Vec<String> = entry.cards.iter()
+            .map(CardName::search_term)
+            .collect();
+
+        let (included, series_doc) = self.change_series(scope, user_id, &series_search, |previous| {
+            let Some(previous) = previous
+            else {
+                return (None, (vec![], None));
+            };
+
+            let mut card_docs = series_cards(previous);
+            let Some(wildcard_doc) = card_docs.iter_mut().find(|card_doc| card_doc.get_str("search").ok() == Some(wildcard_search.as_str()))
+            else {
+                return (Some(previous.clone()), (vec![], None));
+            };
+
+            let (included, excluded): (Vec<CardName>, Vec<CardName>) = wishlisted_card(wildcard_doc).map(|wildcard| wildcard.excluded).unwrap_or_default()
+                .into_iter()
+                .partition(|excluded| cards_search.contains(&excluded.search_term()));
+            let excluded: Vec<&str> = excluded.iter().map(CardName::as_str).collect();
+            wildcard_doc.insert("excluded", excluded);
+
+            let mut series_doc = previous.clone();
+            series_doc.insert("cards", card_docs);
+            (Some(series_doc.clone()), (included, Some(series_doc)))
+        }).await
+        .inspect_err(|err| self.logger.log_error(format!("include_in_wildcard: {err}")))?;
+
+        if let Some(series_doc) = series_doc.filter(|_| !included.is_empty()) {
+            self.card_index_write("include_in_wildcard", self.reindex_cards(scope, user_id, &series_doc, &[wildcard_search]).await).await?;
         }
 
-        return ret;
+        Ok(included)
     }
 
-    async fn get_user_wishlisted_cards_count (
+    async fn get_wishlist_overview (
         &self, 
-        user_id: &str, 
-        series: &str
-    ) -> i32 
+        scope: WishlistScope,
+        user_id: UserId,
+        query: &OverviewQuery
+    ) -> WishlistDbResult<WishlistOverview> 
     {
-        let collection = get_wishlist_collection(&self.db_client);
+        let collection = get_wishlist_collection(&self.db_client, &self.database_name);
+
+        let mut series_projection = doc!{ 
+            "_id": 0, 
+            "position": 1, 
+            "name": "$series.name", 
+            "search": "$series.search", 
+            "cards_count": { "$size": "$series.cards" },
+            "whole": { "$in": [CardName::wildcard().search_term(), "$series.cards.search"] }
+        };
+        if query.with_cards {
+            series_projection.insert("cards", "$series.cards");
+        }
 
-        let series_search = series_to_search_term(series);
+        let sort = match query.order {
+            SeriesOrder::Added => doc!{ "position": 1 },
+            SeriesOrder::Name => doc!{ "search": 1, "position": 1 },
+            SeriesOrder::CardCount => doc!{ "cards_count": -1, "position": 1 }
+        };
+        let mut page_stages = vec![doc!{ "$sort": sort }, doc!{ "$skip": query.skip as i64 }];
+        // $limit has to be positive, an empty page is cut below
+        if let Some(limit) = query.limit {
+            page_stages.push(doc!{ "$limit": limit.max(1) as i64 });
+        }
 
-        let res =
-            collection.aggregate(
-                [
-                    doc!{ "$match": { "id": user_id, "series.search": &series_search }},
-                    doc!{ "$project": { "series":
-                    { "$filter": 
-                        { "input":"$series",
-                          "as": "serie",
-                          "cond": 
-                            { "$eq": ["$$serie.search", series_search] }
-                        }
-                    }}},
-                    doc! { "$project": {
-                        "count": { "$size": { "$arrayElemAt": ["$series.cards", 0] } }
-                      }}
-                ],
-                None
-            ).await;
+        let mut cursor = collection.aggregate(
+            [
+                doc!{ "$match": { "id": user_id.to_string(), "scope": scope.key() }},
+                doc!{ "$unwind": { "path": "$series", "includeArrayIndex": "position" }},
+                doc!{ "$project": series_projection },
+                doc!{ "$facet": {
+                    "page": page_stages,
+                    "totals": [{ "$group": { "_id": null, "series": { "$sum": 1 }, "cards": { "$sum": "$cards_count" }}}]
+                }}
+            ],
+            None
+        ).await
+        .inspect_err(|err| self.logger.log_error(format!("get_wishlist_overview: {err}")))?;
 
-        
-        if let Err(err) = res { 
-            self.logger.log_error(format!("get_user_wishlisted_cards_count: {}", err.to_string()));
-            return 0; 
-        };
+        if !cursor.advance().await? {
+            return Ok(WishlistOverview::default());
+        }
+        let overview_doc: Document = cursor.deserialize_current()?;
+
+        let totals = overview_doc.get_array("totals").ok()
+            .and_then(|totals| totals.first()?.as_document())
+            .map(|totals| (bson_count(totals.get("series")), bson_count(totals.get("cards"))))
+            .unwrap_or_default();
+
+        let mut series = vec![];
+        for series_doc in overview_doc.get_array("page").into_iter().flatten().filter_map(Bson::as_document) {
+            let (Ok(name), Ok(cards_count)) = (series_doc.get_str("name"), series_doc.get_i32("cards_count"))
+            else {
+                self.logger.log_error("get_wishlist_overview: could not parse series");
+                continue;
+            };
+
+            let cards = query.with_cards.then(|| 
+                series_doc.get_array("cards").into_iter().flatten()
+                    .filter_map(Bson::as_document)
+                    .filter_map(wishlisted_card)
+                    .collect()
+            );
 
-        let mut cursor = res.unwrap(); 
-        if cursor.advance().await.unwrap() {
-            match cursor.current().get_i32("count") {
-                Ok(count) => count,
-                Err(err) => {
-                    self.logger.log_error(format!("get_user_wishlisted_cards_count: {}", err.to_string()));
-                    0
-                }
-            }
-        } else {
-            0
+            let whole = series_doc.get_bool("whole").unwrap_or_default();
+            series.push(SeriesOverview { series: SeriesName::new(name), cards_count, whole, cards });
         }
+        series.truncate(query.limit.unwrap_or(usize::MAX));
+
+        Ok(WishlistOverview { series, total_series: totals.0, total_cards: totals.1 })
     }
 
     async fn get_user_wishlisted_cards (
         &self, 
-        user_id: &str, 
-        series: &str
-    ) -> Vec<String> 
+        scope: WishlistScope,
+        user_id: UserId, 
+        series: &SeriesName
+    ) -> WishlistDbResult<Vec<WishlistedCard>> 
     {
-        let collection = get_wishlist_collection(&self.db_client);
+        let collection = get_wishlist_collection(&self.db_client, &self.database_name);
 
-        let series_search = series_to_search_term(series);
+        let series_search = self.canonical_series(series).await?.search_term();
 
-        let res =
+        let mut cursor =
             collection.aggregate(
                 [
-                    doc!{ "$match": { "id": user_id, "series.search": &series_search }},
+                    doc!{ "$match": { "id": user_id.to_string(), "scope": scope.key(), "series.search": &series_search }},
                     doc!{ "$project": { "series":
                     { "$filter": 
                         { "input":"$series",
@@ -377,129 +992,1280 @@ impl <T> WishlistDB for MongoWishlistDB<T>
                         }
                     }}},
                     doc! { "$project": {
-                        "cards": { "$map": { "input": { "$arrayElemAt": ["$series.cards", 0]}, "as": "card", "in": "$$card.name" } }
+                        "cards": { "$map": { "input": { "$arrayElemAt": ["$series.cards", 0]}, "as": "card", "in": {
+                            "name": "$$card.name", "priority": "$$card.priority", "note": "$$card.note", "constraints": "$$card.constraints",
+                            "excluded": "$$card.excluded", "added_at": "$$card.added_at"
+                        } } }
                       }}
                 ],
                 None
-            ).await;
+            ).await
+            .inspect_err(|err| self.logger.log_error(format!("get_user_wishlisted_cards: {err}")))?;
 
-        
-        if let Err(err) = res { 
-            self.logger.log_error(format!("get_user_wishlisted_cards_count: {}", err.to_string()));
-            return vec![]; 
-        };
+        if !cursor.advance().await? {
+            return Ok(vec![]);
+        }
 
-        let mut cursor = res.unwrap(); 
-        if cursor.advance().await.unwrap() {
-            match cursor.current().get_array("cards") {
-                Ok(cards) => {
-                    
-                    let mut vec_string: Vec<String> = Vec::new();
-                    for x in cards.into_iter() {
-                        match x {
-                            Err(_) => (),
-                            Ok(card) => {
-                                vec_string.push(card.as_str().unwrap().to_string());
-                            }
-                        }
-                    }
-                    
-                    vec_string
-                },
-                Err(err) => {
-                    self.logger.log_error(format!("get_user_wishlisted_cards_count: {}", err.to_string()));
-                    vec![]
-                }
+        let cards_doc: Document = cursor.deserialize_current()
+            .inspect_err(|err| self.logger.log_error(format!("get_user_wishlisted_cards: {err}")))?;
+        let cards = cards_doc.get_array("cards")
+            .map_err(backend_error)
+            .inspect_err(|err| self.logger.log_error(format!("get_user_wishlisted_cards: {err}")))?;
+
+        let mut ret = Vec::new();
+        for card in cards {
+            match card.as_document().and_then(wishlisted_card) {
+                Some(card) => ret.push(card),
+                None => self.logger.log_error("get_user_wishlisted_cards: could not parse Bson as card")
             }
-        } else {
-            vec![]
         }
+
+        Ok(ret)
     }
 
     async fn user_has_card (
         &self, 
-        user_id: &str, 
-        series: &str, 
-        card: &str
-    ) -> bool {
-        let collection = get_wishlist_collection(&self.db_client);
+        scope: WishlistScope,
+        user_id: UserId, 
+        card: &CardRef
+    ) -> WishlistDbResult<bool> {
+        let collection = get_wishlist_collection(&self.db_client, &self.database_name);
 
-        let series_search = series_to_search_term(series);
-        let card_search = card_to_search_term(card);
+        let series_search = self.canonical_series(&card.series).await?.search_term();
+        let card_search = card.card.search_term();
 
-        match collection.find_one(
-            doc!{ "id": user_id, "series": { "$elemMatch": {"search": series_search, "cards.search": card_search}}},
+        let user_doc = collection.find_one(
+            doc!{ "id": user_id.to_string(), "scope": scope.key(), "series": { "$elemMatch": {"search": series_search, "cards.search": card_search}}},
             None
-        ).await {
-            Ok(x) => x.is_some(),
-            Err(_) => false,
-        }
+        ).await
+        .inspect_err(|err| self.logger.log_error(format!("user_has_card: {err}")))?;
+
+        Ok(user_doc.is_some())
     }
 
     async fn remove_series_from_wishlist (
         &self, 
-        user_id:&str, 
-        series:&str
-    ) -> Result<i32, Box<dyn std::error::Error + Send + Sync>> 
+        scope: WishlistScope,
+        user_id: UserId, 
+        series: &SeriesName
+    ) -> WishlistDbResult<i32> 
     {
-        let collection = get_wishlist_collection(&self.db_client);
+        let collection = get_wishlist_collection(&self.db_client, &self.database_name);
+
+        let series_search = self.canonical_series(series).await?.search_term();
+
+        // the series as it was before the update tells how many cards it had
+        let previous_doc = 
+            collection.find_one_and_update( 
+                doc!{"id": user_id.to_string(), "scope": scope.key(), "series.search": &series_search}, 
+                doc!{"$pull": { "series": {"search": &series_search}}}, 
+                FindOneAndUpdateOptions::builder()
+                .return_document(ReturnDocument::Before)
+                .projection(doc!{"series": {"$elemMatch": {"search": &series_search}}})
+                .build()
+            ).await
+            .inspect_err(|err| self.logger.log_error(format!("remove_series_from_wishlist: {err}")))?;
 
-        let series_search = series_to_search_term(series);
-        let series_cards_amount = self.get_user_wishlisted_cards_count(user_id, series).await;
+        let Some(previous_doc) = previous_doc
+        else {
+            return Err(WishlistDbError::NotFound(format!("series `{series}`")));
+        };
+        let series_cards_amount = previous_series_cards(&previous_doc).len() as i32;
 
-        let res = 
-            collection.update_one( 
-                doc!{"id": user_id, "series.search": &series_search}, 
-                doc!{"$pull": { "series": {"search": series_search}}}, 
+        let res = get_card_index_collection(&self.db_client, &self.database_name).delete_many(
+            doc!{"scope": scope.key(), "user_id": user_id.to_string(), "series_search": series_search},
+            None
+        ).await;
+        self.card_index_write("remove_series_from_wishlist", res).await?;
+
+        Ok(series_cards_amount)
+    }
+
+    async fn search_user_series (
+        &self, 
+        scope: WishlistScope,
+        user_id: UserId, 
+        prefix: &str,
+        limit: usize
+    ) -> WishlistDbResult<Vec<SeriesName>> 
+    {
+        let collection = get_wishlist_collection(&self.db_client, &self.database_name);
+
+        let (start, end) = search_key_range(prefix);
+
+        let mut cursor =
+            collection.aggregate(
+                [
+                    doc!{ "$match": { "id": user_id.to_string(), "scope": scope.key() }},
+                    doc!{ "$unwind": "$series" },
+                    doc!{ "$match": { "series.search": { "$gte": start, "$lt": end }}},
+                    doc!{ "$sort": { "series.search": 1 }},
+                    doc!{ "$limit": limit as i64 },
+                    doc!{ "$project": { "name": "$series.name" }}
+                ],
                 None
-            ).await;
+            ).await
+            .inspect_err(|err| self.logger.log_error(format!("search_user_series: {err}")))?;
+
+        let mut ret = Vec::new();
+        while cursor.advance().await? {
+            match cursor.current().get_str("name") {
+                Ok(name) => ret.push(SeriesName::new(name)),
+                Err(_) => self.logger.log_error("search_user_series: could not parse series name")
+            }
+        }
+
+        Ok(ret)
+    }
+
+    async fn search_user_cards (
+        &self, 
+        scope: WishlistScope,
+        user_id: UserId, 
+        series: &SeriesName,
+        prefix: &str,
+        limit: usize
+    ) -> WishlistDbResult<Vec<CardName>> 
+    {
+        let collection = get_wishlist_collection(&self.db_client, &self.database_name);
+
+        let series_search = self.canonical_series(series).await?.search_term();
+        let (start, end) = search_key_range(prefix);
+
+        let mut cursor =
+            collection.aggregate(
+                [
+                    doc!{ "$match": { "id": user_id.to_string(), "scope": scope.key(), "series.search": &series_search }},
+                    doc!{ "$unwind": "$series" },
+                    doc!{ "$match": { "series.search": &series_search }},
+                    doc!{ "$unwind": "$series.cards" },
+                    doc!{ "$match": { "series.cards.search": { "$gte": start, "$lt": end }}},
+                    doc!{ "$sort": { "series.cards.search": 1 }},
+                    doc!{ "$limit": limit as i64 },
+                    doc!{ "$project": { "name": "$series.cards.name" }}
+                ],
+                None
+            ).await
+            .inspect_err(|err| self.logger.log_error(format!("search_user_cards: {err}")))?;
+
+        let mut ret = Vec::new();
+        while cursor.advance().await? {
+            match cursor.current().get_str("name") {
+                Ok(name) => ret.push(CardName::new(name)),
+                Err(_) => self.logger.log_error("search_user_cards: could not parse card name")
+            }
+        }
+
+        Ok(ret)
+    }
+
+    async fn record_change (
+        &self, 
+        scope: WishlistScope,
+        user_id: UserId, 
+        change: &WishlistChange
+    ) -> WishlistDbResult<()> 
+    {
+        let collection = get_history_collection(&self.db_client, &self.database_name);
+
+        let reverts = change.reverts.as_ref()
+            .and_then(|id| ObjectId::parse_str(id).ok())
+            .map_or(Bson::Null, Bson::ObjectId);
+        let cards: Vec<&str> = change.entry.cards.iter()
+            .map(CardName::as_str)
+            .collect();
+        let details: Vec<Document> = change.details.iter()
+            .map(history_card_doc)
+            .collect();
+        let wildcard: Vec<&str> = change.wildcard.iter()
+            .map(CardName::as_str)
+            .collect();
+
+        collection.insert_one(
+            doc!{
+                "scope": scope.key(),
+                "user_id": user_id.to_string(),
+                "action": change.action.as_str(),
+                "series": change.entry.series.as_str(),
+                "cards": cards,
+                "details": details,
+                "wildcard": wildcard,
+                "source": change.source.as_str(),
+                "reverts": reverts,
+                "timestamp": Utc::now().timestamp()
+            },
+            None
+        ).await
+        .inspect_err(|err| self.logger.log_error(format!("record_change: {err}")))?;
+
+        Ok(())
+    }
+
+    async fn get_user_history (
+        &self, 
+        scope: WishlistScope,
+        user_id: UserId, 
+        limit: usize
+    ) -> WishlistDbResult<Vec<HistoryRecord>> 
+    {
+        // a $limit of 0 is not a valid stage
+        if limit == 0 {
+            return Ok(vec![]);
+        }
+
+        let collection = get_history_collection(&self.db_client, &self.database_name);
+
+        let mut cursor = collection.aggregate(
+            [
+                doc!{ "$match": { "scope": scope.key(), "user_id": user_id.to_string() }},
+                doc!{ "$sort": { "_id": -1 }},
+                doc!{ "$limit": limit as i64 }
+            ],
+            None
+        ).await
+        .inspect_err(|err| self.logger.log_error(format!("get_user_history: {err}")))?;
+
+        let mut ret = Vec::new();
+        while cursor.advance().await? {
+            let record_doc: Document = cursor.deserialize_current()?;
+            match history_record(&record_doc) {
+                Some(record) => ret.push(record),
+                None => self.logger.log_error("get_user_history: could not parse history document")
+            }
+        }
+
+        Ok(ret)
+    }
+
+    async fn get_last_undoable_change (
+        &self, 
+        scope: WishlistScope,
+        user_id: UserId
+    ) -> WishlistDbResult<Option<HistoryRecord>> 
+    {
+        let collection = get_history_collection(&self.db_client, &self.database_name);
+
+        let mut cursor = collection.aggregate(
+            [
+                doc!{ "$match": { "scope": scope.key(), "user_id": user_id.to_string(), "source": { "$ne": ChangeSource::Undo.as_str() }}},
+                doc!{ "$sort": { "_id": -1 }},
+                doc!{ "$lookup": { "from": HISTORY_COLLECTION_NAME, "localField": "_id", "foreignField": "reverts", "as": "undos" }},
+                doc!{ "$match": { "undos": { "$size": 0 }}},
+                doc!{ "$limit": 1 }
+            ],
+            None
+        ).await
+        .inspect_err(|err| self.logger.log_error(format!("get_last_undoable_change: {err}")))?;
+
+        if !cursor.advance().await? {
+            return Ok(None);
+        }
+
+        let record_doc: Document = cursor.deserialize_current()?;
+        history_record(&record_doc)
+            .map(Some)
+            .ok_or_else(|| WishlistDbError::Backend("could not parse history document".into()))
+            .inspect_err(|err| self.logger.log_error(format!("get_last_undoable_change: {err}")))
+    }
+
+    async fn get_fuzzy_wishlists (
+        &self, 
+        scope: WishlistScope
+    ) -> WishlistDbResult<Vec<FuzzyWishlist>> 
+    {
+        let settings_collection = get_user_settings_collection(&self.db_client, &self.database_name);
+
+        // answered from the opt-in index alone
+        let mut cursor = settings_collection.find(
+            doc!{ "fuzzy_threshold": {"$exists": true} },
+            FindOptions::builder()
+                .projection(doc!{ "_id": 0, "user_id": 1, "fuzzy_threshold": 1 })
+                .build()
+        ).await
+        .inspect_err(|err| self.logger.log_error(format!("get_fuzzy_wishlists: {err}")))?;
+
+        let mut thresholds = Vec::new();
+        while cursor.advance().await? {
+            let settings_doc: Document = cursor.deserialize_current()?;
+            let threshold = settings_doc.get_str("user_id").ok()
+                .and_then(|user_id| Some((user_id.to_string(), u8::try_from(settings_doc.get_i32("fuzzy_threshold").ok()?).ok()?)));
+
+            match threshold {
+                Some(threshold) => thresholds.push(threshold),
+                None => self.logger.log_error("get_fuzzy_wishlists: could not parse user settings")
+            }
+        }
+
+        // nobody opted in, skip reading the wishlists
+        if thresholds.is_empty() {
+            return Ok(vec![]);
+        }
+
+        let collection = get_wishlist_collection(&self.db_client, &self.database_name);
+        let user_ids: Vec<&String> = thresholds.iter().map(|(user_id, _)| user_id).collect();
+
+        let mut cursor = collection.find(doc!{ "scope": scope.key(), "id": {"$in": user_ids} }, None).await
+            .inspect_err(|err| self.logger.log_error(format!("get_fuzzy_wishlists: {err}")))?;
+
+        let mut ret = Vec::new();
+        while cursor.advance().await? {
+            let user_doc: Document = cursor.deserialize_current()?;
+            let Some((user_id, threshold)) = user_doc.get_str("id").ok()
+                .and_then(|id| thresholds.iter().find(|(user_id, _)| user_id == id))
+                .and_then(|(user_id, threshold)| Some((user_id.parse().ok()?, *threshold)))
+            else {
+                continue;
+            };
+
+            let cards = user_doc.get_array("series").into_iter().flatten()
+                .filter_map(Bson::as_document)
+                .filter_map(|series_doc| Some((SeriesName::new(series_doc.get_str("name").ok()?), series_doc.get_array("cards").ok()?)))
+                .flat_map(|(series, cards)| cards.iter()
+                    .filter_map(Bson::as_document)
+                    .filter_map(wishlisted_card)
+                    .map(move |card| (series.clone(), card))
+                )
+                .collect();
+
+            ret.push(FuzzyWishlist { user_id, threshold, cards });
+        }
+
+        Ok(ret)
+    }
+
+    async fn get_fuzzy_threshold (
+        &self, 
+        user_id: UserId
+    ) -> WishlistDbResult<Option<u8>> 
+    {
+        let collection = get_user_settings_collection(&self.db_client, &self.database_name);
+
+        let settings_doc = collection.find_one(doc!{ "user_id": user_id.to_string() }, None).await
+            .inspect_err(|err| self.logger.log_error(format!("get_fuzzy_threshold: {err}")))?;
+
+        let threshold = settings_doc
+            .and_then(|settings_doc| u8::try_from(settings_doc.get_i32("fuzzy_threshold").ok()?).ok());
+
+        Ok(threshold)
+    }
 
-        if let Err(err) = res {
-            self.logger.log_error(format!("remove_series_from_wishlist: {}", err.to_string()));
-            return Err(Box::new(err));
+    async fn set_fuzzy_threshold (
+        &self, 
+        user_id: UserId, 
+        threshold: Option<u8>
+    ) -> WishlistDbResult<()> 
+    {
+        let collection = get_user_settings_collection(&self.db_client, &self.database_name);
+
+        let update = match threshold {
+            Some(threshold) => doc!{ "$set": { "fuzzy_threshold": i32::from(threshold) }},
+            None => doc!{ "$unset": { "fuzzy_threshold": "" }}
+        };
+
+        collection.update_one(
+            doc!{ "user_id": user_id.to_string() },
+            update,
+            UpdateOptions::builder().upsert(true).build()
+        ).await
+        .inspect_err(|err| self.logger.log_error(format!("set_fuzzy_threshold: {err}")))?;
+
+        Ok(())
+    }
+
+    async fn get_wishlist_privacy (
+        &self, 
+        user_id: UserId
+    ) -> WishlistDbResult<WishlistPrivacy> 
+    {
+        let collection = get_user_settings_collection(&self.db_client, &self.database_name);
+
+        let settings_doc = collection.find_one(doc!{ "user_id": user_id.to_string() }, None).await
+            .inspect_err(|err| self.logger.log_error(format!("get_wishlist_privacy: {err}")))?;
+
+        let privacy = settings_doc
+            .and_then(|settings_doc| WishlistPrivacy::parse(settings_doc.get_str("privacy").ok()?))
+            .unwrap_or_default();
+
+        Ok(privacy)
+    }
+
+    async fn set_wishlist_privacy (
+        &self, 
+        user_id: UserId, 
+        privacy: WishlistPrivacy
+    ) -> WishlistDbResult<()> 
+    {
+        let collection = get_user_settings_collection(&self.db_client, &self.database_name);
+
+        collection.update_one(
+            doc!{ "user_id": user_id.to_string() },
+            doc!{ "$set": { "privacy": privacy.as_str() }},
+            UpdateOptions::builder().upsert(true).build()
+        ).await
+        .inspect_err(|err| self.logger.log_error(format!("set_wishlist_privacy: {err}")))?;
+
+        Ok(())
+    }
+
+    async fn canonical_series (
+        &self, 
+        series: &SeriesName
+    ) -> WishlistDbResult<SeriesName> 
+    {
+        let collection = get_series_aliases_collection(&self.db_client, &self.database_name);
+
+        let alias_doc = collection.find_one(doc!{ "search": series.search_term() }, None).await
+            .inspect_err(|err| self.logger.log_error(format!("canonical_series: {err}")))?;
+
+        let canonical = alias_doc
+            .and_then(|alias_doc| Some(SeriesName::new(alias_doc.get_str("canonical").ok()?)))
+            .unwrap_or_else(|| series.clone());
+
+        Ok(canonical)
+    }
+
+    async fn get_series_aliases (&self) -> WishlistDbResult<Vec<(SeriesName, SeriesName)>> 
+    {
+        let collection = get_series_aliases_collection(&self.db_client, &self.database_name);
+
+        let mut cursor = collection.aggregate([doc!{ "$sort": { "canonical_search": 1, "search": 1 }}], None).await
+            .inspect_err(|err| self.logger.log_error(format!("get_series_aliases: {err}")))?;
+
+        let mut ret = Vec::new();
+        while cursor.advance().await? {
+            let alias_doc: Document = cursor.deserialize_current()?;
+            match (alias_doc.get_str("name"), alias_doc.get_str("canonical")) {
+                (Ok(alias), Ok(canonical)) => ret.push((SeriesName::new(alias), SeriesName::new(canonical))),
+                _ => self.logger.log_error("get_series_aliases: could not parse alias document")
+            }
         }
 
-        return Ok(series_cards_amount);
+        Ok(ret)
+    }
+
+    async fn add_series_alias (
+        &self, 
+        alias: &SeriesName, 
+        canonical: &SeriesName
+    ) -> WishlistDbResult<()> 
+    {
+        validate_alias(alias, canonical)?;
+        let canonical = self.canonical_series(canonical).await?;
+        validate_alias(alias, &canonical)?;
+
+        let aliases_collection = get_series_aliases_collection(&self.db_client, &self.database_name);
+
+        let alias_search = alias.search_term();
+        let canonical_search = canonical.search_term();
+
+        aliases_collection.update_one(
+            doc!{ "search": &alias_search },
+            doc!{ "$set": { "name": alias.as_str(), "canonical": canonical.as_str(), "canonical_search": &canonical_search }},
+            UpdateOptions::builder().upsert(true).build()
+        ).await
+        .inspect_err(|err| self.logger.log_error(format!("add_series_alias: {err}")))?;
+
+        aliases_collection.update_many(
+            doc!{ "canonical_search": &alias_search },
+            doc!{ "$set": { "canonical": canonical.as_str(), "canonical_search": &canonical_search }},
+            None
+        ).await
+        .inspect_err(|err| self.logger.log_error(format!("add_series_alias: {err}")))?;
+
+        let collection = get_wishlist_collection(&self.db_client, &self.database_name);
+        let mut cursor = collection.find(doc!{ "series.search": &alias_search }, None).await
+            .inspect_err(|err| self.logger.log_error(format!("add_series_alias: {err}")))?;
+
+        while cursor.advance().await? {
+            let user_doc: Document = cursor.deserialize_current()?;
+            let (Ok(id), Ok(series)) = (user_doc.get_object_id("_id"), user_doc.get_array("series"))
+            else {
+                continue;
+            };
+
+            let merged = aliased_series_merged(series, &alias_search, &canonical);
+            collection.update_one(doc!{ "_id": id }, doc!{ "$set": { "series": &merged }}, None).await
+                .inspect_err(|err| self.logger.log_error(format!("add_series_alias: {err}")))?;
+
+            let mut user_doc = user_doc;
+            user_doc.insert("series", merged);
+            self.card_index_write("add_series_alias", self.reindex_wishlist(&user_doc).await).await?;
+        }
+
+        Ok(())
+    }
+
+    async fn remove_series_alias (
+        &self, 
+        alias: &SeriesName
+    ) -> WishlistDbResult<()> 
+    {
+        let collection = get_series_aliases_collection(&self.db_client, &self.database_name);
+
+        let res = collection.delete_one(doc!{ "search": alias.search_term() }, None).await
+            .inspect_err(|err| self.logger.log_error(format!("remove_series_alias: {err}")))?;
+
+        if res.deleted_count == 0 {
+            return Err(WishlistDbError::NotFound(format!("alias `{alias}`")));
+        }
+
+        Ok(())
+    }
+
+    async fn add_to_catalog (
+        &self, 
+        entries: &[WishlistEntry],
+        seen_at: DateTime<Utc>
+    ) -> WishlistDbResult<()> 
+    {
+        entries.iter().try_for_each(validate_entry)?;
+
+        let seen_at = seen_at.timestamp();
+        let series_collection = get_catalog_series_collection(&self.db_client, &self.database_name);
+        let cards_collection = get_catalog_cards_collection(&self.db_client, &self.database_name);
+
+        for entry in entries {
+            let series = self.canonical_series(&entry.series).await?;
+            let series_search = series.search_term();
+
+            series_collection.update_one(
+                doc!{ "search": &series_search },
+                doc!{
+                    "$setOnInsert": { "name": series.as_str() },
+                    "$min": { "first_seen": seen_at },
+                    "$max": { "last_seen": seen_at }
+                },
+                UpdateOptions::builder().upsert(true).build()
+            ).await
+            .inspect_err(|err| self.logger.log_error(format!("add_to_catalog: {err}")))?;
+
+            for card in entry.cards.iter() {
+                cards_collection.update_one(
+                    doc!{ "series_search": &series_search, "search": card.search_term() },
+                    doc!{
+                        "$setOnInsert": { "name": card.as_str() },
+                        "$min": { "first_seen": seen_at },
+                        "$max": { "last_seen": seen_at }
+                    },
+                    UpdateOptions::builder().upsert(true).build()
+                ).await
+                .inspect_err(|err| self.logger.log_error(format!("add_to_catalog: {err}")))?;
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn search_catalog_series (
+        &self, 
+        prefix: &str,
+        limit: usize
+    ) -> WishlistDbResult<Vec<SeriesName>> 
+    {
+        let collection = get_catalog_series_collection(&self.db_client, &self.database_name);
+
+        let (start, end) = search_key_range(prefix);
+
+        let mut cursor = collection.find(
+            doc!{ "search": { "$gte": start, "$lt": end }},
+            FindOptions::builder().sort(doc!{ "search": 1 }).limit(limit as i64).build()
+        ).await
+        .inspect_err(|err| self.logger.log_error(format!("search_catalog_series: {err}")))?;
+
+        let mut ret = Vec::new();
+        while cursor.advance().await? {
+            match cursor.current().get_str("name") {
+                Ok(name) => ret.push(SeriesName::new(name)),
+                Err(_) => self.logger.log_error("search_catalog_series: could not parse series name")
+            }
+        }
+
+        Ok(ret)
+    }
+
+    async fn search_catalog_cards (
+        &self, 
+        series: &SeriesName,
+        prefix: &str,
+        limit: usize
+    ) -> WishlistDbResult<Vec<CardName>> 
+    {
+        let collection = get_catalog_cards_collection(&self.db_client, &self.database_name);
+
+        let series_search = self.canonical_series(series).await?.search_term();
+        let (start, end) = search_key_range(prefix);
+
+        let mut cursor = collection.find(
+            doc!{ "series_search": series_search, "search": { "$gte": start, "$lt": end }},
+            FindOptions::builder().sort(doc!{ "search": 1 }).limit(limit as i64).build()
+        ).await
+        .inspect_err(|err| self.logger.log_error(format!("search_catalog_cards: {err}")))?;
+
+        let mut ret = Vec::new();
+        while cursor.advance().await? {
+            match cursor.current().get_str("name") {
+                Ok(name) => ret.push(CardName::new(name)),
+                Err(_) => self.logger.log_error("search_catalog_cards: could not parse card name")
+            }
+        }
+
+        Ok(ret)
+    }
+
+    async fn get_catalog_series (
+        &self, 
+        series: &SeriesName
+    ) -> WishlistDbResult<Option<CatalogSeries>> 
+    {
+        let series_search = self.canonical_series(series).await?.search_term();
+
+        let series_doc = get_catalog_series_collection(&self.db_client, &self.database_name)
+            .find_one(doc!{ "search": &series_search }, None)
+            .await
+            .inspect_err(|err| self.logger.log_error(format!("get_catalog_series: {err}")))?;
+
+        let Some(series_doc) = series_doc else {
+            return Ok(None);
+        };
+        let (Ok(name), Some(first_seen), Some(last_seen)) = (series_doc.get_str("name"), seen_time(&series_doc, "first_seen"), seen_time(&series_doc, "last_seen")) 
+        else {
+            self.logger.log_error("get_catalog_series: could not parse catalog series");
+            return Ok(None);
+        };
+
+        let mut cursor = get_catalog_cards_collection(&self.db_client, &self.database_name).find(
+            doc!{ "series_search": &series_search },
+            FindOptions::builder().sort(doc!{ "search": 1 }).build()
+        ).await
+        .inspect_err(|err| self.logger.log_error(format!("get_catalog_series: {err}")))?;
+
+        let mut cards = Vec::new();
+        while cursor.advance().await? {
+            let card_doc: Document = cursor.deserialize_current()?;
+            match (card_doc.get_str("name"), seen_time(&card_doc, "first_seen"), seen_time(&card_doc, "last_seen")) {
+                (Ok(name), Some(first_seen), Some(last_seen)) => cards.push(CatalogCard { name: CardName::new(name), first_seen, last_seen }),
+                _ => self.logger.log_error("get_catalog_series: could not parse catalog card")
+            }
+        }
+
+        Ok(Some(CatalogSeries { series: SeriesName::new(name), cards, first_seen, last_seen }))
+    }
+
+    async fn get_catalog_stats(&self) -> WishlistDbResult<CatalogStats> {
+        let series_collection = get_catalog_series_collection(&self.db_client, &self.database_name);
+
+        let series_count = series_collection.count_documents(doc!{}, None).await
+            .inspect_err(|err| self.logger.log_error(format!("get_catalog_stats: {err}")))?;
+        let cards_count = get_catalog_cards_collection(&self.db_client, &self.database_name).count_documents(doc!{}, None).await
+            .inspect_err(|err| self.logger.log_error(format!("get_catalog_stats: {err}")))?;
+
+        let first_doc = series_collection.find_one(
+            doc!{}, 
+            FindOneOptions::builder().sort(doc!{ "first_seen": 1 }).build()
+        ).await
+        .inspect_err(|err| self.logger.log_error(format!("get_catalog_stats: {err}")))?;
+
+        Ok(CatalogStats {
+            series_count: series_count as usize,
+            cards_count: cards_count as usize,
+            first_seen: first_doc.and_then(|series_doc| seen_time(&series_doc, "first_seen"))
+        })
+    }
+
+    async fn set_cards_owned (
+        &self, 
+        user_id: UserId,
+        entry: &WishlistEntry,
+        owned: bool
+    ) -> WishlistDbResult<()> 
+    {
+        validate_entry(entry)?;
+
+        let collection = get_owned_cards_collection(&self.db_client, &self.database_name);
+
+        let series = self.canonical_series(&entry.series).await?;
+        let series_search = series.search_term();
+
+        if !owned {
+            let searches: Vec<String> = entry.cards.iter().map(CardName::search_term).collect();
+            collection.delete_many(
+                doc!{ "user_id": user_id.to_string(), "series_search": &series_search, "search": { "$in": searches }},
+                None
+            ).await
+            .inspect_err(|err| self.logger.log_error(format!("set_cards_owned: {err}")))?;
+
+            return Ok(());
+        }
+
+        for card in entry.cards.iter() {
+            collection.update_one(
+                doc!{ "user_id": user_id.to_string(), "series_search": &series_search, "search": card.search_term() },
+                doc!{ "$set": { "series": series.as_str(), "name": card.as_str() }},
+                UpdateOptions::builder().upsert(true).build()
+            ).await
+            .inspect_err(|err| self.logger.log_error(format!("set_cards_owned: {err}")))?;
+        }
+
+        Ok(())
+    }
+
+    async fn get_owned_cards (
+        &self, 
+        user_id: UserId
+    ) -> WishlistDbResult<Vec<WishlistEntry>> 
+    {
+        let collection = get_owned_cards_collection(&self.db_client, &self.database_name);
+
+        let mut cursor = collection.find(
+            doc!{ "user_id": user_id.to_string() },
+            FindOptions::builder().sort(doc!{ "series_search": 1, "search": 1 }).build()
+        ).await
+        .inspect_err(|err| self.logger.log_error(format!("get_owned_cards: {err}")))?;
+
+        let mut entries: Vec<(String, WishlistEntry)> = vec![];
+        while cursor.advance().await? {
+            let card_doc = cursor.current();
+            let (Ok(series_search), Ok(series), Ok(name)) = (card_doc.get_str("series_search"), card_doc.get_str("series"), card_doc.get_str("name"))
+            else {
+                self.logger.log_error("get_owned_cards: could not parse owned card");
+                continue;
+            };
+
+            match entries.last_mut() {
+                Some((last_search, entry)) if last_search == series_search => entry.cards.push(CardName::new(name)),
+                _ => entries.push((series_search.to_string(), WishlistEntry { series: SeriesName::new(series), cards: vec![CardName::new(name)] }))
+            }
+        }
+
+        Ok(entries.into_iter().map(|(_, entry)| entry).collect())
+    }
+
+    async fn get_guild_scope_mode (
+        &self, 
+        guild_id: GuildId
+    ) -> WishlistDbResult<ScopeMode> 
+    {
+        let collection = get_guild_settings_collection(&self.db_client, &self.database_name);
+
+        let settings_doc = collection.find_one(doc!{ "guild_id": guild_id.to_string() }, None).await
+            .inspect_err(|err| self.logger.log_error(format!("get_guild_scope_mode: {err}")))?;
+
+        let mode = settings_doc
+            .and_then(|settings_doc| ScopeMode::parse(settings_doc.get_str("scope_mode").ok()?))
+            .unwrap_or_default();
+
+        Ok(mode)
+    }
+
+    async fn set_guild_scope_mode (
+        &self, 
+        guild_id: GuildId, 
+        mode: ScopeMode
+    ) -> WishlistDbResult<()> 
+    {
+        let collection = get_guild_settings_collection(&self.db_client, &self.database_name);
+
+        collection.update_one(
+            doc!{ "guild_id": guild_id.to_string() },
+            doc!{ "$set": { "scope_mode": mode.as_str() }},
+            UpdateOptions::builder().upsert(true).build()
+        ).await
+        .inspect_err(|err| self.logger.log_error(format!("set_guild_scope_mode: {err}")))?;
+
+        Ok(())
     }
 }
 
 impl <T> MongoWishlistDB<T>
     where T: Logger
 {
-    async fn user_has_series(&self, user_id: &str, series: &str) -> bool {
-        let collection = get_wishlist_collection(&self.db_client);
+    #[cfg(test)]
+    pub async fn drop_database(&self) -> Result<(), Error> {
+        self.db_client.database(&self.database_name).drop(None).await
+    }
+
+    /// Compares the card index with the wishlists it is built from.
+    pub async fn check_card_index(&self) -> Result<CardIndexReport, Error> {
+        let mut expected = HashSet::new();
+        let mut cursor = get_wishlist_collection(&self.db_client, &self.database_name).find(doc!{}, None).await?;
+        while cursor.advance().await? {
+            let user_doc: Document = cursor.deserialize_current()?;
+            expected.extend(card_index_docs(&user_doc).iter().filter_map(card_index_entry));
+        }
+
+        let mut report = CardIndexReport::default();
+        let mut cursor = get_card_index_collection(&self.db_client, &self.database_name).find(doc!{}, None).await?;
+        while cursor.advance().await? {
+            let entry_doc: Document = cursor.deserialize_current()?;
+            match card_index_entry(&entry_doc) {
+                Some(entry) if expected.remove(&entry) => (),
+                _ => report.stale += 1
+            }
+        }
+        report.missing = expected.len();
+
+        Ok(report)
+    }
+
+    /// Replaces the card index with one built from the wishlists, returning how many entries it has.
+    /// The entries are written to a separate collection that then replaces the index at once, so drops
+    /// keep being matched against the old index meanwhile. Wishlist changes made while it runs may be
+    /// missing from the new one, it is meant for startup.
+    pub async fn rebuild_card_index(&self) -> Result<usize, Error> {
+        let database = self.db_client.database(&self.database_name);
+        let rebuilt_index = database.collection::<Document>(CARD_INDEX_REBUILD_COLLECTION_NAME);
+
+        // left over by an interrupted rebuild
+        rebuilt_index.drop(None).await?;
+        create_card_index_indexes(&rebuilt_index).await?;
+
+        let mut entries_count = 0;
+        let mut cursor = get_wishlist_collection(&self.db_client, &self.database_name).find(doc!{}, None).await?;
+        while cursor.advance().await? {
+            let user_doc: Document = cursor.deserialize_current()?;
+            let entries = card_index_docs(&user_doc);
+
+            if !entries.is_empty() {
+                entries_count += entries.len();
+                rebuilt_index.insert_many(entries, None).await?;
+            }
+        }
+
+        self.db_client.database("admin").run_command(
+            doc!{
+                "renameCollection": format!("{}.{CARD_INDEX_REBUILD_COLLECTION_NAME}", self.database_name),
+                "to": format!("{}.{CARD_INDEX_COLLECTION_NAME}", self.database_name),
+                "dropTarget": true
+            },
+            None
+        ).await?;
+
+        Ok(entries_count)
+    }
 
-        let series_search = series_to_search_term(series);
+    // Whether the card index was built by another version of it, or marked stale after a failed write
+    async fn card_index_needs_check(&self) -> Result<bool, Error> {
+        let state_doc = get_migrations_collection(&self.db_client, &self.database_name)
+            .find_one(doc!{"name": CARD_INDEX_STATE}, None).await?;
 
-        match collection.find_one(
-            doc! {"id": user_id, "series.search": series_search},
+        Ok(match state_doc {
+            Some(state_doc) => state_doc.get_i32("version").ok() != Some(CARD_INDEX_VERSION) || state_doc.get_bool("stale").unwrap_or(true),
+            None => true
+        })
+    }
+
+    async fn set_card_index_state(&self, stale: bool) -> Result<(), Error> {
+        get_migrations_collection(&self.db_client, &self.database_name).update_one(
+            doc!{"name": CARD_INDEX_STATE},
+            doc!{"$set": {"version": CARD_INDEX_VERSION, "stale": stale, "updated_at": Utc::now().timestamp()}},
+            UpdateOptions::builder().upsert(true).build()
+        ).await?;
+
+        Ok(())
+    }
+
+    // Logs a failed card index write and marks the index stale, so the next startup checks it
+    async fn card_index_write<R>(&self, function: &str, res: Result<R, Error>) -> Result<R, Error> {
+        if let Err(err) = &res {
+            self.logger.log_error(format!("{function}: card index: {err}"));
+            if let Err(err) = self.set_card_index_state(true).await {
+                self.logger.log_error(format!("{function}: could not mark the card index stale: {err}"));
+            }
+        }
+
+        res
+    }
+
+    // Replaces the entries of the given cards of a series with the ones built from the series as updated
+    async fn reindex_cards(&self, scope: WishlistScope, user_id: UserId, series_doc: &Document, cards_search: &[String]) -> Result<(), Error> {
+        let card_index = get_card_index_collection(&self.db_client, &self.database_name);
+
+        let (Ok(series_search), false) = (series_doc.get_str("search"), cards_search.is_empty())
+        else {
+            return Ok(());
+        };
+
+        card_index.delete_many(
+            doc!{"scope": scope.key(), "user_id": user_id.to_string(), "series_search": series_search, "card_search": {"$in": cards_search}},
             None
-        ).await {
-            Ok(x) => x.is_some(),
-            Err(_) => false,
+        ).await?;
+
+        let user_doc = doc!{"id": user_id.to_string(), "scope": scope.key(), "series": [series_doc.clone()]};
+        let entries: Vec<Document> = card_index_docs(&user_doc).into_iter()
+            .filter(|entry| entry.get_str("card_search").is_ok_and(|card_search| cards_search.iter().any(|search| search == card_search)))
+            .collect();
+        if !entries.is_empty() {
+            card_index.insert_many(entries, None).await?;
         }
+
+        Ok(())
     }
+
+    // Replaces all the entries of a wishlist document
+    async fn reindex_wishlist(&self, user_doc: &Document) -> Result<(), Error> {
+        let card_index = get_card_index_collection(&self.db_client, &self.database_name);
+
+        let (Ok(user_id), Ok(scope)) = (user_doc.get_str("id"), user_doc.get_str("scope"))
+        else {
+            return Ok(());
+        };
+
+        card_index.delete_many(doc!{"scope": scope, "user_id": user_id}, None).await?;
+
+        let entries = card_index_docs(user_doc);
+        if !entries.is_empty() {
+            card_index.insert_many(entries, None).await?;
+        }
+
+        Ok(())
+    }
+
+    // Applies an update to a single wishlisted card, bound to `$[elem]` and `$[card]`
+    async fn update_card(&self, scope: WishlistScope, user_id: UserId, card: &CardRef, update: Document) -> WishlistDbResult<()> 
+        where T: Send + Sync
+    {
+        let collection = get_wishlist_collection(&self.db_client, &self.database_name);
+
+        let series_search = self.canonical_series(&card.series).await?.search_term();
+        let card_search = card.card.search_term();
+
+        let res = collection.update_one(
+            doc!{"id": user_id.to_string(), "scope": scope.key(), "series": {"$elemMatch": {"search": &series_search, "cards.search": &card_search}}},
+            update,
+            UpdateOptions::builder()
+            .array_filters(vec![doc! {"elem.search": series_search}, doc! {"card.search": card_search}])
+            .build()
+        ).await?;
+
+        if res.matched_count == 0 {
+            return Err(WishlistDbError::NotFound(format!("card `{}` from `{}`", card.card, card.series)));
+        }
+
+        Ok(())
+    }
+
+    // Replaces the user's series with what `change` makes of the stored one (`None` when missing, and
+    // returned to drop it). The write only applies while the series is still as read, so a concurrent
+    // change to it is not overwritten: `change` runs again on the new series instead.
+    async fn change_series<R>(
+        &self, 
+        scope: WishlistScope, 
+        user_id: UserId, 
+        series_search: &str, 
+        mut change: impl FnMut(Option<&Document>) -> (Option<Document>, R)
+    ) -> WishlistDbResult<R> 
+        where T: Send + Sync
+    {
+        let collection = get_wishlist_collection(&self.db_client, &self.database_name);
+
+        for _ in 0..SERIES_CHANGE_ATTEMPTS {
+            let user_doc = collection.find_one(
+                doc!{"id": user_id.to_string(), "scope": scope.key()},
+                FindOneOptions::builder().projection(doc!{"series": {"$elemMatch": {"search": series_search}}}).build()
+            ).await?;
+            let previous = user_doc.as_ref()
+                .and_then(|user_doc| user_doc.get_array("series").ok()?.first()?.as_document());
+
+            let (series_doc, res) = change(previous);
+            let written = match (previous, series_doc) {
+                (None, None) => true,
+                (Some(previous), Some(series_doc)) if *previous == series_doc => true,
+                // a concurrent first add can insert the wishlist between the match and the upsert,
+                // the unique index then rejects this insert
+                (None, Some(series_doc)) => match collection.update_one(
+                    doc!{"id": user_id.to_string(), "scope": scope.key(), "series.search": {"$ne": series_search}},
+                    doc!{"$push": {"series": series_doc}},
+                    UpdateOptions::builder().upsert(true).build()
+                ).await {
+                    Ok(_) => true,
+                    Err(err) if is_duplicate_key(&err) => false,
+                    Err(err) => return Err(err.into())
+                },
+                (Some(previous), Some(series_doc)) => collection.update_one(
+                    doc!{"id": user_id.to_string(), "scope": scope.key(), "series": previous.clone()},
+                    doc!{"$set": {"series.$": series_doc}},
+                    None
+                ).await?.matched_count > 0,
+                (Some(previous), None) => collection.update_one(
+                    doc!{"id": user_id.to_string(), "scope": scope.key(), "series": previous.clone()},
+                    doc!{"$pull": {"series": {"search": series_search}}},
+                    None
+                ).await?.matched_count > 0
+            };
+
+            if written {
+                return Ok(res);
+            }
+        }
+
+        Err(WishlistDbError::Backend(format!("series `{series_search}` kept changing during the update").into()))
+    }
+}
+
+// How many times `change_series` reads the series again after a concurrent change
+const SERIES_CHANGE_ATTEMPTS : usize = 5;
+
+const DUPLICATE_KEY_CODE : i32 = 11000;
+// `NamespaceNotFound` and `IndexNotFound`
+const INDEX_NOT_FOUND_CODES : [i32; 2] = [26, 27];
+
+// E11000, a unique index rejected the write
+fn is_duplicate_key(err: &Error) -> bool {
+    match err.kind.as_ref() {
+        ErrorKind::Command(command_err) => command_err.code == DUPLICATE_KEY_CODE,
+        ErrorKind::Write(WriteFailure::WriteError(write_err)) => write_err.code == DUPLICATE_KEY_CODE,
+        _ => false
+    }
+}
+
+impl From<Error> for WishlistDbError {
+    fn from(err: Error) -> Self {
+        match *err.kind {
+            ErrorKind::ServerSelection { .. } 
+            | ErrorKind::Io(_) 
+            | ErrorKind::ConnectionPoolCleared { .. } => WishlistDbError::ConnectionLost(err.to_string()),
+            _ => WishlistDbError::Backend(Box::new(err))
+        }
+    }
+}
+
+/// Differences between the card index and the wishlists, see `MongoWishlistDB::check_card_index`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct CardIndexReport {
+    /// Wishlisted cards without an index entry.
+    pub missing: usize,
+    /// Index entries without a wishlisted card, or not matching it.
+    pub stale: usize
+}
+
+impl CardIndexReport {
+    pub fn is_consistent(&self) -> bool {
+        self.missing == 0 && self.stale == 0
+    }
+}
+
+// The card index maps each `(scope, series_search, card_search)` to the users that wishlisted the card,
+// as one entry per user holding what the drop lookup needs. Wishlists are the source of truth:
+// a failed index update after a wishlist one is returned as an error and marks the index stale,
+// the next startup then checks it and rebuilds it if needed.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct CardIndexEntry {
+    scope: String,
+    user_id: UserId,
+    series_search: String,
+    card_search: String,
+    priority: CardPriority,
+    constraints: Vec<CardConstraint>,
+    /// Search terms of the cards a wildcard card leaves out.
+    excluded: Vec<String>
 }
 
+// Index entries of every card in a wishlist document
+fn card_index_docs(user_doc: &Document) -> Vec<Document> {
+    let (Ok(user_id), Ok(scope)) = (user_doc.get_str("id"), user_doc.get_str("scope"))
+    else {
+        return vec![];
+    };
+
+    user_doc.get_array("series").into_iter().flatten()
+        .filter_map(Bson::as_document)
+        .filter_map(|series_doc| Some((series_doc.get_str("search").ok()?, series_doc.get_array("cards").ok()?)))
+        .flat_map(|(series_search, cards)| cards.iter()
+            .filter_map(Bson::as_document)
+            .filter_map(move |card_doc| {
+                let card = wishlisted_card(card_doc)?;
+                let constraints: Vec<String> = card.constraints.iter().map(CardConstraint::to_string).collect();
+                let excluded: Vec<String> = card.excluded.iter().map(CardName::search_term).collect();
+
+                Some(doc!{
+                    "scope": scope,
+                    "user_id": user_id,
+                    "series_search": series_search,
+                    "card_search": card_doc.get_str("search").ok()?,
+                    "priority": card.priority.as_str(),
+                    "constraints": constraints,
+                    "excluded": excluded
+                })
+            })
+        )
+        .collect()
+}
+
+fn card_index_entry(entry_doc: &Document) -> Option<CardIndexEntry> {
+    Some(CardIndexEntry {
+        scope: entry_doc.get_str("scope").ok()?.to_string(),
+        user_id: entry_doc.get_str("user_id").ok()?.parse().ok()?,
+        series_search: entry_doc.get_str("series_search").ok()?.to_string(),
+        card_search: entry_doc.get_str("card_search").ok()?.to_string(),
+        priority: parse_priority(entry_doc.get_str("priority").ok()),
+        constraints: parse_constraints(entry_doc.get_array("constraints").into_iter().flatten().filter_map(Bson::as_str)),
+        excluded: entry_doc.get_array("excluded").into_iter().flatten().filter_map(Bson::as_str).map(str::to_string).collect()
+    })
+}
+
+// Card documents of a series document
+fn series_cards(series_doc: &Document) -> Vec<Document> {
+    series_doc.get_array("cards").into_iter().flatten()
+        .filter_map(Bson::as_document)
+        .cloned()
+        .collect()
+}
+
+// Names and search terms of the cards in the only series of a wishlist projected with `$elemMatch`
+fn previous_series_cards(user_doc: &Document) -> Vec<(String, String)> {
+    user_doc.get_array("series").into_iter().flatten()
+        .filter_map(Bson::as_document)
+        .flat_map(|series_doc| series_doc.get_array("cards").into_iter().flatten())
+        .filter_map(Bson::as_document)
+        .filter_map(|card_doc| Some((card_doc.get_str("name").ok()?.to_string(), card_doc.get_str("search").ok()?.to_string())))
+        .collect()
+}
+
+// $sum outputs an int32 or an int64 depending on the total
+fn bson_count(count: Option<&Bson>) -> usize {
+    match count {
+        Some(Bson::Int32(count)) => *count as usize,
+        Some(Bson::Int64(count)) => *count as usize,
+        _ => 0
+    }
+}
+
+// Cards stored before priorities existed have none, which means normal
+fn parse_priority(priority: Option<&str>) -> CardPriority {
+    priority.and_then(CardPriority::parse).unwrap_or_default()
+}
+
+fn wishlisted_card(card_doc: &Document) -> Option<WishlistedCard> {
+    let name = CardName::new(card_doc.get_str("name").ok()?);
+    let note = card_doc.get_str("note").ok().map(str::to_string);
+    let constraints = parse_constraints(card_doc.get_array("constraints").into_iter().flatten().filter_map(Bson::as_str));
+    let excluded = card_doc.get_array("excluded").into_iter().flatten().filter_map(Bson::as_str).map(CardName::new).collect();
+    // cards added before the time was stored have none
+    let added_at = card_doc.get_i64("added_at").ok().and_then(|added_at| DateTime::from_timestamp(added_at, 0));
+
+    Some(WishlistedCard::new(name, parse_priority(card_doc.get_str("priority").ok()))
+        .with_details(note, constraints)
+        .with_excluded(excluded)
+        .with_added_at(added_at))
+}
+
+// A removed card as recorded in the history, read back by `wishlisted_card`
+fn history_card_doc(card: &WishlistedCard) -> Document {
+    let constraints: Vec<String> = card.constraints.iter().map(CardConstraint::to_string).collect();
+    let excluded: Vec<&str> = card.excluded.iter().map(CardName::as_str).collect();
+
+    doc!{
+        "name": card.name.as_str(),
+        "priority": card.priority.as_str(),
+        "note": card.note.as_deref(),
+        "constraints": constraints,
+        "excluded": excluded
+    }
+}
+
+// Cards stored before constraints existed have none
+fn parse_constraints<'a>(constraints: impl Iterator<Item = &'a str>) -> Vec<CardConstraint> {
+    constraints
+        .filter_map(CardConstraint::parse)
+        .collect()
+}
+
+fn backend_error(err: impl std::error::Error + Send + Sync + 'static) -> WishlistDbError {
+    WishlistDbError::Backend(Box::new(err))
+}
 
 const WISHLIST_DATABASE_NAME : &str = "better_wishlist";
 const WISHLIST_COLLECTION_NAME : &str = "wishlist";
-
-fn get_wishlist_collection(client: &mongodb::Client) -> mongodb::Collection<Document> {
-    let database = client.database(WISHLIST_DATABASE_NAME);
+const GUILD_SETTINGS_COLLECTION_NAME : &str = "guild_settings";
+const HISTORY_COLLECTION_NAME : &str = "history";
+const MIGRATIONS_COLLECTION_NAME : &str = "migrations";
+const USER_SETTINGS_COLLECTION_NAME : &str = "user_settings";
+const SERIES_ALIASES_COLLECTION_NAME : &str = "series_aliases";
+const CARD_INDEX_COLLECTION_NAME : &str = "card_index";
+const CARD_INDEX_REBUILD_COLLECTION_NAME : &str = "card_index_rebuild";
+const CATALOG_SERIES_COLLECTION_NAME : &str = "catalog_series";
+const CATALOG_CARDS_COLLECTION_NAME : &str = "catalog_cards";
+const OWNED_CARDS_COLLECTION_NAME : &str = "owned_cards";
+
+const SEARCH_TERMS_MIGRATION : &str = "normalized_search_terms";
+// Not a migration: the version the card index was built with and whether a failed write left it behind.
+// Bump the version when the entries change, so the next startup rebuilds the index.
+const CARD_INDEX_STATE : &str = "card_index";
+const CARD_INDEX_VERSION : i32 = 1;
+const UNIQUE_WISHLISTS_MIGRATION : &str = "unique_wishlists";
+const ADDED_TIMES_MIGRATION : &str = "card_added_times";
+
+fn get_wishlist_collection(client: &mongodb::Client, database_name: &str) -> mongodb::Collection<Document> {
+    let database = client.database(database_name);
     let collection: mongodb::Collection<Document> = database.collection(WISHLIST_COLLECTION_NAME);
 
-    return collection;
+    collection
+}
+
+fn get_guild_settings_collection(client: &mongodb::Client, database_name: &str) -> mongodb::Collection<Document> {
+    client.database(database_name).collection(GUILD_SETTINGS_COLLECTION_NAME)
+}
+
+fn get_user_settings_collection(client: &mongodb::Client, database_name: &str) -> mongodb::Collection<Document> {
+    client.database(database_name).collection(USER_SETTINGS_COLLECTION_NAME)
+}
+
+fn get_series_aliases_collection(client: &mongodb::Client, database_name: &str) -> mongodb::Collection<Document> {
+    client.database(database_name).collection(SERIES_ALIASES_COLLECTION_NAME)
+}
+
+fn get_card_index_collection(client: &mongodb::Client, database_name: &str) -> mongodb::Collection<Document> {
+    client.database(database_name).collection(CARD_INDEX_COLLECTION_NAME)
+}
+
+fn seen_time(catalog_doc: &Document, key: &str) -> Option<DateTime<Utc>> {
+    DateTime::from_timestamp(catalog_doc.get_i64(key).ok()?, 0)
+}
+
+fn get_catalog_series_collection(client: &mongodb::Client, database_name: &str) -> mongodb::Collection<Document> {
+    client.database(database_name).collection(CATALOG_SERIES_COLLECTION_NAME)
+}
+
+fn get_catalog_cards_collection(client: &mongodb::Client, database_name: &str) -> mongodb::Collection<Document> {
+    client.database(database_name).collection(CATALOG_CARDS_COLLECTION_NAME)
+}
+
+fn get_owned_cards_collection(client: &mongodb::Client, database_name: &str) -> mongodb::Collection<Document> {
+    client.database(database_name).collection(OWNED_CARDS_COLLECTION_NAME)
+}
+
+fn get_migrations_collection(client: &mongodb::Client, database_name: &str) -> mongodb::Collection<Document> {
+    client.database(database_name).collection(MIGRATIONS_COLLECTION_NAME)
 }
 
-pub fn series_to_search_term(name: &str) -> String {
-    let mut search = name.to_lowercase();
-    search.truncate(32);
-    search
+fn get_history_collection(client: &mongodb::Client, database_name: &str) -> mongodb::Collection<Document> {
+    client.database(database_name).collection(HISTORY_COLLECTION_NAME)
 }
 
-pub fn card_to_search_term(name: &str) -> String {
-    let mut search = name.to_lowercase();
-    search.truncate(16);
-    search
+fn history_record(record_doc: &Document) -> Option<HistoryRecord> {
+    let cards = record_doc.get_array("cards").ok()?.iter()
+        .map(|card| card.as_str().map(CardName::new))
+        .collect::<Option<Vec<CardName>>>()?;
+
+    let change = WishlistChange {
+        action: HistoryAction::parse(record_doc.get_str("action").ok()?)?,
+        entry: WishlistEntry { series: SeriesName::new(record_doc.get_str("series").ok()?), cards },
+        // changes recorded before details existed have none
+        details: record_doc.get_array("details").into_iter().flatten()
+            .filter_map(Bson::as_document)
+            .filter_map(wishlisted_card)
+            .collect(),
+        wildcard: record_doc.get_array("wildcard").into_iter().flatten()
+            .filter_map(Bson::as_str)
+            .map(CardName::new)
+            .collect(),
+        source: ChangeSource::parse(record_doc.get_str("source").ok()?)?,
+        reverts: record_doc.get_object_id("reverts").ok().map(|id| id.to_hex())
+    };
+
+    Some(HistoryRecord {
+        id: record_doc.get_object_id("_id").ok()?.to_hex(),
+        change,
+        timestamp: DateTime::from_timestamp(record_doc.get_i64("timestamp").ok()?, 0)?
+    })
 }
\ No newline at end of file
@@ -0,0 +1,1834 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex};
+
+use chrono::{DateTime, Utc};
+use rusqlite::{params, params_from_iter, types::Type, Connection, ErrorCode, OptionalExtension, Row, Transaction};
+use serenity::all::{GuildId, UserId};
+use serenity::async_trait;
+
+use crate::components::logger::Logger;
+use crate::model::catalog::{CatalogCard, CatalogSeries, CatalogStats};
+use crate::model::drop::{CardConstraint, DropMatch, DroppedCard, FuzzyWishlist};
+use crate::model::history::{ChangeSource, HistoryAction, HistoryRecord, WishlistChange};
+use crate::model::wishlist::{AddedCards, CardName, CardPriority, CardRef, OverviewQuery, RemovedCards, ScopeMode, SeriesName, SeriesOrder, SeriesOverview, WishlistEntry, WishlistOverview, WishlistPrivacy, WishlistScope, WishlistedCard};
+use crate::traits::wishlist_db::{removed_cards, validate_alias, validate_entry, with_fuzzy_matches, WishlistDB, WishlistDbError, WishlistDbResult};
+use crate::util::normalize::search_key_range;
+
+pub struct SqliteWishlistDB<T>
+    where T: Logger
+{
+    connection: Arc<Mutex<Connection>>,
+    logger: Arc<T>
+}
+
+// Each entry is applied exactly once, in order, and tracked through `PRAGMA user_version`.
+// Never edit an existing entry, append a new one instead.
+pub(crate) const MIGRATIONS: [&str; 16] = [
+    // 1: initial schema
+    "CREATE TABLE wishlist_series (
+        user_id TEXT NOT NULL,
+        name    TEXT NOT NULL,
+        search  TEXT NOT NULL,
+        PRIMARY KEY (user_id, search)
+    );
+
+    CREATE TABLE wishlist_card (
+        user_id       TEXT NOT NULL,
+        series_search TEXT NOT NULL,
+        name          TEXT NOT NULL,
+        search        TEXT NOT NULL,
+        PRIMARY KEY (user_id, series_search, search),
+        FOREIGN KEY (user_id, series_search)
+            REFERENCES wishlist_series (user_id, search)
+            ON DELETE CASCADE
+    );
+
+    CREATE INDEX wishlist_card_drop_lookup ON wishlist_card (series_search, search);",
+
+    // 2: per-guild scopes, existing wishlists become global
+    "CREATE TABLE wishlist_series_v1 AS SELECT rowid AS position, user_id, name, search FROM wishlist_series;
+    CREATE TABLE wishlist_card_v1 AS SELECT rowid AS position, user_id, series_search, name, search FROM wishlist_card;
+
+    DROP TABLE wishlist_card;
+    DROP TABLE wishlist_series;
+
+    CREATE TABLE wishlist_series (
+        scope   TEXT NOT NULL,
+        user_id TEXT NOT NULL,
+        name    TEXT NOT NULL,
+        search  TEXT NOT NULL,
+        PRIMARY KEY (scope, user_id, search)
+    );
+
+    CREATE TABLE wishlist_card (
+        scope         TEXT NOT NULL,
+        user_id       TEXT NOT NULL,
+        series_search TEXT NOT NULL,
+        name          TEXT NOT NULL,
+        search        TEXT NOT NULL,
+        PRIMARY KEY (scope, user_id, series_search, search),
+        FOREIGN KEY (scope, user_id, series_search)
+            REFERENCES wishlist_series (scope, user_id, search)
+            ON DELETE CASCADE
+    );
+
+    CREATE INDEX wishlist_card_drop_lookup ON wishlist_card (scope, series_search, search);
+
+    INSERT INTO wishlist_series (scope, user_id, name, search)
+        SELECT 'global', user_id, name, search FROM wishlist_series_v1 ORDER BY position;
+    INSERT INTO wishlist_card (scope, user_id, series_search, name, search)
+        SELECT 'global', user_id, series_search, name, search FROM wishlist_card_v1 ORDER BY position;
+
+    DROP TABLE wishlist_card_v1;
+    DROP TABLE wishlist_series_v1;
+
+    CREATE TABLE guild_settings (
+        guild_id   TEXT NOT NULL PRIMARY KEY,
+        scope_mode TEXT NOT NULL
+    );",
+
+    // 3: change history
+    "CREATE TABLE wishlist_history (
+        id        INTEGER PRIMARY KEY AUTOINCREMENT,
+        scope     TEXT NOT NULL,
+        user_id   TEXT NOT NULL,
+        action    TEXT NOT NULL,
+        series    TEXT NOT NULL,
+        source    TEXT NOT NULL,
+        reverts   INTEGER REFERENCES wishlist_history (id),
+        timestamp INTEGER NOT NULL
+    );
+
+    CREATE TABLE wishlist_history_card (
+        history_id INTEGER NOT NULL REFERENCES wishlist_history (id),
+        position   INTEGER NOT NULL,
+        name       TEXT NOT NULL,
+        PRIMARY KEY (history_id, position)
+    );
+
+    CREATE INDEX wishlist_history_user ON wishlist_history (scope, user_id, id);
+    CREATE INDEX wishlist_history_reverts ON wishlist_history (reverts);",
+
+    // 4: card priorities
+    "ALTER TABLE wishlist_card ADD COLUMN priority TEXT NOT NULL DEFAULT 'normal';",
+
+    // 5: card notes and drop constraints, stored as comma separated `CardConstraint`s
+    "ALTER TABLE wishlist_card ADD COLUMN note TEXT;
+    ALTER TABLE wishlist_card ADD COLUMN constraints TEXT NOT NULL DEFAULT '';",
+
+    // 6: normalized search terms, rewritten by `rewrite_search_terms`
+    "",
+
+    // 7: per account settings
+    "CREATE TABLE user_settings (
+        user_id         TEXT NOT NULL PRIMARY KEY,
+        fuzzy_threshold INTEGER
+    );",
+
+    // 8: series aliases
+    "CREATE TABLE series_alias (
+        search           TEXT NOT NULL PRIMARY KEY,
+        name             TEXT NOT NULL,
+        canonical        TEXT NOT NULL,
+        canonical_search TEXT NOT NULL
+    );",
+
+    // 9: wishlist privacy, `NULL` for users that never changed it
+    "ALTER TABLE user_settings ADD COLUMN privacy TEXT;",
+
+    // 10: cards left out by wildcard cards, stored as newline separated names
+    "ALTER TABLE wishlist_card ADD COLUMN excluded TEXT NOT NULL DEFAULT '';",
+
+    // 11: catalog of the series and cards seen in drops and SOFI lookups
+    "CREATE TABLE catalog_series (
+        search TEXT NOT NULL PRIMARY KEY,
+        name   TEXT NOT NULL
+    );
+
+    CREATE TABLE catalog_card (
+        series_search TEXT NOT NULL,
+        search        TEXT NOT NULL,
+        name          TEXT NOT NULL,
+        PRIMARY KEY (series_search, search)
+    );",
+
+    // 12: when catalog entries were first and last seen, in seconds, existing ones counting as seen now
+    "ALTER TABLE catalog_series ADD COLUMN first_seen INTEGER NOT NULL DEFAULT 0;
+    ALTER TABLE catalog_series ADD COLUMN last_seen INTEGER NOT NULL DEFAULT 0;
+    ALTER TABLE catalog_card ADD COLUMN first_seen INTEGER NOT NULL DEFAULT 0;
+    ALTER TABLE catalog_card ADD COLUMN last_seen INTEGER NOT NULL DEFAULT 0;
+    UPDATE catalog_series SET first_seen = CAST(strftime('%s', 'now') AS INTEGER), last_seen = CAST(strftime('%s', 'now') AS INTEGER);
+    UPDATE catalog_card SET first_seen = CAST(strftime('%s', 'now') AS INTEGER), last_seen = CAST(strftime('%s', 'now') AS INTEGER);",
+
+    // 13: cards users own, as shown by their SOFI series lookups
+    "CREATE TABLE owned_card (
+        user_id       TEXT NOT NULL,
+        series_search TEXT NOT NULL,
+        series        TEXT NOT NULL,
+        search        TEXT NOT NULL,
+        name          TEXT NOT NULL,
+        PRIMARY KEY (user_id, series_search, search)
+    );",
+
+    // 14: how removed cards were wishlisted, `NULL` priorities for cards recorded without details
+    "ALTER TABLE wishlist_history_card ADD COLUMN priority TEXT;
+    ALTER TABLE wishlist_history_card ADD COLUMN note TEXT;
+    ALTER TABLE wishlist_history_card ADD COLUMN constraints TEXT NOT NULL DEFAULT '';
+    ALTER TABLE wishlist_history_card ADD COLUMN excluded TEXT NOT NULL DEFAULT '';",
+
+    // 15: when cards were last added, in seconds, filled from the history by `fill_added_times`
+    "ALTER TABLE wishlist_card ADD COLUMN added_at INTEGER;",
+
+    // 16: cards a change left out of, or took back into, a wildcard card
+    "ALTER TABLE wishlist_history_card ADD COLUMN wildcard INTEGER NOT NULL DEFAULT 0;",
+];
+
+type MigrationCode = fn(&Transaction) -> Result<(), rusqlite::Error>;
+
+// Migrations that need more than SQL, run right after the entry with the same number
+fn migration_code(number: usize) -> Option<MigrationCode> {
+    match number {
+        6 => Some(rewrite_search_terms),
+        15 => Some(fill_added_times),
+        _ => None
+    }
+}
+
+pub async fn init_db<T>(logger: Arc<T>, path: impl AsRef<str>) -> Result<SqliteWishlistDB<T>, rusqlite::Error>
+    where T: Logger
+{
+    let mut connection = Connection::open(path.as_ref())?;
+    connection.pragma_update(None, "foreign_keys", "ON")?;
+
+    run_migrations(&mut connection, logger.as_ref())?;
+
+    Ok(SqliteWishlistDB{ connection: Arc::new(Mutex::new(connection)), logger })
+}
+
+fn run_migrations<T>(connection: &mut Connection, logger: &T) -> Result<(), rusqlite::Error>
+    where T: Logger
+{
+    let version: usize = connection.pragma_query_value(None, "user_version", |row| row.get(0))?;
+
+    for (index, migration) in MIGRATIONS.iter().enumerate().skip(version) {
+        logger.log_info(format!("Applying database migration {}", index + 1));
+
+        let transaction = connection.transaction()?;
+        transaction.execute_batch(migration)?;
+        if let Some(code) = migration_code(index + 1) {
+            code(&transaction)?;
+        }
+        transaction.pragma_update(None, "user_version", index + 1)?;
+        transaction.commit()?;
+    }
+
+    Ok(())
+}
+
+// Search terms used to be lowercased and truncated, so series and cards whose names only differed
+// past the cut collided. Recomputes them from the stored names, keeping the first of the series or
+// cards that now share a search term. Rows are put back with their rowid to keep the wishlist order.
+fn rewrite_search_terms(transaction: &Transaction) -> Result<(), rusqlite::Error> {
+    let series_rows = transaction
+        .prepare("SELECT rowid, scope, user_id, name, search FROM wishlist_series ORDER BY rowid")?
+        .query_map([], |row| Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?, row.get::<_, String>(2)?, row.get::<_, String>(3)?, row.get::<_, String>(4)?)))?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let card_rows = transaction
+        .prepare("SELECT rowid, scope, user_id, series_search, name, priority, note, constraints FROM wishlist_card ORDER BY rowid")?
+        .query_map([], |row| Ok((
+            row.get::<_, i64>(0)?, row.get::<_, String>(1)?, row.get::<_, String>(2)?, row.get::<_, String>(3)?,
+            row.get::<_, String>(4)?, row.get::<_, String>(5)?, row.get::<_, Option<String>>(6)?, row.get::<_, String>(7)?
+        )))?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    transaction.execute_batch("DELETE FROM wishlist_card; DELETE FROM wishlist_series;")?;
+
+    let mut series_search = HashMap::new();
+    let mut kept_series = HashSet::new();
+    for (rowid, scope, user_id, name, old_search) in series_rows {
+        let search = SeriesName::new(&name).search_term();
+        series_search.insert((scope.clone(), user_id.clone(), old_search), search.clone());
+
+        if kept_series.insert((scope.clone(), user_id.clone(), search.clone())) {
+            transaction.execute(
+                "INSERT INTO wishlist_series (rowid, scope, user_id, name, search) VALUES (?1, ?2, ?3, ?4, ?5)",
+                params![rowid, scope, user_id, name, search]
+            )?;
+        }
+    }
+
+    let mut kept_cards = HashSet::new();
+    for (rowid, scope, user_id, old_series_search, name, priority, note, constraints) in card_rows {
+        let Some(series_search) = series_search.get(&(scope.clone(), user_id.clone(), old_series_search)).cloned()
+        else {
+            continue;
+        };
+        let search = CardName::new(&name).search_term();
+
+        if kept_cards.insert((scope.clone(), user_id.clone(), series_search.clone(), search.clone())) {
+            transaction.execute(
+                "INSERT INTO wishlist_card (rowid, scope, user_id, series_search, name, search, priority, note, constraints)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+                params![rowid, scope, user_id, series_search, name, search, priority, note, constraints]
+            )?;
+        }
+    }
+
+    Ok(())
+}
+
+// Replays the history, oldest change first, into when each card still wishlisted was last added.
+// Cards without any history keep an unknown time.
+fn fill_added_times(transaction: &Transaction) -> Result<(), rusqlite::Error> {
+    let rows = transaction
+        .prepare(
+            "SELECT h.scope, h.user_id, h.action, h.series, c.name, h.timestamp FROM wishlist_history h
+             JOIN wishlist_history_card c ON c.history_id = h.id
+             ORDER BY h.id, c.position"
+        )?
+        .query_map([], |row| Ok((
+            row.get::<_, String>(0)?, row.get::<_, String>(1)?, row.get::<_, String>(2)?,
+            row.get::<_, String>(3)?, row.get::<_, String>(4)?, row.get::<_, i64>(5)?
+        )))?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let mut added_at = HashMap::new();
+    for (scope, user_id, action, series, card, timestamp) in rows {
+        let key = (scope, user_id, SeriesName::new(series).search_term(), CardName::new(card).search_term());
+        match HistoryAction::parse(&action) {
+            Some(HistoryAction::Added) => added_at.insert(key, timestamp),
+            Some(HistoryAction::Removed) | None => added_at.remove(&key)
+        };
+    }
+
+    let mut update = transaction.prepare(
+        "UPDATE wishlist_card SET added_at = ?1 WHERE scope = ?2 AND user_id = ?3 AND series_search = ?4 AND search = ?5"
+    )?;
+    for ((scope, user_id, series_search, card_search), timestamp) in added_at {
+        update.execute(params![timestamp, scope, user_id, series_search, card_search])?;
+    }
+
+    Ok(())
+}
+
+#[async_trait]
+impl <T> WishlistDB for SqliteWishlistDB<T>
+    where T: Logger + Send + Sync + 'static
+{
+    async fn get_users_with_series_card (
+        &self,
+        scope: WishlistScope,
+        cards: &[DroppedCard]
+    ) -> WishlistDbResult<Vec<(CardRef, Vec<DropMatch>)>>
+    {
+        let cards = cards.to_vec();
+
+        self.blocking(move |connection, logger| {
+            // the wildcard card of users without the card itself, ordered after them
+            let mut statement = connection.prepare_cached(
+                "SELECT user_id, priority, constraints, excluded FROM wishlist_card c
+                 WHERE scope = ?1 AND series_search = ?2 AND (search = ?3 OR (search = ?4 AND NOT EXISTS (
+                    SELECT 1 FROM wishlist_card WHERE scope = c.scope AND user_id = c.user_id AND series_search = c.series_search AND search = ?3
+                 )))
+                 ORDER BY search = ?4, user_id"
+            )
+            .inspect_err(|err| logger.log_error(format!("get_users_with_series_card: {err}")))?;
+
+            let scope = scope.key();
+            let wildcard_search = CardName::wildcard().search_term();
+
+            let mut ret = Vec::with_capacity(cards.len());
+            for dropped in &cards {
+                let card = &dropped.card;
+                let users = resolve_alias(connection, &card.series)
+                    .and_then(|series| statement
+                        .query_map(
+                            params![scope, series.search_term(), card.card.search_term(), wildcard_search], 
+                            |row| Ok((user_id_column(row, 0)?, priority_column(row, 1)?, constraints_column(row, 2)?, excluded_column(row, 3)?))
+                        )?
+                        .collect::<Result<Vec<_>, _>>()
+                    )
+                    .map(|users| users.into_iter()
+                        .filter(|(_, _, constraints, excluded)| {
+                            let card_search = card.card.search_term();
+                            dropped.satisfies(constraints) && !excluded.iter().any(|excluded| excluded.search_term() == card_search)
+                        })
+                        .map(|(user_id, priority, _, _)| DropMatch::exact(user_id, priority))
+                        .collect::<Vec<DropMatch>>()
+                    );
+
+                match users {
+                    Ok(users) if users.is_empty() => (),
+                    Ok(users) => ret.push((card.clone(), users)),
+                    Err(err) => {
+                        logger.log_error(format!("get_users_with_series_card: {err}"));
+                        return Err(err.into());
+                    }
+                }
+            }
+
+            let fuzzy_wishlists = fuzzy_wishlists(connection, &scope)
+                .inspect_err(|err| logger.log_error(format!("get_users_with_series_card: {err}")))?;
+
+            Ok(with_fuzzy_matches(&cards, ret, &fuzzy_wishlists))
+        }).await
+    }
+
+    async fn get_users_with_series (
+        &self,
+        scope: WishlistScope,
+        series: &[SeriesName]
+    ) -> WishlistDbResult<Vec<(SeriesName, Vec<(UserId, i32)>)>>
+    {
+        let series = series.to_vec();
+
+        self.blocking(move |connection, logger| {
+            let mut statement = connection.prepare_cached(
+                "SELECT s.user_id, COUNT(c.search)
+                 FROM wishlist_series s
+                 LEFT JOIN wishlist_card c ON c.scope = s.scope AND c.user_id = s.user_id AND c.series_search = s.search
+                 WHERE s.scope = ?1 AND s.search = ?2
+                 GROUP BY s.user_id
+                 ORDER BY s.user_id"
+            )
+            .inspect_err(|err| logger.log_error(format!("get_users_with_series: {err}")))?;
+
+            let scope = scope.key();
+
+            let mut ret = Vec::with_capacity(series.len());
+            for series_name in series {
+                let users_count = resolve_alias(connection, &series_name)
+                    .and_then(|series| statement
+                        .query_map(params![scope, series.search_term()], |row| Ok((user_id_column(row, 0)?, row.get(1)?)))?
+                        .collect::<Result<Vec<(UserId, i32)>, _>>()
+                    );
+
+                match users_count {
+                    Ok(users_count) if users_count.is_empty() => (),
+                    Ok(users_count) => ret.push((series_name.clone(), users_count)),
+                    Err(err) => {
+                        logger.log_error(format!("get_users_with_series: {err}"));
+                        return Err(err.into());
+                    }
+                }
+            }
+
+            Ok(ret)
+        }).await
+    }
+
+    async fn add_all_to_wishlist (
+        &self,
+        scope: WishlistScope,
+        user_id: UserId,
+        entry: &WishlistEntry,
+        priority: CardPriority,
+        reprioritize: bool
+    ) -> WishlistDbResult<AddedCards>
+    {
+        validate_entry(entry)?;
+
+        let entry = entry.clone();
+
+        self.blocking(move |connection, logger| {
+            let scope = scope.key();
+            let user_id = user_id.to_string();
+            let added_at = Utc::now().timestamp();
+
+            let res = (|| {
+                let transaction = connection.transaction()?;
+
+                let series = resolve_alias(&transaction, &entry.series)?;
+                let series_search = series.search_term();
+
+                transaction.execute(
+                    "INSERT OR IGNORE INTO wishlist_series (scope, user_id, name, search) VALUES (?1, ?2, ?3, ?4)",
+                    params![scope, user_id, series.as_str(), series_search]
+                )?;
+
+                let mut res = AddedCards::default();
+                {
+                    let mut insert = transaction.prepare_cached(
+                        "INSERT OR IGNORE INTO wishlist_card (scope, user_id, series_search, name, search, priority, added_at)
+                         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)"
+                    )?;
+                    let mut wishlisted_name = transaction.prepare_cached(
+                        "SELECT name FROM wishlist_card WHERE scope = ?1 AND user_id = ?2 AND series_search = ?3 AND search = ?4"
+                    )?;
+                    let mut update_priority = transaction.prepare_cached(
+                        "UPDATE wishlist_card SET priority = ?1 WHERE scope = ?2 AND user_id = ?3 AND series_search = ?4 AND search = ?5"
+                    )?;
+
+                    let mut seen_cards_search = HashSet::new();
+                    for card in entry.cards.iter() {
+                        let card_search = card.search_term();
+                        if !seen_cards_search.insert(card_search.clone()) {
+                            continue;
+                        }
+
+                        if insert.execute(params![scope, user_id, series_search, card.as_str(), card_search, priority.as_str(), added_at])? > 0 {
+                            res.added.push(card.clone());
+                        } else {
+                            let name: String = wishlisted_name.query_row(params![scope, user_id, series_search, card_search], |row| row.get(0))?;
+                            res.already_wishlisted.push(CardName::new(name));
+
+                            if reprioritize {
+                                update_priority.execute(params![priority.as_str(), scope, user_id, series_search, card_search])?;
+                            }
+                        }
+                    }
+
+                    // adding a card takes it back into the series' wildcard
+                    if let Some(excluded) = wildcard_exclusions(&transaction, &scope, &user_id, &series_search)? {
+                        let (included, excluded): (Vec<CardName>, Vec<CardName>) = excluded.into_iter()
+                            .partition(|excluded| seen_cards_search.contains(&excluded.search_term()));
+
+                        if !included.is_empty() {
+                            set_exclusions(&transaction, &scope, &user_id, &series_search, &excluded)?;
+                            res.included = included;
+                        }
+                    }
+                }
+
+                transaction.commit()?;
+                Ok::<AddedCards, rusqlite::Error>(res)
+            })();
+
+            res.map_err(|err| {
+                logger.log_error(format!("add_all_to_wishlist: {err}"));
+                err.into()
+            })
+        }).await
+    }
+
+    async fn set_cards_priority (
+        &self,
+        scope: WishlistScope,
+        user_id: UserId,
+        entry: &WishlistEntry,
+        priority: CardPriority
+    ) -> WishlistDbResult<i32>
+    {
+        let entry = entry.clone();
+
+        self.blocking(move |connection, logger| {
+            let scope = scope.key();
+            let user_id = user_id.to_string();
+            let series_search = resolve_alias(connection, &entry.series)
+                .inspect_err(|err| logger.log_error(format!("set_cards_priority: {err}")))?
+                .search_term();
+            let cards_search: Vec<String> = entry.cards.iter()
+                .map(CardName::search_term)
+                .collect();
+
+            let placeholders = vec!["?"; cards_search.len()].join(", ");
+            let query = format!(
+                "UPDATE wishlist_card SET priority = ? WHERE scope = ? AND user_id = ? AND series_search = ? AND search IN ({placeholders})"
+            );
+
+            let params = [priority.as_str(), scope.as_str(), user_id.as_str(), series_search.as_str()].into_iter()
+                .chain(cards_search.iter().map(String::as_str));
+
+            connection.execute(&query, params_from_iter(params))
+                .map(|matched| matched as i32)
+                .map_err(|err| {
+                    logger.log_error(format!("set_cards_priority: {err}"));
+                    err.into()
+                })
+        }).await
+    }
+
+    async fn set_card_note (
+        &self,
+        scope: WishlistScope,
+        user_id: UserId,
+        card: &CardRef,
+        note: Option<&str>
+    ) -> WishlistDbResult<()>
+    {
+        let card = card.clone();
+        let note = note.map(str::to_owned);
+
+        self.blocking(move |connection, logger| {
+            let res = resolve_alias(connection, &card.series).and_then(|series| connection.execute(
+                "UPDATE wishlist_card SET note = ?1 WHERE scope = ?2 AND user_id = ?3 AND series_search = ?4 AND search = ?5",
+                params![note, scope.key(), user_id.to_string(), series.search_term(), card.card.search_term()]
+            ));
+
+            match res {
+                Ok(0) => Err(WishlistDbError::NotFound(format!("card `{}` from `{}`", card.card, card.series))),
+                Ok(_) => Ok(()),
+                Err(err) => {
+                    logger.log_error(format!("set_card_note: {err}"));
+                    Err(err.into())
+                }
+            }
+        }).await
+    }
+
+    async fn set_card_constraints (
+        &self,
+        scope: WishlistScope,
+        user_id: UserId,
+        card: &CardRef,
+        constraints: &[CardConstraint]
+    ) -> WishlistDbResult<()>
+    {
+        let card = card.clone();
+        let constraints = constraints.to_vec();
+
+        self.blocking(move |connection, logger| {
+            let res = resolve_alias(connection, &card.series).and_then(|series| connection.execute(
+                "UPDATE wishlist_card SET constraints = ?1 WHERE scope = ?2 AND user_id = ?3 AND series_search = ?4 AND search = ?5",
+                params![constraints_text(&constraints), scope.key(), user_id.to_string(), series.search_term(), card.card.search_term()]
+            ));
+
+            match res {
+                Ok(0) => Err(WishlistDbError::NotFound(format!("card `{}` from `{}`", card.card, card.series))),
+                Ok(_) => Ok(()),
+                Err(err) => {
+                    logger.log_error(format!("set_card_constraints: {err}"));
+                    Err(err.into())
+                }
+            }
+        }).await
+    }
+
+    async fn set_wildcard_exclusions (
+        &self,
+        scope: WishlistScope,
+        user_id: UserId,
+        series: &SeriesName,
+        excluded: &[CardName]
+    ) -> WishlistDbResult<()>
+    {
+        let series = series.clone();
+        let excluded = excluded.to_vec();
+
+        self.blocking(move |connection, logger| {
+            let res = resolve_alias(connection, &series)
+                .and_then(|series| set_exclusions(connection, &scope.key(), &user_id.to_string(), &series.search_term(), &excluded));
+
+            match res {
+                Ok(0) => Err(WishlistDbError::NotFound(format!("wildcard card from `{series}`"))),
+                Ok(_) => Ok(()),
+                Err(err) => {
+                    logger.log_error(format!("set_wildcard_exclusions: {err}"));
+                    Err(err.into())
+                }
+            }
+        }).await
+    }
+
+    async fn remove_all_from_wishlist (
+        &self,
+        scope: WishlistScope,
+        user_id: UserId,
+        entry: &WishlistEntry,
+        exclude: bool
+    ) -> WishlistDbResult<RemovedCards>
+    {
+        let entry = entry.clone();
+
+        self.blocking(move |connection, logger| {
+            let scope = scope.key();
+            let user_id = user_id.to_string();
+            let cards_search: Vec<String> = entry.cards.iter()
+                .map(CardName::search_term)
+                .collect();
+
+            let res = (|| {
+                let transaction = connection.transaction()?;
+
+                let series_search = resolve_alias(&transaction, &entry.series)?.search_term();
+
+                let removed: Vec<WishlistedCard> = {
+                    let placeholders = vec!["?"; cards_search.len()].join(", ");
+                    let query = format!(
+                        "DELETE FROM wishlist_card WHERE scope = ? AND user_id = ? AND series_search = ? AND search IN ({placeholders})
+                         RETURNING rowid, name, priority, note, constraints, excluded, added_at"
+                    );
+
+                    let params = [scope.as_str(), user_id.as_str(), series_search.as_str()].into_iter()
+                        .chain(cards_search.iter().map(String::as_str));
+
+                    let mut removed = transaction.prepare(&query)?
+                        .query_map(params_from_iter(params), |row| Ok((row.get::<_, i64>(0)?, wishlisted_card_columns(row, 1)?)))?
+                        .collect::<Result<Vec<(i64, WishlistedCard)>, _>>()?;
+
+                    // the order of returned rows is unspecified
+                    removed.sort_by_key(|(rowid, _)| *rowid);
+                    removed.into_iter().map(|(_, card)| card).collect()
+                };
+
+                // a card removed from a series wishlisted whole is left out of its wildcard,
+                // which counts as removing it even when it was never listed
+                let wildcard = match exclude {
+                    true => wildcard_exclusions(&transaction, &scope, &user_id, &series_search)?
+                        .map(|excluded| WishlistedCard::new(CardName::wildcard(), CardPriority::default()).with_excluded(excluded)),
+                    false => None
+                };
+
+                let mut res = removed_cards(&entry, removed, wildcard.as_ref());
+                if let Some(wildcard) = wildcard.filter(|_| !res.excluded.is_empty()) {
+                    let excluded: Vec<CardName> = wildcard.excluded.into_iter().chain(res.excluded.iter().cloned()).collect();
+                    set_exclusions(&transaction, &scope, &user_id, &series_search, &excluded)?;
+                }
+
+                res.left = transaction.query_row(
+                    "SELECT COUNT(*) FROM wishlist_card WHERE scope = ?1 AND user_id = ?2 AND series_search = ?3",
+                    params![scope, user_id, series_search],
+                    |row| row.get(0)
+                )?;
+
+                // an empty series is no longer part of the wishlist
+                if res.left == 0 {
+                    transaction.execute(
+                        "DELETE FROM wishlist_series WHERE scope = ?1 AND user_id = ?2 AND search = ?3",
+                        params![scope, user_id, series_search]
+                    )?;
+                }
+
+                transaction.commit()?;
+                Ok::<RemovedCards, rusqlite::Error>(res)
+            })();
+
+            res.map_err(|err| {
+                logger.log_error(format!("remove_all_from_wishlist: {err}"));
+                err.into()
+            })
+        }).await
+    }
+
+    async fn include_in_wildcard (
+        &self,
+        scope: WishlistScope,
+        user_id: UserId,
+        entry: &WishlistEntry
+    ) -> WishlistDbResult<Vec<CardName>>
+    {
+        let entry = entry.clone();
+
+        self.blocking(move |connection, logger| {
+            let scope = scope.key();
+            let user_id = user_id.to_string();
+            let cards_search: Vec<String> = entry.cards.iter()
+                .map(CardName::search_term)
+                .collect();
+
+            let res = (|| {
+                let transaction = connection.transaction()?;
+
+                let series_search = resolve_alias(&transaction, &entry.series)?.search_term();
+                let Some(excluded) = wildcard_exclusions(&transaction, &scope, &user_id, &series_search)?
+                else {
+                    return Ok(vec![]);
+                };
+
+                let (included, excluded): (Vec<CardName>, Vec<CardName>) = excluded.into_iter()
+                    .partition(|excluded| cards_search.contains(&excluded.search_term()));
+                if !included.is_empty() {
+                    set_exclusions(&transaction, &scope, &user_id, &series_search, &excluded)?;
+                }
+
+                transaction.commit()?;
+                Ok::<Vec<CardName>, rusqlite::Error>(included)
+            })();
+
+            res.map_err(|err| {
+                logger.log_error(format!("include_in_wildcard: {err}"));
+                err.into()
+            })
+        }).await
+    }
+
+    async fn get_wishlist_overview (
+        &self,
+        scope: WishlistScope,
+        user_id: UserId,
+        query: &OverviewQuery
+    ) -> WishlistDbResult<WishlistOverview>
+    {
+        let query = *query;
+
+        self.blocking(move |connection, logger| {
+            wishlist_overview(connection, &scope.key(), &user_id.to_string(), &query).map_err(|err| {
+                logger.log_error(format!("get_wishlist_overview: {err}"));
+                err.into()
+            })
+        }).await
+    }
+
+    async fn get_user_wishlisted_cards (
+        &self,
+        scope: WishlistScope,
+        user_id: UserId,
+        series: &SeriesName
+    ) -> WishlistDbResult<Vec<WishlistedCard>>
+    {
+        let series = series.clone();
+
+        self.blocking(move |connection, logger| {
+            let res = resolve_alias(connection, &series)
+                .and_then(|series| Ok((series, connection.prepare_cached(
+                    "SELECT name, priority, note, constraints, excluded, added_at FROM wishlist_card
+                     WHERE scope = ?1 AND user_id = ?2 AND series_search = ?3
+                     ORDER BY rowid"
+                )?)))
+                .and_then(|(series, mut statement)|
+                    statement
+                        .query_map(
+                            params![scope.key(), user_id.to_string(), series.search_term()], 
+                            |row| wishlisted_card_columns(row, 0)
+                        )?
+                        .collect::<Result<Vec<WishlistedCard>, _>>()
+                );
+
+            res.map_err(|err| {
+                logger.log_error(format!("get_user_wishlisted_cards: {err}"));
+                err.into()
+            })
+        }).await
+    }
+
+    async fn user_has_card (
+        &self,
+        scope: WishlistScope,
+        user_id: UserId,
+        card: &CardRef
+    ) -> WishlistDbResult<bool>
+    {
+        let card = card.clone();
+
+        self.blocking(move |connection, logger| {
+            let res = resolve_alias(connection, &card.series).and_then(|series| connection.query_row(
+                "SELECT 1 FROM wishlist_card WHERE scope = ?1 AND user_id = ?2 AND series_search = ?3 AND search = ?4",
+                params![scope.key(), user_id.to_string(), series.search_term(), card.card.search_term()],
+                |_| Ok(())
+            )
+            .optional());
+
+            res.map(|found| found.is_some())
+                .map_err(|err| {
+                    logger.log_error(format!("user_has_card: {err}"));
+                    err.into()
+                })
+        }).await
+    }
+
+    async fn remove_series_from_wishlist (
+        &self,
+        scope: WishlistScope,
+        user_id: UserId,
+        series: &SeriesName
+    ) -> WishlistDbResult<i32>
+    {
+        let series = series.clone();
+
+        self.blocking(move |connection, logger| {
+            let scope = scope.key();
+            let user_id = user_id.to_string();
+
+            let res = (|| {
+                let transaction = connection.transaction()?;
+
+                let series_search = resolve_alias(&transaction, &series)?.search_term();
+
+                // cards are removed along with the series
+                let series_cards_amount = transaction.execute(
+                    "DELETE FROM wishlist_card WHERE scope = ?1 AND user_id = ?2 AND series_search = ?3",
+                    params![scope, user_id, series_search]
+                )?;
+
+                let series_removed = transaction.execute(
+                    "DELETE FROM wishlist_series WHERE scope = ?1 AND user_id = ?2 AND search = ?3",
+                    params![scope, user_id, series_search]
+                )?;
+
+                transaction.commit()?;
+                Ok::<_, rusqlite::Error>((series_removed, series_cards_amount as i32))
+            })();
+
+            match res {
+                Ok((0, _)) => Err(WishlistDbError::NotFound(format!("series `{series}`"))),
+                Ok((_, series_cards_amount)) => Ok(series_cards_amount),
+                Err(err) => {
+                    logger.log_error(format!("remove_series_from_wishlist: {err}"));
+                    Err(err.into())
+                }
+            }
+        }).await
+    }
+
+    async fn search_user_series (
+        &self,
+        scope: WishlistScope,
+        user_id: UserId,
+        prefix: &str,
+        limit: usize
+    ) -> WishlistDbResult<Vec<SeriesName>>
+    {
+        let (start, end) = search_key_range(prefix);
+        self.blocking(move |connection, logger| {
+            let res = connection
+                .prepare_cached(
+                    "SELECT name FROM wishlist_series
+                     WHERE scope = ?1 AND user_id = ?2 AND search >= ?3 AND search < ?4
+                     ORDER BY search
+                     LIMIT ?5"
+                )
+                .and_then(|mut statement|
+                    statement
+                        .query_map(params![scope.key(), user_id.to_string(), start, end, limit as i64], |row| Ok(SeriesName::new(row.get::<_, String>(0)?)))?
+                        .collect::<Result<Vec<SeriesName>, _>>()
+                );
+
+            res.map_err(|err| {
+                logger.log_error(format!("search_user_series: {err}"));
+                err.into()
+            })
+        }).await
+    }
+
+    async fn search_user_cards (
+        &self,
+        scope: WishlistScope,
+        user_id: UserId,
+        series: &SeriesName,
+        prefix: &str,
+        limit: usize
+    ) -> WishlistDbResult<Vec<CardName>>
+    {
+        let (start, end) = search_key_range(prefix);
+        let series = series.clone();
+
+        self.blocking(move |connection, logger| {
+            let res = resolve_alias(connection, &series)
+                .and_then(|series| Ok((series, connection.prepare_cached(
+                    "SELECT name FROM wishlist_card
+                     WHERE scope = ?1 AND user_id = ?2 AND series_search = ?3 AND search >= ?4 AND search < ?5
+                     ORDER BY search
+                     LIMIT ?6"
+                )?)))
+                .and_then(|(series, mut statement)|
+                    statement
+                        .query_map(
+                            params![scope.key(), user_id.to_string(), series.search_term(), start, end, limit as i64],
+                            |row| Ok(CardName::new(row.get::<_, String>(0)?))
+                        )?
+                        .collect::<Result<Vec<CardName>, _>>()
+                );
+
+            res.map_err(|err| {
+                logger.log_error(format!("search_user_cards: {err}"));
+                err.into()
+            })
+        }).await
+    }
+
+    async fn record_change (
+        &self,
+        scope: WishlistScope,
+        user_id: UserId,
+        change: &WishlistChange
+    ) -> WishlistDbResult<()>
+    {
+        let change = change.clone();
+
+        self.blocking(move |connection, logger| {
+            let res = (|| {
+                let transaction = connection.transaction()?;
+
+                let reverts: Option<i64> = change.reverts.as_ref().and_then(|id| id.parse().ok());
+                transaction.execute(
+                    "INSERT INTO wishlist_history (scope, user_id, action, series, source, reverts, timestamp)
+                     VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+                    params![
+                        scope.key(),
+                        user_id.to_string(),
+                        change.action.as_str(),
+                        change.entry.series.as_str(),
+                        change.source.as_str(),
+                        reverts,
+                        Utc::now().timestamp()
+                    ]
+                )?;
+
+                let history_id = transaction.last_insert_rowid();
+                {
+                    let mut statement = transaction.prepare_cached(
+                        "INSERT INTO wishlist_history_card (history_id, position, name, priority, note, constraints, excluded, wildcard)
+                         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)"
+                    )?;
+
+                    for (position, card) in change.entry.cards.iter().enumerate() {
+                        let card_search = card.search_term();
+                        let details = change.details.iter().find(|details| details.name.search_term() == card_search);
+                        let wildcard = change.wildcard.iter().any(|wildcard| wildcard.search_term() == card_search);
+
+                        statement.execute(params![
+                            history_id,
+                            position,
+                            card.as_str(),
+                            details.map(|details| details.priority.as_str()),
+                            details.and_then(|details| details.note.as_deref()),
+                            details.map(|details| constraints_text(&details.constraints)).unwrap_or_default(),
+                            details.map(|details| excluded_text(&details.excluded)).unwrap_or_default(),
+                            wildcard
+                        ])?;
+                    }
+                }
+
+                transaction.commit()
+            })();
+
+            res.map_err(|err| {
+                logger.log_error(format!("record_change: {err}"));
+                err.into()
+            })
+        }).await
+    }
+
+    async fn get_user_history (
+        &self,
+        scope: WishlistScope,
+        user_id: UserId,
+        limit: usize
+    ) -> WishlistDbResult<Vec<HistoryRecord>>
+    {
+        self.blocking(move |connection, logger| {
+            let res = connection
+                .prepare_cached(
+                    "SELECT id, action, series, source, reverts, timestamp FROM wishlist_history
+                     WHERE scope = ?1 AND user_id = ?2
+                     ORDER BY id DESC
+                     LIMIT ?3"
+                )
+                .and_then(|mut statement|
+                    statement
+                        .query_map(params![scope.key(), user_id.to_string(), limit as i64], history_row)?
+                        .collect::<Result<Vec<_>, _>>()
+                )
+                .and_then(|rows|
+                    rows.into_iter()
+                        .map(|(id, row)| history_record(connection, id, row))
+                        .collect::<Result<Vec<HistoryRecord>, _>>()
+                );
+
+            res.map_err(|err| {
+                logger.log_error(format!("get_user_history: {err}"));
+                err.into()
+            })
+        }).await
+    }
+
+    async fn get_last_undoable_change (
+        &self,
+        scope: WishlistScope,
+        user_id: UserId
+    ) -> WishlistDbResult<Option<HistoryRecord>>
+    {
+        self.blocking(move |connection, logger| {
+            let res = connection
+                .query_row(
+                    "SELECT id, action, series, source, reverts, timestamp FROM wishlist_history h
+                     WHERE scope = ?1 AND user_id = ?2 AND source != ?3
+                     AND NOT EXISTS (SELECT 1 FROM wishlist_history u WHERE u.reverts = h.id)
+                     ORDER BY id DESC
+                     LIMIT 1",
+                    params![scope.key(), user_id.to_string(), ChangeSource::Undo.as_str()],
+                    history_row
+                )
+                .optional()
+                .and_then(|row|
+                    row.map(|(id, row)| history_record(connection, id, row))
+                        .transpose()
+                );
+
+            res.map_err(|err| {
+                logger.log_error(format!("get_last_undoable_change: {err}"));
+                err.into()
+            })
+        }).await
+    }
+
+    async fn get_fuzzy_wishlists (
+        &self,
+        scope: WishlistScope
+    ) -> WishlistDbResult<Vec<FuzzyWishlist>>
+    {
+        let scope = scope.key();
+
+        self.blocking(move |connection, _| fuzzy_wishlists(connection, &scope)).await
+            .map_err(|err| {
+                self.logger.log_error(format!("get_fuzzy_wishlists: {err}"));
+                err.into()
+            })
+    }
+
+    async fn get_fuzzy_threshold (
+        &self,
+        user_id: UserId
+    ) -> WishlistDbResult<Option<u8>>
+    {
+        self.blocking(move |connection, logger| {
+            let res = connection.query_row(
+                "SELECT fuzzy_threshold FROM user_settings WHERE user_id = ?1",
+                params![user_id.to_string()],
+                |row| row.get::<_, Option<u8>>(0)
+            )
+            .optional();
+
+            match res {
+                Ok(threshold) => Ok(threshold.flatten()),
+                Err(err) => {
+                    logger.log_error(format!("get_fuzzy_threshold: {err}"));
+                    Err(err.into())
+                }
+            }
+        }).await
+    }
+
+    async fn set_fuzzy_threshold (
+        &self,
+        user_id: UserId,
+        threshold: Option<u8>
+    ) -> WishlistDbResult<()>
+    {
+        self.blocking(move |connection, logger| {
+            let res = connection.execute(
+                "INSERT INTO user_settings (user_id, fuzzy_threshold) VALUES (?1, ?2)
+                 ON CONFLICT (user_id) DO UPDATE SET fuzzy_threshold = excluded.fuzzy_threshold",
+                params![user_id.to_string(), threshold]
+            );
+
+            res.map(|_| ())
+                .map_err(|err| {
+                    logger.log_error(format!("set_fuzzy_threshold: {err}"));
+                    err.into()
+                })
+        }).await
+    }
+
+    async fn get_wishlist_privacy (
+        &self,
+        user_id: UserId
+    ) -> WishlistDbResult<WishlistPrivacy>
+    {
+        self.blocking(move |connection, logger| {
+            let res = connection.query_row(
+                "SELECT privacy FROM user_settings WHERE user_id = ?1",
+                params![user_id.to_string()],
+                |row| row.get::<_, Option<String>>(0)
+            )
+            .optional();
+
+            match res {
+                Ok(privacy) => Ok(privacy.flatten().and_then(|privacy| WishlistPrivacy::parse(&privacy)).unwrap_or_default()),
+                Err(err) => {
+                    logger.log_error(format!("get_wishlist_privacy: {err}"));
+                    Err(err.into())
+                }
+            }
+        }).await
+    }
+
+    async fn set_wishlist_privacy (
+        &self,
+        user_id: UserId,
+        privacy: WishlistPrivacy
+    ) -> WishlistDbResult<()>
+    {
+        self.blocking(move |connection, logger| {
+            let res = connection.execute(
+                "INSERT INTO user_settings (user_id, privacy) VALUES (?1, ?2)
+                 ON CONFLICT (user_id) DO UPDATE SET privacy = excluded.privacy",
+                params![user_id.to_string(), privacy.as_str()]
+            );
+
+            res.map(|_| ())
+                .map_err(|err| {
+                    logger.log_error(format!("set_wishlist_privacy: {err}"));
+                    err.into()
+                })
+        }).await
+    }
+
+    async fn canonical_series (
+        &self,
+        series: &SeriesName
+    ) -> WishlistDbResult<SeriesName>
+    {
+        let series = series.clone();
+
+        self.blocking(move |connection, _| resolve_alias(connection, &series)).await
+            .map_err(|err| {
+                self.logger.log_error(format!("canonical_series: {err}"));
+                err.into()
+            })
+    }
+
+    async fn get_series_aliases (&self) -> WishlistDbResult<Vec<(SeriesName, SeriesName)>>
+    {
+        self.blocking(move |connection, logger| {
+            let res = connection
+                .prepare_cached("SELECT name, canonical FROM series_alias ORDER BY canonical_search, search")
+                .and_then(|mut statement|
+                    statement
+                        .query_map([], |row| Ok((SeriesName::new(row.get::<_, String>(0)?), SeriesName::new(row.get::<_, String>(1)?))))?
+                        .collect::<Result<Vec<_>, _>>()
+                );
+
+            res.map_err(|err| {
+                logger.log_error(format!("get_series_aliases: {err}"));
+                err.into()
+            })
+        }).await
+    }
+
+    async fn add_series_alias (
+        &self,
+        alias: &SeriesName,
+        canonical: &SeriesName
+    ) -> WishlistDbResult<()>
+    {
+        validate_alias(alias, canonical)?;
+
+        let alias = alias.clone();
+        let canonical = canonical.clone();
+
+        self.blocking(move |connection, logger| {
+            let canonical = resolve_alias(connection, &canonical)
+                .inspect_err(|err| logger.log_error(format!("add_series_alias: {err}")))?;
+            validate_alias(&alias, &canonical)?;
+
+            let alias_search = alias.search_term();
+            let canonical_search = canonical.search_term();
+
+            let res = (|| {
+                let transaction = connection.transaction()?;
+
+                transaction.execute(
+                    "INSERT INTO series_alias (search, name, canonical, canonical_search) VALUES (?1, ?2, ?3, ?4)
+                     ON CONFLICT (search) DO UPDATE SET name = excluded.name, canonical = excluded.canonical, canonical_search = excluded.canonical_search",
+                    params![alias_search, alias.as_str(), canonical.as_str(), canonical_search]
+                )?;
+                transaction.execute(
+                    "UPDATE series_alias SET canonical = ?1, canonical_search = ?2 WHERE canonical_search = ?3",
+                    params![canonical.as_str(), canonical_search, alias_search]
+                )?;
+
+                // moves the alias series in place, the ones conflicting with a wishlisted canonical series
+                // (or card) are left behind and deleted, along with their cards
+                transaction.pragma_update(None, "defer_foreign_keys", "ON")?;
+                transaction.execute(
+                    "UPDATE OR IGNORE wishlist_series SET name = ?1, search = ?2 WHERE search = ?3",
+                    params![canonical.as_str(), canonical_search, alias_search]
+                )?;
+                transaction.execute(
+                    "UPDATE OR IGNORE wishlist_card SET series_search = ?1 WHERE series_search = ?2",
+                    params![canonical_search, alias_search]
+                )?;
+                transaction.execute("DELETE FROM wishlist_card WHERE series_search = ?1", params![alias_search])?;
+                transaction.execute("DELETE FROM wishlist_series WHERE search = ?1", params![alias_search])?;
+
+                transaction.commit()
+            })();
+
+            res.map_err(|err| {
+                logger.log_error(format!("add_series_alias: {err}"));
+                err.into()
+            })
+        }).await
+    }
+
+    async fn remove_series_alias (
+        &self,
+        alias: &SeriesName
+    ) -> WishlistDbResult<()>
+    {
+        let alias_search = alias.search_term();
+        let res = self.blocking(move |connection, _| connection.execute("DELETE FROM series_alias WHERE search = ?1", params![alias_search])).await;
+
+        match res {
+            Ok(0) => Err(WishlistDbError::NotFound(format!("alias `{alias}`"))),
+            Ok(_) => Ok(()),
+            Err(err) => {
+                self.logger.log_error(format!("remove_series_alias: {err}"));
+                Err(err.into())
+            }
+        }
+    }
+
+    async fn add_to_catalog (
+        &self,
+        entries: &[WishlistEntry],
+        seen_at: DateTime<Utc>
+    ) -> WishlistDbResult<()>
+    {
+        entries.iter().try_for_each(validate_entry)?;
+
+        let seen_at = seen_at.timestamp();
+        let entries = entries.to_vec();
+
+        self.blocking(move |connection, logger| {
+            let res = (|| {
+                let transaction = connection.transaction()?;
+
+                {
+                    let mut series_statement = transaction.prepare_cached(
+                        "INSERT INTO catalog_series (search, name, first_seen, last_seen) VALUES (?1, ?2, ?3, ?3)
+                        ON CONFLICT (search) DO UPDATE SET
+                            first_seen = MIN(first_seen, excluded.first_seen),
+                            last_seen = MAX(last_seen, excluded.last_seen)"
+                    )?;
+                    let mut card_statement = transaction.prepare_cached(
+                        "INSERT INTO catalog_card (series_search, search, name, first_seen, last_seen) VALUES (?1, ?2, ?3, ?4, ?4)
+                        ON CONFLICT (series_search, search) DO UPDATE SET
+                            first_seen = MIN(first_seen, excluded.first_seen),
+                            last_seen = MAX(last_seen, excluded.last_seen)"
+                    )?;
+
+                    for entry in entries.iter() {
+                        let series = resolve_alias(&transaction, &entry.series)?;
+                        let series_search = series.search_term();
+
+                        series_statement.execute(params![series_search, series.as_str(), seen_at])?;
+                        for card in entry.cards.iter() {
+                            card_statement.execute(params![series_search, card.search_term(), card.as_str(), seen_at])?;
+                        }
+                    }
+                }
+
+                transaction.commit()
+            })();
+
+            res.map_err(|err| {
+                logger.log_error(format!("add_to_catalog: {err}"));
+                err.into()
+            })
+        }).await
+    }
+
+    async fn search_catalog_series (
+        &self,
+        prefix: &str,
+        limit: usize
+    ) -> WishlistDbResult<Vec<SeriesName>>
+    {
+        let (start, end) = search_key_range(prefix);
+        self.blocking(move |connection, logger| {
+            let res = connection
+                .prepare_cached("SELECT name FROM catalog_series WHERE search >= ?1 AND search < ?2 ORDER BY search LIMIT ?3")
+                .and_then(|mut statement|
+                    statement
+                        .query_map(params![start, end, limit as i64], |row| Ok(SeriesName::new(row.get::<_, String>(0)?)))?
+                        .collect::<Result<Vec<SeriesName>, _>>()
+                );
+
+            res.map_err(|err| {
+                logger.log_error(format!("search_catalog_series: {err}"));
+                err.into()
+            })
+        }).await
+    }
+
+    async fn search_catalog_cards (
+        &self,
+        series: &SeriesName,
+        prefix: &str,
+        limit: usize
+    ) -> WishlistDbResult<Vec<CardName>>
+    {
+        let (start, end) = search_key_range(prefix);
+        let series = series.clone();
+
+        self.blocking(move |connection, logger| {
+            let res = resolve_alias(connection, &series)
+                .and_then(|series| Ok((series, connection.prepare_cached(
+                    "SELECT name FROM catalog_card WHERE series_search = ?1 AND search >= ?2 AND search < ?3 ORDER BY search LIMIT ?4"
+                )?)))
+                .and_then(|(series, mut statement)|
+                    statement
+                        .query_map(params![series.search_term(), start, end, limit as i64], |row| Ok(CardName::new(row.get::<_, String>(0)?)))?
+                        .collect::<Result<Vec<CardName>, _>>()
+                );
+
+            res.map_err(|err| {
+                logger.log_error(format!("search_catalog_cards: {err}"));
+                err.into()
+            })
+        }).await
+    }
+
+    async fn get_catalog_series (
+        &self,
+        series: &SeriesName
+    ) -> WishlistDbResult<Option<CatalogSeries>>
+    {
+        let series = series.clone();
+
+        self.blocking(move |connection, logger| {
+            let res = (|| {
+                let series = resolve_alias(connection, &series)?;
+                let series_search = series.search_term();
+
+                let Some((name, first_seen, last_seen)) = connection
+                    .query_row(
+                        "SELECT name, first_seen, last_seen FROM catalog_series WHERE search = ?1",
+                        params![series_search],
+                        |row| Ok((row.get::<_, String>(0)?, seen_time(row, 1)?, seen_time(row, 2)?))
+                    )
+                    .optional()? else {
+                    return Ok(None);
+                };
+
+                let cards = connection
+                    .prepare_cached("SELECT name, first_seen, last_seen FROM catalog_card WHERE series_search = ?1 ORDER BY search")?
+                    .query_map(params![series_search], |row| Ok(CatalogCard {
+                        name: CardName::new(row.get::<_, String>(0)?),
+                        first_seen: seen_time(row, 1)?,
+                        last_seen: seen_time(row, 2)?
+                    }))?
+                    .collect::<Result<Vec<CatalogCard>, _>>()?;
+
+                Ok(Some(CatalogSeries { series: SeriesName::new(name), cards, first_seen, last_seen }))
+            })();
+
+            res.map_err(|err: rusqlite::Error| {
+                logger.log_error(format!("get_catalog_series: {err}"));
+                err.into()
+            })
+        }).await
+    }
+
+    async fn get_catalog_stats(&self) -> WishlistDbResult<CatalogStats> {
+        self.blocking(move |connection, logger| {
+            let res = connection.query_row(
+                "SELECT (SELECT COUNT(*) FROM catalog_series), (SELECT COUNT(*) FROM catalog_card), (SELECT MIN(first_seen) FROM catalog_series)",
+                [],
+                |row| Ok(CatalogStats {
+                    series_count: row.get::<_, i64>(0)? as usize,
+                    cards_count: row.get::<_, i64>(1)? as usize,
+                    first_seen: row.get::<_, Option<i64>>(2)?.and_then(|seconds| DateTime::from_timestamp(seconds, 0))
+                })
+            );
+
+            res.map_err(|err| {
+                logger.log_error(format!("get_catalog_stats: {err}"));
+                err.into()
+            })
+        }).await
+    }
+
+    async fn set_cards_owned (
+        &self,
+        user_id: UserId,
+        entry: &WishlistEntry,
+        owned: bool
+    ) -> WishlistDbResult<()>
+    {
+        validate_entry(entry)?;
+
+        let entry = entry.clone();
+
+        self.blocking(move |connection, logger| {
+            let res = (|| {
+                let transaction = connection.transaction()?;
+
+                let series = resolve_alias(&transaction, &entry.series)?;
+                let series_search = series.search_term();
+
+                if owned {
+                    let mut statement = transaction.prepare_cached(
+                        "INSERT INTO owned_card (user_id, series_search, series, search, name) VALUES (?1, ?2, ?3, ?4, ?5)
+                        ON CONFLICT (user_id, series_search, search) DO UPDATE SET series = excluded.series, name = excluded.name"
+                    )?;
+                    for card in entry.cards.iter() {
+                        statement.execute(params![user_id.to_string(), series_search, series.as_str(), card.search_term(), card.as_str()])?;
+                    }
+                } else {
+                    let mut statement = transaction.prepare_cached(
+                        "DELETE FROM owned_card WHERE user_id = ?1 AND series_search = ?2 AND search = ?3"
+                    )?;
+                    for card in entry.cards.iter() {
+                        statement.execute(params![user_id.to_string(), series_search, card.search_term()])?;
+                    }
+                }
+
+                transaction.commit()
+            })();
+
+            res.map_err(|err| {
+                logger.log_error(format!("set_cards_owned: {err}"));
+                err.into()
+            })
+        }).await
+    }
+
+    async fn get_owned_cards (
+        &self,
+        user_id: UserId
+    ) -> WishlistDbResult<Vec<WishlistEntry>>
+    {
+        self.blocking(move |connection, logger| {
+            let res = connection
+                .prepare_cached("SELECT series_search, series, name FROM owned_card WHERE user_id = ?1 ORDER BY series_search, search")
+                .and_then(|mut statement|
+                    statement
+                        .query_map(params![user_id.to_string()], |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?, row.get::<_, String>(2)?)))?
+                        .collect::<Result<Vec<(String, String, String)>, _>>()
+                );
+
+            let rows = res.map_err(|err| {
+                logger.log_error(format!("get_owned_cards: {err}"));
+                WishlistDbError::from(err)
+            })?;
+
+            let mut entries: Vec<(String, WishlistEntry)> = vec![];
+            for (series_search, series, name) in rows {
+                match entries.last_mut() {
+                    Some((last_search, entry)) if *last_search == series_search => entry.cards.push(CardName::new(name)),
+                    _ => entries.push((series_search, WishlistEntry { series: SeriesName::new(series), cards: vec![CardName::new(name)] }))
+                }
+            }
+
+            Ok(entries.into_iter().map(|(_, entry)| entry).collect())
+        }).await
+    }
+
+    async fn get_guild_scope_mode (
+        &self,
+        guild_id: GuildId
+    ) -> WishlistDbResult<ScopeMode>
+    {
+        self.blocking(move |connection, logger| {
+            let res = connection.query_row(
+                "SELECT scope_mode FROM guild_settings WHERE guild_id = ?1",
+                params![guild_id.to_string()],
+                |row| row.get::<_, String>(0)
+            )
+            .optional();
+
+            match res {
+                Ok(mode) => Ok(mode.and_then(|mode| ScopeMode::parse(&mode)).unwrap_or_default()),
+                Err(err) => {
+                    logger.log_error(format!("get_guild_scope_mode: {err}"));
+                    Err(err.into())
+                }
+            }
+        }).await
+    }
+
+    async fn set_guild_scope_mode (
+        &self,
+        guild_id: GuildId,
+        mode: ScopeMode
+    ) -> WishlistDbResult<()>
+    {
+        self.blocking(move |connection, logger| {
+            let res = connection.execute(
+                "INSERT INTO guild_settings (guild_id, scope_mode) VALUES (?1, ?2)
+                 ON CONFLICT (guild_id) DO UPDATE SET scope_mode = excluded.scope_mode",
+                params![guild_id.to_string(), mode.as_str()]
+            );
+
+            res.map(|_| ())
+                .map_err(|err| {
+                    logger.log_error(format!("set_guild_scope_mode: {err}"));
+                    err.into()
+                })
+        }).await
+    }
+}
+
+impl <T> SqliteWishlistDB<T>
+    where T: Logger + Send + Sync + 'static
+{
+    // rusqlite calls block, so they run on the blocking thread pool instead of stalling the async workers
+    async fn blocking<R, F>(&self, work: F) -> R
+        where F: FnOnce(&mut Connection, &T) -> R + Send + 'static, R: Send + 'static
+    {
+        let connection = Arc::clone(&self.connection);
+        let logger = Arc::clone(&self.logger);
+
+        tokio::task::spawn_blocking(move || {
+            // a poisoned lock means a previous query panicked mid-transaction, which sqlite rolled back
+            let mut connection = connection.lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner());
+
+            work(&mut connection, &logger)
+        })
+        .await
+        .unwrap_or_else(|err| std::panic::resume_unwind(err.into_panic()))
+    }
+}
+
+// User ids are stored as text, the same way the Mongo backend stores them
+fn user_id_column(row: &Row, index: usize) -> Result<UserId, rusqlite::Error> {
+    row.get::<_, String>(index)?
+        .parse()
+        .map_err(|err| rusqlite::Error::FromSqlConversionFailure(index, Type::Text, Box::new(err)))
+}
+
+fn priority_column(row: &Row, index: usize) -> Result<CardPriority, rusqlite::Error> {
+    let priority: String = row.get(index)?;
+
+    CardPriority::parse(&priority)
+        .ok_or_else(|| rusqlite::Error::FromSqlConversionFailure(index, Type::Text, format!("unknown priority `{priority}`").into()))
+}
+
+// Series the alias registry resolves the given one to, itself when it is no alias
+fn resolve_alias(connection: &Connection, series: &SeriesName) -> Result<SeriesName, rusqlite::Error> {
+    connection
+        .query_row(
+            "SELECT canonical FROM series_alias WHERE search = ?1",
+            params![series.search_term()],
+            |row| row.get::<_, String>(0)
+        )
+        .optional()
+        .map(|canonical| canonical.map_or_else(|| series.clone(), SeriesName::new))
+}
+
+// Shared by `get_fuzzy_wishlists` and the drop lookup, which already holds the connection
+fn fuzzy_wishlists(connection: &Connection, scope: &str) -> Result<Vec<FuzzyWishlist>, rusqlite::Error> {
+    let rows = connection
+        .prepare_cached(
+            "SELECT c.user_id, u.fuzzy_threshold, s.name, c.name, c.priority, c.note, c.constraints
+             FROM wishlist_card c
+             JOIN user_settings u ON u.user_id = c.user_id
+             JOIN wishlist_series s ON s.scope = c.scope AND s.user_id = c.user_id AND s.search = c.series_search
+             WHERE c.scope = ?1 AND u.fuzzy_threshold IS NOT NULL
+             ORDER BY c.user_id, c.rowid"
+        )?
+        .query_map(params![scope], |row| {
+            let card = WishlistedCard::new(CardName::new(row.get::<_, String>(3)?), priority_column(row, 4)?)
+                .with_details(row.get(5)?, constraints_column(row, 6)?);
+
+            Ok((user_id_column(row, 0)?, row.get::<_, u8>(1)?, SeriesName::new(row.get::<_, String>(2)?), card))
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let mut wishlists: Vec<FuzzyWishlist> = Vec::new();
+    for (user_id, threshold, series, card) in rows {
+        match wishlists.last_mut() {
+            Some(wishlist) if wishlist.user_id == user_id => wishlist.cards.push((series, card)),
+            _ => wishlists.push(FuzzyWishlist { user_id, threshold, cards: vec![(series, card)] })
+        }
+    }
+
+    Ok(wishlists)
+}
+
+// Series are grouped, sorted and paged by SQLite, cards are only read when asked for
+fn wishlist_overview(connection: &Connection, scope: &str, user_id: &str, query: &OverviewQuery) -> Result<WishlistOverview, rusqlite::Error> {
+    let (total_series, total_cards) = connection.query_row(
+        "SELECT COUNT(DISTINCT series_search), COUNT(*) FROM wishlist_card WHERE scope = ?1 AND user_id = ?2",
+        params![scope, user_id],
+        |row| Ok((row.get::<_, usize>(0)?, row.get::<_, usize>(1)?))
+    )?;
+
+    let order = match query.order {
+        SeriesOrder::Added => "s.rowid",
+        SeriesOrder::Name => "s.search, s.rowid",
+        SeriesOrder::CardCount => "cards_count DESC, s.rowid"
+    };
+    let limit = query.limit.map_or(-1, |limit| limit as i64);
+
+    let mut statement = connection.prepare_cached(&format!(
+        "SELECT s.name, s.search, COUNT(*) AS cards_count, MAX(c.search = ?5) FROM wishlist_series s
+            JOIN wishlist_card c ON c.scope = s.scope AND c.user_id = s.user_id AND c.series_search = s.search
+        WHERE s.scope = ?1 AND s.user_id = ?2
+        GROUP BY s.rowid
+        ORDER BY {order}
+        LIMIT ?3 OFFSET ?4"
+    ))?;
+    let page = statement
+        .query_map(params![scope, user_id, limit, query.skip as i64, CardName::wildcard().search_term()], |row| Ok((
+            SeriesName::new(row.get::<_, String>(0)?),
+            row.get::<_, String>(1)?,
+            row.get::<_, i32>(2)?,
+            row.get::<_, bool>(3)?
+        )))?
+        .collect::<Result<Vec<(SeriesName, String, i32, bool)>, _>>()?;
+
+    let mut cards: HashMap<String, Vec<WishlistedCard>> = HashMap::new();
+    if query.with_cards && !page.is_empty() {
+        let mut statement = connection.prepare_cached(
+            "SELECT series_search, name, priority, note, constraints, excluded, added_at FROM wishlist_card
+             WHERE scope = ?1 AND user_id = ?2
+             ORDER BY rowid"
+        )?;
+        let rows = statement.query_map(params![scope, user_id], |row| Ok((
+            row.get::<_, String>(0)?,
+            WishlistedCard::new(CardName::new(row.get::<_, String>(1)?), priority_column(row, 2)?)
+                .with_details(row.get(3)?, constraints_column(row, 4)?)
+                .with_excluded(excluded_column(row, 5)?)
+                .with_added_at(added_at_column(row, 6)?)
+        )))?;
+
+        for row in rows {
+            let (series_search, card) = row?;
+            cards.entry(series_search).or_default().push(card);
+        }
+    }
+
+    let series = page.into_iter()
+        .map(|(series, series_search, cards_count, whole)| SeriesOverview {
+            series,
+            cards_count,
+            whole,
+            cards: query.with_cards.then(|| cards.remove(&series_search).unwrap_or_default())
+        })
+        .collect();
+
+    Ok(WishlistOverview { series, total_series, total_cards })
+}
+
+fn constraints_column(row: &Row, index: usize) -> Result<Vec<CardConstraint>, rusqlite::Error> {
+    let constraints: String = row.get(index)?;
+
+    constraints.split(',')
+        .filter(|constraint| !constraint.is_empty())
+        .map(|constraint| CardConstraint::parse(constraint)
+            .ok_or_else(|| rusqlite::Error::FromSqlConversionFailure(index, Type::Text, format!("unknown constraint `{constraint}`").into()))
+        )
+        .collect()
+}
+
+fn constraints_text(constraints: &[CardConstraint]) -> String {
+    constraints.iter()
+        .map(CardConstraint::to_string)
+        .collect::<Vec<String>>()
+        .join(",")
+}
+
+// `name, priority, note, constraints, excluded, added_at` from `index` on
+fn wishlisted_card_columns(row: &Row, index: usize) -> Result<WishlistedCard, rusqlite::Error> {
+    Ok(WishlistedCard::new(CardName::new(row.get::<_, String>(index)?), priority_column(row, index + 1)?)
+        .with_details(row.get(index + 2)?, constraints_column(row, index + 3)?)
+        .with_excluded(excluded_column(row, index + 4)?)
+        .with_added_at(added_at_column(row, index + 5)?))
+}
+
+fn excluded_column(row: &Row, index: usize) -> Result<Vec<CardName>, rusqlite::Error> {
+    let excluded: String = row.get(index)?;
+
+    Ok(excluded.lines().map(CardName::new).collect())
+}
+
+fn excluded_text(excluded: &[CardName]) -> String {
+    excluded.iter()
+        .map(CardName::as_str)
+        .collect::<Vec<&str>>()
+        .join("\n")
+}
+
+// The cards the user's wildcard card of the series leaves out, `None` without a wildcard card
+fn wildcard_exclusions(connection: &Connection, scope: &str, user_id: &str, series_search: &str) -> Result<Option<Vec<CardName>>, rusqlite::Error> {
+    connection.query_row(
+        "SELECT excluded FROM wishlist_card WHERE scope = ?1 AND user_id = ?2 AND series_search = ?3 AND search = ?4",
+        params![scope, user_id, series_search, CardName::wildcard().search_term()],
+        |row| excluded_column(row, 0)
+    )
+    .optional()
+}
+
+// Returns how many wildcard cards were updated
+fn set_exclusions(connection: &Connection, scope: &str, user_id: &str, series_search: &str, excluded: &[CardName]) -> Result<usize, rusqlite::Error> {
+    connection.execute(
+        "UPDATE wishlist_card SET excluded = ?1 WHERE scope = ?2 AND user_id = ?3 AND series_search = ?4 AND search = ?5",
+        params![excluded_text(excluded), scope, user_id, series_search, CardName::wildcard().search_term()]
+    )
+}
+
+// A `wishlist_history` row, the cards are read separately by `history_record`
+struct HistoryRow {
+    action: HistoryAction,
+    series: SeriesName,
+    source: ChangeSource,
+    reverts: Option<i64>,
+    timestamp: DateTime<Utc>
+}
+
+fn history_row(row: &Row) -> Result<(i64, HistoryRow), rusqlite::Error> {
+    let conversion_error = |index: usize, what: &str| 
+        rusqlite::Error::FromSqlConversionFailure(index, Type::Text, format!("unknown {what}").into());
+
+    let action: String = row.get(1)?;
+    let source: String = row.get(3)?;
+
+    let history_row = HistoryRow {
+        action: HistoryAction::parse(&action).ok_or_else(|| conversion_error(1, "history action"))?,
+        series: SeriesName::new(row.get::<_, String>(2)?),
+        source: ChangeSource::parse(&source).ok_or_else(|| conversion_error(3, "change source"))?,
+        reverts: row.get(4)?,
+        timestamp: DateTime::from_timestamp(row.get(5)?, 0).ok_or_else(|| conversion_error(5, "timestamp"))?
+    };
+
+    Ok((row.get(0)?, history_row))
+}
+
+fn added_at_column(row: &Row, index: usize) -> Result<Option<DateTime<Utc>>, rusqlite::Error> {
+    row.get::<_, Option<i64>>(index)?
+        .map(|added_at| DateTime::from_timestamp(added_at, 0)
+            .ok_or_else(|| rusqlite::Error::FromSqlConversionFailure(index, Type::Integer, "invalid added time".into()))
+        )
+        .transpose()
+}
+
+fn seen_time(row: &Row, index: usize) -> Result<DateTime<Utc>, rusqlite::Error> {
+    DateTime::from_timestamp(row.get(index)?, 0)
+        .ok_or_else(|| rusqlite::Error::FromSqlConversionFailure(index, Type::Integer, "invalid seen time".into()))
+}
+
+fn history_record(connection: &Connection, id: i64, row: HistoryRow) -> Result<HistoryRecord, rusqlite::Error> {
+    let card_rows = connection
+        .prepare_cached(
+            "SELECT name, priority, note, constraints, excluded, wildcard FROM wishlist_history_card WHERE history_id = ?1 ORDER BY position"
+        )?
+        .query_map(params![id], |row| {
+            let name = CardName::new(row.get::<_, String>(0)?);
+            let details = match row.get::<_, Option<String>>(1)? {
+                Some(_) => Some(
+                    WishlistedCard::new(name.clone(), priority_column(row, 1)?)
+                        .with_details(row.get(2)?, constraints_column(row, 3)?)
+                        .with_excluded(excluded_column(row, 4)?)
+                ),
+                None => None
+            };
+
+            Ok((name, details, row.get::<_, bool>(5)?))
+        })?
+        .collect::<Result<Vec<(CardName, Option<WishlistedCard>, bool)>, _>>()?;
+
+    let wildcard = card_rows.iter()
+        .filter(|(_, _, wildcard)| *wildcard)
+        .map(|(name, _, _)| name.clone())
+        .collect();
+    let (cards, details): (Vec<CardName>, Vec<Option<WishlistedCard>>) = card_rows.into_iter()
+        .map(|(name, details, _)| (name, details))
+        .unzip();
+
+    let change = WishlistChange {
+        action: row.action,
+        entry: WishlistEntry { series: row.series, cards },
+        details: details.into_iter().flatten().collect(),
+        wildcard,
+        source: row.source,
+        reverts: row.reverts.map(|id| id.to_string())
+    };
+
+    Ok(HistoryRecord { id: id.to_string(), change, timestamp: row.timestamp })
+}
+
+impl From<rusqlite::Error> for WishlistDbError {
+    fn from(err: rusqlite::Error) -> Self {
+        match err.sqlite_error_code() {
+            Some(ErrorCode::CannotOpen | ErrorCode::DatabaseBusy | ErrorCode::DatabaseLocked) =>
+                WishlistDbError::ConnectionLost(err.to_string()),
+            _ => WishlistDbError::Backend(Box::new(err))
+        }
+    }
+}
@@ -1,13 +1,17 @@
 
 use std::time::Duration;
 
-use ::serenity::all::{Message, MessageCollector, ReactionType};
+use chrono::Utc;
+
+use ::serenity::all::{Message, MessageCollector, ReactionType, UserId};
 use serenity::all::ReactionCollector;
 use poise::serenity_prelude as serenity;
 
 use crate::commands::{wa, wr_cards};
 use crate::components::logger::Logger;
-use crate::bot::{Context, Error, _SOFI_USER_ID};
+use crate::model::history::ChangeSource;
+use crate::model::wishlist::{CardRef, WishlistEntry};
+use crate::bot::{Context, Data, Error, _SOFI_USER_ID};
 use crate::util::either::Either;
 use crate::util::parse_util::{parse_card_from_series_lookup, parse_series_from_embed_description, parse_series_from_give_command};
 
@@ -30,7 +34,7 @@ pub async fn integration_ssl (
     #[rest] _full_command: Option<String>,
 ) -> Result<(), Error> 
 {
-    let msg_id = ctx.id().clone();
+    let msg_id = ctx.id();
 
     if let Some(first_reply) = MessageCollector::new(ctx)
         .author_id(_SOFI_USER_ID)
@@ -55,6 +59,10 @@ pub async fn integration_ssl (
             }
         };
 
+        record_series_lookup(ctx.data(), ctx.author().id, &sofi_msg).await;
+
+        let scope = ctx.data().wishlist_db.resolve_scope(ctx.guild_id()).await?;
+
         sofi_msg.react(ctx.http(), ReactionType::Unicode("✅".to_string())).await?;
         sofi_msg.react(ctx.http(), ReactionType::Unicode("❌".to_string())).await?;
 
@@ -82,12 +90,13 @@ pub async fn integration_ssl (
             if !is_series_lookup(&sofi_msg) {
                 continue;
             }
+            record_series_lookup(ctx.data(), ctx.author().id, &sofi_msg).await;
             
-            let embed = sofi_msg.embeds.get(0).unwrap();
+            let embed = sofi_msg.embeds.first().unwrap();
             let description = embed.description.clone().unwrap();
             let series = parse_series_from_embed_description(description.as_str()).unwrap();
             let cards: Vec<(bool, &str)> = 
-                embed.fields.get(0).unwrap().value.split("\n").into_iter().map(parse_card_from_series_lookup).map(Option::unwrap).collect();
+                embed.fields.first().unwrap().value.split("\n").map(parse_card_from_series_lookup).map(Option::unwrap).collect();
 
             match &reaction.emoji {
                 ReactionType::Unicode(emoji) if emoji == "✅" => {
@@ -95,38 +104,46 @@ pub async fn integration_ssl (
                     
                     let card_names = cards.iter()
                     .filter(|card| !card.0)
-                    .map(|(_, card)| *card)
-                    .collect();
+                    .map(|(_, card)| *card);
     
-                    let response = wa( ctx.serenity_context(), 
+                    let res = wa( ctx.serenity_context(), 
                         &first_msg, 
                         ctx.data(), 
+                        scope,
                         ctx.author().id, 
-                        series, 
-                        card_names,
-                        wa_response_msg
-                    ).await.unwrap();
+                        &WishlistEntry::new(series, card_names),
+                        None,
+                        ChangeSource::SofiSsl,
+                        wa_response_msg.clone()
+                    ).await;
 
-                    wa_response_msg = Some(response);
+                    // on errors the previous response is still edited by the next reaction
+                    match res {
+                        Ok(response) => wa_response_msg = Some(response),
+                        Err(why) => ctx.data().logger.log_error(format!("ssl: Error adding cards to the wishlist: {why:?}"))
+                    }
                 }
                 ReactionType::Unicode(emoji) if emoji == "❌" => {
                     reaction.delete(ctx.http()).await?;
                     
                     let card_names = cards.iter()
                     .filter(|card| card.0)
-                    .map(|(_, card)| *card)
-                    .collect();
+                    .map(|(_, card)| *card);
                     
-                    let response = wr_cards( ctx.serenity_context(), 
+                    let res = wr_cards( ctx.serenity_context(), 
                               Either::Left(&first_msg), 
                               ctx.data(), 
+                              scope,
                               ctx.author().id, 
-                              series, 
-                              card_names,
-                              wr_response_msg
-                    ).await.unwrap();
+                              &WishlistEntry::new(series, card_names),
+                              ChangeSource::SofiSsl,
+                              wr_response_msg.clone()
+                    ).await;
 
-                    wr_response_msg = Some(response);
+                    match res {
+                        Ok(response) => wr_response_msg = Some(response),
+                        Err(why) => ctx.data().logger.log_error(format!("ssl: Error removing cards from the wishlist: {why:?}"))
+                    }
                 }
                 _ => ()
             }
@@ -139,8 +156,37 @@ pub async fn integration_ssl (
     Ok(())
 }
 
+// The series lookup lists every card of the series, or a page of them, along with whether
+// the user who looked it up owns each card
+async fn record_series_lookup(data: &Data, user_id: UserId, message: &Message) {
+    let Some(embed) = message.embeds.first().filter(|_| is_series_lookup(message))
+    else {
+        return;
+    };
+    let Some(series) = embed.description.as_deref().and_then(parse_series_from_embed_description)
+    else {
+        return;
+    };
+
+    let cards: Vec<(bool, &str)> = embed.fields.first()
+        .map(|field| field.value.split("\n").filter_map(parse_card_from_series_lookup).collect())
+        .unwrap_or_default();
+
+    let card_names = cards.iter().map(|(_, card)| *card);
+    if let Err(why) = data.wishlist_db.add_to_catalog(&[WishlistEntry::new(series, card_names)], Utc::now()).await {
+        data.logger.log_error(format!("record_series_lookup: Error adding to the catalog: {why:?}"));
+    }
+
+    for owned in [true, false] {
+        let entry = WishlistEntry::new(series, cards.iter().filter(|card| card.0 == owned).map(|(_, card)| *card));
+        if !entry.cards.is_empty() {
+            data.wishlist_db.set_cards_owned(user_id, &entry, owned).await.ok();
+        }
+    }
+}
+
 fn is_series_lookup(message: &Message) -> bool {
-    message.embeds.get(0).is_some_and(|embed| 
+    message.embeds.first().is_some_and(|embed| 
         embed.title.clone().is_some_and(|title| title == "SOFI: SERIES LOOKUP") 
         // && embed.fields.len() > 0
     )
@@ -160,7 +206,7 @@ pub async fn integration_sg (
     #[rest] _full_command: Option<String>,
 ) -> Result<(), Error> 
 {
-    let msg_id = ctx.id().clone();
+    let msg_id = ctx.id();
 
     if let Some(first_reply) = MessageCollector::new(ctx)
         .author_id(_SOFI_USER_ID)
@@ -185,15 +231,17 @@ pub async fn integration_sg (
             }
         };
 
-        let target_user = first_msg.mentions.get(0).unwrap();
+        let target_user = first_msg.mentions.first().unwrap();
         
-        let embed = sofi_msg.embeds.get(0).unwrap();
+        let embed = sofi_msg.embeds.first().unwrap();
         let description = embed.description.clone().unwrap();
         let (series, card) = parse_series_from_give_command(description.as_str()).unwrap();
-        let has_card = ctx.data().wishlist_db.user_has_card(target_user.id.to_string().as_str(), series, card).await;
+        let card = CardRef::new(series, card);
+        let scope = ctx.data().wishlist_db.resolve_scope(ctx.guild_id()).await?;
+        let has_card = ctx.data().wishlist_db.user_has_card(scope, target_user.id, &card).await?;
         
         if has_card {
-            sofi_msg.react(ctx.http(), ReactionType::Unicode("❌".to_string())).await.unwrap();
+            sofi_msg.react(ctx.http(), ReactionType::Unicode("❌".to_string())).await?;
 
             while let Some(reaction) = ReactionCollector::new(ctx)
                 // only the target user's reaction activates the integration
@@ -208,14 +256,18 @@ pub async fn integration_sg (
                     ReactionType::Unicode(emoji) if emoji == "❌" => {
                         reaction.delete_all(ctx.http()).await?;
                         
-                        wr_cards( ctx.serenity_context(), 
+                        let res = wr_cards( ctx.serenity_context(), 
                                   Either::Right(ctx.channel_id()), 
                                   ctx.data(), 
+                                  scope,
                                   target_user.id, 
-                                  series, 
-                                  vec![card],
+                                  &WishlistEntry::from(card.clone()),
+                                  ChangeSource::SofiSg,
                                   None
-                        ).await.unwrap();
+                        ).await;
+                        if let Err(why) = res {
+                            ctx.data().logger.log_error(format!("sg: Error removing the given card from the wishlist: {why:?}"));
+                        }
                     }
                     _ => ()
                 }
@@ -1,6 +1,7 @@
 mod bot;
 mod util;
 mod traits;
+mod model;
 mod components;
 mod commands;
 mod integrations;
@@ -8,9 +9,10 @@ mod tests;
 
 use std::sync::Arc;
 use bot::start_bot;
-use components::{logger::StdoutLogger, logger::Logger, mongo_wishlist_db::init_db};
+use components::{cached_wishlist_db::{CachedWishlistDB, DEFAULT_CACHE_CAPACITY, DEFAULT_CACHE_TTL}, logger::StdoutLogger, logger::Logger, mongo_wishlist_db, sqlite_wishlist_db};
+use traits::wishlist_db::WishlistDB;
 
-use util::parse_util::parse_secrets;
+use util::parse_util::{parse_secrets, parse_sqlite_path};
 
 
 #[tokio::main]
@@ -18,19 +20,32 @@ async fn main() {
     let logger = Arc::new(StdoutLogger);
     
     // Read secrets
-    let Some((discord_token, mongodb_url)) = parse_secrets()
+    let Some((discord_token, database_url)) = parse_secrets()
     else {
         logger.log_error("Missing credentials");
         return;
     };
 
     
-    // Init db connection
+    // Init db connection, a `sqlite://` url selects the local SQLite backend
     logger.log_info("Connecting to database");
-    let Ok(db_connection) = init_db(logger.clone(), mongodb_url).await 
-    else {
-        logger.log_error("Unable to connect to database");
-        return;
+    let db_connection: Box<dyn WishlistDB> = match parse_sqlite_path(&database_url) {
+        Some(path) => {
+            let Ok(db_connection) = sqlite_wishlist_db::init_db(logger.clone(), path).await
+            else {
+                logger.log_error("Unable to open SQLite database");
+                return;
+            };
+            Box::new(CachedWishlistDB::new(db_connection, logger.clone(), DEFAULT_CACHE_CAPACITY, DEFAULT_CACHE_TTL))
+        }
+        None => {
+            let Ok(db_connection) = mongo_wishlist_db::init_db(logger.clone(), database_url).await
+            else {
+                logger.log_error("Unable to connect to database");
+                return;
+            };
+            Box::new(CachedWishlistDB::new(db_connection, logger.clone(), DEFAULT_CACHE_CAPACITY, DEFAULT_CACHE_TTL))
+        }
     };
     logger.log_info("Connected to database");
     
@@ -0,0 +1,51 @@
+use chrono::{DateTime, Utc};
+
+use crate::model::wishlist::{CardName, SeriesName, WishlistedCard};
+use crate::util::fuzzy::closest_match;
+
+/// A card seen in a drop or a SOFI series lookup.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CatalogCard {
+    pub name: CardName,
+    pub first_seen: DateTime<Utc>,
+    pub last_seen: DateTime<Utc>
+}
+
+/// A series seen in a drop, a series analysis or a SOFI series lookup, with the cards seen from it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CatalogSeries {
+    pub series: SeriesName,
+    /// Sorted by search term.
+    pub cards: Vec<CatalogCard>,
+    pub first_seen: DateTime<Utc>,
+    pub last_seen: DateTime<Utc>
+}
+
+/// Size of the whole catalog.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct CatalogStats {
+    pub series_count: usize,
+    pub cards_count: usize,
+    /// When the first series was seen, `None` for an empty catalog.
+    pub first_seen: Option<DateTime<Utc>>
+}
+
+impl CatalogSeries {
+    pub fn has_card(&self, card: &CardName) -> bool {
+        let card_search = card.search_term();
+
+        self.cards.iter().any(|known| known.name.search_term() == card_search)
+    }
+
+    /// The known card most similar to `card`, when similar enough to be the one meant.
+    pub fn closest_card(&self, card: &CardName) -> Option<&CardName> {
+        closest_match(&card.search_term(), self.cards.iter().map(|known| (known.name.search_term(), &known.name)))
+    }
+
+    /// How many of the known cards are wishlisted, a wildcard card counting every card it does not exclude.
+    pub fn wishlisted_count(&self, wishlisted: &[WishlistedCard]) -> usize {
+        self.cards.iter()
+            .filter(|known| wishlisted.iter().any(|card| card.covers(&known.name)))
+            .count()
+    }
+}
@@ -0,0 +1,88 @@
+use crate::model::wishlist::{CardName, WishlistEntry, WishlistOverview, WishlistedCard};
+
+/// What two wishlists have in common, and which of the cards each user owns the other wants.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct WishlistComparison {
+    /// Series both users wishlist, in the first wishlist's order, with the cards both want.
+    /// The wildcard card stands for the rest of the series when both wishlist it whole.
+    pub shared: Vec<WishlistEntry>,
+    /// Cards the first user owns that the second one wishlists.
+    pub first_owns: Vec<WishlistEntry>,
+    /// Cards the second user owns that the first one wishlists.
+    pub second_owns: Vec<WishlistEntry>
+}
+
+impl WishlistComparison {
+    /// Compares two wishlist overviews read with their cards, along with the cards each user owns.
+    pub fn new(
+        first: &WishlistOverview,
+        second: &WishlistOverview,
+        first_owned: &[WishlistEntry],
+        second_owned: &[WishlistEntry]
+    ) -> Self
+    {
+        let shared = first.series.iter()
+            .filter_map(|series| {
+                let second_cards = series_cards(second, &series.series.search_term())?;
+                let first_cards = series.cards.as_deref().unwrap_or_default();
+
+                Some(WishlistEntry { series: series.series.clone(), cards: shared_cards(first_cards, second_cards) })
+            })
+            .collect();
+
+        Self {
+            shared,
+            first_owns: wanted_cards(first_owned, second),
+            second_owns: wanted_cards(second_owned, first)
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.shared.is_empty() && self.first_owns.is_empty() && self.second_owns.is_empty()
+    }
+}
+
+fn series_cards<'a>(overview: &'a WishlistOverview, series_search: &str) -> Option<&'a [WishlistedCard]> {
+    overview.series.iter()
+        .find(|series| series.series.search_term() == series_search)
+        .map(|series| series.cards.as_deref().unwrap_or_default())
+}
+
+// Cards of either side the other side covers, the first side's ones first
+fn shared_cards(first: &[WishlistedCard], second: &[WishlistedCard]) -> Vec<CardName> {
+    let mut cards: Vec<CardName> = vec![];
+
+    let covered = |card: &&WishlistedCard, other: &[WishlistedCard]|
+        !card.name.is_wildcard() && other.iter().any(|other_card| other_card.covers(&card.name));
+
+    let candidates = first.iter().filter(|card| covered(card, second))
+        .chain(second.iter().filter(|card| covered(card, first)));
+    for card in candidates {
+        let card_search = card.name.search_term();
+        if !cards.iter().any(|shared| shared.search_term() == card_search) {
+            cards.push(card.name.clone());
+        }
+    }
+
+    let is_whole = |cards: &[WishlistedCard]| cards.iter().any(|card| card.name.is_wildcard());
+    if is_whole(first) && is_whole(second) {
+        cards.push(CardName::wildcard());
+    }
+
+    cards
+}
+
+// The owned cards the wishlist covers, by series
+fn wanted_cards(owned: &[WishlistEntry], wishlist: &WishlistOverview) -> Vec<WishlistEntry> {
+    owned.iter()
+        .filter_map(|entry| {
+            let wishlisted = series_cards(wishlist, &entry.series.search_term())?;
+            let cards: Vec<CardName> = entry.cards.iter()
+                .filter(|card| wishlisted.iter().any(|wishlisted| wishlisted.covers(card)))
+                .cloned()
+                .collect();
+
+            (!cards.is_empty()).then(|| WishlistEntry { series: entry.series.clone(), cards })
+        })
+        .collect()
+}
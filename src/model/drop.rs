@@ -0,0 +1,156 @@
+use std::fmt::Display;
+
+use serenity::all::UserId;
+
+use crate::model::wishlist::{CardPriority, CardRef, SeriesName, WishlistedCard};
+
+/// A card seen in a drop analysis, with the numbered fields shown next to it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DroppedCard {
+    pub card: CardRef,
+    pub gen: Option<u32>,
+    pub edition: Option<u32>
+}
+
+/// A user to ping for a dropped card.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DropMatch {
+    pub user_id: UserId,
+    pub priority: CardPriority,
+    /// The user's wishlisted card when it only resembles the dropped one, through fuzzy matching.
+    pub possible_match: Option<CardRef>
+}
+
+/// Wishlist of a user that opted into fuzzy matching, used to find possible matches for a drop.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FuzzyWishlist {
+    pub user_id: UserId,
+    /// Lowest similarity, as a percentage, for a card to be a possible match.
+    pub threshold: u8,
+    pub cards: Vec<(SeriesName, WishlistedCard)>
+}
+
+/// Numbered field of a dropped card that a constraint can target.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DropAttribute {
+    /// `ɢ` field, the card's print number
+    Gen,
+    /// `◈` field
+    Edition
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ConstraintOp {
+    Lt,
+    Le,
+    Eq,
+    Ge,
+    Gt
+}
+
+/// Condition a drop has to meet to ping for a wishlisted card, like `gen<=100`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct CardConstraint {
+    pub attribute: DropAttribute,
+    pub op: ConstraintOp,
+    pub value: u32
+}
+
+impl DroppedCard {
+    pub fn new(card: CardRef) -> Self {
+        Self { card, gen: None, edition: None }
+    }
+
+    pub fn attribute(&self, attribute: DropAttribute) -> Option<u32> {
+        match attribute {
+            DropAttribute::Gen => self.gen,
+            DropAttribute::Edition => self.edition
+        }
+    }
+
+    /// Whether the drop meets every constraint.
+    /// A field missing from the analysis meets any constraint, so format changes don't silence pings.
+    pub fn satisfies(&self, constraints: &[CardConstraint]) -> bool {
+        constraints.iter().all(|constraint|
+            self.attribute(constraint.attribute)
+                .is_none_or(|value| constraint.op.compare(value, constraint.value))
+        )
+    }
+}
+
+impl DropMatch {
+    pub fn exact(user_id: UserId, priority: CardPriority) -> Self {
+        Self { user_id, priority, possible_match: None }
+    }
+}
+
+impl DropAttribute {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DropAttribute::Gen => "gen",
+            DropAttribute::Edition => "edition"
+        }
+    }
+
+    pub fn parse(attribute: &str) -> Option<Self> {
+        match attribute.trim().to_lowercase().as_str() {
+            "gen" | "print" => Some(DropAttribute::Gen),
+            "edition" | "ed" => Some(DropAttribute::Edition),
+            _ => None
+        }
+    }
+}
+
+impl ConstraintOp {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ConstraintOp::Lt => "<",
+            ConstraintOp::Le => "<=",
+            ConstraintOp::Eq => "=",
+            ConstraintOp::Ge => ">=",
+            ConstraintOp::Gt => ">"
+        }
+    }
+
+    pub fn parse(op: &str) -> Option<Self> {
+        match op.trim() {
+            "<" => Some(ConstraintOp::Lt),
+            "<=" | "≤" => Some(ConstraintOp::Le),
+            "=" | "==" => Some(ConstraintOp::Eq),
+            ">=" | "≥" => Some(ConstraintOp::Ge),
+            ">" => Some(ConstraintOp::Gt),
+            _ => None
+        }
+    }
+
+    pub fn compare(&self, value: u32, target: u32) -> bool {
+        match self {
+            ConstraintOp::Lt => value < target,
+            ConstraintOp::Le => value <= target,
+            ConstraintOp::Eq => value == target,
+            ConstraintOp::Ge => value >= target,
+            ConstraintOp::Gt => value > target
+        }
+    }
+}
+
+impl CardConstraint {
+    /// Parses the `<attribute><op><value>` form, which is also how constraints are stored.
+    pub fn parse(constraint: &str) -> Option<Self> {
+        let constraint = constraint.trim();
+        let op_start = constraint.find(['<', '>', '=', '≤', '≥'])?;
+        let value_start = constraint[op_start..].find(|c: char| !matches!(c, '<' | '>' | '=' | '≤' | '≥'))? + op_start;
+
+        Some(Self {
+            attribute: DropAttribute::parse(&constraint[..op_start])?,
+            op: ConstraintOp::parse(&constraint[op_start..value_start])?,
+            value: constraint[value_start..].trim().parse().ok()?
+        })
+    }
+}
+
+impl Display for CardConstraint {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}{}{}", self.attribute.as_str(), self.op.as_str(), self.value)
+    }
+}
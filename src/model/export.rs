@@ -0,0 +1,110 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serenity::all::UserId;
+
+use crate::model::wishlist::{WishlistOverview, WishlistScope};
+
+/// Version of the export format, bumped whenever a field changes meaning or goes away.
+/// New optional fields keep the version.
+pub const EXPORT_FORMAT_VERSION: u32 = 1;
+
+/// A user's whole wishlist as written to `.wexport` files.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WishlistExport {
+    pub version: u32,
+    pub exported_at: DateTime<Utc>,
+    /// Discord ids are kept as strings, JSON numbers can not hold all of them.
+    pub user_id: String,
+    /// `"global"` or the guild id, see `WishlistScope::key`.
+    pub scope: String,
+    pub series: Vec<ExportedSeries>
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ExportedSeries {
+    pub name: String,
+    pub cards: Vec<ExportedCard>
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ExportedCard {
+    pub name: String,
+    /// `CardPriority::as_str`
+    pub priority: String,
+    pub note: Option<String>,
+    /// `CardConstraint`s as typed in `.wfilter`
+    pub constraints: Vec<String>,
+    /// When the card was last added, unknown for cards added before it was stored.
+    pub added_at: Option<DateTime<Utc>>
+}
+
+// Flat layout of the CSV export, one row per card
+#[derive(Debug, Serialize, Deserialize)]
+pub(crate) struct CsvRow {
+    pub version: u32,
+    pub series: String,
+    pub card: String,
+    pub priority: String,
+    pub note: Option<String>,
+    pub constraints: String,
+    pub added_at: Option<String>
+}
+
+impl WishlistExport {
+    /// Builds the export from an overview read with its cards.
+    pub fn new(user_id: UserId, scope: WishlistScope, exported_at: DateTime<Utc>, overview: &WishlistOverview) -> Self {
+        let series = overview.series.iter()
+            .map(|series| ExportedSeries {
+                name: series.series.as_str().to_string(),
+                cards: series.cards.iter().flatten()
+                    .map(|card| ExportedCard {
+                        name: card.name.as_str().to_string(),
+                        priority: card.priority.as_str().to_string(),
+                        note: card.note.clone(),
+                        constraints: card.constraints.iter().map(ToString::to_string).collect(),
+                        added_at: card.added_at
+                    })
+                    .collect()
+            })
+            .collect();
+
+        Self {
+            version: EXPORT_FORMAT_VERSION,
+            exported_at,
+            user_id: user_id.to_string(),
+            scope: scope.key(),
+            series
+        }
+    }
+
+    pub fn cards_count(&self) -> usize {
+        self.series.iter().map(|series| series.cards.len()).sum()
+    }
+
+    pub fn to_json(&self) -> Result<String, serde_json::Error> {
+        serde_json::to_string_pretty(self)
+    }
+
+    /// One row per card, with a header. The version is repeated on every row since CSV has no
+    /// place for the rest of the metadata, constraints are comma separated like in `.wfilter`.
+    pub fn to_csv(&self) -> Result<String, csv::Error> {
+        let mut writer = csv::Writer::from_writer(vec![]);
+
+        for series in &self.series {
+            for card in &series.cards {
+                writer.serialize(CsvRow {
+                    version: self.version,
+                    series: series.name.clone(),
+                    card: card.name.clone(),
+                    priority: card.priority.clone(),
+                    note: card.note.clone(),
+                    constraints: card.constraints.join(", "),
+                    added_at: card.added_at.map(|added_at| added_at.to_rfc3339())
+                })?;
+            }
+        }
+
+        let bytes = writer.into_inner().map_err(|err| err.into_error())?;
+        Ok(String::from_utf8_lossy(&bytes).into_owned())
+    }
+}
@@ -0,0 +1,127 @@
+use chrono::{DateTime, Utc};
+
+use crate::model::wishlist::{CardName, WishlistEntry, WishlistedCard};
+
+/// Whether a change put cards in a wishlist or took them out.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HistoryAction {
+    Added,
+    Removed
+}
+
+/// Where a wishlist change came from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChangeSource {
+    /// `.wa` or `.wr`
+    Command,
+    /// 1️⃣/2️⃣/3️⃣ reactions on a drop ping
+    DropReaction,
+    /// ✅/❌ reactions on a SOFI series lookup
+    SofiSsl,
+    /// ❌ reaction on a SOFI gift
+    SofiSg,
+    /// `.wundo`
+    Undo,
+    /// `.wimport`
+    Import
+}
+
+/// A change to a wishlist, with only the cards that actually changed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WishlistChange {
+    pub action: HistoryAction,
+    pub entry: WishlistEntry,
+    /// How the removed cards were wishlisted, so undoing the removal restores them.
+    /// Cards without details come back with the default ones.
+    pub details: Vec<WishlistedCard>,
+    /// Cards of the entry the change left out of, or took back into, the series' wildcard card.
+    /// Undoing the removal of one only left out takes it back into the wildcard card, without listing it.
+    pub wildcard: Vec<CardName>,
+    pub source: ChangeSource,
+    /// Id of the change an undo reverted.
+    pub reverts: Option<String>
+}
+
+/// A change as stored in the history.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HistoryRecord {
+    /// Opaque id given by the store, newer changes are listed first regardless of its value.
+    pub id: String,
+    pub change: WishlistChange,
+    pub timestamp: DateTime<Utc>
+}
+
+impl HistoryAction {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            HistoryAction::Added => "added",
+            HistoryAction::Removed => "removed"
+        }
+    }
+
+    pub fn parse(action: &str) -> Option<Self> {
+        match action {
+            "added" => Some(HistoryAction::Added),
+            "removed" => Some(HistoryAction::Removed),
+            _ => None
+        }
+    }
+
+    pub fn inverse(&self) -> Self {
+        match self {
+            HistoryAction::Added => HistoryAction::Removed,
+            HistoryAction::Removed => HistoryAction::Added
+        }
+    }
+}
+
+impl ChangeSource {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ChangeSource::Command => "command",
+            ChangeSource::DropReaction => "drop",
+            ChangeSource::SofiSsl => "ssl",
+            ChangeSource::SofiSg => "sg",
+            ChangeSource::Undo => "undo",
+            ChangeSource::Import => "import"
+        }
+    }
+
+    pub fn parse(source: &str) -> Option<Self> {
+        match source {
+            "command" => Some(ChangeSource::Command),
+            "drop" => Some(ChangeSource::DropReaction),
+            "ssl" => Some(ChangeSource::SofiSsl),
+            "sg" => Some(ChangeSource::SofiSg),
+            "undo" => Some(ChangeSource::Undo),
+            "import" => Some(ChangeSource::Import),
+            _ => None
+        }
+    }
+}
+
+impl WishlistChange {
+    pub fn new(action: HistoryAction, entry: WishlistEntry, source: ChangeSource) -> Self {
+        Self { action, entry, details: vec![], wildcard: vec![], source, reverts: None }
+    }
+
+    pub fn with_details(self, details: Vec<WishlistedCard>) -> Self {
+        Self { details, ..self }
+    }
+
+    pub fn with_wildcard(self, wildcard: Vec<CardName>) -> Self {
+        Self { wildcard, ..self }
+    }
+
+    /// The change that reverts a recorded one.
+    pub fn undo(record: &HistoryRecord) -> Self {
+        Self {
+            action: record.change.action.inverse(),
+            entry: record.change.entry.clone(),
+            details: record.change.details.clone(),
+            wildcard: record.change.wildcard.clone(),
+            source: ChangeSource::Undo,
+            reverts: Some(record.id.clone())
+        }
+    }
+}
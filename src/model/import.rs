@@ -0,0 +1,236 @@
+use std::collections::HashSet;
+use std::fmt::Display;
+
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+
+use crate::model::drop::CardConstraint;
+use crate::model::export::{CsvRow, ExportedCard, ExportedSeries, EXPORT_FORMAT_VERSION};
+use crate::model::wishlist::{CardName, CardPriority, CardRef, SeriesName, WishlistEntry, WishlistOverview};
+use crate::util::parse_util::parse_constraints;
+
+/// How an import treats the wishlist it goes into.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ImportMode {
+    /// Keeps the wishlist and only adds the cards it is missing.
+    #[default]
+    Merge,
+    /// Empties the wishlist first.
+    Replace
+}
+
+#[derive(Debug)]
+pub enum ImportError {
+    Json(serde_json::Error),
+    Csv(csv::Error),
+    /// The file was written by a newer version of the bot.
+    UnsupportedVersion(u32),
+    InvalidCard { series: String, card: String, reason: String }
+}
+
+/// Cards added by a single `add_all_to_wishlist` call.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ImportBatch {
+    pub entry: WishlistEntry,
+    pub priority: CardPriority
+}
+
+/// Note and constraints of an imported card, set once the card is added.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ImportedDetails {
+    pub card: CardRef,
+    pub note: Option<String>,
+    pub constraints: Vec<CardConstraint>
+}
+
+/// Changes an import makes to a wishlist, shown as a preview before they are applied.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ImportPlan {
+    pub mode: ImportMode,
+    /// Series in the file.
+    pub series_count: usize,
+    /// Series emptied first in `ImportMode::Replace`, with their cards.
+    pub replaced: Vec<WishlistEntry>,
+    pub batches: Vec<ImportBatch>,
+    pub details: Vec<ImportedDetails>,
+    /// Cards skipped because the file repeats them or, when merging, they are already wishlisted.
+    pub duplicates: usize
+}
+
+// The part of a JSON export an import reads, the rest describes where it came from
+#[derive(Deserialize)]
+struct JsonImport {
+    version: u32,
+    series: Vec<ExportedSeries>
+}
+
+/// Reads the series of a JSON file written by `WishlistExport::to_json`.
+pub fn read_json(content: &str) -> Result<Vec<ExportedSeries>, ImportError> {
+    let import: JsonImport = serde_json::from_str(content).map_err(ImportError::Json)?;
+    check_version(import.version)?;
+
+    Ok(import.series)
+}
+
+/// Reads the series of a CSV file written by `WishlistExport::to_csv`, rows of the same series
+/// are grouped in the order the series first shows up.
+pub fn read_csv(content: &str) -> Result<Vec<ExportedSeries>, ImportError> {
+    let mut reader = csv::Reader::from_reader(content.as_bytes());
+
+    let mut series: Vec<ExportedSeries> = vec![];
+    for row in reader.deserialize::<CsvRow>() {
+        let row = row.map_err(ImportError::Csv)?;
+        check_version(row.version)?;
+
+        let added_at = row.added_at.as_deref()
+            .and_then(|added_at| DateTime::parse_from_rfc3339(added_at).ok())
+            .map(|added_at| added_at.with_timezone(&Utc));
+        let card = ExportedCard {
+            name: row.card,
+            priority: row.priority,
+            note: row.note.filter(|note| !note.is_empty()),
+            constraints: row.constraints.split(',').map(str::trim).filter(|c| !c.is_empty()).map(str::to_string).collect(),
+            added_at
+        };
+
+        let series_search = SeriesName::new(&row.series).search_term();
+        match series.iter_mut().find(|s| SeriesName::new(&s.name).search_term() == series_search) {
+            Some(s) => s.cards.push(card),
+            None => series.push(ExportedSeries { name: row.series, cards: vec![card] })
+        }
+    }
+
+    Ok(series)
+}
+
+impl ImportMode {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ImportMode::Merge => "merge",
+            ImportMode::Replace => "replace"
+        }
+    }
+
+    pub fn parse(mode: &str) -> Option<Self> {
+        match mode.trim().to_lowercase().as_str() {
+            "merge" => Some(ImportMode::Merge),
+            "replace" => Some(ImportMode::Replace),
+            _ => None
+        }
+    }
+}
+
+fn check_version(version: u32) -> Result<(), ImportError> {
+    if version > EXPORT_FORMAT_VERSION {
+        return Err(ImportError::UnsupportedVersion(version));
+    }
+
+    Ok(())
+}
+
+impl ImportPlan {
+    /// Validates the imported series and plans how they go into the current wishlist,
+    /// given as an overview with cards. Cards are batched per series and priority,
+    /// at most `batch_size` at a time.
+    pub fn new(
+        series: &[ExportedSeries],
+        current: &WishlistOverview,
+        mode: ImportMode,
+        batch_size: usize
+    ) -> Result<Self, ImportError> 
+    {
+        let wishlisted: HashSet<(String, String)> = match mode {
+            ImportMode::Merge => current.series.iter()
+                .flat_map(|s| s.cards.iter().flatten().map(|card| (s.series.search_term(), card.name.search_term())))
+                .collect(),
+            ImportMode::Replace => HashSet::new()
+        };
+
+        let replaced = match mode {
+            ImportMode::Merge => vec![],
+            ImportMode::Replace => current.series.iter()
+                .map(|s| WishlistEntry {
+                    series: s.series.clone(),
+                    cards: s.cards.iter().flatten().map(|card| card.name.clone()).collect()
+                })
+                .collect()
+        };
+
+        let mut seen = HashSet::new();
+        let mut batches: Vec<ImportBatch> = vec![];
+        let mut details = vec![];
+        let mut duplicates = 0;
+
+        for imported_series in series {
+            let series_name = SeriesName::new(imported_series.name.trim());
+
+            for card in &imported_series.cards {
+                let (priority, constraints) = validate_card(&imported_series.name, card)?;
+                let card_name = CardName::new(card.name.trim());
+
+                let key = (series_name.search_term(), card_name.search_term());
+                if wishlisted.contains(&key) || !seen.insert(key) {
+                    duplicates += 1;
+                    continue;
+                }
+
+                match batches.last_mut() {
+                    Some(batch) if batch.entry.series == series_name 
+                        && batch.priority == priority 
+                        && batch.entry.cards.len() < batch_size => batch.entry.cards.push(card_name.clone()),
+                    _ => batches.push(ImportBatch { entry: WishlistEntry { series: series_name.clone(), cards: vec![card_name.clone()] }, priority })
+                }
+
+                if card.note.is_some() || !constraints.is_empty() {
+                    details.push(ImportedDetails {
+                        card: CardRef { series: series_name.clone(), card: card_name },
+                        note: card.note.clone(),
+                        constraints
+                    });
+                }
+            }
+        }
+
+        Ok(Self { mode, series_count: series.len(), replaced, batches, details, duplicates })
+    }
+
+    pub fn cards_to_add(&self) -> usize {
+        self.batches.iter().map(|batch| batch.entry.cards.len()).sum()
+    }
+
+    pub fn cards_to_remove(&self) -> usize {
+        self.replaced.iter().map(|entry| entry.cards.len()).sum()
+    }
+}
+
+fn validate_card(series: &str, card: &ExportedCard) -> Result<(CardPriority, Vec<CardConstraint>), ImportError> {
+    let invalid = |reason: &str| ImportError::InvalidCard { series: series.to_string(), card: card.name.clone(), reason: reason.to_string() };
+
+    if series.trim().is_empty() {
+        return Err(invalid("the series name is blank"));
+    }
+    if card.name.trim().is_empty() {
+        return Err(invalid("the card name is blank"));
+    }
+
+    let priority = CardPriority::parse(&card.priority).ok_or_else(|| invalid("unknown priority"))?;
+    let constraints = match card.constraints.is_empty() {
+        true => vec![],
+        false => parse_constraints(&card.constraints.join(",")).ok_or_else(|| invalid("invalid constraints"))?
+    };
+
+    Ok((priority, constraints))
+}
+
+impl Display for ImportError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ImportError::Json(err) => write!(f, "invalid JSON file: {err}"),
+            ImportError::Csv(err) => write!(f, "invalid CSV file: {err}"),
+            ImportError::UnsupportedVersion(version) => write!(f, "the file uses format version {version}, this bot only reads up to {EXPORT_FORMAT_VERSION}"),
+            ImportError::InvalidCard { series, card, reason } => write!(f, "`{card}` from `{series}`: {reason}")
+        }
+    }
+}
+
+impl std::error::Error for ImportError {}
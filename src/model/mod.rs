@@ -0,0 +1,7 @@
+pub mod catalog;
+pub mod compare;
+pub mod drop;
+pub mod export;
+pub mod history;
+pub mod import;
+pub mod wishlist;
\ No newline at end of file
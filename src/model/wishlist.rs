@@ -0,0 +1,351 @@
+use std::fmt::Display;
+
+use chrono::{DateTime, Utc};
+use serenity::all::GuildId;
+
+use crate::model::drop::CardConstraint;
+use crate::util::normalize::search_key;
+
+/// Name of a series, as typed by a user or shown by another bot.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct SeriesName(String);
+
+/// Name of a card, only meaningful together with its series (see `CardRef`).
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct CardName(String);
+
+/// A card from a specific series.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct CardRef {
+    pub series: SeriesName,
+    pub card: CardName
+}
+
+/// Cards from a single series, as added to or removed from a wishlist.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WishlistEntry {
+    pub series: SeriesName,
+    pub cards: Vec<CardName>
+}
+
+/// How much a user wants a wishlisted card, sorting puts higher priorities first.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Default)]
+pub enum CardPriority {
+    High,
+    #[default]
+    Normal,
+    Low
+}
+
+/// A card as stored in a user's wishlist.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WishlistedCard {
+    pub name: CardName,
+    pub priority: CardPriority,
+    pub note: Option<String>,
+    /// Drops only ping for the card when they meet all of them.
+    pub constraints: Vec<CardConstraint>,
+    /// Cards of the series left out by a wildcard card, see `CardName::is_wildcard`.
+    pub excluded: Vec<CardName>,
+    /// When the card was last added, unknown for cards added before it was stored.
+    pub added_at: Option<DateTime<Utc>>
+}
+
+/// Outcome of adding cards to a wishlist, repeated cards are only listed once.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct AddedCards {
+    pub added: Vec<CardName>,
+    /// Named as in the wishlist.
+    pub already_wishlisted: Vec<CardName>,
+    /// Cards the series' wildcard card no longer leaves out, named as it did.
+    pub included: Vec<CardName>
+}
+
+/// Outcome of removing cards from a wishlist, repeated cards are only listed once.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct RemovedCards {
+    /// Named as in the wishlist, in its order.
+    pub removed: Vec<CardName>,
+    pub not_found: Vec<CardName>,
+    /// Cards newly left out of the series' wildcard card, also listed in `removed`.
+    pub excluded: Vec<CardName>,
+    /// The removed cards as they were wishlisted, the ones that were only excluded aside.
+    pub details: Vec<WishlistedCard>,
+    /// How many cards are left in the series.
+    pub left: i32
+}
+
+/// Per-account setting choosing whether other users can list, export or compare the user's wishlists.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum WishlistPrivacy {
+    #[default]
+    Public,
+    Private
+}
+
+/// Order of the series in a `WishlistOverview`, ties keep the wishlist order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum SeriesOrder {
+    /// The order the series were added in.
+    #[default]
+    Added,
+    /// Alphabetical, by search term.
+    Name,
+    /// Series with the most cards first.
+    CardCount
+}
+
+/// Which part of a wishlist to summarize, see `WishlistDB::get_wishlist_overview`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct OverviewQuery {
+    pub order: SeriesOrder,
+    /// Series skipped, in `order`.
+    pub skip: usize,
+    /// Most series returned, all of them when `None`.
+    pub limit: Option<usize>,
+    /// Also return the cards of each series.
+    pub with_cards: bool
+}
+
+/// A wishlisted series as listed in a `WishlistOverview`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SeriesOverview {
+    pub series: SeriesName,
+    pub cards_count: i32,
+    /// Whether the series is wishlisted whole, through a wildcard card.
+    pub whole: bool,
+    /// In wishlist order, only set when the query asks for cards.
+    pub cards: Option<Vec<WishlistedCard>>
+}
+
+/// A page of a user's wishlist along with the totals of the whole wishlist.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct WishlistOverview {
+    pub series: Vec<SeriesOverview>,
+    pub total_series: usize,
+    pub total_cards: usize
+}
+
+/// Which wishlist a user is working with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum WishlistScope {
+    /// Shared by every guild in `ScopeMode::Global`, and used in DMs.
+    Global,
+    /// Only seen in the guild it was built in.
+    Guild(GuildId)
+}
+
+/// Per-guild setting choosing which wishlists the guild's commands and drops use.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ScopeMode {
+    #[default]
+    Global,
+    Isolated
+}
+
+impl WishlistScope {
+    /// Scope used by a guild (or a DM, without one) in the given mode.
+    pub fn resolve(guild_id: Option<GuildId>, mode: ScopeMode) -> Self {
+        match (guild_id, mode) {
+            (Some(guild_id), ScopeMode::Isolated) => WishlistScope::Guild(guild_id),
+            _ => WishlistScope::Global
+        }
+    }
+
+    /// Value the scope is stored as, existing data without a scope belongs to `"global"`.
+    pub fn key(&self) -> String {
+        match self {
+            WishlistScope::Global => GLOBAL_SCOPE_KEY.to_string(),
+            WishlistScope::Guild(guild_id) => guild_id.to_string()
+        }
+    }
+}
+
+pub const GLOBAL_SCOPE_KEY: &str = "global";
+
+impl ScopeMode {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ScopeMode::Global => "global",
+            ScopeMode::Isolated => "isolated"
+        }
+    }
+
+    pub fn parse(mode: &str) -> Option<Self> {
+        match mode.trim().to_lowercase().as_str() {
+            "global" => Some(ScopeMode::Global),
+            "isolated" => Some(ScopeMode::Isolated),
+            _ => None
+        }
+    }
+}
+
+impl CardPriority {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CardPriority::High => "high",
+            CardPriority::Normal => "normal",
+            CardPriority::Low => "low"
+        }
+    }
+
+    pub fn parse(priority: &str) -> Option<Self> {
+        match priority.trim().to_lowercase().as_str() {
+            "high" => Some(CardPriority::High),
+            "normal" => Some(CardPriority::Normal),
+            "low" => Some(CardPriority::Low),
+            _ => None
+        }
+    }
+}
+
+impl WishlistPrivacy {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            WishlistPrivacy::Public => "public",
+            WishlistPrivacy::Private => "private"
+        }
+    }
+
+    pub fn parse(privacy: &str) -> Option<Self> {
+        match privacy.trim().to_lowercase().as_str() {
+            "public" => Some(WishlistPrivacy::Public),
+            "private" => Some(WishlistPrivacy::Private),
+            _ => None
+        }
+    }
+}
+
+impl SeriesOrder {
+    pub fn parse(order: &str) -> Option<Self> {
+        match order.trim().to_lowercase().as_str() {
+            "added" => Some(SeriesOrder::Added),
+            "name" => Some(SeriesOrder::Name),
+            "cards" => Some(SeriesOrder::CardCount),
+            _ => None
+        }
+    }
+}
+
+impl WishlistedCard {
+    pub fn new(name: CardName, priority: CardPriority) -> Self {
+        Self { name, priority, note: None, constraints: vec![], excluded: vec![], added_at: None }
+    }
+
+    pub fn with_details(self, note: Option<String>, constraints: Vec<CardConstraint>) -> Self {
+        Self { note, constraints, ..self }
+    }
+
+    pub fn with_excluded(self, excluded: Vec<CardName>) -> Self {
+        Self { excluded, ..self }
+    }
+
+    pub fn with_added_at(self, added_at: Option<DateTime<Utc>>) -> Self {
+        Self { added_at, ..self }
+    }
+
+    /// Whether a drop of the card from the same series pings for this one, being it or,
+    /// for a wildcard card, any card it does not exclude.
+    pub fn covers(&self, card: &CardName) -> bool {
+        let card_search = card.search_term();
+
+        match self.name.is_wildcard() {
+            true => !self.excluded.iter().any(|excluded| excluded.search_term() == card_search),
+            false => self.name.search_term() == card_search
+        }
+    }
+}
+
+impl SeriesName {
+    pub fn new(name: impl AsRef<str>) -> Self {
+        Self(name.as_ref().trim().to_string())
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// Key series are matched by.
+    pub fn search_term(&self) -> String {
+        search_key(&self.0)
+    }
+}
+
+impl CardName {
+    /// Name of the card standing for every card of its series.
+    pub const WILDCARD: &'static str = "*";
+
+    pub fn new(name: impl AsRef<str>) -> Self {
+        Self(name.as_ref().trim().to_string())
+    }
+
+    pub fn wildcard() -> Self {
+        Self(Self::WILDCARD.to_string())
+    }
+
+    /// Whether the card stands for every card of its series, even the ones it was never told about.
+    /// It is stored like any other card, and can leave some out of it (see `WishlistedCard::excluded`).
+    pub fn is_wildcard(&self) -> bool {
+        self.0 == Self::WILDCARD
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// Key cards are matched by, within their series.
+    pub fn search_term(&self) -> String {
+        search_key(&self.0)
+    }
+}
+
+impl CardRef {
+    pub fn new(series: impl AsRef<str>, card: impl AsRef<str>) -> Self {
+        Self { series: SeriesName::new(series), card: CardName::new(card) }
+    }
+}
+
+impl WishlistEntry {
+    pub fn new<T: AsRef<str>>(series: impl AsRef<str>, cards: impl IntoIterator<Item = T>) -> Self {
+        Self {
+            series: SeriesName::new(series),
+            cards: cards.into_iter().map(CardName::new).collect()
+        }
+    }
+}
+
+impl From<CardRef> for WishlistEntry {
+    fn from(card: CardRef) -> Self {
+        Self { series: card.series, cards: vec![card.card] }
+    }
+}
+
+impl Display for SeriesName {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl Display for CardName {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl Display for CardRef {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} || {}", self.series, self.card)
+    }
+}
+
+impl PartialEq<&str> for SeriesName {
+    fn eq(&self, other: &&str) -> bool {
+        self.0 == *other
+    }
+}
+
+impl PartialEq<&str> for CardName {
+    fn eq(&self, other: &&str) -> bool {
+        self.0 == *other
+    }
+}
@@ -0,0 +1,90 @@
+#[cfg(test)]
+mod wishlist_privacy {
+    use std::sync::Arc;
+
+    use serenity::all::UserId;
+
+    use crate::bot::Data;
+    use crate::commands::can_read_wishlist;
+    use crate::components::logger::StdoutLogger;
+    use crate::components::mocks::mock_wishlist_db::MockWishlistDB;
+    use crate::model::wishlist::WishlistPrivacy;
+
+    fn data() -> Data {
+        Data { wishlist_db: Box::new(MockWishlistDB::new()), logger: Arc::new(StdoutLogger) }
+    }
+
+    #[tokio::test]
+    async fn public_wishlists_are_read_by_anyone() {
+        let data = data();
+
+        assert!(can_read_wishlist(&data, UserId::new(2), UserId::new(1)).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn private_wishlists_are_only_read_by_their_owner() {
+        let data = data();
+        data.wishlist_db.set_wishlist_privacy(UserId::new(1), WishlistPrivacy::Private).await.unwrap();
+
+        assert!(!can_read_wishlist(&data, UserId::new(2), UserId::new(1)).await.unwrap());
+        assert!(can_read_wishlist(&data, UserId::new(1), UserId::new(1)).await.unwrap());
+        assert!(can_read_wishlist(&data, UserId::new(1), UserId::new(2)).await.unwrap());
+    }
+}
+
+#[cfg(test)]
+mod wishlist_undo {
+    use std::sync::Arc;
+
+    use serenity::all::UserId;
+
+    use crate::bot::Data;
+    use crate::commands::{add_entry, remove_entry, undo_last_change};
+    use crate::components::logger::StdoutLogger;
+    use crate::components::mocks::mock_wishlist_db::MockWishlistDB;
+    use crate::model::history::ChangeSource;
+    use crate::model::wishlist::{CardName, SeriesName, WishlistEntry, WishlistScope, WishlistedCard};
+
+    const GLOBAL: WishlistScope = WishlistScope::Global;
+
+    fn data() -> Data {
+        Data { wishlist_db: Box::new(MockWishlistDB::new()), logger: Arc::new(StdoutLogger) }
+    }
+
+    fn entry(cards: &[&str]) -> WishlistEntry {
+        WishlistEntry::new("One Piece", cards)
+    }
+
+    async fn wishlisted_cards(data: &Data) -> Vec<WishlistedCard> {
+        data.wishlist_db.get_user_wishlisted_cards(GLOBAL, UserId::new(1), &SeriesName::new("One Piece")).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn undoing_a_wildcard_exclusion_takes_the_card_back_into_it() {
+        let data = data();
+        add_entry(&data, GLOBAL, UserId::new(1), &entry(&["*"]), None, ChangeSource::Command).await.unwrap();
+        remove_entry(&data, GLOBAL, UserId::new(1), &entry(&["Nami"]), ChangeSource::Command).await.unwrap();
+        assert_eq!(wishlisted_cards(&data).await[0].excluded, vec![CardName::new("Nami")]);
+
+        let (undo, _) = undo_last_change(&data, GLOBAL, UserId::new(1)).await.unwrap().unwrap();
+        assert_eq!(undo.entry.cards, vec![CardName::new("Nami")]);
+
+        let cards = wishlisted_cards(&data).await;
+        assert_eq!(cards.iter().map(|card| card.name.clone()).collect::<Vec<CardName>>(), vec![CardName::wildcard()]);
+        assert!(cards[0].excluded.is_empty());
+    }
+
+    #[tokio::test]
+    async fn undoing_an_add_into_the_wildcard_leaves_the_card_out_again() {
+        let data = data();
+        add_entry(&data, GLOBAL, UserId::new(1), &entry(&["*"]), None, ChangeSource::Command).await.unwrap();
+        remove_entry(&data, GLOBAL, UserId::new(1), &entry(&["Nami"]), ChangeSource::Command).await.unwrap();
+        add_entry(&data, GLOBAL, UserId::new(1), &entry(&["Nami", "Zoro"]), None, ChangeSource::Command).await.unwrap();
+
+        undo_last_change(&data, GLOBAL, UserId::new(1)).await.unwrap().unwrap();
+
+        let cards = wishlisted_cards(&data).await;
+        assert_eq!(cards.iter().map(|card| card.name.clone()).collect::<Vec<CardName>>(), vec![CardName::wildcard()]);
+        assert_eq!(cards[0].excluded, vec![CardName::new("Nami")]);
+    }
+}
@@ -0,0 +1,108 @@
+#[cfg(test)]
+mod cache {
+    use std::{slice, sync::Arc, time::Duration};
+
+    use serenity::all::UserId;
+
+    use crate::components::cached_wishlist_db::{CacheStats, CachedWishlistDB, DEFAULT_CACHE_CAPACITY, DEFAULT_CACHE_TTL};
+    use crate::components::logger::VoidLogger;
+    use crate::components::mocks::mock_wishlist_db::MockWishlistDB;
+    use crate::model::drop::{DropMatch, DroppedCard};
+    use crate::model::wishlist::{CardName, CardPriority, CardRef, OverviewQuery, SeriesName, WishlistEntry, WishlistScope};
+    use crate::traits::wishlist_db::WishlistDB;
+
+    const SCOPE: WishlistScope = WishlistScope::Global;
+
+    fn cached(capacity: usize, ttl: Duration) -> CachedWishlistDB<MockWishlistDB, VoidLogger> {
+        CachedWishlistDB::new(MockWishlistDB::new(), Arc::new(VoidLogger), capacity, ttl)
+    }
+
+    fn dropped(series: &str, card: &str) -> DroppedCard {
+        DroppedCard::new(CardRef::new(series, card))
+    }
+
+    #[tokio::test]
+    async fn repeated_reads_are_hits() {
+        let db = cached(DEFAULT_CACHE_CAPACITY, DEFAULT_CACHE_TTL);
+        let user_id = UserId::new(1);
+        db.add_all_to_wishlist(SCOPE, user_id, &WishlistEntry::new("One Piece", ["Zoro", "Luffy"]), CardPriority::Normal, false).await.unwrap();
+
+        let series = SeriesName::new("One Piece");
+        db.get_user_wishlisted_cards(SCOPE, user_id, &series).await.unwrap();
+        assert_eq!(db.get_user_wishlisted_cards(SCOPE, user_id, &series).await.unwrap().len(), 2);
+        assert!(db.user_has_card(SCOPE, user_id, &CardRef::new("one piece", "ZORO")).await.unwrap());
+
+        assert_eq!(db.stats(), CacheStats { hits: 2, misses: 1 });
+    }
+
+    #[tokio::test]
+    async fn drop_lookups_only_fetch_missed_cards() {
+        let db = cached(DEFAULT_CACHE_CAPACITY, DEFAULT_CACHE_TTL);
+        let user_id = UserId::new(1);
+        db.add_all_to_wishlist(SCOPE, user_id, &WishlistEntry::new("One Piece", ["Zoro"]), CardPriority::High, false).await.unwrap();
+
+        db.get_users_with_series_card(SCOPE, &[dropped("One Piece", "Zoro")]).await.unwrap();
+        let users = db.get_users_with_series_card(SCOPE, &[dropped("Bleach", "Ichigo"), dropped("One Piece", "Zoro")]).await.unwrap();
+
+        assert_eq!(users, vec![(CardRef::new("One Piece", "Zoro"), vec![DropMatch::exact(user_id, CardPriority::High)])]);
+        assert_eq!(db.stats(), CacheStats { hits: 1, misses: 2 });
+    }
+
+    #[tokio::test]
+    async fn mutations_invalidate_the_scope() {
+        let db = cached(DEFAULT_CACHE_CAPACITY, DEFAULT_CACHE_TTL);
+        let series = SeriesName::new("One Piece");
+        db.add_all_to_wishlist(SCOPE, UserId::new(1), &WishlistEntry::new("One Piece", ["Zoro"]), CardPriority::Normal, false).await.unwrap();
+
+        let users = db.get_users_with_series(SCOPE, slice::from_ref(&series)).await.unwrap();
+        assert_eq!(users, vec![(series.clone(), vec![(UserId::new(1), 1)])]);
+        assert_eq!(db.get_user_wishlisted_cards(SCOPE, UserId::new(1), &series).await.unwrap().len(), 1);
+
+        db.add_all_to_wishlist(SCOPE, UserId::new(2), &WishlistEntry::new("One Piece", ["Luffy"]), CardPriority::Normal, false).await.unwrap();
+        db.add_all_to_wishlist(SCOPE, UserId::new(1), &WishlistEntry::new("One Piece", ["Nami"]), CardPriority::Normal, false).await.unwrap();
+
+        let mut users = db.get_users_with_series(SCOPE, slice::from_ref(&series)).await.unwrap().remove(0).1;
+        users.sort();
+        assert_eq!(users, vec![(UserId::new(1), 2), (UserId::new(2), 1)]);
+        assert_eq!(db.get_user_wishlisted_cards(SCOPE, UserId::new(1), &series).await.unwrap().len(), 2);
+        assert_eq!(db.stats().hits, 0);
+    }
+
+    #[tokio::test]
+    async fn aliases_invalidate_everything() {
+        let db = cached(DEFAULT_CACHE_CAPACITY, DEFAULT_CACHE_TTL);
+        let user_id = UserId::new(1);
+        db.add_all_to_wishlist(SCOPE, user_id, &WishlistEntry::new("OP", ["Zoro"]), CardPriority::Normal, false).await.unwrap();
+        db.add_all_to_wishlist(SCOPE, user_id, &WishlistEntry::new("One Piece", ["Luffy"]), CardPriority::Normal, false).await.unwrap();
+        assert_eq!(db.get_wishlist_overview(SCOPE, user_id, &OverviewQuery::default()).await.unwrap().total_series, 2);
+
+        db.add_series_alias(&SeriesName::new("OP"), &SeriesName::new("One Piece")).await.unwrap();
+
+        assert_eq!(db.get_wishlist_overview(SCOPE, user_id, &OverviewQuery::default()).await.unwrap().total_series, 1);
+        let cards = db.get_user_wishlisted_cards(SCOPE, user_id, &SeriesName::new("OP")).await.unwrap();
+        assert_eq!(cards.into_iter().map(|card| card.name).collect::<Vec<_>>(), vec![CardName::new("Luffy"), CardName::new("Zoro")]);
+    }
+
+    #[tokio::test]
+    async fn expired_entries_are_misses() {
+        let db = cached(DEFAULT_CACHE_CAPACITY, Duration::ZERO);
+        let user_id = UserId::new(1);
+
+        db.get_wishlist_overview(SCOPE, user_id, &OverviewQuery::default()).await.unwrap();
+        db.get_wishlist_overview(SCOPE, user_id, &OverviewQuery::default()).await.unwrap();
+
+        assert_eq!(db.stats(), CacheStats { hits: 0, misses: 2 });
+    }
+
+    #[tokio::test]
+    async fn full_cache_evicts_the_oldest_entry() {
+        let db = cached(1, DEFAULT_CACHE_TTL);
+
+        db.get_wishlist_overview(SCOPE, UserId::new(1), &OverviewQuery::default()).await.unwrap();
+        db.get_wishlist_overview(SCOPE, UserId::new(2), &OverviewQuery::default()).await.unwrap();
+        db.get_wishlist_overview(SCOPE, UserId::new(2), &OverviewQuery::default()).await.unwrap();
+        db.get_wishlist_overview(SCOPE, UserId::new(1), &OverviewQuery::default()).await.unwrap();
+
+        assert_eq!(db.stats(), CacheStats { hits: 1, misses: 3 });
+    }
+}
@@ -0,0 +1,3 @@
+mod cached_wishlist_db;
+mod mongo_wishlist_db;
+mod sqlite_wishlist_db;
@@ -0,0 +1,157 @@
+#[cfg(test)]
+mod card_index {
+    use std::{env, sync::Arc};
+
+    use mongodb::{bson::{doc, Document}, Client};
+    use rand::Rng;
+    use serenity::all::UserId;
+
+    use crate::components::logger::VoidLogger;
+    use crate::components::mongo_wishlist_db::{self, CardIndexReport};
+    use crate::model::drop::{DropMatch, DroppedCard};
+    use crate::model::wishlist::{CardPriority, CardRef, WishlistEntry, WishlistScope};
+    use crate::traits::wishlist_db::WishlistDB;
+
+    const MONGODB_TEST_URL_KEY: &str = "MONGODB_TEST_URL";
+
+    #[tokio::test]
+    #[ignore = "needs a MongoDB server at `MONGODB_TEST_URL`"]
+    async fn drift_is_detected_and_rebuilt() {
+        let url = env::var(MONGODB_TEST_URL_KEY)
+            .unwrap_or_else(|_| panic!("`{MONGODB_TEST_URL_KEY}` must point to a MongoDB server"));
+        let database_name = format!("better_wishlist_test_{}", rand::thread_rng().gen::<u32>());
+
+        let db = mongo_wishlist_db::init_db_with_database(Arc::new(VoidLogger), url.clone(), database_name.clone()).await.unwrap();
+        let user_id = UserId::new(1);
+        db.add_all_to_wishlist(WishlistScope::Global, user_id, &WishlistEntry::new("One Piece", ["Zoro", "Luffy"]), CardPriority::High, false).await.unwrap();
+        assert!(db.check_card_index().await.unwrap().is_consistent());
+
+        let card_index = Client::with_uri_str(url).await.unwrap()
+            .database(&database_name)
+            .collection("card_index");
+        card_index.delete_one(doc!{"card_search": "zoro"}, None).await.unwrap();
+        card_index.insert_one(doc!{"scope": "global", "user_id": "2", "series_search": "bleach", "card_search": "ichigo", "priority": "normal", "constraints": []}, None).await.unwrap();
+        assert_eq!(db.check_card_index().await.unwrap(), CardIndexReport { missing: 1, stale: 1 });
+
+        assert_eq!(db.rebuild_card_index().await.unwrap(), 2);
+        assert!(db.check_card_index().await.unwrap().is_consistent());
+        // the rebuilt collection replaced the index along with its indexes
+        assert_eq!(card_index.list_index_names().await.unwrap().len(), 3);
+
+        let users = db.get_users_with_series_card(WishlistScope::Global, &[DroppedCard::new(CardRef::new("One Piece", "Zoro"))]).await.unwrap();
+        assert_eq!(users, vec![(CardRef::new("One Piece", "Zoro"), vec![DropMatch::exact(user_id, CardPriority::High)])]);
+
+        db.drop_database().await.unwrap();
+    }
+
+    #[tokio::test]
+    #[ignore = "needs a MongoDB server at `MONGODB_TEST_URL`"]
+    async fn startup_only_checks_a_stale_index() {
+        let url = env::var(MONGODB_TEST_URL_KEY)
+            .unwrap_or_else(|_| panic!("`{MONGODB_TEST_URL_KEY}` must point to a MongoDB server"));
+        let database_name = format!("better_wishlist_test_{}", rand::thread_rng().gen::<u32>());
+
+        let db = mongo_wishlist_db::init_db_with_database(Arc::new(VoidLogger), url.clone(), database_name.clone()).await.unwrap();
+        db.add_all_to_wishlist(WishlistScope::Global, UserId::new(1), &WishlistEntry::new("One Piece", ["Zoro"]), CardPriority::Normal, false).await.unwrap();
+
+        let database = Client::with_uri_str(url.clone()).await.unwrap().database(&database_name);
+        database.collection::<Document>("card_index").delete_many(doc!{}, None).await.unwrap();
+
+        let db = mongo_wishlist_db::init_db_with_database(Arc::new(VoidLogger), url.clone(), database_name.clone()).await.unwrap();
+        assert_eq!(db.check_card_index().await.unwrap(), CardIndexReport { missing: 1, stale: 0 });
+
+        // as left by a failed index write
+        database.collection::<Document>("migrations").update_one(doc!{"name": "card_index"}, doc!{"$set": {"stale": true}}, None).await.unwrap();
+
+        let db = mongo_wishlist_db::init_db_with_database(Arc::new(VoidLogger), url, database_name).await.unwrap();
+        assert!(db.check_card_index().await.unwrap().is_consistent());
+
+        db.drop_database().await.unwrap();
+    }
+}
+
+#[cfg(test)]
+mod wishlists {
+    use std::{env, sync::Arc};
+
+    use mongodb::{bson::{doc, Document}, Client, Collection};
+    use rand::Rng;
+    use serenity::all::UserId;
+
+    use crate::components::logger::VoidLogger;
+    use crate::components::mongo_wishlist_db;
+    use crate::model::wishlist::{CardPriority, SeriesName, WishlistEntry, WishlistScope};
+    use crate::traits::wishlist_db::WishlistDB;
+
+    const MONGODB_TEST_URL_KEY: &str = "MONGODB_TEST_URL";
+
+    fn test_database() -> (String, String) {
+        let url = env::var(MONGODB_TEST_URL_KEY)
+            .unwrap_or_else(|_| panic!("`{MONGODB_TEST_URL_KEY}` must point to a MongoDB server"));
+        (url, format!("better_wishlist_test_{}", rand::thread_rng().gen::<u32>()))
+    }
+
+    async fn wishlist_collection(url: &str, database_name: &str) -> Collection<Document> {
+        Client::with_uri_str(url).await.unwrap()
+            .database(database_name)
+            .collection("wishlist")
+    }
+
+    #[tokio::test]
+    #[ignore = "needs a MongoDB server at `MONGODB_TEST_URL`"]
+    async fn concurrent_first_adds_share_one_wishlist() {
+        let (url, database_name) = test_database();
+        let db = mongo_wishlist_db::init_db_with_database(Arc::new(VoidLogger), url.clone(), database_name.clone()).await.unwrap();
+        let user_id = UserId::new(1);
+
+        let add = |series: &'static str, card: &'static str| {
+            let entry = WishlistEntry::new(series, [card]);
+            let db = &db;
+            async move { db.add_all_to_wishlist(WishlistScope::Global, user_id, &entry, CardPriority::Normal, false).await }
+        };
+        let (luffy, zoro, ichigo, naruto) = tokio::join!(
+            add("One Piece", "Luffy"), add("One Piece", "Zoro"), add("Bleach", "Ichigo"), add("Naruto", "Naruto")
+        );
+        for res in [luffy, zoro, ichigo, naruto] {
+            assert_eq!(res.unwrap().added.len(), 1);
+        }
+
+        let wishlists = wishlist_collection(&url, &database_name).await;
+        assert_eq!(wishlists.count_documents(doc! {"id": "1", "scope": "global"}, None).await.unwrap(), 1);
+        let cards = db.get_user_wishlisted_cards(WishlistScope::Global, user_id, &SeriesName::new("One Piece")).await.unwrap();
+        assert_eq!(cards.len(), 2);
+
+        db.drop_database().await.unwrap();
+    }
+
+    #[tokio::test]
+    #[ignore = "needs a MongoDB server at `MONGODB_TEST_URL`"]
+    async fn duplicate_wishlists_are_merged() {
+        let (url, database_name) = test_database();
+
+        let wishlists = wishlist_collection(&url, &database_name).await;
+        wishlists.insert_many([
+            doc! {"id": "1", "scope": "global", "series": [
+                {"name": "One Piece", "search": "one piece", "cards": [{"name": "Luffy", "search": "luffy", "priority": "high"}]}
+            ]},
+            doc! {"id": "1", "scope": "global", "series": [
+                {"name": "One Piece", "search": "one piece", "cards": [
+                    {"name": "luffy", "search": "luffy", "priority": "low"}, {"name": "Zoro", "search": "zoro", "priority": "normal"}
+                ]},
+                {"name": "Bleach", "search": "bleach", "cards": [{"name": "Ichigo", "search": "ichigo", "priority": "normal"}]}
+            ]},
+        ], None).await.unwrap();
+        wishlists.create_index(mongodb::IndexModel::builder().keys(doc! {"id": 1, "scope": 1}).build(), None).await.unwrap();
+
+        let db = mongo_wishlist_db::init_db_with_database(Arc::new(VoidLogger), url, database_name).await.unwrap();
+        let user_id = UserId::new(1);
+
+        assert_eq!(wishlists.count_documents(doc! {"id": "1"}, None).await.unwrap(), 1);
+        let cards = db.get_user_wishlisted_cards(WishlistScope::Global, user_id, &SeriesName::new("One Piece")).await.unwrap();
+        let cards: Vec<(&str, CardPriority)> = cards.iter().map(|card| (card.name.as_str(), card.priority)).collect();
+        assert_eq!(cards, vec![("Luffy", CardPriority::High), ("Zoro", CardPriority::Normal)]);
+        assert_eq!(db.get_user_wishlisted_cards(WishlistScope::Global, user_id, &SeriesName::new("Bleach")).await.unwrap().len(), 1);
+
+        db.drop_database().await.unwrap();
+    }
+}
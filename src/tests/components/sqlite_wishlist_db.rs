@@ -0,0 +1,118 @@
+#[cfg(test)]
+mod migrations {
+    use std::{env, fs, sync::Arc};
+
+    use rand::Rng;
+    use rusqlite::Connection;
+    use serenity::all::UserId;
+
+    use crate::components::logger::VoidLogger;
+    use crate::components::sqlite_wishlist_db::{self, MIGRATIONS};
+    use crate::model::wishlist::{CardName, CardPriority, CardRef, OverviewQuery, SeriesName, WishlistScope, WishlistedCard};
+    use crate::traits::wishlist_db::WishlistDB;
+
+    async fn series_names(db: &impl WishlistDB, user_id: UserId) -> Vec<SeriesName> {
+        let overview = db.get_wishlist_overview(WishlistScope::Global, user_id, &OverviewQuery::default()).await.unwrap();
+        overview.series.into_iter().map(|s| s.series).collect()
+    }
+
+    #[tokio::test]
+    async fn unscoped_wishlists_become_global() {
+        let path = env::temp_dir().join(format!("better_wishlist_test_{}.db", rand::thread_rng().gen::<u32>()));
+        let path = path.to_str().unwrap();
+
+        {
+            let connection = Connection::open(path).unwrap();
+            connection.execute_batch(MIGRATIONS[0]).unwrap();
+            connection.pragma_update(None, "user_version", 1).unwrap();
+            connection.execute_batch(
+                "INSERT INTO wishlist_series VALUES ('1', 'One Piece', 'one piece'), ('1', 'Bleach', 'bleach');
+                 INSERT INTO wishlist_card VALUES ('1', 'one piece', 'Zoro', 'zoro'), ('1', 'one piece', 'Luffy', 'luffy'), ('1', 'bleach', 'Ichigo', 'ichigo');"
+            ).unwrap();
+        }
+
+        let db = sqlite_wishlist_db::init_db(Arc::new(VoidLogger), path).await.unwrap();
+        let user_id = UserId::new(1);
+
+        assert_eq!(series_names(&db, user_id).await, vec!["One Piece", "Bleach"]);
+        let cards = db.get_user_wishlisted_cards(WishlistScope::Global, user_id, &SeriesName::new("One Piece")).await.unwrap();
+        assert_eq!(cards, vec![
+            WishlistedCard::new(CardName::new("Zoro"), CardPriority::Normal),
+            WishlistedCard::new(CardName::new("Luffy"), CardPriority::Normal),
+        ]);
+        assert!(db.user_has_card(WishlistScope::Global, user_id, &CardRef::new("Bleach", "Ichigo")).await.unwrap());
+
+        drop(db);
+        fs::remove_file(path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn search_terms_are_normalized() {
+        let path = env::temp_dir().join(format!("better_wishlist_test_{}.db", rand::thread_rng().gen::<u32>()));
+        let path = path.to_str().unwrap();
+
+        {
+            let connection = Connection::open(path).unwrap();
+            connection.execute_batch(&MIGRATIONS[..5].join("\n")).unwrap();
+            connection.pragma_update(None, "user_version", 5).unwrap();
+            connection.execute_batch(
+                "INSERT INTO wishlist_series (scope, user_id, name, search) VALUES
+                    ('global', '1', 'Pokémon', 'pokémon'), ('global', '1', 'Pokemon', 'pokemon'), ('global', '1', 'One Piece', 'one piece');
+                 INSERT INTO wishlist_card (scope, user_id, series_search, name, search, priority) VALUES
+                    ('global', '1', 'pokémon', 'Pikachu', 'pikachu', 'high'),
+                    ('global', '1', 'pokemon', 'pikachu', 'pikachu', 'normal'),
+                    ('global', '1', 'pokemon', 'Eevee', 'eevee', 'normal'),
+                    ('global', '1', 'one piece', 'Monkey D. Luffy (Gear 4)', 'monkey d. luffy ', 'normal');"
+            ).unwrap();
+        }
+
+        let db = sqlite_wishlist_db::init_db(Arc::new(VoidLogger), path).await.unwrap();
+        let user_id = UserId::new(1);
+
+        assert_eq!(series_names(&db, user_id).await, vec!["Pokémon", "One Piece"]);
+        let cards = db.get_user_wishlisted_cards(WishlistScope::Global, user_id, &SeriesName::new("pokemon")).await.unwrap();
+        assert_eq!(cards, vec![
+            WishlistedCard::new(CardName::new("Pikachu"), CardPriority::High),
+            WishlistedCard::new(CardName::new("Eevee"), CardPriority::Normal),
+        ]);
+        assert!(db.user_has_card(WishlistScope::Global, user_id, &CardRef::new("One Piece", "monkey d luffy gear 4")).await.unwrap());
+        assert!(!db.user_has_card(WishlistScope::Global, user_id, &CardRef::new("One Piece", "Monkey D. Luffy (Gear 5)")).await.unwrap());
+
+        drop(db);
+        fs::remove_file(path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn added_times_come_from_the_history() {
+        let path = env::temp_dir().join(format!("better_wishlist_test_{}.db", rand::thread_rng().gen::<u32>()));
+        let path = path.to_str().unwrap();
+
+        {
+            let connection = Connection::open(path).unwrap();
+            connection.execute_batch(&MIGRATIONS[..14].join("\n")).unwrap();
+            connection.pragma_update(None, "user_version", 14).unwrap();
+            connection.execute_batch(
+                "INSERT INTO wishlist_series (scope, user_id, name, search) VALUES ('global', '1', 'One Piece', 'one piece');
+                 INSERT INTO wishlist_card (scope, user_id, series_search, name, search, priority) VALUES
+                    ('global', '1', 'one piece', 'Zoro', 'zoro', 'normal'),
+                    ('global', '1', 'one piece', 'Luffy', 'luffy', 'normal'),
+                    ('global', '1', 'one piece', 'Nami', 'nami', 'normal');
+                 INSERT INTO wishlist_history (id, scope, user_id, action, series, source, timestamp) VALUES
+                    (1, 'global', '1', 'added', 'One Piece', 'command', 100),
+                    (2, 'global', '1', 'removed', 'One Piece', 'command', 200),
+                    (3, 'global', '1', 'added', 'one piece', 'command', 300);
+                 INSERT INTO wishlist_history_card (history_id, position, name) VALUES
+                    (1, 0, 'Zoro'), (1, 1, 'Luffy'), (2, 0, 'Zoro'), (3, 0, 'ZORO');"
+            ).unwrap();
+        }
+
+        let db = sqlite_wishlist_db::init_db(Arc::new(VoidLogger), path).await.unwrap();
+
+        let cards = db.get_user_wishlisted_cards(WishlistScope::Global, UserId::new(1), &SeriesName::new("One Piece")).await.unwrap();
+        let added_at: Vec<Option<i64>> = cards.iter().map(|card| card.added_at.map(|at| at.timestamp())).collect();
+        assert_eq!(added_at, vec![Some(300), Some(100), None]);
+
+        drop(db);
+        fs::remove_file(path).unwrap();
+    }
+}
@@ -1 +1,5 @@
+mod commands;
+mod components;
+mod model;
+mod traits;
 mod util;
\ No newline at end of file
@@ -0,0 +1,62 @@
+#[cfg(test)]
+mod catalog_series {
+    use chrono::{DateTime, Utc};
+
+    use crate::model::catalog::{CatalogCard, CatalogSeries};
+    use crate::model::wishlist::{CardName, CardPriority, SeriesName, WishlistedCard};
+
+    fn at(timestamp: i64) -> DateTime<Utc> {
+        DateTime::from_timestamp(timestamp, 0).unwrap()
+    }
+
+    fn catalog_series(cards: &[&str]) -> CatalogSeries {
+        CatalogSeries {
+            series: SeriesName::new("One Piece"),
+            cards: cards.iter().map(|card| CatalogCard { name: CardName::new(card), first_seen: at(0), last_seen: at(10) }).collect(),
+            first_seen: at(0),
+            last_seen: at(10)
+        }
+    }
+
+    fn wishlisted(name: &str) -> WishlistedCard {
+        WishlistedCard::new(CardName::new(name), CardPriority::Normal)
+    }
+
+    #[test]
+    fn has_card() {
+        let series = catalog_series(&["Monkey D. Luffy", "Nami"]);
+
+        assert!(series.has_card(&CardName::new("monkey d luffy")));
+        assert!(!series.has_card(&CardName::new("Zoro")));
+    }
+
+    #[test]
+    fn closest_card() {
+        let series = catalog_series(&["Monkey D. Luffy", "Nami", "Nico Robin"]);
+
+        assert_eq!(series.closest_card(&CardName::new("Monkey D. Lufy")), Some(&CardName::new("Monkey D. Luffy")));
+        assert_eq!(series.closest_card(&CardName::new("Robin")), Some(&CardName::new("Nico Robin")));
+        assert_eq!(series.closest_card(&CardName::new("Trafalgar Law")), None);
+    }
+
+    #[test]
+    fn closest_card_without_cards() {
+        assert_eq!(catalog_series(&[]).closest_card(&CardName::new("Nami")), None);
+    }
+
+    #[test]
+    fn wishlisted_count() {
+        let series = catalog_series(&["Monkey D. Luffy", "Nami", "Nico Robin"]);
+
+        assert_eq!(series.wishlisted_count(&[]), 0);
+        assert_eq!(series.wishlisted_count(&[wishlisted("NAMI"), wishlisted("Trafalgar Law")]), 1);
+    }
+
+    #[test]
+    fn wishlisted_count_with_wildcard() {
+        let series = catalog_series(&["Monkey D. Luffy", "Nami", "Nico Robin"]);
+        let wildcard = WishlistedCard::new(CardName::wildcard(), CardPriority::Normal).with_excluded(vec![CardName::new("Nami")]);
+
+        assert_eq!(series.wishlisted_count(&[wildcard]), 2);
+    }
+}
@@ -0,0 +1,92 @@
+#[cfg(test)]
+mod wishlist_comparison {
+    use crate::model::compare::WishlistComparison;
+    use crate::model::wishlist::{CardName, CardPriority, SeriesName, SeriesOverview, WishlistEntry, WishlistOverview, WishlistedCard};
+
+    fn wishlisted(name: &str) -> WishlistedCard {
+        WishlistedCard::new(CardName::new(name), CardPriority::Normal)
+    }
+
+    fn whole(excluded: &[&str]) -> WishlistedCard {
+        wishlisted("*").with_excluded(excluded.iter().map(CardName::new).collect())
+    }
+
+    fn overview(series: Vec<(&str, Vec<WishlistedCard>)>) -> WishlistOverview {
+        let series: Vec<SeriesOverview> = series.into_iter()
+            .map(|(name, cards)| SeriesOverview {
+                series: SeriesName::new(name),
+                cards_count: cards.len() as i32,
+                whole: cards.iter().any(|card| card.name.is_wildcard()),
+                cards: Some(cards)
+            })
+            .collect();
+
+        WishlistOverview { total_series: series.len(), total_cards: 0, series }
+    }
+
+    fn entry(series: &str, cards: &[&str]) -> WishlistEntry {
+        WishlistEntry::new(series, cards)
+    }
+
+    #[test]
+    fn shared_series_and_cards() {
+        let first = overview(vec![
+            ("One Piece", vec![wishlisted("Nami"), wishlisted("Zoro")]),
+            ("Naruto", vec![wishlisted("Itachi")]),
+            ("Bleach", vec![wishlisted("Ichigo")])
+        ]);
+        let second = overview(vec![
+            ("naruto", vec![wishlisted("Sasuke")]),
+            ("ONE PIECE", vec![wishlisted("Sanji"), wishlisted("NAMI")])
+        ]);
+
+        let comparison = WishlistComparison::new(&first, &second, &[], &[]);
+
+        assert_eq!(comparison.shared, vec![entry("One Piece", &["Nami"]), entry("Naruto", &[])]);
+        assert!(comparison.first_owns.is_empty() && comparison.second_owns.is_empty());
+    }
+
+    #[test]
+    fn wildcards_cover_the_other_cards() {
+        let first = overview(vec![("One Piece", vec![whole(&["Sanji"])])]);
+        let second = overview(vec![("One Piece", vec![wishlisted("Nami"), wishlisted("Sanji")])]);
+
+        let comparison = WishlistComparison::new(&first, &second, &[], &[]);
+
+        assert_eq!(comparison.shared, vec![entry("One Piece", &["Nami"])]);
+    }
+
+    #[test]
+    fn both_wildcards() {
+        let first = overview(vec![("One Piece", vec![whole(&[]), wishlisted("Nami")])]);
+        let second = overview(vec![("One Piece", vec![whole(&[])])]);
+
+        let comparison = WishlistComparison::new(&first, &second, &[], &[]);
+
+        assert_eq!(comparison.shared, vec![entry("One Piece", &["Nami", "*"])]);
+    }
+
+    #[test]
+    fn owned_cards_the_other_wants() {
+        let first = overview(vec![("One Piece", vec![wishlisted("Zoro")])]);
+        let second = overview(vec![
+            ("One Piece", vec![wishlisted("Nami")]),
+            ("Naruto", vec![whole(&["Sakura"])])
+        ]);
+        let first_owned = vec![entry("One Piece", &["Nami", "Sanji"]), entry("Naruto", &["Itachi", "Sakura"]), entry("Bleach", &["Ichigo"])];
+        let second_owned = vec![entry("one piece", &["Zoro"])];
+
+        let comparison = WishlistComparison::new(&first, &second, &first_owned, &second_owned);
+
+        assert_eq!(comparison.first_owns, vec![entry("One Piece", &["Nami"]), entry("Naruto", &["Itachi"])]);
+        assert_eq!(comparison.second_owns, vec![entry("one piece", &["Zoro"])]);
+    }
+
+    #[test]
+    fn nothing_in_common() {
+        let first = overview(vec![("One Piece", vec![wishlisted("Zoro")])]);
+        let second = overview(vec![("Naruto", vec![wishlisted("Itachi")])]);
+
+        assert!(WishlistComparison::new(&first, &second, &[entry("Bleach", &["Ichigo"])], &[]).is_empty());
+    }
+}
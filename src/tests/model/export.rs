@@ -0,0 +1,98 @@
+#[cfg(test)]
+mod wishlist_export {
+    use chrono::{DateTime, Utc};
+    use serenity::all::{GuildId, UserId};
+
+    use crate::model::drop::CardConstraint;
+    use crate::model::export::{ExportedCard, ExportedSeries, WishlistExport, EXPORT_FORMAT_VERSION};
+    use crate::model::wishlist::{CardName, CardPriority, SeriesName, SeriesOverview, WishlistOverview, WishlistScope, WishlistedCard};
+
+    fn at(timestamp: i64) -> DateTime<Utc> {
+        DateTime::from_timestamp(timestamp, 0).unwrap()
+    }
+
+    fn overview() -> WishlistOverview {
+        WishlistOverview {
+            series: vec![SeriesOverview {
+                series: SeriesName::new("One Piece"),
+                cards_count: 2,
+                whole: false,
+                cards: Some(vec![
+                    WishlistedCard::new(CardName::new("Zoro"), CardPriority::High)
+                        .with_details(Some("the \"swordsman\", obviously".to_string()), vec![CardConstraint::parse("gen<=100").unwrap()]),
+                    WishlistedCard::new(CardName::new("Luffy"), CardPriority::Normal),
+                ])
+            }],
+            total_series: 1,
+            total_cards: 2
+        }
+    }
+
+    #[test]
+    fn cards_keep_their_details() {
+        let export = WishlistExport::new(UserId::new(1), WishlistScope::Guild(GuildId::new(10)), at(0), &overview());
+
+        assert_eq!(export, WishlistExport {
+            version: EXPORT_FORMAT_VERSION,
+            exported_at: at(0),
+            user_id: "1".to_string(),
+            scope: "10".to_string(),
+            series: vec![ExportedSeries {
+                name: "One Piece".to_string(),
+                cards: vec![
+                    ExportedCard {
+                        name: "Zoro".to_string(),
+                        priority: "high".to_string(),
+                        note: Some("the \"swordsman\", obviously".to_string()),
+                        constraints: vec!["gen<=100".to_string()],
+                        added_at: None
+                    },
+                    ExportedCard {
+                        name: "Luffy".to_string(),
+                        priority: "normal".to_string(),
+                        note: None,
+                        constraints: vec![],
+                        added_at: None
+                    },
+                ]
+            }]
+        });
+        assert_eq!(export.cards_count(), 2);
+    }
+
+    #[test]
+    fn cards_keep_when_they_were_added() {
+        let mut overview = overview();
+        let cards = overview.series[0].cards.as_mut().unwrap();
+        cards[1] = cards[1].clone().with_added_at(Some(at(200)));
+
+        let export = WishlistExport::new(UserId::new(1), WishlistScope::Global, at(500), &overview);
+        let added_at: Vec<_> = export.series[0].cards.iter().map(|card| card.added_at).collect();
+
+        assert_eq!(added_at, vec![None, Some(at(200))]);
+    }
+
+    #[test]
+    fn json_round_trip() {
+        let export = WishlistExport::new(UserId::new(1), WishlistScope::Global, at(0), &overview());
+        let json = export.to_json().unwrap();
+
+        assert!(json.contains("\"version\": 1"));
+        assert!(json.contains("\"exported_at\": \"1970-01-01T00:00:00Z\""));
+        assert_eq!(serde_json::from_str::<WishlistExport>(&json).unwrap(), export);
+    }
+
+    #[test]
+    fn csv_has_one_row_per_card() {
+        let mut overview = overview();
+        let cards = overview.series[0].cards.as_mut().unwrap();
+        cards[1] = cards[1].clone().with_added_at(Some(at(100)));
+        let export = WishlistExport::new(UserId::new(1), WishlistScope::Global, at(0), &overview);
+
+        assert_eq!(export.to_csv().unwrap(), concat!(
+            "version,series,card,priority,note,constraints,added_at\n",
+            "1,One Piece,Zoro,high,\"the \"\"swordsman\"\", obviously\",gen<=100,\n",
+            "1,One Piece,Luffy,normal,,,1970-01-01T00:01:40+00:00\n",
+        ));
+    }
+}
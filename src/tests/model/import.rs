@@ -0,0 +1,142 @@
+#[cfg(test)]
+mod wishlist_import {
+    use chrono::{DateTime, Utc};
+
+    use crate::model::drop::CardConstraint;
+    use crate::model::export::{ExportedCard, ExportedSeries, WishlistExport, EXPORT_FORMAT_VERSION};
+    use crate::model::import::{read_csv, read_json, ImportBatch, ImportError, ImportMode, ImportPlan, ImportedDetails};
+    use crate::model::wishlist::{CardName, CardPriority, CardRef, SeriesName, SeriesOverview, WishlistEntry, WishlistOverview, WishlistScope, WishlistedCard};
+
+    fn card(name: &str, priority: &str) -> ExportedCard {
+        ExportedCard { name: name.to_string(), priority: priority.to_string(), note: None, constraints: vec![], added_at: None }
+    }
+
+    fn series(name: &str, cards: Vec<ExportedCard>) -> ExportedSeries {
+        ExportedSeries { name: name.to_string(), cards }
+    }
+
+    fn export() -> WishlistExport {
+        WishlistExport {
+            version: EXPORT_FORMAT_VERSION,
+            exported_at: DateTime::<Utc>::from_timestamp(0, 0).unwrap(),
+            user_id: "1".to_string(),
+            scope: WishlistScope::Global.key(),
+            series: vec![
+                series("One Piece", vec![
+                    ExportedCard {
+                        note: Some("the \"swordsman\", obviously".to_string()),
+                        constraints: vec!["gen<=100".to_string(), "edition=1".to_string()],
+                        ..card("Zoro", "high")
+                    },
+                    card("Luffy", "normal"),
+                ]),
+                series("Naruto", vec![card("Itachi", "low")]),
+            ]
+        }
+    }
+
+    fn current() -> WishlistOverview {
+        WishlistOverview {
+            series: vec![SeriesOverview {
+                series: SeriesName::new("One Piece"),
+                cards_count: 1,
+                whole: false,
+                cards: Some(vec![WishlistedCard::new(CardName::new("Luffy"), CardPriority::Normal)])
+            }],
+            total_series: 1,
+            total_cards: 1
+        }
+    }
+
+    #[test]
+    fn exports_read_back() {
+        let export = export();
+
+        assert_eq!(read_json(&export.to_json().unwrap()).unwrap(), export.series);
+        assert_eq!(read_csv(&export.to_csv().unwrap()).unwrap(), export.series);
+    }
+
+    #[test]
+    fn csv_rows_are_grouped_by_series() {
+        let csv = "version,series,card,priority,note,constraints,added_at\n\
+            1,One Piece,Zoro,high,,,\n\
+            1,Naruto,Itachi,low,,,\n\
+            1,one piece,Luffy,normal,,,\n";
+
+        assert_eq!(read_csv(csv).unwrap(), vec![
+            series("One Piece", vec![card("Zoro", "high"), card("Luffy", "normal")]),
+            series("Naruto", vec![card("Itachi", "low")]),
+        ]);
+    }
+
+    #[test]
+    fn newer_versions_are_rejected() {
+        let mut export = export();
+        export.version = EXPORT_FORMAT_VERSION + 1;
+
+        assert!(matches!(read_json(&export.to_json().unwrap()), Err(ImportError::UnsupportedVersion(_))));
+        assert!(matches!(read_csv(&export.to_csv().unwrap()), Err(ImportError::UnsupportedVersion(_))));
+        assert!(matches!(read_json("{ \"series\": [] }"), Err(ImportError::Json(_))));
+    }
+
+    #[test]
+    fn invalid_cards_are_rejected() {
+        let empty = WishlistOverview::default();
+
+        for invalid in [
+            series("One Piece", vec![card("Zoro", "urgent")]),
+            series("One Piece", vec![card(" ", "high")]),
+            series("", vec![card("Zoro", "high")]),
+            series("One Piece", vec![ExportedCard { constraints: vec!["gen<<".to_string()], ..card("Zoro", "high") }]),
+        ] {
+            assert!(matches!(ImportPlan::new(&[invalid], &empty, ImportMode::Merge, 50), Err(ImportError::InvalidCard { .. })));
+        }
+    }
+
+    #[test]
+    fn merge_skips_wishlisted_cards() {
+        let mut series = export().series;
+        series[1].cards.push(card("ITACHI", "low"));
+
+        let plan = ImportPlan::new(&series, &current(), ImportMode::Merge, 50).unwrap();
+
+        assert_eq!(plan.series_count, 2);
+        assert!(plan.replaced.is_empty());
+        assert_eq!(plan.batches, vec![
+            ImportBatch { entry: WishlistEntry::new("One Piece", ["Zoro"]), priority: CardPriority::High },
+            ImportBatch { entry: WishlistEntry::new("Naruto", ["Itachi"]), priority: CardPriority::Low },
+        ]);
+        assert_eq!(plan.details, vec![ImportedDetails {
+            card: CardRef::new("One Piece", "Zoro"),
+            note: Some("the \"swordsman\", obviously".to_string()),
+            constraints: vec![CardConstraint::parse("gen<=100").unwrap(), CardConstraint::parse("edition=1").unwrap()]
+        }]);
+        assert_eq!(plan.duplicates, 2);
+        assert_eq!(plan.cards_to_add(), 2);
+    }
+
+    #[test]
+    fn replace_empties_the_wishlist_first() {
+        let plan = ImportPlan::new(&export().series, &current(), ImportMode::Replace, 50).unwrap();
+
+        assert_eq!(plan.replaced, vec![WishlistEntry::new("One Piece", ["Luffy"])]);
+        assert_eq!(plan.duplicates, 0);
+        assert_eq!(plan.cards_to_add(), 3);
+        assert_eq!(plan.cards_to_remove(), 1);
+    }
+
+    #[test]
+    fn batches_split_by_priority_and_size() {
+        let cards = ["A", "B", "C", "D"].map(|name| card(name, "normal")).into_iter()
+            .chain([card("E", "high")])
+            .collect();
+
+        let plan = ImportPlan::new(&[series("One Piece", cards)], &WishlistOverview::default(), ImportMode::Merge, 3).unwrap();
+
+        assert_eq!(plan.batches, vec![
+            ImportBatch { entry: WishlistEntry::new("One Piece", ["A", "B", "C"]), priority: CardPriority::Normal },
+            ImportBatch { entry: WishlistEntry::new("One Piece", ["D"]), priority: CardPriority::Normal },
+            ImportBatch { entry: WishlistEntry::new("One Piece", ["E"]), priority: CardPriority::High },
+        ]);
+    }
+}
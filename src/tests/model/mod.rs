@@ -0,0 +1,4 @@
+mod catalog;
+mod compare;
+mod export;
+mod import;
\ No newline at end of file
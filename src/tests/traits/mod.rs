@@ -0,0 +1 @@
+mod wishlist_db;
\ No newline at end of file
@@ -0,0 +1,964 @@
+// Behaviour every `WishlistDB` implementation must follow.
+// Each backend module at the bottom runs the whole suite against a fresh database.
+
+#[cfg(test)]
+mod conformance {
+    use chrono::{DateTime, Utc};
+    use serenity::all::{GuildId, UserId};
+
+    use crate::model::catalog::{CatalogCard, CatalogStats};
+    use crate::model::drop::{CardConstraint, DropMatch, DroppedCard};
+    use crate::model::history::{ChangeSource, HistoryAction, WishlistChange};
+    use crate::model::wishlist::{AddedCards, CardName, CardPriority, CardRef, OverviewQuery, RemovedCards, ScopeMode, SeriesName, SeriesOrder, SeriesOverview, WishlistEntry, WishlistOverview, WishlistPrivacy, WishlistScope, WishlistedCard};
+    use crate::traits::wishlist_db::{WishlistDB, WishlistDbError};
+
+    const GLOBAL: WishlistScope = WishlistScope::Global;
+
+    fn guild(id: u64) -> WishlistScope {
+        WishlistScope::Guild(GuildId::new(id))
+    }
+
+    fn user(id: u64) -> UserId {
+        UserId::new(id)
+    }
+
+    fn series(name: &str) -> SeriesName {
+        SeriesName::new(name)
+    }
+
+    fn entry(series: &str, cards: &[&str]) -> WishlistEntry {
+        WishlistEntry::new(series, cards)
+    }
+
+    fn seen(seconds: i64) -> DateTime<Utc> {
+        DateTime::from_timestamp(1_700_000_000 + seconds, 0).unwrap()
+    }
+
+    fn dropped(series: &str, card: &str) -> DroppedCard {
+        DroppedCard::new(CardRef::new(series, card))
+    }
+
+    fn change(action: HistoryAction, series: &str, cards: &[&str]) -> WishlistChange {
+        WishlistChange::new(action, entry(series, cards), ChangeSource::Command)
+    }
+
+    fn names(cards: Vec<WishlistedCard>) -> Vec<CardName> {
+        cards.into_iter().map(|card| card.name).collect()
+    }
+
+    // Cards without the time they were added, which the tests can not know
+    fn undated(cards: Vec<WishlistedCard>) -> Vec<WishlistedCard> {
+        cards.into_iter().map(|card| card.with_added_at(None)).collect()
+    }
+
+    fn removed_counts(res: RemovedCards) -> (usize, i32) {
+        (res.removed.len(), res.left)
+    }
+
+    fn by_user(mut users: Vec<DropMatch>) -> Vec<DropMatch> {
+        users.sort_by_key(|drop_match| drop_match.user_id);
+        users
+    }
+
+    async fn wishlisted_series(db: &impl WishlistDB, scope: WishlistScope, user_id: UserId) -> Vec<SeriesName> {
+        let overview = db.get_wishlist_overview(scope, user_id, &OverviewQuery::default()).await.unwrap();
+        overview.series.into_iter().map(|s| s.series).collect()
+    }
+
+    fn sorted<T: Ord>(mut items: Vec<T>) -> Vec<T> {
+        items.sort();
+        items
+    }
+
+    pub async fn add_returns_added_count(db: &impl WishlistDB) {
+        assert_eq!(db.add_all_to_wishlist(GLOBAL, user(1), &entry("One Piece", &["Luffy", "Zoro"]), CardPriority::Normal, false).await.unwrap().added.len(), 2);
+        assert_eq!(db.add_all_to_wishlist(GLOBAL, user(1), &entry("One Piece", &["Nami"]), CardPriority::Normal, false).await.unwrap().added.len(), 1);
+        assert_eq!(db.get_user_wishlisted_cards(GLOBAL, user(1), &series("One Piece")).await.unwrap().len(), 3);
+    }
+
+    pub async fn add_reports_each_card(db: &impl WishlistDB) {
+        db.add_all_to_wishlist(GLOBAL, user(1), &entry("One Piece", &["Zoro"]), CardPriority::Normal, false).await.unwrap();
+
+        let res = db.add_all_to_wishlist(GLOBAL, user(1), &entry("one piece", &["Luffy", "ZORO", "luffy", "Nami"]), CardPriority::Normal, false).await.unwrap();
+        assert_eq!(res, AddedCards {
+            added: vec![CardName::new("Luffy"), CardName::new("Nami")],
+            already_wishlisted: vec![CardName::new("Zoro")],
+            included: vec![]
+        });
+    }
+
+    pub async fn remove_reports_each_card(db: &impl WishlistDB) {
+        db.add_all_to_wishlist(GLOBAL, user(1), &entry("One Piece", &["Luffy", "Zoro", "Nami"]), CardPriority::Normal, false).await.unwrap();
+
+        let res = db.remove_all_from_wishlist(GLOBAL, user(1), &entry("ONE PIECE", &["nami", "Sanji", "LUFFY", "sanji"]), false).await.unwrap();
+        assert_eq!(undated(res.details.clone()), vec![
+            WishlistedCard::new(CardName::new("Luffy"), CardPriority::Normal),
+            WishlistedCard::new(CardName::new("Nami"), CardPriority::Normal)
+        ]);
+        assert_eq!(RemovedCards { details: vec![], ..res }, RemovedCards {
+            removed: vec![CardName::new("Luffy"), CardName::new("Nami")],
+            not_found: vec![CardName::new("Sanji")],
+            left: 1,
+            ..Default::default()
+        });
+
+        let res = db.remove_all_from_wishlist(GLOBAL, user(1), &entry("Bleach", &["Ichigo"]), false).await.unwrap();
+        assert_eq!(res, RemovedCards { not_found: vec![CardName::new("Ichigo")], ..Default::default() });
+    }
+
+    pub async fn add_rejects_blank_names(db: &impl WishlistDB) {
+        assert!(matches!(db.add_all_to_wishlist(GLOBAL, user(1), &entry("  ", &["Luffy"]), CardPriority::Normal, false).await, Err(WishlistDbError::InvalidInput(_))));
+        assert!(matches!(db.add_all_to_wishlist(GLOBAL, user(1), &entry("One Piece", &["Luffy", ""]), CardPriority::Normal, false).await, Err(WishlistDbError::InvalidInput(_))));
+        assert!(wishlisted_series(db, GLOBAL, user(1)).await.is_empty());
+    }
+
+    pub async fn add_ignores_repeated_cards(db: &impl WishlistDB) {
+        assert_eq!(db.add_all_to_wishlist(GLOBAL, user(1), &entry("One Piece", &["Luffy", "Luffy", "LUFFY"]), CardPriority::Normal, false).await.unwrap().added.len(), 1);
+        assert_eq!(names(db.get_user_wishlisted_cards(GLOBAL, user(1), &series("One Piece")).await.unwrap()), vec!["Luffy"]);
+    }
+
+    pub async fn add_ignores_wishlisted_cards(db: &impl WishlistDB) {
+        db.add_all_to_wishlist(GLOBAL, user(1), &entry("One Piece", &["Luffy"]), CardPriority::Normal, false).await.unwrap();
+
+        assert_eq!(db.add_all_to_wishlist(GLOBAL, user(1), &entry("one piece", &["luffy", "Zoro"]), CardPriority::Normal, false).await.unwrap().added.len(), 1);
+        assert_eq!(wishlisted_series(db, GLOBAL, user(1)).await, vec!["One Piece"]);
+        assert_eq!(names(db.get_user_wishlisted_cards(GLOBAL, user(1), &series("One Piece")).await.unwrap()), vec!["Luffy", "Zoro"]);
+    }
+
+    pub async fn wishlist_keeps_insertion_order(db: &impl WishlistDB) {
+        db.add_all_to_wishlist(GLOBAL, user(1), &entry("One Piece", &["Zoro", "Luffy"]), CardPriority::Normal, false).await.unwrap();
+        db.add_all_to_wishlist(GLOBAL, user(1), &entry("Bleach", &["Ichigo"]), CardPriority::Normal, false).await.unwrap();
+        db.add_all_to_wishlist(GLOBAL, user(1), &entry("One Piece", &["Nami"]), CardPriority::Normal, false).await.unwrap();
+
+        assert_eq!(wishlisted_series(db, GLOBAL, user(1)).await, vec!["One Piece", "Bleach"]);
+        assert_eq!(names(db.get_user_wishlisted_cards(GLOBAL, user(1), &series("One Piece")).await.unwrap()), vec!["Zoro", "Luffy", "Nami"]);
+    }
+
+    pub async fn wishlists_are_per_user(db: &impl WishlistDB) {
+        db.add_all_to_wishlist(GLOBAL, user(1), &entry("One Piece", &["Luffy"]), CardPriority::Normal, false).await.unwrap();
+        db.add_all_to_wishlist(GLOBAL, user(2), &entry("Bleach", &["Ichigo"]), CardPriority::Normal, false).await.unwrap();
+
+        assert_eq!(wishlisted_series(db, GLOBAL, user(1)).await, vec!["One Piece"]);
+        assert_eq!(wishlisted_series(db, GLOBAL, user(2)).await, vec!["Bleach"]);
+        assert!(!db.user_has_card(GLOBAL, user(2), &CardRef::new("One Piece", "Luffy")).await.unwrap());
+        assert!(wishlisted_series(db, GLOBAL, user(3)).await.is_empty());
+    }
+
+    pub async fn matching_is_case_insensitive(db: &impl WishlistDB) {
+        db.add_all_to_wishlist(GLOBAL, user(1), &entry("One Piece", &["Monkey D. Luffy"]), CardPriority::Normal, false).await.unwrap();
+
+        assert!(db.user_has_card(GLOBAL, user(1), &CardRef::new("ONE PIECE", "monkey d. luffy")).await.unwrap());
+        assert!(!db.user_has_card(GLOBAL, user(1), &CardRef::new("One Piece", "Zoro")).await.unwrap());
+        assert_eq!(db.get_user_wishlisted_cards(GLOBAL, user(1), &series("one piece")).await.unwrap().len(), 1);
+        assert_eq!(names(db.get_user_wishlisted_cards(GLOBAL, user(1), &series("one PIECE")).await.unwrap()), vec!["Monkey D. Luffy"]);
+    }
+
+    pub async fn add_sets_priority(db: &impl WishlistDB) {
+        db.add_all_to_wishlist(GLOBAL, user(1), &entry("One Piece", &["Luffy"]), CardPriority::High, false).await.unwrap();
+        db.add_all_to_wishlist(GLOBAL, user(1), &entry("One Piece", &["luffy", "Zoro"]), CardPriority::Low, false).await.unwrap();
+
+        assert_eq!(undated(db.get_user_wishlisted_cards(GLOBAL, user(1), &series("One Piece")).await.unwrap()), vec![
+            WishlistedCard::new(CardName::new("Luffy"), CardPriority::High),
+            WishlistedCard::new(CardName::new("Zoro"), CardPriority::Low),
+        ]);
+    }
+
+    pub async fn add_records_added_time(db: &impl WishlistDB) {
+        let before = Utc::now().timestamp();
+        db.add_all_to_wishlist(GLOBAL, user(1), &entry("One Piece", &["Luffy"]), CardPriority::Normal, false).await.unwrap();
+        let added_at = db.get_user_wishlisted_cards(GLOBAL, user(1), &series("One Piece")).await.unwrap()[0].added_at.unwrap();
+        assert!((before..=Utc::now().timestamp()).contains(&added_at.timestamp()));
+
+        db.add_all_to_wishlist(GLOBAL, user(1), &entry("One Piece", &["LUFFY", "Zoro"]), CardPriority::High, false).await.unwrap();
+        let cards = db.get_user_wishlisted_cards(GLOBAL, user(1), &series("One Piece")).await.unwrap();
+        assert_eq!(cards[0].added_at.map(|at| at.timestamp()), Some(added_at.timestamp()));
+        assert!(cards[1].added_at.is_some());
+
+        let query = OverviewQuery { with_cards: true, ..Default::default() };
+        let overview = db.get_wishlist_overview(GLOBAL, user(1), &query).await.unwrap();
+        assert!(overview.series[0].cards.iter().flatten().all(|card| card.added_at.is_some()));
+    }
+
+    pub async fn set_priority_changes_wishlisted_cards_only(db: &impl WishlistDB) {
+        db.add_all_to_wishlist(GLOBAL, user(1), &entry("One Piece", &["Luffy", "Zoro"]), CardPriority::Normal, false).await.unwrap();
+        db.add_all_to_wishlist(GLOBAL, user(2), &entry("One Piece", &["Luffy"]), CardPriority::Normal, false).await.unwrap();
+
+        assert_eq!(db.set_cards_priority(GLOBAL, user(1), &entry("ONE PIECE", &["luffy", "Nami"]), CardPriority::High).await.unwrap(), 1);
+        assert_eq!(db.set_cards_priority(GLOBAL, user(1), &entry("Bleach", &["Ichigo"]), CardPriority::High).await.unwrap(), 0);
+        assert_eq!(db.set_cards_priority(guild(10), user(1), &entry("One Piece", &["Luffy"]), CardPriority::Low).await.unwrap(), 0);
+
+        assert_eq!(undated(db.get_user_wishlisted_cards(GLOBAL, user(1), &series("One Piece")).await.unwrap()), vec![
+            WishlistedCard::new(CardName::new("Luffy"), CardPriority::High),
+            WishlistedCard::new(CardName::new("Zoro"), CardPriority::Normal),
+        ]);
+        assert!(db.get_user_wishlisted_cards(GLOBAL, user(1), &series("Bleach")).await.unwrap().is_empty());
+
+        let drop = [dropped("One Piece", "Luffy")];
+        let res: Vec<_> = db.get_users_with_series_card(GLOBAL, &drop).await.unwrap()
+            .into_iter()
+            .map(|(card, users)| (card, by_user(users)))
+            .collect();
+
+        assert_eq!(res, vec![(drop[0].card.clone(), vec![DropMatch::exact(user(1), CardPriority::High), DropMatch::exact(user(2), CardPriority::Normal)])]);
+    }
+
+    pub async fn card_details(db: &impl WishlistDB) {
+        let luffy = CardRef::new("one piece", "LUFFY");
+        let constraints = [CardConstraint::parse("gen<=100").unwrap(), CardConstraint::parse("edition=1").unwrap()];
+        db.add_all_to_wishlist(GLOBAL, user(1), &entry("One Piece", &["Luffy", "Zoro"]), CardPriority::High, false).await.unwrap();
+
+        db.set_card_note(GLOBAL, user(1), &luffy, Some("for the deck")).await.unwrap();
+        db.set_card_constraints(GLOBAL, user(1), &luffy, &constraints).await.unwrap();
+        assert!(matches!(db.set_card_note(GLOBAL, user(1), &CardRef::new("One Piece", "Nami"), Some("note")).await, Err(WishlistDbError::NotFound(_))));
+        assert!(matches!(db.set_card_constraints(GLOBAL, user(2), &luffy, &constraints).await, Err(WishlistDbError::NotFound(_))));
+
+        let expected = WishlistedCard::new(CardName::new("Luffy"), CardPriority::High)
+            .with_details(Some("for the deck".to_string()), constraints.to_vec());
+        assert_eq!(undated(db.get_user_wishlisted_cards(GLOBAL, user(1), &series("One Piece")).await.unwrap()), vec![
+            expected,
+            WishlistedCard::new(CardName::new("Zoro"), CardPriority::High),
+        ]);
+
+        db.set_card_note(GLOBAL, user(1), &luffy, None).await.unwrap();
+        db.set_card_constraints(GLOBAL, user(1), &luffy, &[]).await.unwrap();
+        assert_eq!(undated(db.get_user_wishlisted_cards(GLOBAL, user(1), &series("One Piece")).await.unwrap())[0], WishlistedCard::new(CardName::new("Luffy"), CardPriority::High));
+    }
+
+    pub async fn users_with_series_card_constraints(db: &impl WishlistDB) {
+        db.add_all_to_wishlist(GLOBAL, user(1), &entry("One Piece", &["Luffy"]), CardPriority::Normal, false).await.unwrap();
+        db.add_all_to_wishlist(GLOBAL, user(2), &entry("One Piece", &["Luffy"]), CardPriority::Normal, false).await.unwrap();
+        db.set_card_constraints(GLOBAL, user(2), &CardRef::new("One Piece", "Luffy"), &[CardConstraint::parse("gen<=100").unwrap()]).await.unwrap();
+
+        let low_gen = DroppedCard { gen: Some(57), ..dropped("One Piece", "Luffy") };
+        let high_gen = DroppedCard { gen: Some(1234), ..dropped("One Piece", "Luffy") };
+        let no_gen = dropped("One Piece", "Luffy");
+
+        let users = |res: Vec<(CardRef, Vec<DropMatch>)>| -> Vec<UserId> {
+            sorted(res.into_iter().flat_map(|(_, users)| users).map(|drop_match| drop_match.user_id).collect())
+        };
+        assert_eq!(users(db.get_users_with_series_card(GLOBAL, &[low_gen]).await.unwrap()), vec![user(1), user(2)]);
+        assert_eq!(users(db.get_users_with_series_card(GLOBAL, &[high_gen]).await.unwrap()), vec![user(1)]);
+        assert_eq!(users(db.get_users_with_series_card(GLOBAL, &[no_gen]).await.unwrap()), vec![user(1), user(2)]);
+    }
+
+    pub async fn matching_is_normalized(db: &impl WishlistDB) {
+        db.add_all_to_wishlist(GLOBAL, user(1), &entry("JoJo's Bizarre Adventure", &["Jōtarō Kūjō"]), CardPriority::Normal, false).await.unwrap();
+
+        assert!(db.user_has_card(GLOBAL, user(1), &CardRef::new("jojo’s bizarre  adventure", "JOTARO KUJO")).await.unwrap());
+        assert_eq!(db.add_all_to_wishlist(GLOBAL, user(1), &entry("JOJOS BIZARRE ADVENTURE", &["jotaro kujo"]), CardPriority::Normal, false).await.unwrap().added.len(), 0);
+        assert_eq!(wishlisted_series(db, GLOBAL, user(1)).await, vec!["JoJo's Bizarre Adventure"]);
+    }
+
+    pub async fn long_names_do_not_collide(db: &impl WishlistDB) {
+        let cards = ["Monkey D. Luffy (Gear 4)", "Monkey D. Luffy (Gear 5)"];
+        assert_eq!(db.add_all_to_wishlist(GLOBAL, user(1), &entry("One Piece", &cards), CardPriority::Normal, false).await.unwrap().added.len(), 2);
+        db.add_all_to_wishlist(GLOBAL, user(1), &entry("Attack on Titan: The Final Season Part 1", &["Eren"]), CardPriority::Normal, false).await.unwrap();
+        db.add_all_to_wishlist(GLOBAL, user(1), &entry("Attack on Titan: The Final Season Part 2", &["Eren"]), CardPriority::Normal, false).await.unwrap();
+
+        assert_eq!(wishlisted_series(db, GLOBAL, user(1)).await.len(), 3);
+        assert_eq!(removed_counts(db.remove_all_from_wishlist(GLOBAL, user(1), &entry("One Piece", &["Monkey D. Luffy (Gear 5)"]), false).await.unwrap()), (1, 1));
+        assert_eq!(names(db.get_user_wishlisted_cards(GLOBAL, user(1), &series("One Piece")).await.unwrap()), vec![cards[0]]);
+    }
+
+    pub async fn remove_returns_removed_and_left(db: &impl WishlistDB) {
+        db.add_all_to_wishlist(GLOBAL, user(1), &entry("One Piece", &["Luffy", "Zoro", "Nami"]), CardPriority::Normal, false).await.unwrap();
+
+        assert_eq!(removed_counts(db.remove_all_from_wishlist(GLOBAL, user(1), &entry("one piece", &["LUFFY", "Sanji"]), false).await.unwrap()), (1, 2));
+        assert_eq!(names(db.get_user_wishlisted_cards(GLOBAL, user(1), &series("One Piece")).await.unwrap()), vec!["Zoro", "Nami"]);
+    }
+
+    pub async fn remove_from_unknown_series(db: &impl WishlistDB) {
+        db.add_all_to_wishlist(GLOBAL, user(1), &entry("One Piece", &["Luffy"]), CardPriority::Normal, false).await.unwrap();
+
+        assert_eq!(removed_counts(db.remove_all_from_wishlist(GLOBAL, user(1), &entry("Bleach", &["Ichigo"]), false).await.unwrap()), (0, 0));
+        assert_eq!(removed_counts(db.remove_all_from_wishlist(GLOBAL, user(2), &entry("One Piece", &["Luffy"]), false).await.unwrap()), (0, 0));
+        assert_eq!(names(db.get_user_wishlisted_cards(GLOBAL, user(1), &series("One Piece")).await.unwrap()), vec!["Luffy"]);
+    }
+
+    pub async fn remove_last_card_drops_series(db: &impl WishlistDB) {
+        db.add_all_to_wishlist(GLOBAL, user(1), &entry("One Piece", &["Luffy", "Zoro"]), CardPriority::Normal, false).await.unwrap();
+        db.add_all_to_wishlist(GLOBAL, user(1), &entry("Bleach", &["Ichigo"]), CardPriority::Normal, false).await.unwrap();
+
+        assert_eq!(removed_counts(db.remove_all_from_wishlist(GLOBAL, user(1), &entry("One Piece", &["Luffy", "Zoro"]), false).await.unwrap()), (2, 0));
+        assert_eq!(wishlisted_series(db, GLOBAL, user(1)).await, vec!["Bleach"]);
+        assert!(db.get_users_with_series(GLOBAL, &[series("One Piece")]).await.unwrap().is_empty());
+    }
+
+    pub async fn remove_series_returns_card_count(db: &impl WishlistDB) {
+        db.add_all_to_wishlist(GLOBAL, user(1), &entry("One Piece", &["Luffy", "Zoro"]), CardPriority::Normal, false).await.unwrap();
+        db.add_all_to_wishlist(GLOBAL, user(1), &entry("Bleach", &["Ichigo"]), CardPriority::Normal, false).await.unwrap();
+
+        assert_eq!(db.remove_series_from_wishlist(GLOBAL, user(1), &series("ONE PIECE")).await.unwrap(), 2);
+        assert_eq!(wishlisted_series(db, GLOBAL, user(1)).await, vec!["Bleach"]);
+        assert_eq!(db.get_user_wishlisted_cards(GLOBAL, user(1), &series("One Piece")).await.unwrap().len(), 0);
+        assert!(matches!(db.remove_series_from_wishlist(GLOBAL, user(1), &series("One Piece")).await, Err(WishlistDbError::NotFound(_))));
+    }
+
+    pub async fn users_with_series_card(db: &impl WishlistDB) {
+        db.add_all_to_wishlist(GLOBAL, user(1), &entry("One Piece", &["Luffy", "Zoro"]), CardPriority::Normal, false).await.unwrap();
+        db.add_all_to_wishlist(GLOBAL, user(2), &entry("one piece", &["zoro"]), CardPriority::Normal, false).await.unwrap();
+        db.add_all_to_wishlist(GLOBAL, user(3), &entry("Bleach", &["Ichigo"]), CardPriority::Normal, false).await.unwrap();
+
+        let drop = [dropped("ONE PIECE", "ZORO"), dropped("One Piece", "Nami"), dropped("Bleach", "Ichigo")];
+        let res: Vec<_> = db.get_users_with_series_card(GLOBAL, &drop).await.unwrap()
+            .into_iter()
+            .map(|(card, users)| (card, by_user(users)))
+            .collect();
+
+        assert_eq!(res, vec![
+            (CardRef::new("ONE PIECE", "ZORO"), vec![DropMatch::exact(user(1), CardPriority::Normal), DropMatch::exact(user(2), CardPriority::Normal)]),
+            (CardRef::new("Bleach", "Ichigo"), vec![DropMatch::exact(user(3), CardPriority::Normal)]),
+        ]);
+    }
+
+    pub async fn wildcard_matches_unlisted_cards(db: &impl WishlistDB) {
+        db.add_all_to_wishlist(GLOBAL, user(1), &entry("One Piece", &["*"]), CardPriority::High, false).await.unwrap();
+        db.add_all_to_wishlist(GLOBAL, user(2), &entry("One Piece", &["Zoro"]), CardPriority::Low, false).await.unwrap();
+        db.add_all_to_wishlist(GLOBAL, user(3), &entry("One Piece", &["Zoro", "*"]), CardPriority::Normal, false).await.unwrap();
+        // the card itself takes precedence over the wildcard, constraints included
+        db.set_card_constraints(GLOBAL, user(3), &CardRef::new("One Piece", "Zoro"), &[CardConstraint::parse("gen<=100").unwrap()]).await.unwrap();
+
+        let drop = [DroppedCard { gen: Some(500), ..dropped("one piece", "Zoro") }, dropped("One Piece", "Nami"), dropped("Bleach", "Ichigo")];
+        let res: Vec<_> = db.get_users_with_series_card(GLOBAL, &drop).await.unwrap()
+            .into_iter()
+            .map(|(card, users)| (card, by_user(users)))
+            .collect();
+
+        assert_eq!(res, vec![
+            (CardRef::new("one piece", "Zoro"), vec![DropMatch::exact(user(1), CardPriority::High), DropMatch::exact(user(2), CardPriority::Low)]),
+            (CardRef::new("One Piece", "Nami"), vec![DropMatch::exact(user(1), CardPriority::High), DropMatch::exact(user(3), CardPriority::Normal)]),
+        ]);
+    }
+
+    pub async fn wildcard_exclusions(db: &impl WishlistDB) {
+        let users = |res: Vec<(CardRef, Vec<DropMatch>)>| -> Vec<(CardName, UserId)> {
+            res.into_iter().flat_map(|(card, users)| users.into_iter().map(move |drop_match| (card.card.clone(), drop_match.user_id))).collect()
+        };
+        let drop = [dropped("One Piece", "Luffy"), dropped("One Piece", "Zoro")];
+        db.add_all_to_wishlist(GLOBAL, user(1), &entry("One Piece", &["*"]), CardPriority::Normal, false).await.unwrap();
+        db.add_all_to_wishlist(GLOBAL, user(1), &entry("Bleach", &["Ichigo"]), CardPriority::Normal, false).await.unwrap();
+
+        db.set_wildcard_exclusions(GLOBAL, user(1), &series("one piece"), &[CardName::new("LUFFY")]).await.unwrap();
+        assert_eq!(users(db.get_users_with_series_card(GLOBAL, &drop).await.unwrap()), vec![(CardName::new("Zoro"), user(1))]);
+        assert_eq!(undated(db.get_user_wishlisted_cards(GLOBAL, user(1), &series("One Piece")).await.unwrap()), vec![
+            WishlistedCard::new(CardName::wildcard(), CardPriority::Normal).with_excluded(vec![CardName::new("LUFFY")])
+        ]);
+
+        db.set_wildcard_exclusions(GLOBAL, user(1), &series("One Piece"), &[]).await.unwrap();
+        assert_eq!(users(db.get_users_with_series_card(GLOBAL, &drop).await.unwrap()).len(), 2);
+
+        assert!(matches!(db.set_wildcard_exclusions(GLOBAL, user(1), &series("Bleach"), &[CardName::new("Rukia")]).await, Err(WishlistDbError::NotFound(_))));
+        assert!(matches!(db.set_wildcard_exclusions(GLOBAL, user(2), &series("One Piece"), &[]).await, Err(WishlistDbError::NotFound(_))));
+    }
+
+    pub async fn include_in_wildcard_clears_exclusions(db: &impl WishlistDB) {
+        db.add_all_to_wishlist(GLOBAL, user(1), &entry("One Piece", &["*"]), CardPriority::Normal, false).await.unwrap();
+        db.set_wildcard_exclusions(GLOBAL, user(1), &series("One Piece"), &[CardName::new("Luffy"), CardName::new("Nami")]).await.unwrap();
+
+        let included = db.include_in_wildcard(GLOBAL, user(1), &entry("one piece", &["NAMI", "Zoro"])).await.unwrap();
+        assert_eq!(included, vec![CardName::new("Nami")]);
+        assert_eq!(names(db.get_user_wishlisted_cards(GLOBAL, user(1), &series("One Piece")).await.unwrap()), vec![CardName::wildcard()]);
+        assert_eq!(db.get_user_wishlisted_cards(GLOBAL, user(1), &series("One Piece")).await.unwrap()[0].excluded, vec![CardName::new("Luffy")]);
+
+        let matches = db.get_users_with_series_card(GLOBAL, &[dropped("One Piece", "Nami")]).await.unwrap();
+        assert_eq!(matches.len(), 1);
+
+        assert!(db.include_in_wildcard(GLOBAL, user(1), &entry("Bleach", &["Ichigo"])).await.unwrap().is_empty());
+        assert_eq!(wishlisted_series(db, GLOBAL, user(1)).await, vec!["One Piece"]);
+    }
+
+    pub async fn add_sets_priority_of_wishlisted_cards_when_asked(db: &impl WishlistDB) {
+        db.add_all_to_wishlist(GLOBAL, user(1), &entry("One Piece", &["Luffy", "Zoro"]), CardPriority::Normal, false).await.unwrap();
+
+        db.add_all_to_wishlist(GLOBAL, user(1), &entry("One Piece", &["Luffy"]), CardPriority::High, false).await.unwrap();
+        let res = db.add_all_to_wishlist(GLOBAL, user(1), &entry("One Piece", &["zoro", "Nami"]), CardPriority::Low, true).await.unwrap();
+        assert_eq!(res.already_wishlisted, vec![CardName::new("Zoro")]);
+
+        let priorities: Vec<(CardName, CardPriority)> = db.get_user_wishlisted_cards(GLOBAL, user(1), &series("One Piece")).await.unwrap().into_iter()
+            .map(|card| (card.name, card.priority))
+            .collect();
+        assert_eq!(priorities, vec![
+            (CardName::new("Luffy"), CardPriority::Normal),
+            (CardName::new("Zoro"), CardPriority::Low),
+            (CardName::new("Nami"), CardPriority::Low)
+        ]);
+    }
+
+    pub async fn add_takes_cards_back_into_wildcard(db: &impl WishlistDB) {
+        db.add_all_to_wishlist(GLOBAL, user(1), &entry("One Piece", &["*"]), CardPriority::Normal, false).await.unwrap();
+        db.set_wildcard_exclusions(GLOBAL, user(1), &series("One Piece"), &[CardName::new("Luffy"), CardName::new("Nami")]).await.unwrap();
+
+        let res = db.add_all_to_wishlist(GLOBAL, user(1), &entry("one piece", &["NAMI", "Zoro"]), CardPriority::Normal, false).await.unwrap();
+        assert_eq!(res.added, vec![CardName::new("NAMI"), CardName::new("Zoro")]);
+        assert_eq!(res.included, vec![CardName::new("Nami")]);
+
+        let wildcard = db.get_user_wishlisted_cards(GLOBAL, user(1), &series("One Piece")).await.unwrap().remove(0);
+        assert_eq!(wildcard.excluded, vec![CardName::new("Luffy")]);
+    }
+
+    pub async fn remove_leaves_cards_out_of_wildcard(db: &impl WishlistDB) {
+        db.add_all_to_wishlist(GLOBAL, user(1), &entry("One Piece", &["*", "Zoro"]), CardPriority::Normal, false).await.unwrap();
+        db.set_wildcard_exclusions(GLOBAL, user(1), &series("One Piece"), &[CardName::new("Luffy")]).await.unwrap();
+
+        // only removing, the wildcard still covers the card
+        let res = db.remove_all_from_wishlist(GLOBAL, user(1), &entry("One Piece", &["Zoro", "Nami"]), false).await.unwrap();
+        assert_eq!((res.removed, res.not_found, res.excluded), (vec![CardName::new("Zoro")], vec![CardName::new("Nami")], vec![]));
+        db.add_all_to_wishlist(GLOBAL, user(1), &entry("One Piece", &["Zoro"]), CardPriority::High, false).await.unwrap();
+
+        let res = db.remove_all_from_wishlist(GLOBAL, user(1), &entry("one piece", &["nami", "ZORO", "Luffy"]), true).await.unwrap();
+        assert_eq!(res.removed, vec![CardName::new("Zoro"), CardName::new("nami")]);
+        assert_eq!(res.excluded, vec![CardName::new("nami"), CardName::new("Zoro")]);
+        assert_eq!(res.not_found, vec![CardName::new("Luffy")]);
+        assert_eq!(undated(res.details), vec![WishlistedCard::new(CardName::new("Zoro"), CardPriority::High)]);
+        assert_eq!(res.left, 1);
+
+        let wildcard = db.get_user_wishlisted_cards(GLOBAL, user(1), &series("One Piece")).await.unwrap().remove(0);
+        assert_eq!(wildcard.excluded, vec![CardName::new("Luffy"), CardName::new("nami"), CardName::new("Zoro")]);
+        assert!(db.get_users_with_series_card(GLOBAL, &[dropped("One Piece", "Nami")]).await.unwrap().is_empty());
+
+        // the wildcard itself leaves nothing to exclude from
+        let res = db.remove_all_from_wishlist(GLOBAL, user(1), &entry("One Piece", &["*", "Sanji"]), true).await.unwrap();
+        assert_eq!((res.removed, res.not_found, res.excluded, res.left), (vec![CardName::wildcard()], vec![CardName::new("Sanji")], vec![], 0));
+    }
+
+    pub async fn search_user_wishlist(db: &impl WishlistDB) {
+        db.add_all_to_wishlist(GLOBAL, user(1), &entry("One Punch Man", &["Saitama"]), CardPriority::Normal, false).await.unwrap();
+        db.add_all_to_wishlist(GLOBAL, user(1), &entry("One Piece", &["Roronoa Zoro", "Nami", "Nico Robin"]), CardPriority::Normal, false).await.unwrap();
+        db.add_all_to_wishlist(GLOBAL, user(1), &entry("Naruto", &["Itachi"]), CardPriority::Normal, false).await.unwrap();
+        db.add_all_to_wishlist(GLOBAL, user(2), &entry("One Outs", &["Tokuchi"]), CardPriority::Normal, false).await.unwrap();
+
+        assert_eq!(db.search_user_series(GLOBAL, user(1), "one p", 10).await.unwrap(), vec![series("One Piece"), series("One Punch Man")]);
+        assert_eq!(db.search_user_series(GLOBAL, user(1), "ONE", 1).await.unwrap(), vec![series("One Piece")]);
+        assert_eq!(db.search_user_series(GLOBAL, user(1), "", 10).await.unwrap().len(), 3);
+        assert!(db.search_user_series(GLOBAL, user(1), "bleach", 10).await.unwrap().is_empty());
+        assert!(db.search_user_series(guild(1), user(1), "one", 10).await.unwrap().is_empty());
+
+        assert_eq!(db.search_user_cards(GLOBAL, user(1), &series("one piece"), "ni", 10).await.unwrap(), vec![CardName::new("Nico Robin")]);
+        assert_eq!(db.search_user_cards(GLOBAL, user(1), &series("One Piece"), "n", 10).await.unwrap(), vec![CardName::new("Nami"), CardName::new("Nico Robin")]);
+        assert!(db.search_user_cards(GLOBAL, user(2), &series("One Piece"), "", 10).await.unwrap().is_empty());
+    }
+
+    pub async fn catalog(db: &impl WishlistDB) {
+        db.add_to_catalog(&[entry("One Piece", &["Monkey D. Luffy", "Nami"])], seen(1)).await.unwrap();
+        db.add_to_catalog(&[entry("ONE PIECE", &["NAMI", "Nico Robin"]), WishlistEntry { series: series("One Punch Man"), cards: vec![] }], seen(2)).await.unwrap();
+
+        assert_eq!(db.search_catalog_series("one", 10).await.unwrap(), vec![series("One Piece"), series("One Punch Man")]);
+        assert_eq!(db.search_catalog_series("one pu", 10).await.unwrap(), vec![series("One Punch Man")]);
+        assert_eq!(db.search_catalog_cards(&series("one piece"), "n", 10).await.unwrap(), vec![CardName::new("Nami"), CardName::new("Nico Robin")]);
+        assert_eq!(db.search_catalog_cards(&series("One Piece"), "", 2).await.unwrap(), vec![CardName::new("Monkey D. Luffy"), CardName::new("Nami")]);
+        assert!(db.search_catalog_cards(&series("One Punch Man"), "", 10).await.unwrap().is_empty());
+
+        assert!(matches!(db.add_to_catalog(&[entry("Bleach", &["Ichigo"]), entry("", &["Nami"])], seen(4)).await, Err(WishlistDbError::InvalidInput(_))));
+        assert!(db.search_catalog_series("bleach", 10).await.unwrap().is_empty());
+    }
+
+    pub async fn catalog_seen_times(db: &impl WishlistDB) {
+        db.add_to_catalog(&[entry("One Piece", &["Nami", "Monkey D. Luffy"])], seen(20)).await.unwrap();
+        db.add_to_catalog(&[entry("one piece", &["NAMI"])], seen(10)).await.unwrap();
+        db.add_to_catalog(&[entry("One Piece", &["Nico Robin"])], seen(30)).await.unwrap();
+
+        let catalog_series = db.get_catalog_series(&series("ONE PIECE")).await.unwrap().unwrap();
+        assert_eq!(catalog_series.series, series("One Piece"));
+        assert_eq!((catalog_series.first_seen, catalog_series.last_seen), (seen(10), seen(30)));
+        assert_eq!(
+            catalog_series.cards,
+            vec![
+                CatalogCard { name: CardName::new("Monkey D. Luffy"), first_seen: seen(20), last_seen: seen(20) },
+                CatalogCard { name: CardName::new("Nami"), first_seen: seen(10), last_seen: seen(20) },
+                CatalogCard { name: CardName::new("Nico Robin"), first_seen: seen(30), last_seen: seen(30) }
+            ]
+        );
+
+        assert_eq!(db.get_catalog_series(&series("Naruto")).await.unwrap(), None);
+    }
+
+    pub async fn catalog_stats(db: &impl WishlistDB) {
+        assert_eq!(db.get_catalog_stats().await.unwrap(), CatalogStats::default());
+
+        db.add_to_catalog(&[entry("One Piece", &["Nami", "Nico Robin"])], seen(20)).await.unwrap();
+        db.add_to_catalog(&[entry("Naruto", &["Itachi"])], seen(10)).await.unwrap();
+        db.add_to_catalog(&[WishlistEntry { series: series("Bleach"), cards: vec![] }], seen(30)).await.unwrap();
+
+        assert_eq!(
+            db.get_catalog_stats().await.unwrap(),
+            CatalogStats { series_count: 3, cards_count: 3, first_seen: Some(seen(10)) }
+        );
+    }
+
+    pub async fn card_ownership(db: &impl WishlistDB) {
+        db.set_cards_owned(user(1), &entry("One Piece", &["Nico Robin", "Nami"]), true).await.unwrap();
+        db.set_cards_owned(user(1), &entry("Naruto", &["Itachi"]), true).await.unwrap();
+        db.set_cards_owned(user(1), &entry("ONE PIECE", &["Zoro"]), false).await.unwrap();
+        db.set_cards_owned(user(1), &entry("One Piece", &["NICO ROBIN"]), false).await.unwrap();
+        db.set_cards_owned(user(2), &entry("Bleach", &["Ichigo"]), true).await.unwrap();
+
+        assert_eq!(db.get_owned_cards(user(1)).await.unwrap(), vec![entry("Naruto", &["Itachi"]), entry("One Piece", &["Nami"])]);
+        assert_eq!(db.get_owned_cards(user(2)).await.unwrap(), vec![entry("Bleach", &["Ichigo"])]);
+        assert!(db.get_owned_cards(user(3)).await.unwrap().is_empty());
+
+        assert!(matches!(db.set_cards_owned(user(1), &entry("", &["Nami"]), true).await, Err(WishlistDbError::InvalidInput(_))));
+    }
+
+    pub async fn users_with_series_card_no_drop(db: &impl WishlistDB) {
+        db.add_all_to_wishlist(GLOBAL, user(1), &entry("One Piece", &["Luffy"]), CardPriority::Normal, false).await.unwrap();
+
+        assert!(db.get_users_with_series_card(GLOBAL, &[]).await.unwrap().is_empty());
+        assert!(db.get_users_with_series_card(GLOBAL, &[dropped("One Piece", "Zoro")]).await.unwrap().is_empty());
+    }
+
+    pub async fn users_with_series(db: &impl WishlistDB) {
+        db.add_all_to_wishlist(GLOBAL, user(1), &entry("One Piece", &["Luffy", "Zoro"]), CardPriority::Normal, false).await.unwrap();
+        db.add_all_to_wishlist(GLOBAL, user(1), &entry("Bleach", &["Ichigo", "Rukia", "Renji"]), CardPriority::Normal, false).await.unwrap();
+        db.add_all_to_wishlist(GLOBAL, user(2), &entry("one piece", &["Nami"]), CardPriority::Normal, false).await.unwrap();
+
+        let drop = [series("Naruto"), series("ONE PIECE"), series("bleach")];
+        let res: Vec<_> = db.get_users_with_series(GLOBAL, &drop).await.unwrap()
+            .into_iter()
+            .map(|(series, users)| (series, sorted(users)))
+            .collect();
+
+        assert_eq!(res, vec![
+            (series("ONE PIECE"), vec![(user(1), 2), (user(2), 1)]),
+            (series("bleach"), vec![(user(1), 3)]),
+        ]);
+    }
+
+    pub async fn users_with_series_no_drop(db: &impl WishlistDB) {
+        db.add_all_to_wishlist(GLOBAL, user(1), &entry("One Piece", &["Luffy"]), CardPriority::Normal, false).await.unwrap();
+
+        assert!(db.get_users_with_series(GLOBAL, &[]).await.unwrap().is_empty());
+        assert!(db.get_users_with_series(GLOBAL, &[series("Bleach")]).await.unwrap().is_empty());
+    }
+
+    fn overview_counts(overview: &WishlistOverview) -> Vec<(&str, i32)> {
+        overview.series.iter().map(|s| (s.series.as_str(), s.cards_count)).collect()
+    }
+
+    pub async fn wishlist_overview(db: &impl WishlistDB) {
+        db.add_all_to_wishlist(GLOBAL, user(1), &entry("One Piece", &["Luffy", "Zoro"]), CardPriority::Normal, false).await.unwrap();
+        db.add_all_to_wishlist(GLOBAL, user(1), &entry("Bleach", &["Ichigo", "Rukia", "Renji"]), CardPriority::Normal, false).await.unwrap();
+        db.add_all_to_wishlist(GLOBAL, user(1), &entry("Naruto", &["Sasuke"]), CardPriority::High, false).await.unwrap();
+        db.add_all_to_wishlist(GLOBAL, user(1), &entry("Air", &["Misuzu", "Kano"]), CardPriority::Normal, false).await.unwrap();
+        db.add_all_to_wishlist(guild(10), user(1), &entry("Bleach", &["Renji"]), CardPriority::Normal, false).await.unwrap();
+
+        let overview = db.get_wishlist_overview(GLOBAL, user(1), &OverviewQuery::default()).await.unwrap();
+        assert_eq!(overview_counts(&overview), vec![("One Piece", 2), ("Bleach", 3), ("Naruto", 1), ("Air", 2)]);
+        assert_eq!((overview.total_series, overview.total_cards), (4, 8));
+        assert!(overview.series.iter().all(|s| s.cards.is_none()));
+
+        let by_name = OverviewQuery { order: SeriesOrder::Name, ..Default::default() };
+        let overview = db.get_wishlist_overview(GLOBAL, user(1), &by_name).await.unwrap();
+        assert_eq!(overview_counts(&overview), vec![("Air", 2), ("Bleach", 3), ("Naruto", 1), ("One Piece", 2)]);
+
+        // ties keep the wishlist order
+        let by_count = OverviewQuery { order: SeriesOrder::CardCount, ..Default::default() };
+        let overview = db.get_wishlist_overview(GLOBAL, user(1), &by_count).await.unwrap();
+        assert_eq!(overview_counts(&overview), vec![("Bleach", 3), ("One Piece", 2), ("Air", 2), ("Naruto", 1)]);
+    }
+
+    pub async fn wishlist_overview_pages(db: &impl WishlistDB) {
+        db.add_all_to_wishlist(GLOBAL, user(1), &entry("One Piece", &["Luffy", "Zoro"]), CardPriority::Normal, false).await.unwrap();
+        db.add_all_to_wishlist(GLOBAL, user(1), &entry("Bleach", &["Ichigo"]), CardPriority::Normal, false).await.unwrap();
+        db.add_all_to_wishlist(GLOBAL, user(1), &entry("Naruto", &["Sasuke"]), CardPriority::High, false).await.unwrap();
+
+        let page = OverviewQuery { skip: 1, limit: Some(1), with_cards: true, ..Default::default() };
+        let mut overview = db.get_wishlist_overview(GLOBAL, user(1), &page).await.unwrap();
+        overview.series[0].cards = overview.series[0].cards.take().map(undated);
+        assert_eq!(overview.series, vec![SeriesOverview {
+            series: series("Bleach"),
+            cards_count: 1,
+            whole: false,
+            cards: Some(vec![WishlistedCard::new(CardName::new("Ichigo"), CardPriority::Normal)])
+        }]);
+        assert_eq!((overview.total_series, overview.total_cards), (3, 4));
+
+        let past_the_end = OverviewQuery { skip: 3, ..Default::default() };
+        let overview = db.get_wishlist_overview(GLOBAL, user(1), &past_the_end).await.unwrap();
+        assert!(overview.series.is_empty());
+        assert_eq!((overview.total_series, overview.total_cards), (3, 4));
+
+        let empty_page = OverviewQuery { limit: Some(0), ..Default::default() };
+        assert!(db.get_wishlist_overview(GLOBAL, user(1), &empty_page).await.unwrap().series.is_empty());
+
+        assert_eq!(db.get_wishlist_overview(GLOBAL, user(2), &OverviewQuery::default()).await.unwrap(), WishlistOverview::default());
+    }
+
+    pub async fn wishlist_overview_whole_series(db: &impl WishlistDB) {
+        db.add_all_to_wishlist(GLOBAL, user(1), &entry("One Piece", &["Luffy", "*"]), CardPriority::Normal, false).await.unwrap();
+        db.add_all_to_wishlist(GLOBAL, user(1), &entry("Bleach", &["Ichigo"]), CardPriority::Normal, false).await.unwrap();
+
+        let overview = db.get_wishlist_overview(GLOBAL, user(1), &OverviewQuery::default()).await.unwrap();
+        let whole: Vec<(SeriesName, bool)> = overview.series.into_iter().map(|s| (s.series, s.whole)).collect();
+        assert_eq!(whole, vec![(series("One Piece"), true), (series("Bleach"), false)]);
+    }
+
+    pub async fn wishlists_are_per_scope(db: &impl WishlistDB) {
+        db.add_all_to_wishlist(GLOBAL, user(1), &entry("One Piece", &["Luffy"]), CardPriority::Normal, false).await.unwrap();
+        db.add_all_to_wishlist(guild(10), user(1), &entry("One Piece", &["Zoro"]), CardPriority::Normal, false).await.unwrap();
+        db.add_all_to_wishlist(guild(20), user(2), &entry("One Piece", &["Luffy"]), CardPriority::Normal, false).await.unwrap();
+
+        assert_eq!(names(db.get_user_wishlisted_cards(GLOBAL, user(1), &series("One Piece")).await.unwrap()), vec!["Luffy"]);
+        assert_eq!(names(db.get_user_wishlisted_cards(guild(10), user(1), &series("One Piece")).await.unwrap()), vec!["Zoro"]);
+        assert!(wishlisted_series(db, guild(20), user(1)).await.is_empty());
+
+        let drop = [dropped("One Piece", "Luffy")];
+        assert_eq!(db.get_users_with_series_card(guild(20), &drop).await.unwrap(), vec![(drop[0].card.clone(), vec![DropMatch::exact(user(2), CardPriority::Normal)])]);
+        assert!(db.get_users_with_series_card(guild(10), &drop).await.unwrap().is_empty());
+        assert_eq!(db.get_users_with_series(GLOBAL, &[series("One Piece")]).await.unwrap(), vec![(series("One Piece"), vec![(user(1), 1)])]);
+
+        assert_eq!(db.remove_series_from_wishlist(guild(10), user(1), &series("One Piece")).await.unwrap(), 1);
+        assert!(db.user_has_card(GLOBAL, user(1), &CardRef::new("One Piece", "Luffy")).await.unwrap());
+    }
+
+    pub async fn fuzzy_threshold_setting(db: &impl WishlistDB) {
+        assert_eq!(db.get_fuzzy_threshold(user(1)).await.unwrap(), None);
+
+        db.set_fuzzy_threshold(user(1), Some(80)).await.unwrap();
+        assert_eq!(db.get_fuzzy_threshold(user(1)).await.unwrap(), Some(80));
+        assert_eq!(db.get_fuzzy_threshold(user(2)).await.unwrap(), None);
+
+        db.set_fuzzy_threshold(user(1), Some(90)).await.unwrap();
+        assert_eq!(db.get_fuzzy_threshold(user(1)).await.unwrap(), Some(90));
+
+        db.set_fuzzy_threshold(user(1), None).await.unwrap();
+        assert_eq!(db.get_fuzzy_threshold(user(1)).await.unwrap(), None);
+    }
+
+    pub async fn wishlist_privacy_setting(db: &impl WishlistDB) {
+        assert_eq!(db.get_wishlist_privacy(user(1)).await.unwrap(), WishlistPrivacy::Public);
+
+        db.set_wishlist_privacy(user(1), WishlistPrivacy::Private).await.unwrap();
+        assert_eq!(db.get_wishlist_privacy(user(1)).await.unwrap(), WishlistPrivacy::Private);
+        assert_eq!(db.get_wishlist_privacy(user(2)).await.unwrap(), WishlistPrivacy::Public);
+
+        // settings share a row, changing one keeps the others
+        db.set_fuzzy_threshold(user(1), Some(80)).await.unwrap();
+        assert_eq!(db.get_wishlist_privacy(user(1)).await.unwrap(), WishlistPrivacy::Private);
+
+        db.set_wishlist_privacy(user(1), WishlistPrivacy::Public).await.unwrap();
+        assert_eq!(db.get_wishlist_privacy(user(1)).await.unwrap(), WishlistPrivacy::Public);
+        assert_eq!(db.get_fuzzy_threshold(user(1)).await.unwrap(), Some(80));
+    }
+
+    pub async fn fuzzy_matches_opted_in_users(db: &impl WishlistDB) {
+        db.add_all_to_wishlist(GLOBAL, user(1), &entry("One Piece", &["Monkey D. Luffy"]), CardPriority::High, false).await.unwrap();
+        db.add_all_to_wishlist(GLOBAL, user(2), &entry("One Piece", &["Luffy"]), CardPriority::Normal, false).await.unwrap();
+        db.add_all_to_wishlist(GLOBAL, user(3), &entry("One Piece", &["Monkey D. Luffy"]), CardPriority::Normal, false).await.unwrap();
+        db.add_all_to_wishlist(guild(10), user(4), &entry("One Piece", &["Monkey D. Luffy"]), CardPriority::Normal, false).await.unwrap();
+        db.set_fuzzy_threshold(user(1), Some(80)).await.unwrap();
+        db.set_fuzzy_threshold(user(2), Some(80)).await.unwrap();
+        db.set_fuzzy_threshold(user(4), Some(80)).await.unwrap();
+
+        let drop = [dropped("One Piece", "Luffy"), dropped("Bleach", "Ichigo")];
+        let possible_match = Some(CardRef::new("One Piece", "Monkey D. Luffy"));
+        assert_eq!(db.get_users_with_series_card(GLOBAL, &drop).await.unwrap(), vec![
+            (drop[0].card.clone(), vec![
+                DropMatch::exact(user(2), CardPriority::Normal),
+                DropMatch { user_id: user(1), priority: CardPriority::High, possible_match }
+            ])
+        ]);
+
+        db.set_fuzzy_threshold(user(1), None).await.unwrap();
+        let res = db.get_users_with_series_card(GLOBAL, &drop).await.unwrap();
+        assert_eq!(res, vec![(drop[0].card.clone(), vec![DropMatch::exact(user(2), CardPriority::Normal)])]);
+
+        let drop = [dropped("Two Piece", "Luffy")];
+        assert!(db.get_users_with_series_card(GLOBAL, &drop).await.unwrap().is_empty());
+    }
+
+    pub async fn series_aliases_are_resolved(db: &impl WishlistDB) {
+        db.add_series_alias(&series("JJBA"), &series("JoJo's Bizarre Adventure")).await.unwrap();
+        assert_eq!(db.canonical_series(&series("jjba")).await.unwrap(), series("JoJo's Bizarre Adventure"));
+        assert_eq!(db.canonical_series(&series("Bleach")).await.unwrap(), series("Bleach"));
+
+        assert_eq!(db.add_all_to_wishlist(GLOBAL, user(1), &entry("JJBA", &["Jotaro", "Dio"]), CardPriority::Normal, false).await.unwrap().added.len(), 2);
+        assert_eq!(db.add_all_to_wishlist(GLOBAL, user(1), &entry("JoJo's Bizarre Adventure", &["Dio"]), CardPriority::Normal, false).await.unwrap().added.len(), 0);
+        assert_eq!(wishlisted_series(db, GLOBAL, user(1)).await, vec![series("JoJo's Bizarre Adventure")]);
+        assert_eq!(db.get_user_wishlisted_cards(GLOBAL, user(1), &series("JJBA")).await.unwrap().len(), 2);
+        assert!(db.user_has_card(GLOBAL, user(1), &CardRef::new("JJBA", "Dio")).await.unwrap());
+
+        let drop = [dropped("JJBA", "Jotaro"), dropped("JoJo's Bizarre Adventure", "Dio")];
+        assert_eq!(db.get_users_with_series_card(GLOBAL, &drop).await.unwrap(), vec![
+            (drop[0].card.clone(), vec![DropMatch::exact(user(1), CardPriority::Normal)]),
+            (drop[1].card.clone(), vec![DropMatch::exact(user(1), CardPriority::Normal)]),
+        ]);
+        assert_eq!(db.get_users_with_series(GLOBAL, &[series("JJBA")]).await.unwrap(), vec![(series("JJBA"), vec![(user(1), 2)])]);
+
+        assert_eq!(removed_counts(db.remove_all_from_wishlist(GLOBAL, user(1), &entry("jjba", &["Dio"]), false).await.unwrap()), (1, 1));
+        assert_eq!(db.remove_series_from_wishlist(GLOBAL, user(1), &series("JJBA")).await.unwrap(), 1);
+    }
+
+    pub async fn adding_alias_merges_wishlists(db: &impl WishlistDB) {
+        db.add_all_to_wishlist(GLOBAL, user(1), &entry("JoJo's Bizarre Adventure", &["Jotaro"]), CardPriority::Normal, false).await.unwrap();
+        db.add_all_to_wishlist(GLOBAL, user(1), &entry("Bleach", &["Ichigo"]), CardPriority::Normal, false).await.unwrap();
+        db.add_all_to_wishlist(GLOBAL, user(1), &entry("JJBA", &["jotaro", "Dio"]), CardPriority::Normal, false).await.unwrap();
+        db.add_all_to_wishlist(guild(10), user(2), &entry("JJBA", &["Giorno"]), CardPriority::Normal, false).await.unwrap();
+
+        db.add_series_alias(&series("JJBA"), &series("JoJo's Bizarre Adventure")).await.unwrap();
+
+        assert_eq!(wishlisted_series(db, GLOBAL, user(1)).await, vec![series("JoJo's Bizarre Adventure"), series("Bleach")]);
+        assert_eq!(sorted(names(db.get_user_wishlisted_cards(GLOBAL, user(1), &series("JoJo's Bizarre Adventure")).await.unwrap())), vec!["Dio", "Jotaro"]);
+        assert_eq!(wishlisted_series(db, guild(10), user(2)).await, vec![series("JoJo's Bizarre Adventure")]);
+        assert_eq!(names(db.get_user_wishlisted_cards(guild(10), user(2), &series("JJBA")).await.unwrap()), vec!["Giorno"]);
+    }
+
+    pub async fn alias_registry(db: &impl WishlistDB) {
+        assert!(matches!(db.add_series_alias(&series("JoJo"), &series("jojo")).await, Err(WishlistDbError::InvalidInput(_))));
+        assert!(matches!(db.add_series_alias(&series(" "), &series("JoJo")).await, Err(WishlistDbError::InvalidInput(_))));
+
+        db.add_series_alias(&series("JJBA"), &series("JoJo")).await.unwrap();
+        db.add_series_alias(&series("JoJo"), &series("JoJo's Bizarre Adventure")).await.unwrap();
+        db.add_series_alias(&series("OP"), &series("One Piece")).await.unwrap();
+        assert_eq!(db.canonical_series(&series("JJBA")).await.unwrap(), series("JoJo's Bizarre Adventure"));
+        assert!(matches!(db.add_series_alias(&series("JoJo's Bizarre Adventure"), &series("JJBA")).await, Err(WishlistDbError::InvalidInput(_))));
+
+        assert_eq!(db.get_series_aliases().await.unwrap(), vec![
+            (series("JJBA"), series("JoJo's Bizarre Adventure")),
+            (series("JoJo"), series("JoJo's Bizarre Adventure")),
+            (series("OP"), series("One Piece")),
+        ]);
+
+        db.remove_series_alias(&series("jjba")).await.unwrap();
+        assert!(matches!(db.remove_series_alias(&series("JJBA")).await, Err(WishlistDbError::NotFound(_))));
+        assert_eq!(db.canonical_series(&series("JJBA")).await.unwrap(), series("JJBA"));
+        assert_eq!(db.get_series_aliases().await.unwrap().len(), 2);
+    }
+
+    pub async fn guild_scope_mode(db: &impl WishlistDB) {
+        let guild_id = GuildId::new(10);
+
+        assert_eq!(db.get_guild_scope_mode(guild_id).await.unwrap(), ScopeMode::Global);
+        assert_eq!(db.resolve_scope(Some(guild_id)).await.unwrap(), GLOBAL);
+
+        db.set_guild_scope_mode(guild_id, ScopeMode::Isolated).await.unwrap();
+        assert_eq!(db.get_guild_scope_mode(guild_id).await.unwrap(), ScopeMode::Isolated);
+        assert_eq!(db.resolve_scope(Some(guild_id)).await.unwrap(), guild(10));
+        assert_eq!(db.resolve_scope(Some(GuildId::new(20))).await.unwrap(), GLOBAL);
+        assert_eq!(db.resolve_scope(None).await.unwrap(), GLOBAL);
+
+        db.set_guild_scope_mode(guild_id, ScopeMode::Global).await.unwrap();
+        assert_eq!(db.resolve_scope(Some(guild_id)).await.unwrap(), GLOBAL);
+    }
+
+    pub async fn history_is_newest_first(db: &impl WishlistDB) {
+        let added = change(HistoryAction::Added, "One Piece", &["Luffy", "Zoro"]);
+        let removed = WishlistChange::new(HistoryAction::Removed, entry("One Piece", &["Zoro"]), ChangeSource::DropReaction);
+        db.record_change(GLOBAL, user(1), &added).await.unwrap();
+        db.record_change(GLOBAL, user(1), &removed).await.unwrap();
+        db.record_change(GLOBAL, user(2), &change(HistoryAction::Added, "Bleach", &["Ichigo"])).await.unwrap();
+        db.record_change(guild(10), user(1), &change(HistoryAction::Added, "Bleach", &["Rukia"])).await.unwrap();
+
+        let history = db.get_user_history(GLOBAL, user(1), 10).await.unwrap();
+        let changes: Vec<_> = history.iter().map(|record| record.change.clone()).collect();
+        assert_eq!(changes, vec![removed, added]);
+
+        assert_eq!(db.get_user_history(GLOBAL, user(1), 1).await.unwrap().len(), 1);
+        assert!(db.get_user_history(GLOBAL, user(1), 0).await.unwrap().is_empty());
+        assert!(db.get_user_history(guild(20), user(1), 10).await.unwrap().is_empty());
+    }
+
+    pub async fn history_keeps_card_details(db: &impl WishlistDB) {
+        let zoro = WishlistedCard::new(CardName::new("Zoro"), CardPriority::High)
+            .with_details(Some("alt art".to_string()), vec![CardConstraint::parse("gen<=100").unwrap()]);
+        let wildcard = WishlistedCard::new(CardName::wildcard(), CardPriority::Low)
+            .with_excluded(vec![CardName::new("Nami"), CardName::new("Sanji")]);
+        let removed = change(HistoryAction::Removed, "One Piece", &["Zoro", "*", "Luffy"])
+            .with_details(vec![zoro, wildcard]);
+        db.record_change(GLOBAL, user(1), &removed).await.unwrap();
+
+        let history = db.get_user_history(GLOBAL, user(1), 10).await.unwrap();
+        assert_eq!(history[0].change.entry, removed.entry);
+        let details: Vec<(CardName, CardPriority, Option<String>, usize, usize)> = history[0].change.details.iter()
+            .map(|card| (card.name.clone(), card.priority, card.note.clone(), card.constraints.len(), card.excluded.len()))
+            .collect();
+        assert_eq!(details, vec![
+            (CardName::new("Zoro"), CardPriority::High, Some("alt art".to_string()), 1, 0),
+            (CardName::wildcard(), CardPriority::Low, None, 0, 2)
+        ]);
+    }
+
+    pub async fn history_keeps_wildcard_cards(db: &impl WishlistDB) {
+        let removed = change(HistoryAction::Removed, "One Piece", &["Zoro", "Nami"])
+            .with_wildcard(vec![CardName::new("Nami")]);
+        db.record_change(GLOBAL, user(1), &removed).await.unwrap();
+        db.record_change(GLOBAL, user(1), &change(HistoryAction::Added, "One Piece", &["Luffy"])).await.unwrap();
+
+        let history = db.get_user_history(GLOBAL, user(1), 10).await.unwrap();
+        assert!(history[0].change.wildcard.is_empty());
+        assert_eq!(history[1].change.wildcard, vec![CardName::new("Nami")]);
+        assert_eq!(WishlistChange::undo(&history[1]).wildcard, vec![CardName::new("Nami")]);
+    }
+
+    pub async fn undo_skips_undone_changes(db: &impl WishlistDB) {
+        assert!(db.get_last_undoable_change(GLOBAL, user(1)).await.unwrap().is_none());
+
+        let first = change(HistoryAction::Added, "One Piece", &["Luffy"]);
+        db.record_change(GLOBAL, user(1), &first).await.unwrap();
+        db.record_change(GLOBAL, user(1), &change(HistoryAction::Removed, "One Piece", &["Luffy"])).await.unwrap();
+
+        let last = db.get_last_undoable_change(GLOBAL, user(1)).await.unwrap().unwrap();
+        assert_eq!(last.change.action, HistoryAction::Removed);
+        db.record_change(GLOBAL, user(1), &WishlistChange::undo(&last)).await.unwrap();
+
+        let last = db.get_last_undoable_change(GLOBAL, user(1)).await.unwrap().unwrap();
+        assert_eq!(last.change, first);
+        db.record_change(GLOBAL, user(1), &WishlistChange::undo(&last)).await.unwrap();
+
+        assert!(db.get_last_undoable_change(GLOBAL, user(1)).await.unwrap().is_none());
+        assert_eq!(db.get_user_history(GLOBAL, user(1), 10).await.unwrap()[0].change.source, ChangeSource::Undo);
+    }
+}
+
+// Instantiates every conformance check as a test, using the `init_db` and `teardown`
+// functions of the module it is called from. Attributes (e.g. `#[ignore]`) go on every test,
+// one check at a time since they can't be repeated inside the checks' repetition.
+#[cfg(test)]
+macro_rules! conformance_tests {
+    ($(#[$attr:meta])* ; $check:ident $(, $rest:ident)* $(,)?) => {
+        #[tokio::test]
+        $(#[$attr])*
+        async fn $check() {
+            let db = init_db().await;
+
+            crate::tests::traits::wishlist_db::conformance::$check(&db).await;
+            teardown(db).await;
+        }
+
+        conformance_tests!($(#[$attr])* ; $($rest),*);
+    };
+    ($(#[$attr:meta])* ;) => {};
+}
+
+#[cfg(test)]
+macro_rules! wishlist_db_conformance {
+    ($(#[$attr:meta])*) => {
+        conformance_tests!(
+            $(#[$attr])*;
+            add_returns_added_count,
+            add_reports_each_card,
+            remove_reports_each_card,
+            add_rejects_blank_names,
+            add_ignores_repeated_cards,
+            add_ignores_wishlisted_cards,
+            wishlist_keeps_insertion_order,
+            wishlists_are_per_user,
+            matching_is_case_insensitive,
+            matching_is_normalized,
+            long_names_do_not_collide,
+            add_sets_priority,
+            add_records_added_time,
+            set_priority_changes_wishlisted_cards_only,
+            card_details,
+            users_with_series_card_constraints,
+            remove_returns_removed_and_left,
+            remove_from_unknown_series,
+            remove_last_card_drops_series,
+            remove_series_returns_card_count,
+            users_with_series_card,
+            users_with_series_card_no_drop,
+            wildcard_matches_unlisted_cards,
+            wildcard_exclusions,
+            add_sets_priority_of_wishlisted_cards_when_asked,
+            add_takes_cards_back_into_wildcard,
+            remove_leaves_cards_out_of_wildcard,
+            include_in_wildcard_clears_exclusions,
+            search_user_wishlist,
+            catalog,
+            catalog_seen_times,
+            catalog_stats,
+            card_ownership,
+            users_with_series,
+            users_with_series_no_drop,
+            wishlist_overview,
+            wishlist_overview_pages,
+            wishlist_overview_whole_series,
+            wishlists_are_per_scope,
+            fuzzy_threshold_setting,
+            wishlist_privacy_setting,
+            fuzzy_matches_opted_in_users,
+            series_aliases_are_resolved,
+            adding_alias_merges_wishlists,
+            alias_registry,
+            guild_scope_mode,
+            history_is_newest_first,
+            history_keeps_card_details,
+            history_keeps_wildcard_cards,
+            undo_skips_undone_changes,
+        );
+    };
+}
+
+#[cfg(test)]
+mod mock_wishlist_db {
+    use crate::components::mocks::mock_wishlist_db::MockWishlistDB;
+
+    async fn init_db() -> MockWishlistDB {
+        MockWishlistDB::new()
+    }
+
+    async fn teardown(_: MockWishlistDB) {}
+
+    wishlist_db_conformance!();
+}
+
+// Every check reads back what it wrote, which also covers the cache invalidation
+#[cfg(test)]
+mod cached_wishlist_db {
+    use std::sync::Arc;
+
+    use crate::components::cached_wishlist_db::{CachedWishlistDB, DEFAULT_CACHE_CAPACITY, DEFAULT_CACHE_TTL};
+    use crate::components::logger::VoidLogger;
+    use crate::components::mocks::mock_wishlist_db::MockWishlistDB;
+
+    async fn init_db() -> CachedWishlistDB<MockWishlistDB, VoidLogger> {
+        CachedWishlistDB::new(MockWishlistDB::new(), Arc::new(VoidLogger), DEFAULT_CACHE_CAPACITY, DEFAULT_CACHE_TTL)
+    }
+
+    async fn teardown(_: CachedWishlistDB<MockWishlistDB, VoidLogger>) {}
+
+    wishlist_db_conformance!();
+}
+
+#[cfg(test)]
+mod sqlite_wishlist_db {
+    use std::sync::Arc;
+
+    use crate::components::logger::VoidLogger;
+    use crate::components::sqlite_wishlist_db::{self, SqliteWishlistDB};
+
+    async fn init_db() -> SqliteWishlistDB<VoidLogger> {
+        sqlite_wishlist_db::init_db(Arc::new(VoidLogger), ":memory:").await.unwrap()
+    }
+
+    async fn teardown(_: SqliteWishlistDB<VoidLogger>) {}
+
+    wishlist_db_conformance!();
+}
+
+// Ignored by default, run with `cargo test -- --ignored` and `MONGODB_TEST_URL` pointing to a MongoDB server.
+// Each test uses its own database.
+#[cfg(test)]
+mod mongo_wishlist_db {
+    use std::{env, sync::Arc};
+
+    use rand::Rng;
+
+    use crate::components::logger::VoidLogger;
+    use crate::components::mongo_wishlist_db::{self, MongoWishlistDB};
+
+    const MONGODB_TEST_URL_KEY: &str = "MONGODB_TEST_URL";
+
+    async fn init_db() -> MongoWishlistDB<VoidLogger> {
+        let url = env::var(MONGODB_TEST_URL_KEY)
+            .unwrap_or_else(|_| panic!("`{MONGODB_TEST_URL_KEY}` must point to a MongoDB server"));
+        let database_name = format!("better_wishlist_test_{}", rand::thread_rng().gen::<u32>());
+
+        mongo_wishlist_db::init_db_with_database(Arc::new(VoidLogger), url, database_name).await.unwrap()
+    }
+
+    async fn teardown(db: MongoWishlistDB<VoidLogger>) {
+        db.drop_database().await.unwrap();
+    }
+
+    wishlist_db_conformance!(#[ignore = "needs a MongoDB server at `MONGODB_TEST_URL`"]);
+}
@@ -0,0 +1,55 @@
+#[cfg(test)]
+mod similarity {
+    use crate::util::fuzzy::similarity;
+
+    #[test]
+    fn identical() {
+        assert_eq!(similarity("monkey d luffy", "monkey d luffy"), 100);
+        assert_eq!(similarity("", ""), 100);
+    }
+
+    #[test]
+    fn typos() {
+        assert_eq!(similarity("roronoa zoro", "roronoa zorro"), 93);
+        assert!(similarity("kamado tanjiro", "kamado tanjirou") >= 90);
+    }
+
+    #[test]
+    fn partial_names() {
+        assert_eq!(similarity("luffy", "monkey d luffy"), 100);
+        assert_eq!(similarity("monkey d luffy", "luffy"), 100);
+    }
+
+    #[test]
+    fn initials_do_not_match() {
+        assert!(similarity("d", "monkey d luffy") < 50);
+    }
+
+    #[test]
+    fn unrelated_names() {
+        assert!(similarity("ichigo", "luffy") < 50);
+        assert!(similarity("one piece", "two piece") < 80);
+        assert_eq!(similarity("", "luffy"), 0);
+    }
+}
+
+#[cfg(test)]
+mod closest_match {
+    use crate::util::fuzzy::closest_match;
+
+    fn candidates<'a>(names: &[&'a str]) -> Vec<(String, &'a str)> {
+        names.iter().map(|name| (name.to_string(), *name)).collect()
+    }
+
+    #[test]
+    fn most_similar() {
+        assert_eq!(closest_match("namii", candidates(&["nico robin", "nami", "sanji"])), Some("nami"));
+        assert_eq!(closest_match("roronoa zorro", candidates(&["roronoa zoro", "monkey d luffy"])), Some("roronoa zoro"));
+    }
+
+    #[test]
+    fn none_similar_enough() {
+        assert_eq!(closest_match("ichigo", candidates(&["nami", "luffy"])), None);
+        assert_eq!(closest_match("nami", candidates(&[])), None);
+    }
+}
@@ -1 +1,3 @@
+mod fuzzy;
+mod normalize;
 mod parse_util;
\ No newline at end of file
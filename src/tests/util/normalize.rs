@@ -0,0 +1,89 @@
+#[cfg(test)]
+mod search_key {
+    use crate::util::normalize::search_key;
+
+    #[test]
+    fn empty_string() {
+        assert_eq!(search_key(""), "");
+    }
+
+    #[test]
+    fn lowercase() {
+        assert_eq!(search_key("Monkey D LUFFY"), "monkey d luffy");
+    }
+
+    #[test]
+    fn no_truncation() {
+        assert_eq!(search_key("Roronoa Zoro (Wano Country Arc)"), "roronoa zoro wano country arc");
+    }
+
+    #[test]
+    fn diacritics() {
+        assert_eq!(search_key("Pokémon"), search_key("Pokemon"));
+        assert_eq!(search_key("Ōkami Amaterasu"), "okami amaterasu");
+    }
+
+    #[test]
+    fn apostrophes() {
+        assert_eq!(search_key("JoJo’s Bizarre Adventure"), search_key("JoJo's Bizarre Adventure"));
+        assert_eq!(search_key("JoJo's"), "jojos");
+    }
+
+    #[test]
+    fn full_width() {
+        assert_eq!(search_key("ＬＵＦＦＹ"), "luffy");
+    }
+
+    #[test]
+    fn case_folding() {
+        assert_eq!(search_key("Straße"), search_key("STRASSE"));
+    }
+
+    #[test]
+    fn punctuation_and_whitespace() {
+        assert_eq!(search_key("  Re:Zero -  Starting Life\tin Another World  "), "re zero starting life in another world");
+        assert_eq!(search_key("Monkey D. Luffy"), search_key("monkey d luffy"));
+    }
+
+    #[test]
+    fn non_latin_scripts() {
+        assert_eq!(search_key("進撃の巨人"), "進撃の巨人");
+    }
+
+    #[test]
+    fn only_punctuation() {
+        assert_eq!(search_key(" ??? "), "???");
+    }
+}
+
+#[cfg(test)]
+mod search_key_range {
+    use crate::util::normalize::{search_key, search_key_range};
+
+    fn in_range(prefix: &str, name: &str) -> bool {
+        let (start, end) = search_key_range(prefix);
+        let key = search_key(name);
+
+        start <= key && key < end
+    }
+
+    #[test]
+    fn starts_with() {
+        assert!(in_range("One P", "One Piece"));
+        assert!(in_range("one piece", "One Piece"));
+        assert!(!in_range("One Pu", "One Piece"));
+        assert!(!in_range("Naruto", "One Piece"));
+    }
+
+    #[test]
+    fn normalized() {
+        assert!(in_range("jojo’s", "JoJo's Bizarre Adventure"));
+        assert!(in_range("pokémon", "Pokemon Adventures"));
+    }
+
+    #[test]
+    fn empty_prefix() {
+        assert!(in_range("", "One Piece"));
+        assert!(in_range("  ", "Naruto"));
+    }
+}
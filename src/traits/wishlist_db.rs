@@ -1,60 +1,483 @@
 use std::error::Error;
+use std::fmt::Display;
 
+use chrono::{DateTime, Utc};
+use serenity::all::{GuildId, UserId};
 use serenity::async_trait;
 
+use crate::model::catalog::{CatalogSeries, CatalogStats};
+use crate::model::drop::{CardConstraint, DropMatch, DroppedCard, FuzzyWishlist};
+use crate::model::history::{HistoryRecord, WishlistChange};
+use crate::model::wishlist::{AddedCards, CardName, CardPriority, CardRef, OverviewQuery, RemovedCards, ScopeMode, SeriesName, WishlistEntry, WishlistOverview, WishlistPrivacy, WishlistScope, WishlistedCard};
+use crate::util::fuzzy::similarity;
+
+#[derive(Debug)]
+pub enum WishlistDbError {
+    /// The database could not be reached.
+    ConnectionLost(String),
+    /// The request was rejected before reaching the database.
+    InvalidInput(String),
+    /// The requested series or card is not in the wishlist.
+    NotFound(String),
+    /// Any other failure reported by the database.
+    Backend(Box<dyn Error + Send + Sync>)
+}
+
+impl Display for WishlistDbError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            WishlistDbError::ConnectionLost(why) => write!(f, "database connection lost: {why}"),
+            WishlistDbError::InvalidInput(why)   => write!(f, "invalid input: {why}"),
+            WishlistDbError::NotFound(what)      => write!(f, "not found: {what}"),
+            WishlistDbError::Backend(err)        => write!(f, "database error: {err}"),
+        }
+    }
+}
+
+impl Error for WishlistDbError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            WishlistDbError::Backend(err) => Some(err.as_ref()),
+            _ => None
+        }
+    }
+}
+
+pub type WishlistDbResult<T> = Result<T, WishlistDbError>;
+
+/// Rejects blank series or card names before they reach the database.
+pub fn validate_entry(entry: &WishlistEntry) -> WishlistDbResult<()> {
+    if entry.series.as_str().is_empty() {
+        return Err(WishlistDbError::InvalidInput("series name is empty".to_string()));
+    }
+
+    if entry.cards.iter().any(|card| card.as_str().is_empty()) {
+        return Err(WishlistDbError::InvalidInput(format!("empty card name in `{}`", entry.series)));
+    }
+
+    Ok(())
+}
+
+/// Reports a removal from the wishlisted cards it removed, in wishlist order, and the wildcard card the
+/// series keeps when the cards are to be left out of it. Cards it covers are `excluded` (and `removed`
+/// when they were not listed), the others that were not wishlisted are `not_found`. `left` is not set.
+pub fn removed_cards(entry: &WishlistEntry, removed: Vec<WishlistedCard>, wildcard: Option<&WishlistedCard>) -> RemovedCards {
+    let mut res = RemovedCards {
+        removed: removed.iter().map(|card| card.name.clone()).collect(),
+        ..Default::default()
+    };
+
+    let mut seen_cards_search = Vec::with_capacity(entry.cards.len());
+    for card in entry.cards.iter() {
+        let card_search = card.search_term();
+        if seen_cards_search.contains(&card_search) {
+            continue;
+        }
+        let removed_name = removed.iter().find(|removed| removed.name.search_term() == card_search).map(|removed| &removed.name);
+        seen_cards_search.push(card_search);
+
+        match (wildcard, removed_name) {
+            (Some(wildcard), removed_name) if !card.is_wildcard() && wildcard.covers(card) => {
+                res.excluded.push(removed_name.unwrap_or(card).clone());
+                if removed_name.is_none() {
+                    res.removed.push(card.clone());
+                }
+            },
+            (_, None) => res.not_found.push(card.clone()),
+            (_, Some(_)) => ()
+        }
+    }
+
+    res.details = removed;
+    res
+}
+
+/// Rejects blank aliases or canonical names, and aliases of a series to itself.
+pub fn validate_alias(alias: &SeriesName, canonical: &SeriesName) -> WishlistDbResult<()> {
+    if alias.as_str().is_empty() || canonical.as_str().is_empty() {
+        return Err(WishlistDbError::InvalidInput("series name is empty".to_string()));
+    }
+
+    if alias.search_term() == canonical.search_term() {
+        return Err(WishlistDbError::InvalidInput(format!("`{alias}` can't be an alias of itself")));
+    }
+
+    Ok(())
+}
+
+/// Adds the possible matches from fuzzy wishlists to the exact matches of each dropped card.
+/// Users already matched exactly get no possible match for that card, and the best match is kept
+/// when several of a user's cards resemble it. Cards are returned in drop order, without the ones
+/// nobody matched.
+pub fn with_fuzzy_matches(
+    cards: &[DroppedCard],
+    exact: Vec<(CardRef, Vec<DropMatch>)>,
+    wishlists: &[FuzzyWishlist]
+) -> Vec<(CardRef, Vec<DropMatch>)> 
+{
+    if wishlists.is_empty() {
+        return exact;
+    }
+
+    cards.iter()
+        .filter_map(|dropped| {
+            let mut matches = exact.iter()
+                .find(|(card, _)| *card == dropped.card)
+                .map(|(_, matches)| matches.clone())
+                .unwrap_or_default();
+
+            let series_search = dropped.card.series.search_term();
+            let card_search = dropped.card.card.search_term();
+
+            for wishlist in wishlists {
+                if matches.iter().any(|drop_match| drop_match.user_id == wishlist.user_id) {
+                    continue;
+                }
+
+                let best = wishlist.cards.iter()
+                    .filter(|(_, card)| dropped.satisfies(&card.constraints))
+                    .map(|(series, card)| {
+                        let score = similarity(&series_search, &series.search_term())
+                            .min(similarity(&card_search, &card.name.search_term()));
+                        (score, series, card)
+                    })
+                    .filter(|(score, _, _)| *score >= wishlist.threshold)
+                    .max_by_key(|(score, _, _)| *score);
+
+                if let Some((_, series, card)) = best {
+                    matches.push(DropMatch {
+                        user_id: wishlist.user_id,
+                        priority: card.priority,
+                        possible_match: Some(CardRef { series: series.clone(), card: card.name.clone() })
+                    });
+                }
+            }
+
+            (!matches.is_empty()).then(|| (dropped.card.clone(), matches))
+        })
+        .collect()
+}
+
+/// Storage for user wishlists.
+///
+/// Series and cards are matched through their normalized search terms
+/// (`SeriesName::search_term` and `CardName::search_term`, see `normalize::search_key`), while the
+/// name they were first added with is the one returned.
+/// A series is only part of a wishlist while it has cards.
+///
+/// Series given to any method are first resolved through the alias registry (see `canonical_series`),
+/// so a wishlisted series can be referred to, and matched in drops, by any of its aliases.
+///
+/// Every wishlist belongs to a `WishlistScope`, the same user has an independent wishlist per scope.
 #[async_trait]
 pub trait WishlistDB: Send + Sync {
-    async fn get_users_with_series_card<'a> (
-        &'a self, 
-        cards: Vec<(&'a str, &'a str)>
-    ) -> Result<Vec<((&str, &str), Vec<String>)>, Box<dyn Error + Send + Sync>>;
-
-    async fn get_users_with_series<'a>(
-        &'a self, 
-        series:&Vec<&'a str>
-    ) -> Result<Vec<(&str, Vec<(String, i32)>)>, Box<dyn Error + Send + Sync>>;
-    
-    async fn add_all_to_wishlist<'a>(
-        &self, 
-        user_id:&'a str, 
-        series:&'a str, 
-        card_names:Vec<&'a str>
-    ) -> Result<i32, Box<dyn Error + Send + Sync>>;
-    
+    /// Finds the users that wishlisted each of the dropped cards, along with the priority they gave it.
+    /// Users whose constraints the drop does not meet (see `DroppedCard::satisfies`) are left out,
+    /// as are cards nobody is left for. The rest keep their relative order.
+    ///
+    /// A wildcard card (see `CardName::is_wildcard`) matches any card of its series it does not
+    /// exclude, after the users that wishlisted the card itself, who are only matched once.
+    ///
+    /// Users that opted into fuzzy matching are also matched to cards resembling the ones they
+    /// wishlisted, see `with_fuzzy_matches`.
+    async fn get_users_with_series_card(
+        &self,
+        scope: WishlistScope,
+        cards: &[DroppedCard]
+    ) -> WishlistDbResult<Vec<(CardRef, Vec<DropMatch>)>>;
+
+    /// Finds the users that wishlisted each of the given series, along with how many cards
+    /// of that series they wishlisted. Series nobody wishlisted are left out.
+    async fn get_users_with_series(
+        &self,
+        scope: WishlistScope,
+        series: &[SeriesName]
+    ) -> WishlistDbResult<Vec<(SeriesName, Vec<(UserId, i32)>)>>;
+
+    /// Adds the cards to the user's wishlist with the given priority and takes them back into the series'
+    /// wildcard card, as a single atomic change. Cards already there are reported apart and only get
+    /// the priority when `reprioritize`, blank names are `InvalidInput`.
+    async fn add_all_to_wishlist(
+        &self,
+        scope: WishlistScope,
+        user_id: UserId,
+        entry: &WishlistEntry,
+        priority: CardPriority,
+        reprioritize: bool
+    ) -> WishlistDbResult<AddedCards>;
+
+    /// Changes the priority of the cards already in the user's wishlist.
+    /// Returns how many wishlisted cards were matched.
+    async fn set_cards_priority(
+        &self,
+        scope: WishlistScope,
+        user_id: UserId,
+        entry: &WishlistEntry,
+        priority: CardPriority
+    ) -> WishlistDbResult<i32>;
+
+    /// Sets the note of a wishlisted card, `None` clears it. `NotFound` if the card is not wishlisted.
+    async fn set_card_note(
+        &self,
+        scope: WishlistScope,
+        user_id: UserId,
+        card: &CardRef,
+        note: Option<&str>
+    ) -> WishlistDbResult<()>;
+
+    /// Replaces the constraints of a wishlisted card, an empty slice clears them.
+    /// `NotFound` if the card is not wishlisted.
+    async fn set_card_constraints(
+        &self,
+        scope: WishlistScope,
+        user_id: UserId,
+        card: &CardRef,
+        constraints: &[CardConstraint]
+    ) -> WishlistDbResult<()>;
+
+    /// Replaces the cards the user's wildcard card of a series leaves out, an empty slice clears them.
+    /// `NotFound` if the series has no wildcard card.
+    async fn set_wildcard_exclusions(
+        &self,
+        scope: WishlistScope,
+        user_id: UserId,
+        series: &SeriesName,
+        excluded: &[CardName]
+    ) -> WishlistDbResult<()>;
+
+    /// Removes the cards from the user's wishlist as a single atomic change, dropping the series
+    /// once it has no cards left. Cards that were not wishlisted are reported apart.
+    /// When `exclude` and the series keeps its wildcard card, the cards are also left out of it,
+    /// which counts as removing them even when they were not listed.
     async fn remove_all_from_wishlist(
-        &self, 
-        user_id:&str, 
-        series:&str, 
-        card_names:Vec<&str>
-    ) -> Result<(i32, i32), Box<dyn Error + Send + Sync>>;
-    
-    async fn get_user_wishlisted_series(
-        &self, 
-        user_id: &str
-    ) -> Vec<String>;
-    
-    async fn get_user_wishlisted_cards_count(
-        &self, 
-        user_id: &str, 
-        series: &str
-    ) -> i32;
-    
+        &self,
+        scope: WishlistScope,
+        user_id: UserId,
+        entry: &WishlistEntry,
+        exclude: bool
+    ) -> WishlistDbResult<RemovedCards>;
+
+    /// Takes the cards back into the user's wildcard card of the series without listing them, as a single
+    /// atomic change. Returns the ones it left out, named as it did, none without a wildcard card.
+    async fn include_in_wildcard(
+        &self,
+        scope: WishlistScope,
+        user_id: UserId,
+        entry: &WishlistEntry
+    ) -> WishlistDbResult<Vec<CardName>>;
+
+    /// Summarizes the user's wishlist in a single query: its series sorted as asked, each with its
+    /// card count and, when asked, its cards. `skip` and `limit` page through the sorted series,
+    /// the totals always cover the whole wishlist.
+    async fn get_wishlist_overview(
+        &self,
+        scope: WishlistScope,
+        user_id: UserId,
+        query: &OverviewQuery
+    ) -> WishlistDbResult<WishlistOverview>;
+
+    /// Lists the user's wishlisted cards from a series, in the order they were added.
     async fn get_user_wishlisted_cards(
-        &self, 
-        user_id: &str, 
-        series: &str
-    ) -> Vec<String>;
-    
+        &self,
+        scope: WishlistScope,
+        user_id: UserId,
+        series: &SeriesName
+    ) -> WishlistDbResult<Vec<WishlistedCard>>;
+
     async fn user_has_card(
-        &self, 
-        user_id: &str, 
-        series: &str, 
-        card: &str
-    ) -> bool;
-    
+        &self,
+        scope: WishlistScope,
+        user_id: UserId,
+        card: &CardRef
+    ) -> WishlistDbResult<bool>;
+
+    /// Removes a whole series from the user's wishlist.
+    /// Returns how many cards the series had, or `NotFound` if it was not wishlisted.
     async fn remove_series_from_wishlist(
-        &self, 
-        user_id:&str, 
-        series:&str
-    ) -> Result<i32, Box<dyn Error + Send + Sync>>;
-}
\ No newline at end of file
+        &self,
+        scope: WishlistScope,
+        user_id: UserId,
+        series: &SeriesName
+    ) -> WishlistDbResult<i32>;
+
+    /// Lists up to `limit` of the user's wishlisted series whose search term starts with the one
+    /// of `prefix` (see `normalize::search_key_range`), sorted by search term.
+    async fn search_user_series(
+        &self,
+        scope: WishlistScope,
+        user_id: UserId,
+        prefix: &str,
+        limit: usize
+    ) -> WishlistDbResult<Vec<SeriesName>>;
+
+    /// Lists up to `limit` of the user's wishlisted cards from a series whose search term starts
+    /// with the one of `prefix`, sorted by search term.
+    async fn search_user_cards(
+        &self,
+        scope: WishlistScope,
+        user_id: UserId,
+        series: &SeriesName,
+        prefix: &str,
+        limit: usize
+    ) -> WishlistDbResult<Vec<CardName>>;
+
+    /// Appends a change to the user's history, recorded changes are never edited or deleted.
+    async fn record_change(
+        &self,
+        scope: WishlistScope,
+        user_id: UserId,
+        change: &WishlistChange
+    ) -> WishlistDbResult<()>;
+
+    /// Lists up to `limit` of the user's most recent changes, newest first.
+    async fn get_user_history(
+        &self,
+        scope: WishlistScope,
+        user_id: UserId,
+        limit: usize
+    ) -> WishlistDbResult<Vec<HistoryRecord>>;
+
+    /// Finds the user's newest change that is neither an undo nor already undone.
+    async fn get_last_undoable_change(
+        &self,
+        scope: WishlistScope,
+        user_id: UserId
+    ) -> WishlistDbResult<Option<HistoryRecord>>;
+
+    /// Lists the wishlists in the scope of users that opted into fuzzy matching.
+    async fn get_fuzzy_wishlists(
+        &self,
+        scope: WishlistScope
+    ) -> WishlistDbResult<Vec<FuzzyWishlist>>;
+
+    /// Reads a user's fuzzy matching threshold, `None` when they did not opt in.
+    /// The setting belongs to the account, it applies to every scope.
+    async fn get_fuzzy_threshold(
+        &self,
+        user_id: UserId
+    ) -> WishlistDbResult<Option<u8>>;
+
+    /// Opts a user into fuzzy matching with the given threshold, or out of it with `None`.
+    async fn set_fuzzy_threshold(
+        &self,
+        user_id: UserId,
+        threshold: Option<u8>
+    ) -> WishlistDbResult<()>;
+
+    /// Reads a user's privacy setting, users that never changed it are `WishlistPrivacy::Public`.
+    /// The setting belongs to the account, it applies to every scope.
+    async fn get_wishlist_privacy(
+        &self,
+        user_id: UserId
+    ) -> WishlistDbResult<WishlistPrivacy>;
+
+    async fn set_wishlist_privacy(
+        &self,
+        user_id: UserId,
+        privacy: WishlistPrivacy
+    ) -> WishlistDbResult<()>;
+
+    /// Resolves a series through the alias registry, returning the series itself when it is no alias.
+    async fn canonical_series(
+        &self,
+        series: &SeriesName
+    ) -> WishlistDbResult<SeriesName>;
+
+    /// Lists the registered aliases along with their canonical series, sorted by canonical series.
+    async fn get_series_aliases(&self) -> WishlistDbResult<Vec<(SeriesName, SeriesName)>>;
+
+    /// Registers `alias` as another name of `canonical`, replacing any previous registration of it.
+    /// Aliases of aliases are resolved to the canonical series, and wishlists that have the alias
+    /// as a series get it merged into the canonical one. `InvalidInput` for an alias of itself.
+    async fn add_series_alias(
+        &self,
+        alias: &SeriesName,
+        canonical: &SeriesName
+    ) -> WishlistDbResult<()>;
+
+    /// Unregisters an alias, `NotFound` if it was not one. Wishlists are left as they are.
+    async fn remove_series_alias(
+        &self,
+        alias: &SeriesName
+    ) -> WishlistDbResult<()>;
+
+    /// Records series, along with their cards if any, in the catalog of the series and cards seen
+    /// in drops and SOFI lookups. The catalog is shared by every scope, and series or cards already
+    /// in it keep the name they were first seen with. Blank names are `InvalidInput`, and nothing
+    /// is recorded then.
+    ///
+    /// `seen_at` is kept as the first time the series and cards were seen if earlier than the
+    /// recorded one, and as the last time if later.
+    async fn add_to_catalog(
+        &self,
+        entries: &[WishlistEntry],
+        seen_at: DateTime<Utc>
+    ) -> WishlistDbResult<()>;
+
+    /// Reads a series from the catalog along with every card seen from it, `None` if it was never seen.
+    async fn get_catalog_series(
+        &self,
+        series: &SeriesName
+    ) -> WishlistDbResult<Option<CatalogSeries>>;
+
+    async fn get_catalog_stats(&self) -> WishlistDbResult<CatalogStats>;
+
+    /// Lists up to `limit` of the catalog series whose search term starts with the one of `prefix`,
+    /// sorted by search term.
+    async fn search_catalog_series(
+        &self,
+        prefix: &str,
+        limit: usize
+    ) -> WishlistDbResult<Vec<SeriesName>>;
+
+    /// Lists up to `limit` of the catalog cards from a series whose search term starts with the one
+    /// of `prefix`, sorted by search term.
+    async fn search_catalog_cards(
+        &self,
+        series: &SeriesName,
+        prefix: &str,
+        limit: usize
+    ) -> WishlistDbResult<Vec<CardName>>;
+
+    /// Records whether the user owns the cards of an entry, as shown by their SOFI series lookup.
+    /// Ownership is shared by every scope, and cards never recorded count as not owned.
+    /// Blank names are `InvalidInput`.
+    async fn set_cards_owned(
+        &self,
+        user_id: UserId,
+        entry: &WishlistEntry,
+        owned: bool
+    ) -> WishlistDbResult<()>;
+
+    /// Lists the cards the user owns, one entry per series, both sorted by search term.
+    async fn get_owned_cards(
+        &self,
+        user_id: UserId
+    ) -> WishlistDbResult<Vec<WishlistEntry>>;
+
+    /// Reads a guild's scope setting, guilds that never changed it use `ScopeMode::Global`.
+    async fn get_guild_scope_mode(
+        &self,
+        guild_id: GuildId
+    ) -> WishlistDbResult<ScopeMode>;
+
+    async fn set_guild_scope_mode(
+        &self,
+        guild_id: GuildId,
+        mode: ScopeMode
+    ) -> WishlistDbResult<()>;
+
+    /// Scope of the wishlists used in a guild, or in DMs when there is no guild.
+    async fn resolve_scope(
+        &self,
+        guild_id: Option<GuildId>
+    ) -> WishlistDbResult<WishlistScope>
+    {
+        let mode = match guild_id {
+            Some(guild_id) => self.get_guild_scope_mode(guild_id).await?,
+            None => ScopeMode::Global
+        };
+
+        Ok(WishlistScope::resolve(guild_id, mode))
+    }
+}
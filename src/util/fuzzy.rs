@@ -0,0 +1,73 @@
+// Similarity between search keys (see `normalize::search_key`), as a percentage.
+//
+// The best of two scores is used: edit distance catches typos and missing characters, while the
+// token set score catches partial names like `luffy` against `monkey d luffy`. Tokens shorter than
+// two characters are left out of the token set, so initials alone never match.
+pub fn similarity(a: &str, b: &str) -> u8 {
+    edit_similarity(a, b).max(token_set_similarity(a, b))
+}
+
+// Names less similar than this to an unknown one are not suggested in its place
+const SUGGESTION_THRESHOLD: u8 = 60;
+
+// The candidate whose search key is the most similar to `search_key`, when similar enough
+// to be the one meant (see `SUGGESTION_THRESHOLD`).
+pub fn closest_match<T>(search_key: &str, candidates: impl IntoIterator<Item = (String, T)>) -> Option<T> {
+    candidates.into_iter()
+        .map(|(candidate_key, candidate)| (similarity(search_key, &candidate_key), candidate))
+        .filter(|(score, _)| *score >= SUGGESTION_THRESHOLD)
+        .max_by_key(|(score, _)| *score)
+        .map(|(_, candidate)| candidate)
+}
+
+fn edit_similarity(a: &str, b: &str) -> u8 {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+
+    let longest = a.len().max(b.len());
+    if longest == 0 {
+        return 100;
+    }
+
+    (100 - levenshtein(&a, &b) * 100 / longest) as u8
+}
+
+fn token_set_similarity(a: &str, b: &str) -> u8 {
+    let (a, b) = (tokens_of(a), tokens_of(b));
+
+    let fewest = a.len().min(b.len());
+    if fewest == 0 {
+        return 0;
+    }
+
+    let shared = a.iter().filter(|token| b.contains(token)).count();
+    (shared * 100 / fewest) as u8
+}
+
+fn tokens_of(text: &str) -> Vec<&str> {
+    let mut tokens: Vec<&str> = text.split(' ')
+        .filter(|token| token.chars().count() >= 2)
+        .collect();
+
+    tokens.sort_unstable();
+    tokens.dedup();
+    tokens
+}
+
+fn levenshtein(a: &[char], b: &[char]) -> usize {
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    let mut current = vec![0; b.len() + 1];
+
+    for (i, a_char) in a.iter().enumerate() {
+        current[0] = i + 1;
+
+        for (j, b_char) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(a_char != b_char);
+            current[j + 1] = substitution.min(previous[j + 1] + 1).min(current[j] + 1);
+        }
+
+        std::mem::swap(&mut previous, &mut current);
+    }
+
+    previous[b.len()]
+}
@@ -1,3 +1,5 @@
 pub mod either;
+pub mod fuzzy;
+pub mod normalize;
 pub mod parse_util;
 pub mod text_util;
\ No newline at end of file
@@ -0,0 +1,58 @@
+use unicode_normalization::char::is_combining_mark;
+use unicode_normalization::UnicodeNormalization;
+
+// Apostrophes are dropped rather than turned into a separator, so `Hell's` matches `Hells`
+const APOSTROPHES: [char; 5] = ['\'', '’', '‘', 'ʼ', '`'];
+
+/// Key two names are matched by, ignoring case, diacritics, width, punctuation and spacing.
+///
+/// The name is decomposed (NFKD, which also maps full-width and other compatibility characters),
+/// combining marks are stripped and the rest is case folded. Any run of characters that are not
+/// letters or digits becomes a single space, trimmed at both ends. Names are never truncated.
+/// Names without any letter or digit, like `???`, keep their punctuation instead of becoming empty.
+pub fn search_key(name: &str) -> String {
+    let mut key = String::with_capacity(name.len());
+    let mut pending_space = false;
+
+    for c in name.nfkd() {
+        if is_combining_mark(c) || APOSTROPHES.contains(&c) {
+            continue;
+        }
+
+        if !c.is_alphanumeric() {
+            pending_space = true;
+            continue;
+        }
+
+        if pending_space && !key.is_empty() {
+            key.push(' ');
+        }
+        pending_space = false;
+
+        fold_case(c, &mut key);
+    }
+
+    if key.is_empty() {
+        return name.split_whitespace().collect::<Vec<&str>>().join(" ").to_lowercase();
+    }
+
+    key
+}
+
+/// Range of the search keys starting with the key of `prefix`, for range queries over sorted keys.
+/// The start is included and the end excluded, an empty prefix covers every key.
+pub fn search_key_range(prefix: &str) -> (String, String) {
+    let start = search_key(prefix);
+    let end = format!("{start}{}", char::MAX);
+
+    (start, end)
+}
+
+// `char::to_lowercase` plus the full case foldings it leaves out
+fn fold_case(c: char, key: &mut String) {
+    match c {
+        'ß' | 'ẞ' => key.push_str("ss"),
+        'ς' => key.push('σ'),
+        c => key.extend(c.to_lowercase())
+    }
+}
@@ -1,13 +1,21 @@
+use std::cmp::min;
 use std::env;
+use std::fmt::Display;
 
 use regex::Regex;
 
+use crate::model::drop::{CardConstraint, DropAttribute, DroppedCard};
+use crate::model::wishlist::{CardPriority, CardRef, SeriesOrder};
+
 const DISCORD_TOKEN_KEY : &str = "DISCORD_TOKEN";
 const MONGODB_URL_KEY   : &str = "MONGODB_URL";
 
+const SQLITE_URL_SCHEME : &str = "sqlite://";
+
 const HAS_CARD_EMOJI : &str = "☑️";
 
-const CARDS_ANALYSIS_REGEX : &str = r"^[^•]+•[^•]+•[^•]+•[^•]+•\s\*\*([^•]+?)\*\*\s•([^•]*).*";
+const CARDS_ANALYSIS_REGEX : &str = r"^([^•]+)•([^•]+)•([^•]+)•([^•]+)•\s\*\*([^•]+?)\*\*\s•([^•]*).*";
+const DROP_FIELD_REGEX : &str = r"([ɢ◈])\D*(\d+)";
 const SERIES_ANALYSIS_REGEX : &str = r"^[^•]+?•[^•]+?•\s+([^ɢ`•\*]+)$";
 const SERIES_LOOKUP_REGEX : &str = r"[^•]+?•[^•]+?•\s([^•]+?)\s•[^•]+?•[^•]+?•\s\*\*([^•]+?)\*\*$";
 
@@ -21,28 +29,54 @@ pub fn parse_secrets() -> Option<(String, String)> {
     }
 
     // If no main args, try searching environment variables
-    match (env::var(DISCORD_TOKEN_KEY), env::var(MONGODB_URL_KEY)) {
-        (Ok(discord_token), Ok(mongodb_url)) => return Some((discord_token, mongodb_url)),
-        _ => ()
+    if let (Ok(discord_token), Ok(mongodb_url)) = (env::var(DISCORD_TOKEN_KEY), env::var(MONGODB_URL_KEY)) {
+        return Some((discord_token, mongodb_url));
     }
 
     // If everything fails, return none
     None
 }
 
-pub fn parse_series_card_from_analysis(line: &str) -> Option<(&str, &str)> {
+pub fn parse_sqlite_path(database_url: &str) -> Option<&str> {
+    database_url.strip_prefix(SQLITE_URL_SCHEME)
+        .filter(|path| !path.is_empty())
+}
+
+pub fn parse_drop_from_analysis(line: &str) -> Option<DroppedCard> {
     
     let re = Regex::new(CARDS_ANALYSIS_REGEX).unwrap();
 
     match re.captures(line) {
         Some(matches) => {
-            let (_, [card, series]) = matches.extract(); 
-            Some((series.trim(), card.trim()))
+            let (_, [fields @ .., card, series]) = matches.extract::<6>(); 
+
+            let mut dropped = DroppedCard::new(CardRef::new(series, card));
+            for (attribute, value) in fields.into_iter().filter_map(parse_drop_field) {
+                match attribute {
+                    DropAttribute::Gen => dropped.gen = Some(value),
+                    DropAttribute::Edition => dropped.edition = Some(value)
+                }
+            }
+
+            Some(dropped)
         },
         None => None
     }
 }
 
+// One of the fields before the card name, like `ɢ 1234` or `◈2`
+fn parse_drop_field(field: &str) -> Option<(DropAttribute, u32)> {
+    let re = Regex::new(DROP_FIELD_REGEX).unwrap();
+
+    let (_, [marker, value]) = re.captures(field)?.extract();
+    let attribute = match marker {
+        "ɢ" => DropAttribute::Gen,
+        _ => DropAttribute::Edition
+    };
+
+    Some((attribute, value.parse().ok()?))
+}
+
 pub fn parse_series_from_analysis(line: &str) -> Option<&str> {
     
     let re = Regex::new(SERIES_ANALYSIS_REGEX).unwrap();
@@ -88,20 +122,297 @@ pub fn parse_series_from_embed_description(description: &str) -> Option<&str> {
     }
 }
 
-pub fn parse_series_cards(line: &str) -> Option<(&str, Vec<&str>)> {
-    let re = Regex::new(r"\s*(.+)\s*\|\|\s*(.+)").unwrap();
+/// Why a `<series> || <card>, <card>` argument could not be parsed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ArgumentError {
+    pub kind: ArgumentErrorKind,
+    /// Position of the bad token in the line, in characters.
+    pub position: usize,
+    /// The bad token, empty when something is missing at the end of the line.
+    pub token: String
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArgumentErrorKind {
+    /// No `||` between the series and the cards
+    MissingSeparator,
+    EmptySeries,
+    EmptyCard,
+    UnclosedQuote,
+    /// Text right after a closing quote, like `"Zoro"x`
+    TextAfterQuote,
+    /// `\` ending the line
+    DanglingEscape
+}
+
+impl ArgumentError {
+    fn new(kind: ArgumentErrorKind, chars: &[char], position: usize, token_end: usize) -> Self {
+        Self { kind, position, token: chars[position..token_end].iter().collect() }
+    }
+
+    /// The line in a code block, with a caret under the bad token.
+    pub fn pointer(&self, line: &str) -> String {
+        format!("```\n{line}\n{}^\n```", " ".repeat(self.position))
+    }
+}
+
+impl Display for ArgumentError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let reason = match self.kind {
+            ArgumentErrorKind::MissingSeparator => "missing `||` between the series and the cards",
+            ArgumentErrorKind::EmptySeries => "missing series name",
+            ArgumentErrorKind::EmptyCard => "missing card name",
+            ArgumentErrorKind::UnclosedQuote => "unclosed quote",
+            ArgumentErrorKind::TextAfterQuote => "unexpected text after a quoted name",
+            ArgumentErrorKind::DanglingEscape => "nothing to escape after `\\`"
+        };
+
+        match self.token.trim() {
+            "" => write!(f, "{reason} at column {}", self.position + 1),
+            token => write!(f, "{reason} at column {}: `{token}`", self.position + 1)
+        }
+    }
+}
+
+// `<series> || <card> (, <card>)*`. A name can be quoted, like `"Name, with comma"`, and `\` escapes
+// the character after it, like `\,` or `\|`. Quotes only count at the start of a name.
+pub fn parse_series_cards(line: &str) -> Result<(String, Vec<String>), ArgumentError> {
+    let chars: Vec<char> = line.chars().collect();
+
+    let (series, series_start, separator) = parse_name(&chars, 0, &['|', '|'])?;
+    if separator == chars.len() {
+        return Err(ArgumentError::new(ArgumentErrorKind::MissingSeparator, &chars, chars.len(), chars.len()));
+    }
+    if series.is_empty() {
+        return Err(ArgumentError::new(ArgumentErrorKind::EmptySeries, &chars, series_start, separator + 2));
+    }
+
+    let cards = parse_card_list(&chars, separator + 2)?;
+
+    Ok((series, cards))
+}
+
+// The cards part of `parse_series_cards` on its own, like the cards option of `/wa`
+pub fn parse_card_names(cards: &str) -> Result<Vec<String>, ArgumentError> {
+    let chars: Vec<char> = cards.chars().collect();
+
+    parse_card_list(&chars, 0)
+}
+
+// Splits comma separated card names being typed into the complete ones, along with their
+// trailing comma, and the last one, which may be partial and is left quoted or escaped
+pub fn split_last_card(cards: &str) -> (&str, &str) {
+    let mut last_start = 0;
+    let mut name_start = true;
+    let mut quoted = false;
+    let mut escaped = false;
 
-    re.captures(line.trim())
+    for (i, c) in cards.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' => escaped = true,
+            '"' if name_start => quoted = true,
+            '"' if quoted => quoted = false,
+            ',' if !quoted => {
+                last_start = i + 1;
+                name_start = true;
+                continue;
+            },
+            c if c.is_whitespace() && name_start => continue,
+            _ => ()
+        }
+        name_start = false;
+    }
+
+    cards.split_at(last_start)
+}
+
+// Escapes a card name so `parse_card_names` reads it back as it is
+pub fn escape_card_name(name: &str) -> String {
+    let mut escaped = String::with_capacity(name.len());
+
+    for (i, c) in name.chars().enumerate() {
+        if c == '\\' || c == ',' || (i == 0 && c == '"') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+
+    escaped
+}
+
+// Reads the comma separated card names from `start` to the end of the line
+fn parse_card_list(chars: &[char], start: usize) -> Result<Vec<String>, ArgumentError> {
+    let mut cards = vec![];
+    let mut start = start;
+    loop {
+        let (card, card_start, end) = parse_name(chars, start, &[','])?;
+        if card.is_empty() {
+            return Err(ArgumentError::new(ArgumentErrorKind::EmptyCard, chars, card_start, min(card_start + 1, chars.len())));
+        }
+        cards.push(card);
+
+        if end == chars.len() {
+            return Ok(cards);
+        }
+        start = end + 1;
+    }
+}
+
+// A series on its own, quoted or escaped like in `parse_series_cards`
+pub fn parse_series_name(line: &str) -> Result<String, ArgumentError> {
+    let chars: Vec<char> = line.chars().collect();
+
+    let (series, series_start, _) = parse_name(&chars, 0, &[])?;
+    if series.is_empty() {
+        return Err(ArgumentError::new(ArgumentErrorKind::EmptySeries, &chars, series_start, chars.len()));
+    }
+
+    Ok(series)
+}
+
+// Reads a name up to the first unquoted and unescaped `stop` (or the end with an empty `stop`).
+// Returns the name, where it starts and where the stop is (the length of the line if not found).
+fn parse_name(chars: &[char], start: usize, stop: &[char]) -> Result<(String, usize, usize), ArgumentError> {
+    let at_stop = |i: usize| !stop.is_empty() && chars[i..].starts_with(stop);
+
+    let mut i = start;
+    while i < chars.len() && chars[i].is_whitespace() {
+        i += 1;
+    }
+    let name_start = i;
+    let quoted = chars.get(i) == Some(&'"');
+    if quoted {
+        i += 1;
+    }
+
+    let mut name = String::new();
+    loop {
+        if i == chars.len() {
+            if quoted {
+                return Err(ArgumentError::new(ArgumentErrorKind::UnclosedQuote, chars, name_start, chars.len()));
+            }
+            break;
+        }
+
+        match chars[i] {
+            '\\' => {
+                let Some(&escaped) = chars.get(i + 1)
+                else {
+                    return Err(ArgumentError::new(ArgumentErrorKind::DanglingEscape, chars, i, i + 1));
+                };
+                name.push(escaped);
+                i += 2;
+            },
+            '"' if quoted => {
+                i += 1;
+                break;
+            },
+            _ if !quoted && at_stop(i) => break,
+            c => {
+                name.push(c);
+                i += 1;
+            }
+        }
+    }
+
+    if quoted {
+        while i < chars.len() && chars[i].is_whitespace() {
+            i += 1;
+        }
+        if i < chars.len() && !at_stop(i) {
+            let text_end = (i..chars.len()).find(|&end| at_stop(end)).unwrap_or(chars.len());
+            return Err(ArgumentError::new(ArgumentErrorKind::TextAfterQuote, chars, i, text_end));
+        }
+
+        return Ok((name, name_start, i));
+    }
+
+    Ok((name.trim_end().to_string(), name_start, i))
+}
+
+// Numbered non-empty lines of a command, which can be wrapped in a code block.
+// Line numbers count from the first line inside the code block.
+pub fn parse_command_lines(command: &str) -> Vec<(usize, &str)> {
+    let mut command = command.trim();
+
+    if let Some(block) = command.strip_prefix("```") {
+        let block = block.strip_suffix("```").unwrap_or(block);
+
+        // the rest of the opening fence line is a language tag, like ```txt
+        command = match block.split_once('\n') {
+            Some((tag, rest)) if !tag.trim().contains(char::is_whitespace) => rest,
+            _ => block
+        };
+    }
+
+    command.lines()
+        .enumerate()
+        .map(|(index, line)| (index + 1, line.trim()))
+        .filter(|(_, line)| !line.is_empty())
+        .collect()
+}
+
+// `<series> || <card> || <value>`, the value is `None` when missing or empty
+pub fn parse_series_card_value(line: &str) -> Option<(&str, &str, Option<&str>)> {
+    let re = Regex::new(r"(?s)^\s*(.+?)\s*\|\|\s*([^|]+?)\s*(?:\|\|\s*(.*?)\s*)?$").unwrap();
+
+    re.captures(line)
       .map(|capt| {
-        let (_, [series, cards]) = capt.extract();
-        let card_names:Vec<&str> = cards.split(",")
-            .map(str::trim)
-            .collect();
+        let value = capt.get(3)
+            .map(|value| value.as_str())
+            .filter(|value| !value.is_empty());
 
-        (series.trim(), card_names)
+        (capt.get(1).unwrap().as_str(), capt.get(2).unwrap().as_str(), value)
       })
 }
 
+// `<alias> || <series>`, both names required
+pub fn parse_series_alias(line: &str) -> Option<(&str, &str)> {
+    let re = Regex::new(r"(?s)^\s*([^|\s][^|]*?)\s*\|\|\s*([^|\s][^|]*?)\s*$").unwrap();
+
+    re.captures(line)
+      .map(|capt| {
+        let (_, [alias, series]) = capt.extract();
+        (alias, series)
+      })
+}
+
+// Comma separated constraints, like `gen<=100, edition=1`. Any invalid one makes the whole list invalid.
+pub fn parse_constraints(constraints: &str) -> Option<Vec<CardConstraint>> {
+    constraints.split(',')
+        .map(CardConstraint::parse)
+        .collect()
+}
+
+// Splits a leading `priority:<tier>` option from the rest of the command.
+// An unknown tier makes the whole command invalid.
+pub fn parse_priority_option(command: &str) -> Option<(Option<CardPriority>, &str)> {
+    let re = Regex::new(r"(?is)^\s*priority:(\S*)\s*(.*)$").unwrap();
+
+    match re.captures(command) {
+        Some(matches) => {
+            let (_, [priority, rest]) = matches.extract();
+            CardPriority::parse(priority).map(|priority| (Some(priority), rest))
+        },
+        None => Some((None, command))
+    }
+}
+
+// Splits a leading `sort:<order>` option from the rest of the command.
+// An unknown order makes the whole command invalid.
+pub fn parse_sort_option(command: &str) -> Option<(Option<SeriesOrder>, &str)> {
+    let re = Regex::new(r"(?is)^\s*sort:(\S*)\s*(.*)$").unwrap();
+
+    match re.captures(command) {
+        Some(matches) => {
+            let (_, [order, rest]) = matches.extract();
+            SeriesOrder::parse(order).map(|order| (Some(order), rest))
+        },
+        None => Some((None, command))
+    }
+}
+
 pub fn parse_series_from_give_command(description: &str) -> Option<(&str, &str)> {
     let re = Regex::new(r"Name: \*\*(.+)\*\*\nSeries: \*\*(.+)\*\*.*").unwrap();
 